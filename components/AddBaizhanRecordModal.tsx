@@ -7,6 +7,7 @@ import { toast } from '../utils/toastManager';
 import { db } from '../services/db';
 import { DateTimePicker } from './DateTimePicker';
 import { getBaseServerName } from '../utils/serverUtils';
+import { getRecordExpense, getRecordIncome, toMoneyFields } from '../utils/recordUtils';
 
 interface RoleWithStatus {
     id: string;
@@ -66,8 +67,8 @@ export const AddBaizhanRecordModal: React.FC<AddBaizhanRecordModalProps> = ({
         if (isOpen) {
             if (initialData) {
                 setSelectedRoleId(initialData.roleId);
-                setGoldIncome(initialData.goldIncome);
-                setGoldExpense(initialData.goldExpense || 0);
+                setGoldIncome(getRecordIncome(initialData));
+                setGoldExpense(getRecordExpense(initialData));
                 setNotes(initialData.notes || '');
                 setRecordDate(formatDateForInput(initialData.date));
             } else {
@@ -136,8 +137,8 @@ export const AddBaizhanRecordModal: React.FC<AddBaizhanRecordModalProps> = ({
                 roleName: role.name,
                 server: role.server,
                 date: formatDateFromInput(recordDate),
-                goldIncome: goldIncome || 0,
-                goldExpense: goldExpense || 0,
+                // 铜字段为存储金额，输入的银铜小数部分不会丢失
+                ...toMoneyFields(goldIncome, goldExpense),
                 type: 'baizhan',
                 notes: notes.trim() || undefined
             };
@@ -248,9 +249,9 @@ export const AddBaizhanRecordModal: React.FC<AddBaizhanRecordModalProps> = ({
                                     <input
                                         type="number"
                                         min="0"
-                                        step="1"
+                                        step="0.0001"
                                         value={goldIncome || ''}
-                                        onChange={e => setGoldIncome(parseInt(e.target.value) || 0)}
+                                        onChange={e => setGoldIncome(Number(e.target.value) || 0)}
                                         placeholder="收入金额"
                                         className="w-full pl-9 pr-3 py-2.5 bg-slate-50 dark:bg-slate-900/50 border border-slate-200 dark:border-slate-800 rounded-xl text-main placeholder:text-muted/50 focus:outline-none focus:ring-2 focus:ring-emerald-500/30 transition-all font-mono text-[1rem]"
                                     />
@@ -268,9 +269,9 @@ export const AddBaizhanRecordModal: React.FC<AddBaizhanRecordModalProps> = ({
                                     <input
                                         type="number"
                                         min="0"
-                                        step="1"
+                                        step="0.0001"
                                         value={goldExpense || ''}
-                                        onChange={e => setGoldExpense(parseInt(e.target.value) || 0)}
+                                        onChange={e => setGoldExpense(Number(e.target.value) || 0)}
                                         placeholder="支出金额"
                                         className="w-full pl-9 pr-3 py-2.5 bg-slate-50 dark:bg-slate-900/50 border border-slate-200 dark:border-slate-800 rounded-xl text-main placeholder:text-muted/50 focus:outline-none focus:ring-2 focus:ring-amber-500/30 transition-all font-mono text-[1rem]"
                                     />
@@ -5,6 +5,7 @@ import { X, Calendar, Coins, Sparkles, FileText, TrendingUp, TrendingDown, Alert
 import { generateUUID } from '../utils/uuid';
 import { logOperation } from '../utils/cooldownManager';
 import { getBaseServerName } from '../utils/serverUtils';
+import { getRecordExpense, getRecordIncome, toMoneyFields } from '../utils/recordUtils';
 import { DateTimePicker } from './DateTimePicker';
 
 interface RoleWithStatus {
@@ -75,8 +76,8 @@ export const AddRecordModal: React.FC<AddRecordModalProps> = ({
   useEffect(() => {
     if (isOpen) {
       if (initialData) {
-        setGoldIncome(initialData.goldIncome || 0);
-        setGoldExpense(initialData.goldExpense || 0);
+        setGoldIncome(getRecordIncome(initialData));
+        setGoldExpense(getRecordExpense(initialData));
         setHasXuanjing(initialData.hasXuanjing || false);
         setHasMaJu(initialData.hasMaJu || false);
         setHasPet(initialData.hasPet || false);
@@ -134,14 +135,18 @@ export const AddRecordModal: React.FC<AddRecordModalProps> = ({
     try {
       await new Promise(resolve => setTimeout(resolve, 300));
 
+      // 铜字段为存储金额，输入的银铜小数部分不会丢失
+      const money = toMoneyFields(goldIncome, goldExpense);
       const record: Partial<RaidRecord> = {
         id: initialData ? initialData.id : generateUUID(),
         accountId: role.accountId,
         roleId: role.id,
         raidName: initialData ? initialData.raidName : constructRaidName(),
         date: formatDateFromInput(recordDate),
-        goldIncome: goldIncome || 0,
-        goldExpense: goldExpense > 0 ? goldExpense : undefined,
+        goldIncome: money.goldIncome,
+        goldExpense: goldExpense > 0 ? money.goldExpense : undefined,
+        goldIncomeCopper: money.goldIncomeCopper,
+        goldExpenseCopper: money.goldExpenseCopper,
         hasXuanjing,
         hasMaJu,
         hasPet,
@@ -278,6 +283,7 @@ export const AddRecordModal: React.FC<AddRecordModalProps> = ({
                   <input
                     type="number"
                     min="0"
+                    step="0.0001"
                     value={goldIncome || ''}
                     onChange={e => setGoldIncome(Number(e.target.value))}
                     placeholder="收入金额"
@@ -296,6 +302,7 @@ export const AddRecordModal: React.FC<AddRecordModalProps> = ({
                   <input
                     type="number"
                     min="0"
+                    step="0.0001"
                     value={goldExpense || ''}
                     onChange={e => setGoldExpense(Number(e.target.value))}
                     placeholder="支出金额"
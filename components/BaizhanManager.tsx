@@ -8,6 +8,7 @@ import { db } from '../services/db';
 import { toast } from '../utils/toastManager';
 import { SectIcon } from './SectIcon';
 import { filterRaidRoles } from '../utils/raidRoleUtils';
+import { formatGoldAmount, getRecordIncome } from '../utils/recordUtils';
 
 interface BaizhanManagerProps {
     records: BaizhanRecord[];
@@ -61,7 +62,7 @@ export const BaizhanManager: React.FC<BaizhanManagerProps> = ({
             const thisWeekRecords = roleRecords.filter(r => getRecordTime(r.date) >= startOfWeek.getTime());
 
             // 本周收入合计
-            const weeklyIncome = thisWeekRecords.reduce((sum, r) => sum + (r.goldIncome || 0), 0);
+            const weeklyIncome = thisWeekRecords.reduce((sum, r) => sum + getRecordIncome(r), 0);
 
             // 最后运行日期
             const lastRunRecord = [...roleRecords].sort((a, b) => getRecordTime(b.date) - getRecordTime(a.date))[0];
@@ -278,7 +279,7 @@ export const BaizhanManager: React.FC<BaizhanManagerProps> = ({
                                         <div className="flex items-baseline gap-1">
                                             <span className="text-xs text-muted">本周收入:</span>
                                             <span className={`font-bold font-mono ${stats.weeklyIncome > 0 ? 'text-emerald-600' : 'text-main'}`}>
-                                                {stats.weeklyIncome > 0 ? `+${stats.weeklyIncome.toLocaleString()}` : '-'}
+                                                {stats.weeklyIncome > 0 ? `+${formatGoldAmount(stats.weeklyIncome)}` : '-'}
                                                 {stats.weeklyIncome > 0 && <span className="text-xs text-muted font-normal">金</span>}
                                             </span>
                                         </div>
@@ -4,11 +4,7 @@ import { BaizhanRecord } from '../types';
 import { X, Calendar, Swords, Trash2, AlertCircle, Loader2, TrendingUp, TrendingDown, Wallet, Info, Pencil } from 'lucide-react';
 import { SectIcon } from './SectIcon';
 import { getLastMonday, getNextMonday } from '../utils/cooldownManager';
-
-// 格式化金币显示（对齐 RoleRecordsModal 风格）
-const formatGoldAmount = (amount: number): string => {
-    return amount.toLocaleString();
-};
+import { formatGoldAmount, getRecordExpense, getRecordIncome } from '../utils/recordUtils';
 
 interface RoleDisplayData {
     id: string;
@@ -80,8 +76,8 @@ export const BaizhanRoleRecordsModal: React.FC<BaizhanRoleRecordsModalProps> = (
 
     // 汇总统计
     const stats = useMemo(() => {
-        const totalIncome = roleRecords.reduce((sum, r) => sum + (r.goldIncome || 0), 0);
-        const totalExpense = roleRecords.reduce((sum, r) => sum + (r.goldExpense || 0), 0);
+        const totalIncome = roleRecords.reduce((sum, r) => sum + getRecordIncome(r), 0);
+        const totalExpense = roleRecords.reduce((sum, r) => sum + getRecordExpense(r), 0);
         const netGold = totalIncome - totalExpense;
         return { totalRecords: roleRecords.length, totalIncome, totalExpense, netGold };
     }, [roleRecords]);
@@ -191,8 +187,8 @@ export const BaizhanRoleRecordsModal: React.FC<BaizhanRoleRecordsModalProps> = (
                     ) : (
                         <div className="space-y-3">
                             {roleRecords.map((record) => {
-                                const income = record.goldIncome || 0;
-                                const expense = record.goldExpense || 0;
+                                const income = getRecordIncome(record);
+                                const expense = getRecordExpense(record);
 
                                 return (
                                     <div
@@ -2,7 +2,7 @@ import React, { useMemo, useState } from 'react';
 import { ArrowLeft } from 'lucide-react';
 import { RaidRecord, Account, Season } from '../types';
 import { getLastMonday } from '../utils/cooldownManager';
-import { buildSpecialDropRecords, SpecialDropRecord, SpecialDropType, SPECIAL_DROP_DEFINITIONS } from '../utils/rareDropUtils';
+import { buildSpecialDropRecords, dedupeRunGroupDrops, SpecialDropRecord, SpecialDropType, SPECIAL_DROP_DEFINITIONS } from '../utils/rareDropUtils';
 import { db } from '../services/db';
 
 interface CrystalDetailProps {
@@ -211,11 +211,11 @@ export const CrystalDetail: React.FC<CrystalDetailProps> = ({ records, accounts,
       // 无赛季配置时回退到当前筛选范围的玄晶数
       return xuanjingTotal;
     }
-    return safeRecords.filter(r => {
-      if (!r.hasXuanjing) return false;
+    const seasonRecords = safeRecords.filter(r => {
       const t = typeof r.date === 'number' ? r.date : new Date(r.date).getTime();
       return t >= seasonRange.start && t <= seasonRange.end;
-    }).length;
+    });
+    return dedupeRunGroupDrops(seasonRecords).filter(r => r.hasXuanjing).length;
   }, [safeRecords, seasonRange, xuanjingTotal]);
 
   return (
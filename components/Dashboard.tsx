@@ -4,6 +4,8 @@ import { RaidRecord, Account, AccountType, DashboardStats, BaizhanRecord, TrialP
 import { ArrowRight, Star, Zap } from 'lucide-react';
 import { db } from '../services/db';
 import { getLastMonday } from '../utils/cooldownManager';
+import { formatGoldAmount, getRecordExpense, getRecordIncome, isAutoSourceRecord } from '../utils/recordUtils';
+import { dedupeRunGroupDrops } from '../utils/rareDropUtils';
 import { getBaseServerName } from '../utils/serverUtils';
 import { calculateTrialFlipStats } from '../utils/trialFlipStats';
 import { getTrialRecordEquipmentEntries } from '../utils/trialRecordUtils';
@@ -82,7 +84,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
   const filteredRecords = useMemo(() => {
     return safeRecords.filter(r => {
       // 排除待确认和已拒绝的自动扫描记录，不计入统计
-      if (r.source === 'auto' && (r.status === 'pending' || r.status === 'rejected')) return false;
+      if (isAutoSourceRecord(r) && (r.status === 'pending' || r.status === 'rejected')) return false;
       const recordTime = typeof r.date === 'number' ? r.date : new Date(r.date).getTime();
       return periodStartTime === null ? true : recordTime >= periodStartTime;
     });
@@ -121,6 +123,9 @@ export const Dashboard: React.FC<DashboardProps> = ({
     });
   }, [safeTrialRecords, periodStartTime]);
 
+  // 掉落统计用记录：多开同团的共享掉落只计一次
+  const dropCountRecords = useMemo(() => dedupeRunGroupDrops(filteredRecords), [filteredRecords]);
+
   // 稀有掉落分类统计（基于筛选后的记录）
   const rareDropStats = useMemo(() => {
     const drops = {
@@ -133,7 +138,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
       title: 0,      // 称号
       secretBook: 0, // 秘籍
     };
-    filteredRecords.forEach(r => {
+    dropCountRecords.forEach(r => {
       if (r.hasXuanjing) drops.xuanjing++;
       if (r.hasMaJu) drops.maju++;
       if (r.hasPet) drops.pet++;
@@ -145,23 +150,23 @@ export const Dashboard: React.FC<DashboardProps> = ({
     });
     const total = drops.xuanjing + drops.maju + drops.pet + drops.pendant + drops.mount + drops.appearance + drops.title + drops.secretBook;
     return { ...drops, total };
-  }, [filteredRecords]);
+  }, [dropCountRecords]);
 
   const stats: DashboardStats = useMemo(() => {
-    const totalRaidGold = filteredRecords.reduce((acc, r) => acc + r.goldIncome, 0);
-    const totalBaizhanGold = filteredBaizhanRecords.reduce((acc, r) => acc + r.goldIncome, 0);
+    const totalRaidGold = filteredRecords.reduce((acc, r) => acc + getRecordIncome(r), 0);
+    const totalBaizhanGold = filteredBaizhanRecords.reduce((acc, r) => acc + getRecordIncome(r), 0);
     const totalGold = totalRaidGold + totalBaizhanGold;
 
-    const xuanjingCount = filteredRecords.filter(r => r.hasXuanjing).length;
+    const xuanjingCount = dropCountRecords.filter(r => r.hasXuanjing).length;
     const dropRate = filteredRecords.length > 0 ? (xuanjingCount / filteredRecords.length) * 100 : 0;
 
     const clientAccountIds = buildClientAccountIdSet(safeAccounts);
     const clientRaidIncome = filteredRecords
       .filter(r => clientAccountIds.has(r.accountId))
-      .reduce((acc, r) => acc + r.goldIncome, 0);
+      .reduce((acc, r) => acc + getRecordIncome(r), 0);
     const clientBaizhanIncome = filteredBaizhanRecords
       .filter(r => clientAccountIds.has(r.accountId))
-      .reduce((acc, r) => acc + r.goldIncome, 0);
+      .reduce((acc, r) => acc + getRecordIncome(r), 0);
     const clientIncome = clientRaidIncome + clientBaizhanIncome;
 
     let equipCount = 0;
@@ -183,11 +188,11 @@ export const Dashboard: React.FC<DashboardProps> = ({
       dropRate,
       clientIncome,
     };
-  }, [filteredRecords, filteredBaizhanRecords, filteredTrialRecords, safeAccounts, findEquipmentById]);
+  }, [filteredRecords, dropCountRecords, filteredBaizhanRecords, filteredTrialRecords, safeAccounts, findEquipmentById]);
 
   const incomeBreakdown = useMemo(() => {
-    const raidGold = filteredRecords.reduce((acc, r) => acc + r.goldIncome, 0);
-    const baizhanGold = filteredBaizhanRecords.reduce((acc, r) => acc + r.goldIncome, 0);
+    const raidGold = filteredRecords.reduce((acc, r) => acc + getRecordIncome(r), 0);
+    const baizhanGold = filteredBaizhanRecords.reduce((acc, r) => acc + getRecordIncome(r), 0);
     return {
       raidGold,
       baizhanGold,
@@ -211,7 +216,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
       }
 
       const role = roleMap.get(roleId)!;
-      role.totalGold += r.goldIncome;
+      role.totalGold += getRecordIncome(r);
     });
 
     filteredBaizhanRecords.forEach(r => {
@@ -224,7 +229,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
       }
 
       const role = roleMap.get(roleId)!;
-      role.totalGold += r.goldIncome;
+      role.totalGold += getRecordIncome(r);
     });
 
     let maxRole: typeof roleMap extends Map<string, infer V> ? V : never = { roleName: '暂无数据', server: '', totalGold: 0 };
@@ -249,7 +254,8 @@ export const Dashboard: React.FC<DashboardProps> = ({
     filteredRecords
       .filter(r => ownAccountIds.has(r.accountId))
       .forEach(r => {
-        if (r.goldExpense && r.goldExpense > 0) {
+        const expense = getRecordExpense(r);
+        if (expense > 0) {
           const roleId = r.roleId || r.accountId;
           const roleName = r.roleName || '未知角色';
           const server = getBaseServerName(r.server || '未知服务器');
@@ -259,14 +265,15 @@ export const Dashboard: React.FC<DashboardProps> = ({
           }
 
           const role = roleMap.get(roleId)!;
-          role.totalExpense += r.goldExpense;
+          role.totalExpense += expense;
         }
       });
 
     filteredBaizhanRecords
       .filter(r => ownAccountIds.has(r.accountId))
       .forEach(r => {
-        if (r.goldExpense && r.goldExpense > 0) {
+        const expense = getRecordExpense(r);
+        if (expense > 0) {
           const roleId = r.roleId || r.accountId;
           const roleName = r.roleName || '未知角色';
           const server = getBaseServerName(r.server || '未知服务器');
@@ -276,7 +283,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
           }
 
           const role = roleMap.get(roleId)!;
-          role.totalExpense += r.goldExpense;
+          role.totalExpense += expense;
         }
       });
 
@@ -348,7 +355,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
             <div className="flex items-center gap-6">
               <div className="text-right">
                 <p className="text-muted text-xs">金币收益</p>
-                <p className="text-2xl font-bold text-emerald-600 mt-0.5">{luckyRole.totalGold.toLocaleString()}</p>
+                <p className="text-2xl font-bold text-emerald-600 mt-0.5">{formatGoldAmount(luckyRole.totalGold)}</p>
               </div>
             </div>
           </div>
@@ -379,7 +386,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
             </div>
             <div className="text-right">
               <p className="text-muted text-xs">总支出</p>
-              <p className="text-2xl font-bold text-amber-600 mt-0.5">{bigSpender.totalExpense.toLocaleString()}</p>
+              <p className="text-2xl font-bold text-amber-600 mt-0.5">{formatGoldAmount(bigSpender.totalExpense)}</p>
             </div>
           </div>
           <div className="flex items-center gap-1.5 mt-3 pt-2.5 border-t border-base">
@@ -409,7 +416,7 @@ export const Dashboard: React.FC<DashboardProps> = ({
           <div className="rounded-lg border border-emerald-200/60 bg-emerald-50/60 p-3 dark:border-emerald-800/30 dark:bg-emerald-900/10">
             <div className="text-xs font-medium text-emerald-700 dark:text-emerald-300">本期总收入</div>
             <div className="mt-1.5 text-2xl font-bold text-emerald-700 dark:text-emerald-300">
-              {stats.totalGold.toLocaleString()}
+              {formatGoldAmount(stats.totalGold)}
               <span className="ml-1 text-xs font-normal text-emerald-600/70 dark:text-emerald-400/70">金</span>
             </div>
           </div>
@@ -426,13 +433,13 @@ export const Dashboard: React.FC<DashboardProps> = ({
           {/* 团队副本 / 百战收益明细 */}
           <div className="rounded-lg border border-base bg-slate-50/80 p-3 dark:bg-slate-800/30">
             <div className="text-xs font-medium text-muted">团队副本</div>
-            <div className="mt-1.5 text-lg font-bold text-main">{incomeBreakdown.raidGold.toLocaleString()}<span className="ml-1 text-xs font-normal text-muted">金</span></div>
+            <div className="mt-1.5 text-lg font-bold text-main">{formatGoldAmount(incomeBreakdown.raidGold)}<span className="ml-1 text-xs font-normal text-muted">金</span></div>
             <div className="mt-1 text-xs text-muted">{incomeBreakdown.raidCount} 次通关</div>
           </div>
 
           <div className="rounded-lg border border-base bg-slate-50/80 p-3 dark:bg-slate-800/30">
             <div className="text-xs font-medium text-muted">百战异闻录</div>
-            <div className="mt-1.5 text-lg font-bold text-main">{incomeBreakdown.baizhanGold.toLocaleString()}<span className="ml-1 text-xs font-normal text-muted">金</span></div>
+            <div className="mt-1.5 text-lg font-bold text-main">{formatGoldAmount(incomeBreakdown.baizhanGold)}<span className="ml-1 text-xs font-normal text-muted">金</span></div>
             <div className="mt-1 text-xs text-muted">{incomeBreakdown.baizhanCount} 次通关</div>
           </div>
         </div>
@@ -4,6 +4,8 @@ import { ArrowLeft, Coins, TrendingUp, TrendingDown, Search, Calendar, Trash2, P
 import { RaidRecord, Account, BaizhanRecord, Season } from '../types';
 import { toast } from '../utils/toastManager';
 import { getLastMonday } from '../utils/cooldownManager';
+import { formatGoldAmount, getRecordExpense, getRecordIncome, isAutoSourceRecord } from '../utils/recordUtils';
+import { dedupeRunGroupDrops } from '../utils/rareDropUtils';
 import { buildClientAccountIdSet, buildRoleInfoLookup, getRoleInfoKey, getVisibleRecordRange } from '../utils/recordLookupUtils';
 import { db } from '../services/db';
 
@@ -29,6 +31,8 @@ interface EnhancedRecord {
   raidName: string;
   goldIncome: number;
   goldExpense?: number;
+  goldIncomeCopper?: number;
+  goldExpenseCopper?: number;
   notes?: string;
   hasXuanjing?: boolean;
   hasMaJu?: boolean;
@@ -42,8 +46,9 @@ interface EnhancedRecord {
   displayServer: string;
   isBaizhan?: boolean;
   isTrial?: boolean;
-  source?: 'auto' | 'manual';
+  source?: 'auto' | 'manual' | 'gkp';
   status?: 'pending' | 'confirmed' | 'rejected' | 'scanning';
+  runGroupId?: string;
 }
 
 export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanRecords, accounts, initialPeriod, onPeriodChange, onBack, onDeleteRecord, onEditRecord, onEditBaizhanRecord }) => {
@@ -142,7 +147,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
     return enhancedRecords.filter(r => {
       // 排除待确认的自动扫描记录，不计入收益统计
       // rejected 记录保留显示在"全部"页签中，但不参与统计
-      if (r.source === 'auto' && r.status === 'pending') return false;
+      if (isAutoSourceRecord(r) && r.status === 'pending') return false;
       const recordTime = typeof r.date === 'number' ? r.date : new Date(r.date).getTime();
       return recordTime >= startTime;
     });
@@ -150,7 +155,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
 
   // 用于统计和图表的记录：排除 rejected 记录
   const confirmedRecords = useMemo(() => {
-    return filteredRecords.filter(r => !(r.source === 'auto' && r.status === 'rejected'));
+    return filteredRecords.filter(r => !(isAutoSourceRecord(r) && r.status === 'rejected'));
   }, [filteredRecords]);
 
   const searchedRecords = useMemo(() => {
@@ -162,29 +167,30 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
 
   const tabFilteredRecords = useMemo(() => {
     if (activeTab === 'income') {
-      return searchedRecords.filter(r => r.goldIncome > 0 && !(r.source === 'auto' && r.status === 'rejected'));
+      return searchedRecords.filter(r => getRecordIncome(r) > 0 && !(isAutoSourceRecord(r) && r.status === 'rejected'));
     } else if (activeTab === 'expense') {
-      return searchedRecords.filter(r => (r.goldExpense || 0) > 0 && !(r.source === 'auto' && r.status === 'rejected'));
+      return searchedRecords.filter(r => getRecordExpense(r) > 0 && !(isAutoSourceRecord(r) && r.status === 'rejected'));
     }
     return searchedRecords;
   }, [searchedRecords, activeTab]);
 
   const stats = useMemo(() => {
     const clientAccountIds = buildClientAccountIdSet(safeAccounts);
-    const totalIncome = confirmedRecords.reduce((acc, r) => acc + r.goldIncome, 0);
+    const totalIncome = confirmedRecords.reduce((acc, r) => acc + getRecordIncome(r), 0);
     // 总支出仅统计本人账号,代清账号的支出由老板承担,不计入本人支出
     const totalExpense = confirmedRecords
       .filter(r => !clientAccountIds.has(r.accountId))
-      .reduce((acc, r) => acc + (r.goldExpense || 0), 0);
+      .reduce((acc, r) => acc + getRecordExpense(r), 0);
     const netIncome = totalIncome - totalExpense;
-    const xuanjingCount = confirmedRecords.filter(r => r.hasXuanjing).length;
+    // 多开同团的共享掉落只计一次
+    const xuanjingCount = dedupeRunGroupDrops(confirmedRecords).filter(r => r.hasXuanjing).length;
     const clientIncome = confirmedRecords
       .filter(r => clientAccountIds.has(r.accountId))
-      .reduce((acc, r) => acc + r.goldIncome, 0);
+      .reduce((acc, r) => acc + getRecordIncome(r), 0);
 
     const clientExpense = confirmedRecords
       .filter(r => clientAccountIds.has(r.accountId))
-      .reduce((acc, r) => acc + (r.goldExpense || 0), 0);
+      .reduce((acc, r) => acc + getRecordExpense(r), 0);
 
     return {
       totalIncome,
@@ -201,7 +207,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
   const chartData = useMemo(() => {
     const grouped: Record<string, number> = {};
     confirmedRecords.forEach(r => {
-      grouped[r.raidName] = (grouped[r.raidName] || 0) + r.goldIncome;
+      grouped[r.raidName] = (grouped[r.raidName] || 0) + getRecordIncome(r);
     });
     return Object.keys(grouped)
       .map(k => ({ name: k, value: grouped[k] }))
@@ -216,8 +222,8 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
       if (!grouped[key]) {
         grouped[key] = { income: 0, expense: 0, name: r.displayRoleName };
       }
-      grouped[key].income += r.goldIncome;
-      grouped[key].expense += (r.goldExpense || 0);
+      grouped[key].income += getRecordIncome(r);
+      grouped[key].expense += getRecordExpense(r);
     });
     return Object.values(grouped)
       .map(d => ({ name: d.name, 收入: d.income, 支出: d.expense, netIncome: d.income - d.expense }))
@@ -258,9 +264,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
     });
   };
 
-  const formatGold = (amount: number) => {
-    return amount.toLocaleString();
-  };
+  const formatGold = formatGoldAmount;
 
   const handleDeleteClick = (recordId: string) => {
     setDeleteConfirmRecordId(recordId);
@@ -431,7 +435,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
                       color: 'rgb(var(--text-main))'
                     }}
                     itemStyle={{ color: 'rgb(var(--text-main))' }}
-                    formatter={(value: number) => [`${value.toLocaleString()} 金`, '']}
+                    formatter={(value: number) => [`${formatGoldAmount(value)} 金`, '']}
                   />
                   <Bar dataKey="value" radius={[4, 4, 0, 0]} maxBarSize={60}>
                     {chartData.map((_entry: any, index: number) => (
@@ -481,7 +485,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
                       color: 'rgb(var(--text-main))'
                     }}
                     itemStyle={{ color: 'rgb(var(--text-main))' }}
-                    formatter={(value: number, name: string) => [`${value.toLocaleString()} 金`, name]}
+                    formatter={(value: number, name: string) => [`${formatGoldAmount(value)} 金`, name]}
                   />
                   <Bar dataKey="收入" fill="rgb(var(--chart-income))" radius={[4, 4, 0, 0]} maxBarSize={40}>
                     <LabelList dataKey="收入" position="top" formatter={(val: number) => val.toLocaleString()} style={{ fill: 'rgb(var(--chart-income))', fontSize: 10 }} />
@@ -574,11 +578,13 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
                 <div style={{ height: virtualRange.topPadding }} aria-hidden="true" />
               )}
               {visibleRecords.map((record) => {
-                const netIncome = record.goldIncome - (record.goldExpense || 0);
+                const income = getRecordIncome(record);
+                const expense = getRecordExpense(record);
+                const netIncome = income - expense;
                 const summaryAmount = activeTab === 'income'
-                  ? record.goldIncome
+                  ? income
                   : activeTab === 'expense'
-                    ? record.goldExpense || 0
+                    ? expense
                     : netIncome;
                 const summaryLabel = activeTab === 'income'
                   ? '收入'
@@ -598,7 +604,7 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
                   ? '+'
                   : '';
                 const isExpanded = expandedRecordId === record.id;
-                const isRejected = record.source === 'auto' && record.status === 'rejected';
+                const isRejected = isAutoSourceRecord(record) && record.status === 'rejected';
 
                 return (
                   <div
@@ -755,12 +761,12 @@ export const IncomeDetail: React.FC<IncomeDetailProps> = ({ records, baizhanReco
                           <div className="flex gap-6 text-sm">
                             <div className="flex items-center gap-2 text-emerald-600 dark:text-emerald-400 bg-emerald-50 dark:bg-emerald-900/10 px-2 py-1 rounded">
                               <TrendingUp className="w-3.5 h-3.5" />
-                              <span className="font-medium">收入: {formatGold(record.goldIncome)}</span>
+                              <span className="font-medium">收入: {formatGold(income)}</span>
                             </div>
-                            {(record.goldExpense || 0) > 0 && (
+                            {expense > 0 && (
                               <div className="flex items-center gap-2 text-amber-600 dark:text-amber-400 bg-amber-50 dark:bg-amber-900/10 px-2 py-1 rounded">
                                 <TrendingDown className="w-3.5 h-3.5" />
-                                <span className="font-medium">支出: {formatGold(record.goldExpense || 0)}</span>
+                                <span className="font-medium">支出: {formatGold(expense)}</span>
                               </div>
                             )}
                           </div>
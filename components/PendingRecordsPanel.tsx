@@ -10,7 +10,7 @@ import { dropScannerService } from '../services/dropScanner';
 import { db } from '../services/db';
 import { getLastMonday, getNextMonday, getTenPersonCycle, getMonthStart, getMonthEnd } from '../utils/cooldownManager';
 import { getBaseServerName } from '../utils/serverUtils';
-import { formatGoldAmount } from '../utils/recordUtils';
+import { formatGoldAmount, getRecordExpense, getRecordIncome, isAutoSourceRecord, toMoneyFields } from '../utils/recordUtils';
 import { toast } from '../utils/toastManager';
 import { getDefaultBosses } from '../data/raidBosses';
 import { useDebug } from '../contexts/DebugContext';
@@ -83,7 +83,7 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
   // scanning 记录显示为锁定状态，不可确认/拒绝；pending 记录可正常确认/拒绝
   const pendingRecords = useMemo(() => {
     return records.filter(r =>
-      r.source === 'auto' && (r.status === 'pending' || r.status === 'scanning')
+      isAutoSourceRecord(r) && (r.status === 'pending' || r.status === 'scanning')
     );
   }, [records]);
 
@@ -153,8 +153,8 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
     // 掉落物中包含"玄晶"时默认勾选玄晶
     const dropsHasXuanjing = record.drops?.some(d => d.includes('玄晶')) ?? false;
     setEditForm({
-      goldIncome: record.goldIncome || 0,
-      goldExpense: record.goldExpense || 0,
+      goldIncome: getRecordIncome(record),
+      goldExpense: getRecordExpense(record),
       hasXuanjing: record.hasXuanjing || dropsHasXuanjing,
       hasMaJu: record.hasMaJu || false,
       hasPet: record.hasPet || false,
@@ -214,8 +214,8 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
     setPendingActionId(editingRecord.id);
     try {
       await dropScannerService.confirmRecord(editingRecord.id, {
-        goldIncome: editForm.goldIncome,
-        goldExpense: editForm.goldExpense,
+        // 铜字段为存储金额，一并提交才能保留银铜部分的修改
+        ...toMoneyFields(editForm.goldIncome, editForm.goldExpense),
         hasXuanjing: editForm.hasXuanjing,
         hasMaJu: editForm.hasMaJu,
         hasPet: editForm.hasPet,
@@ -445,7 +445,7 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
                 const roleInfo = roleMap.get(record.roleId);
                 const roleName = roleInfo?.name ?? record.roleName ?? '未知角色';
                 const server = roleInfo?.server ?? record.server ?? '';
-                const income = record.goldIncome || 0;
+                const income = getRecordIncome(record);
                 const bosses = record.bossNames?.filter(n => n) ?? [];
                 const isScanning = record.status === 'scanning';
 
@@ -487,7 +487,7 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
                         <TrendingDown className="w-3.5 h-3.5 text-amber-600" />
                         <span className="text-muted text-xs">支出</span>
                         <span className="font-mono font-semibold text-amber-700 dark:text-amber-400">
-                          {formatGoldAmount(record.goldExpense || 0)}
+                          {formatGoldAmount(getRecordExpense(record))}
                         </span>
                       </span>
                       {record.drops && record.drops.length > 0 && (
@@ -605,6 +605,7 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
                     <input
                       type="number"
                       min="0"
+                      step="0.0001"
                       value={editForm.goldIncome || ''}
                       onChange={e => setEditForm({ ...editForm, goldIncome: Number(e.target.value) })}
                       placeholder="收入金额"
@@ -622,6 +623,7 @@ export const PendingRecordsPanel: React.FC<PendingRecordsPanelProps> = ({
                     <input
                       type="number"
                       min="0"
+                      step="0.0001"
                       value={editForm.goldExpense || ''}
                       onChange={e => setEditForm({ ...editForm, goldExpense: Number(e.target.value) })}
                       placeholder="支出金额"
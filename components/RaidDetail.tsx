@@ -4,7 +4,7 @@ import { Shield, Calendar, TrendingUp, TrendingDown, RefreshCw, Clock, Copy, Che
 import { AddRecordModal } from './AddRecordModal';
 import { RoleRecordsModal } from './RoleRecordsModal';
 import { BossCooldownSummary } from './BossCooldownDisplay';
-import { formatGoldAmount } from '../utils/recordUtils';
+import { formatGoldAmount, getRecordExpense, getRecordIncome, isAutoSourceRecord } from '../utils/recordUtils';
 import { calculateCooldown, formatCountdown, getRaidRefreshInfo, CooldownInfo, getLastMonday, getNextMonday } from '../utils/cooldownManager';
 import { db } from '../services/db';
 import { shouldShowClientRoleInRaid } from '../utils/raidVersionUtils';
@@ -259,7 +259,7 @@ export const RaidDetail: React.FC<RaidDetailProps> = ({ raid, accounts, records,
 
     // 用于统计的记录：排除待确认和已拒绝的自动扫描记录
     const confirmedThisWeekRecords = thisWeekRecords.filter(r => {
-      if (r.source === 'auto' && (r.status === 'pending' || r.status === 'rejected')) return false;
+      if (isAutoSourceRecord(r) && (r.status === 'pending' || r.status === 'rejected')) return false;
       return true;
     });
 
@@ -309,7 +309,7 @@ export const RaidDetail: React.FC<RaidDetailProps> = ({ raid, accounts, records,
       // 统计数据使用排除 pending 的记录
       const roleRecords = confirmedThisWeekRecords.filter(r => r.roleId === record.roleId);
       // 待确认状态检测使用全部记录
-      const hasPendingRecord = thisWeekRecords.some(r => r.roleId === record.roleId && r.source === 'auto' && r.status === 'pending');
+      const hasPendingRecord = thisWeekRecords.some(r => r.roleId === record.roleId && isAutoSourceRecord(r) && r.status === 'pending');
 
       const lastRunRecord = roleRecords.sort((a, b) =>
         new Date(b.date).getTime() - new Date(a.date).getTime()
@@ -322,8 +322,8 @@ export const RaidDetail: React.FC<RaidDetailProps> = ({ raid, accounts, records,
 
       if (lastRunRecord) {
         lastRunDate = lastRunRecord.date;
-        lastRunIncome = lastRunRecord.goldIncome;
-        lastRunExpense = lastRunRecord.goldExpense || 0;
+        lastRunIncome = getRecordIncome(lastRunRecord);
+        lastRunExpense = getRecordExpense(lastRunRecord);
         lastRunGold = lastRunIncome - lastRunExpense;
       }
 
@@ -5,6 +5,7 @@ import { generateUUID } from '../utils/uuid';
 import { getRaidKey } from '../utils/raidUtils';
 import { toast } from '../utils/toastManager';
 import { db } from '../services/db';
+import { formatGoldAmount, getRecordIncome } from '../utils/recordUtils';
 
 // 扩展RaidRecord，添加角色信息
 interface ExtendedRaidRecord extends RaidRecord {
@@ -563,7 +564,7 @@ export const RaidLogger: React.FC<RaidLoggerProps> = ({ accounts, records, setRe
                   <div className="flex items-center gap-4">
                     <div className="text-right">
                       <div className="text-lg font-bold text-emerald-600 dark:text-emerald-400 font-mono">
-                        +{record.goldIncome.toLocaleString()}
+                        +{formatGoldAmount(getRecordIncome(record))}
                       </div>
                       <div className="text-xs text-muted">金币</div>
                     </div>
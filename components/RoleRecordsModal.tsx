@@ -2,7 +2,8 @@ import React, { useState, useMemo, useCallback } from 'react';
 import { createPortal } from 'react-dom';
 import { RaidRecord, Raid } from '../types';
 import { X, Search, Calendar, Sparkles, Trash2, CheckCircle, AlertCircle, Loader2, TrendingUp, TrendingDown, Wallet, Info, Anchor, Ghost, Package, Shirt, Crown, Flag, Pencil, BookOpen, Check, Clock, AlertTriangle } from 'lucide-react';
-import { formatGoldAmount } from '../utils/recordUtils';
+import { formatGoldAmount, getRecordExpense, getRecordIncome, isAutoSourceRecord } from '../utils/recordUtils';
+import { dedupeRunGroupDrops } from '../utils/rareDropUtils';
 import { getLastMonday, getNextMonday, getTenPersonCycle } from '../utils/cooldownManager';
 import { calculateBossCooldowns } from '../utils/bossCooldownManager';
 import { BossCooldownSummary } from './BossCooldownDisplay';
@@ -92,14 +93,14 @@ export const RoleRecordsModal: React.FC<RoleRecordsModalProps> = ({
   // 用于统计的记录：排除待确认和已拒绝的自动扫描记录
   const confirmedRoleRecords = useMemo(() => {
     return roleRecords.filter(r => {
-      if (r.source === 'auto' && (r.status === 'pending' || r.status === 'rejected')) return false;
+      if (isAutoSourceRecord(r) && (r.status === 'pending' || r.status === 'rejected')) return false;
       return true;
     });
   }, [roleRecords]);
 
-  const totalIncome = confirmedRoleRecords.reduce((sum, r) => sum + (Number(r.goldIncome) || 0), 0);
-  const totalExpense = confirmedRoleRecords.reduce((sum, r) => sum + (Number(r.goldExpense) || 0), 0);
-  const xuanjingCount = confirmedRoleRecords.filter(r => r.hasXuanjing).length;
+  const totalIncome = confirmedRoleRecords.reduce((sum, r) => sum + getRecordIncome(r), 0);
+  const totalExpense = confirmedRoleRecords.reduce((sum, r) => sum + getRecordExpense(r), 0);
+  const xuanjingCount = dedupeRunGroupDrops(confirmedRoleRecords).filter(r => r.hasXuanjing).length;
   const totalNet = totalIncome - totalExpense;
 
   const bossCooldowns = useMemo(() => {
@@ -346,10 +347,10 @@ export const RoleRecordsModal: React.FC<RoleRecordsModalProps> = ({
           ) : (
             <div className="space-y-3">
               {roleRecords.map((record) => {
-                const isPending = record.source === 'auto' && record.status === 'pending';
-                const isScanning = record.source === 'auto' && record.status === 'scanning';
+                const isPending = isAutoSourceRecord(record) && record.status === 'pending';
+                const isScanning = isAutoSourceRecord(record) && record.status === 'scanning';
                 const isRejected = record.status === 'rejected';
-                const isAutoRecord = record.source === 'auto';
+                const isAutoRecord = isAutoSourceRecord(record);
 
                 return (
                 <div
@@ -399,16 +400,16 @@ export const RoleRecordsModal: React.FC<RoleRecordsModalProps> = ({
                           </span>
                         )}
 
-                        {record.goldIncome > 0 && (
+                        {getRecordIncome(record) > 0 && (
                           <div className="flex items-center gap-1" title="收入">
                             <TrendingUp className="w-3.5 h-3.5 text-emerald-600 dark:text-emerald-500 flex-shrink-0" />
-                            <span className="text-sm font-semibold text-emerald-600 dark:text-emerald-500">{formatGoldAmount(record.goldIncome)}</span>
+                            <span className="text-sm font-semibold text-emerald-600 dark:text-emerald-500">{formatGoldAmount(getRecordIncome(record))}</span>
                           </div>
                         )}
-                        {record.goldExpense && record.goldExpense > 0 ? (
+                        {getRecordExpense(record) > 0 ? (
                           <div className="flex items-center gap-1" title="支出">
                             <TrendingDown className="w-3.5 h-3.5 text-amber-600 dark:text-amber-500 flex-shrink-0" />
-                            <span className="text-sm font-semibold text-amber-600 dark:text-amber-500">{formatGoldAmount(record.goldExpense)}</span>
+                            <span className="text-sm font-semibold text-amber-600 dark:text-amber-500">{formatGoldAmount(getRecordExpense(record))}</span>
                           </div>
                         ) : null}
                       </div>
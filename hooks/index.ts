@@ -1,3 +1,5 @@
 export { useCountdown, CountdownDisplay } from './useCountdown';
 export { useDropScanner } from './useDropScanner';
 export type { UseDropScannerOptions, UseDropScannerReturn } from './useDropScanner';
+export { useRaidSessionTimeline } from './useRaidSessionTimeline';
+export type { UseRaidSessionTimelineReturn } from './useRaidSessionTimeline';
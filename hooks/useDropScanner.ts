@@ -8,7 +8,10 @@ import { getLastMonday, getNextMonday } from '../utils/cooldownManager';
  *
  * 工作原理：
  * 1. 订阅 useActivePoller 的活跃检测结果
- * 2. JX3 运行时，每 30 秒调用后端 `scan_all_active_raid_drops`：
+ * 2. JX3 运行时启动后端目录监听（`start_raid_scan_watcher`）：
+ *    - 账号 combat_logs 出现新 JCL 或 chatlog 写入时，后端只扫描该账号
+ *    - 扫描结果通过 `raid-scan-updated` 事件推送（含该账号待确认记录）
+ *    - 监听启动失败时回退到每 30 秒调用 `scan_all_active_raid_drops`
  *    - 后端自动识别活跃账号（combat_logs mtime > 进程启动时间）
  *    - 后端基于 chatlog mtime 判断角色在线状态
  *    - 后端为每个副本实例计算 record_status：
@@ -22,7 +25,7 @@ import { getLastMonday, getNextMonday } from '../utils/cooldownManager';
  *
  * 日志规范：
  * - 统一前缀 `[AutoScan]`，便于在控制台过滤自动扫描流程
- * - 三种扫描场景：polling（30秒轮询）/ exit（JX3退出后）/ manual（手动刷新）
+ * - 扫描场景：watch（目录监听）/ polling（30秒轮询）/ exit（JX3退出后）/ manual（手动刷新）
  * - 关键节点记录耗时、账号数、副本实例数等摘要信息
  */
 
@@ -125,32 +128,83 @@ export const useDropScanner = (options: UseDropScannerOptions = {}): UseDropScan
     }
   }, []);
 
-  // 订阅活跃检测结果 + 30 秒轮询（仅在自动扫描开启时）
+  // 订阅活跃检测结果：优先启动后端目录监听，失败时回退到 30 秒轮询（仅在自动扫描开启时）
   useEffect(() => {
     if (!autoScanEnabled) return;
     if (!activeResult) return;
     if (!activeResult.jx3Running) return;
 
-    console.log(`${LOG_TAG} [polling] JX3 已启动，开启 30 秒轮询（首次延迟 2 秒）`);
+    let disposed = false;
+    let intervalId: number | null = null;
+    let unlisten: (() => void) | null = null;
 
-    // JX3 启动后延迟 2 秒触发首次扫描，避免与活跃检测 IO 抢占
+    // JX3 启动后延迟 2 秒触发首次扫描（补扫监听启动前的数据），避免与活跃检测 IO 抢占
     const initialTimer = window.setTimeout(() => {
       void scanAll();
     }, 2000);
 
-    // 30 秒轮询
-    const intervalId = window.setInterval(() => {
-      const result = activeResultRef.current;
-      if (!result || !result.jx3Running) {
-        return;
+    const startPolling = () => {
+      console.log(`${LOG_TAG} [polling] 开启 30 秒轮询`);
+      intervalId = window.setInterval(() => {
+        const result = activeResultRef.current;
+        if (!result || !result.jx3Running) {
+          return;
+        }
+        void scanAll();
+      }, SCAN_POLL_INTERVAL_MS);
+    };
+
+    void (async () => {
+      try {
+        const unlistenFn = await dropScannerService.onScanUpdated(update => {
+          setLastScanAt(Date.now());
+          if (update.error) {
+            console.error(`${LOG_TAG} [watch] 账号 ${update.accountId} 扫描失败：${update.error}`);
+            setLastError(update.error);
+            return;
+          }
+          setLastError(null);
+          const instanceCount = update.instanceCount ?? 0;
+          if (instanceCount > 0) {
+            onRecordsUpdatedRef.current?.(instanceCount);
+          }
+          console.log(
+            `${LOG_TAG} [watch] 账号 ${update.accountId}（${update.triggers.join('/')}）：副本实例 ${instanceCount} 个，待确认记录 ${update.pendingRecords.length} 条`,
+          );
+        });
+        if (disposed) {
+          unlistenFn();
+          return;
+        }
+        unlisten = unlistenFn;
+
+        const status = await dropScannerService.startScanWatcher();
+        if (disposed) {
+          void dropScannerService.stopScanWatcher();
+          return;
+        }
+        console.log(
+          `${LOG_TAG} [watch] JX3 已启动，目录监听已开启：账号 ${status.accountCount} 个，目录 ${status.watchedDirCount} 个`,
+        );
+      } catch (err) {
+        if (disposed) return;
+        const message = err instanceof Error ? err.message : String(err);
+        console.warn(`${LOG_TAG} [watch] 目录监听启动失败，回退到轮询：${message}`);
+        startPolling();
       }
-      void scanAll();
-    }, SCAN_POLL_INTERVAL_MS);
+    })();
 
     return () => {
+      disposed = true;
       window.clearTimeout(initialTimer);
-      window.clearInterval(intervalId);
-      console.log(`${LOG_TAG} [polling] JX3 已退出，停止 30 秒轮询`);
+      if (intervalId !== null) {
+        window.clearInterval(intervalId);
+      }
+      unlisten?.();
+      dropScannerService.stopScanWatcher().catch(err => {
+        console.warn(`${LOG_TAG} [watch] 停止目录监听失败：${String(err)}`);
+      });
+      console.log(`${LOG_TAG} [watch] JX3 已退出，停止自动扫描`);
     };
   }, [activeResult?.jx3Running, scanAll, autoScanEnabled]);
 
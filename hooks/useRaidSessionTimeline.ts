@@ -0,0 +1,75 @@
+import { useEffect, useState } from 'react';
+import { dropScannerService, RaidSessionEvent } from '../services/dropScanner';
+
+/** 时间线日志前缀 */
+const LOG_TAG = '[SessionTimeline]';
+
+export interface UseRaidSessionTimelineReturn {
+  /** 进行中副本的时间线（记录 ID → 按时间排序的事件） */
+  timelines: Record<string, RaidSessionEvent[]>;
+  /** 已结束的记录 ID（收到 sessionEnded 事件） */
+  endedRecordIds: string[];
+}
+
+const eventKey = (event: RaidSessionEvent): string =>
+  `${event.kind}|${event.timeMs}|${event.bossName ?? ''}|${event.attemptIndex ?? 0}|${event.text ?? ''}`;
+
+/**
+ * 进行中副本实时时间线 Hook
+ *
+ * 订阅后端 `raid-session-event`（目录监听扫描后推送增量事件：开怪、击杀/灭团、掉落、拍卖、底薪），
+ * 按记录聚合为时间线。首次收到某条记录的事件时拉取一次完整时间线，避免错过应用启动前的事件。
+ */
+export const useRaidSessionTimeline = (): UseRaidSessionTimelineReturn => {
+  const [timelines, setTimelines] = useState<Record<string, RaidSessionEvent[]>>({});
+  const [endedRecordIds, setEndedRecordIds] = useState<string[]>([]);
+
+  useEffect(() => {
+    let disposed = false;
+    let unlisten: (() => void) | null = null;
+    const loadedRecordIds = new Set<string>();
+
+    const merge = (recordId: string, events: RaidSessionEvent[]) => {
+      setTimelines(prev => {
+        const existing = prev[recordId] ?? [];
+        const seen = new Set(existing.map(eventKey));
+        const merged = [...existing, ...events.filter(e => !seen.has(eventKey(e)))];
+        merged.sort((a, b) => a.timeMs - b.timeMs);
+        return { ...prev, [recordId]: merged };
+      });
+    };
+
+    void (async () => {
+      const unlistenFn = await dropScannerService.onSessionEvent(event => {
+        merge(event.recordId, [event]);
+        if (event.kind === 'sessionEnded') {
+          setEndedRecordIds(prev => (prev.includes(event.recordId) ? prev : [...prev, event.recordId]));
+          return;
+        }
+        if (!loadedRecordIds.has(event.recordId)) {
+          loadedRecordIds.add(event.recordId);
+          dropScannerService
+            .getRaidSessionTimeline(event.recordId)
+            .then(events => {
+              if (!disposed) merge(event.recordId, events);
+            })
+            .catch(err => {
+              console.warn(`${LOG_TAG} 加载记录 ${event.recordId} 时间线失败：${String(err)}`);
+            });
+        }
+      });
+      if (disposed) {
+        unlistenFn();
+        return;
+      }
+      unlisten = unlistenFn;
+    })();
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, []);
+
+  return { timelines, endedRecordIds };
+};
@@ -1,6 +1,6 @@
 import { invoke as tauriInvoke } from '@tauri-apps/api/core';
 import { mockInvoke } from './mockInvoke';
-import type { GameVersion, Season, RaidRecord, Account, Role, Raid, Config, TrialPlaceRecord, BaizhanRecord, InstanceType, RoleInstanceVisibility } from '../types';
+import type { GameVersion, Season, RaidRecord, Account, Role, Raid, Config, TrialPlaceRecord, BaizhanRecord, InstanceType, RoleInstanceVisibility, RecordQueryFilter, RecordQuerySort, RecordQueryPage, IncomeStatsGroupBy, IncomeStatsBucket, RecordHistoryEntry, TrashItem } from '../types';
 
 // 环境检测：如果没有注入 __TAURI_INTERNALS__ ，说明是在纯浏览器环境运行
 // __TAURI_INTERNALS__ 类型声明位于 src/tauri.d.ts
@@ -203,6 +203,99 @@ class DatabaseService {
     }
   }
 
+  /**
+   * 按筛选条件分页查询记录（过滤在 SQLite 中完成）。
+   *
+   * @param cursor 上一页返回的 nextCursor，首页不传
+   * @param limit 每页条数，默认 50，最大 500
+   */
+  async queryRecords(
+    filter: RecordQueryFilter,
+    sort?: RecordQuerySort,
+    cursor?: string,
+    limit?: number,
+  ): Promise<RecordQueryPage> {
+    await this.init();
+    const page = await invoke<{ records: string[]; nextCursor: string | null; total: number }>(
+      'db_query_records',
+      { filter, sort, cursor, limit },
+    );
+    return { ...page, records: page.records.map(item => JSON.parse(item) as RaidRecord) };
+  }
+
+  /**
+   * 按维度统计收入（副本 + 百战 + 试炼，排除待确认 / 已拒绝 / 进行中的副本记录）。
+   *
+   * @param startMs 时间下限（毫秒，闭区间），不传表示不限
+   * @param endMs 时间上限（毫秒，开区间），不传表示不限
+   */
+  async getIncomeStats(
+    groupBy: IncomeStatsGroupBy,
+    startMs?: number,
+    endMs?: number,
+  ): Promise<IncomeStatsBucket[]> {
+    await this.init();
+    return invoke<IncomeStatsBucket[]>('db_get_income_stats', { groupBy, startMs, endMs });
+  }
+
+  /** 查询记录的变更历史（最新在前） */
+  async getRecordHistory(recordId: string): Promise<RecordHistoryEntry[]> {
+    await this.init();
+    return invoke<RecordHistoryEntry[]>('db_get_record_history', { recordId });
+  }
+
+  /**
+   * 撤销一条历史变更，把记录恢复为该变更之前的版本（撤销本身也会写入历史）；
+   * 撤销新建会把记录移入回收站。
+   *
+   * @returns 被恢复的记录 ID
+   */
+  async undoRecordChange(historyId: number): Promise<string> {
+    await this.init();
+    return invoke<string>('db_undo_record_change', { historyId });
+  }
+
+  /** 查询回收站（最近删除在前） */
+  async getTrash(): Promise<TrashItem[]> {
+    await this.init();
+    return invoke<TrashItem[]>('db_get_trash');
+  }
+
+  /**
+   * 恢复回收站条目。恢复账号 / 角色后需重新加载账号列表，
+   * 否则旧状态再次保存（saveAccounts）时会把恢复的数据重新移入回收站。
+   */
+  async restoreTrashItem(trashId: number): Promise<void> {
+    await this.init();
+    await invoke('db_restore_trash_item', { trashId });
+  }
+
+  /**
+   * 彻底清除回收站条目，不传 trashId 时清空回收站。
+   *
+   * @returns 清除的条目数
+   */
+  async purgeTrash(trashId?: number): Promise<number> {
+    await this.init();
+    return invoke<number>('db_purge_trash', { trashId });
+  }
+
+  /** 回收站保留天数（超过后启动时自动清除，记录变更历史按同一天数清理，默认 30 天） */
+  async getTrashRetentionDays(): Promise<number> {
+    await this.init();
+    return invoke<number>('db_get_trash_retention_days');
+  }
+
+  /**
+   * 设置回收站保留天数（至少 1 天），并立即清除已过期的条目。
+   *
+   * @returns 清除的条目数
+   */
+  async setTrashRetentionDays(days: number): Promise<number> {
+    await this.init();
+    return invoke<number>('db_set_trash_retention_days', { days });
+  }
+
   async getPendingRecords(): Promise<RaidRecord[]> {
     await this.init();
     try {
@@ -1,6 +1,13 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { RaidRecord } from '../types';
 
+/** 目录监听扫描更新事件（与后端 RAID_SCAN_UPDATED_EVENT 对齐） */
+export const RAID_SCAN_UPDATED_EVENT = 'raid-scan-updated';
+
+/** 进行中副本时间线事件（与后端 RAID_SESSION_EVENT 对齐） */
+export const RAID_SESSION_EVENT = 'raid-session-event';
+
 /**
  * 单个账号的批量扫描结果（与后端 AccountScanResult 对齐）
  */
@@ -15,6 +22,177 @@ export interface AccountScanResult {
   error?: string;
 }
 
+/**
+ * 目录监听触发的单账号扫描结果（与后端 ScanWatchUpdate 对齐）
+ */
+export interface ScanWatchUpdate {
+  /** 茗伊账号 UID */
+  accountId: string;
+  /** 触发扫描的目录变化：jcl（新战斗日志）/ chatlog（聊天记录写入） */
+  triggers: Array<'jcl' | 'chatlog'>;
+  /** 扫描到的副本实例数量（成功时） */
+  instanceCount?: number;
+  /** 失败原因（失败时） */
+  error?: string;
+  /** 该账号当前的待确认记录（pending / scanning） */
+  pendingRecords: RaidRecord[];
+}
+
+/** 副本时间线事件类型（与后端 SessionEventKind 对齐） */
+export type RaidSessionEventKind =
+  | 'sessionStarted'
+  | 'bossPulled'
+  | 'bossKilled'
+  | 'bossWiped'
+  | 'itemDropped'
+  | 'itemAuctioned'
+  | 'salaryPosted'
+  | 'sessionEnded';
+
+/**
+ * 进行中副本的时间线事件（与后端 SessionEvent 对齐）
+ */
+export interface RaidSessionEvent {
+  recordId: string;
+  /** 茗伊账号 UID */
+  accountId: string;
+  raidName: string;
+  kind: RaidSessionEventKind;
+  /** 事件时间（毫秒） */
+  timeMs: number;
+  bossName?: string;
+  /** 该 BOSS 的第几次尝试 */
+  attemptIndex?: number;
+  durationMs?: number;
+  /** 灭团时 BOSS 最低血量百分比 */
+  bossHpPercent?: number;
+  itemName?: string;
+  /** 拍卖买家 / 分配接收者 */
+  buyer?: string;
+  /** 成交 / 底薪金额（铜） */
+  amount?: number;
+  /** chatlog 消息原文 */
+  text?: string;
+}
+
+/**
+ * 目录监听状态（与后端 ScanWatcherStatus 对齐）
+ */
+export interface ScanWatcherStatus {
+  running: boolean;
+  gameDirectory?: string;
+  /** 已监听的账号数量 */
+  accountCount: number;
+  /** 已监听的目录数量 */
+  watchedDirCount: number;
+}
+
+/** 参与收支计算的 chatlog 消息（与后端 ChatlogLineItem 对齐） */
+export interface ScanChatlogItem {
+  kind: 'itemDrop' | 'bossReward' | 'income' | 'salary' | 'expense' | 'allocation' | 'auctionItem';
+  /** 消息时间（秒级时间戳） */
+  timeSec: number;
+  channel: string;
+  text: string;
+  itemName?: string;
+  buyer?: string;
+  /** 金额（铜） */
+  amount?: number;
+  /** 是否计入统计（重复消息、被覆盖的底薪为 false） */
+  counted: boolean;
+  note?: string;
+}
+
+/** 单个 JCL 的击杀判定（与后端 JclVerdict 对齐） */
+export interface ScanJclVerdict {
+  /** JCL 内容是否解析成功 */
+  parsed: boolean;
+  bossName?: string;
+  fightStartMs: number;
+  fightEndMs: number;
+  isKill: boolean;
+  bossHpPercent?: number;
+  playerCount: number;
+  /** 击杀判定中成立的信号（如 treasure_box、death_notify） */
+  signals: string[];
+}
+
+/** 参与扫描的 JCL 文件（与后端 JclFileTrace 对齐） */
+export interface ScanJclFileTrace {
+  fileName: string;
+  timestamp: number;
+  raidDisplayName: string;
+  fileBossName: string;
+  /** 匹配到的副本（raid_id），未配置副本为空 */
+  matchedRaid?: string;
+  verdict?: ScanJclVerdict;
+  /** 所属副本实例下标，未进入任何实例（如拉托）为空 */
+  instanceIndex?: number;
+}
+
+/** 聚类得到的副本实例（与后端 InstanceTrace 对齐） */
+export interface ScanInstanceTrace {
+  raidDisplayName: string;
+  matchedRaid?: string;
+  raidName?: string;
+  startTime: number;
+  lastJclTime: number;
+  jclFiles: string[];
+  attemptFiles: string[];
+  bossesKilled: string[];
+  bossIds: string[];
+  chatlogStart: number;
+  chatlogEnd: number;
+  chatlogEndReason: 'nextJcl' | 'now' | 'lastJclPlus2h';
+  /** 每人底薪（铜） */
+  baseSalary?: number;
+  /** 收入（铜） */
+  totalIncome: number;
+  /** 支出（铜） */
+  totalExpense: number;
+  raidComplete: boolean;
+  recordStatus: 'scanning' | 'pending';
+  chatlogItems: ScanChatlogItem[];
+}
+
+/**
+ * 扫描解释结果（与后端 ScanTrace 对齐）
+ */
+export interface ScanTrace {
+  accountId: string;
+  startMs: number;
+  endMs: number;
+  jx3Running: boolean;
+  /** 判定使用的在线状态 */
+  roleOnline: boolean;
+  jclFiles: ScanJclFileTrace[];
+  chatlogFiles: string[];
+  chatlogRecordCount: number;
+  instances: ScanInstanceTrace[];
+  notes: string[];
+}
+
+/** 可重新扫描的字段（与后端 RESCAN_FIELDS 对齐） */
+export type RescanField = 'drops' | 'bosses' | 'goldIncome' | 'goldExpense';
+
+/** 单个字段的重新扫描对比（金额为铜） */
+export interface RescanFieldDiff {
+  field: RescanField;
+  current: unknown;
+  rescanned: unknown;
+  changed: boolean;
+}
+
+/**
+ * 记录重新扫描结果（与后端 RecordRescanDiff 对齐）
+ */
+export interface RecordRescanDiff {
+  recordId: string;
+  /** 重新扫描使用的茗伊账号 UID */
+  accountUid: string;
+  fields: RescanFieldDiff[];
+}
+
 /**
  * 副本掉落自动扫描服务（B 阶段）
  *
@@ -43,6 +221,34 @@ export const dropScannerService = {
     return invoke<AccountScanResult[]>('scan_all_active_raid_drops');
   },
 
+  /**
+   * 启动后端目录监听：账号 combat_logs 出现新 JCL 或 chatlog 写入时，
+   * 只扫描该账号并通过 RAID_SCAN_UPDATED_EVENT 推送结果（替代定时轮询）。
+   */
+  async startScanWatcher(): Promise<ScanWatcherStatus> {
+    return invoke<ScanWatcherStatus>('start_raid_scan_watcher');
+  },
+
+  /** 停止后端目录监听 */
+  async stopScanWatcher(): Promise<void> {
+    await invoke('stop_raid_scan_watcher');
+  },
+
+  /** 订阅目录监听扫描结果，返回取消订阅函数 */
+  async onScanUpdated(handler: (update: ScanWatchUpdate) => void): Promise<UnlistenFn> {
+    return listen<ScanWatchUpdate>(RAID_SCAN_UPDATED_EVENT, event => handler(event.payload));
+  },
+
+  /** 订阅进行中副本的时间线事件（目录监听扫描后推送增量），返回取消订阅函数 */
+  async onSessionEvent(handler: (event: RaidSessionEvent) => void): Promise<UnlistenFn> {
+    return listen<RaidSessionEvent>(RAID_SESSION_EVENT, event => handler(event.payload));
+  },
+
+  /** 查询记录的完整副本时间线（打开进行中副本时初始化） */
+  async getRaidSessionTimeline(recordId: string): Promise<RaidSessionEvent[]> {
+    return invoke<RaidSessionEvent[]>('get_raid_session_timeline', { recordId });
+  },
+
   /**
    * 按时间范围扫描所有账号的掉落记录（离线扫描，不依赖 JX3 进程状态）。
    *
@@ -67,6 +273,35 @@ export const dropScannerService = {
     });
   },
 
+  /**
+   * 解释指定账号在时间范围内的扫描过程（dry-run，不写入数据库）：
+   * JCL 击杀判定及信号、副本实例聚类、副本匹配、计入收支的 chatlog 消息。
+   *
+   * @param accountId 茗伊 uid
+   * @param startMs 副本时间下限（毫秒，0 表示不限）
+   * @param endMs 副本时间上限（毫秒，开区间，0 表示不限）
+   */
+  async explainScan(accountId: string, startMs: number, endMs: number): Promise<ScanTrace> {
+    return invoke<ScanTrace>('scan_raid_drops_explain', { accountId, startMs, endMs });
+  },
+
+  /**
+   * 重新扫描记录（含已确认记录），返回掉落 / BOSS / 收入 / 支出的逐字段对比，不修改记录。
+   * 用于击杀判定或收支提取修复后核对旧数据。
+   */
+  async rescanRecord(recordId: string): Promise<RecordRescanDiff> {
+    return invoke<RecordRescanDiff>('rescan_record', { recordId });
+  },
+
+  /**
+   * 接受重新扫描的指定字段，未选中的字段保留记录现有数据（含手动修改）。
+   *
+   * @returns 接受后的逐字段对比
+   */
+  async acceptRescanFields(recordId: string, fields: RescanField[]): Promise<RecordRescanDiff> {
+    return invoke<RecordRescanDiff>('accept_rescan_fields', { recordId, fields });
+  },
+
   /**
    * 确认一条 pending 记录，将其 status 更新为 'confirmed'。
    * confirmed 记录与手动记录完全等价参与 CD 计算。
@@ -85,6 +85,21 @@ export async function mockInvoke<T>(cmd: string, args?: Record<string, unknown>)
         case 'db_get_records':
           result = MOCK_RECORDS.map(r => JSON.stringify(r));
           break;
+        case 'db_query_records':
+          result = { records: MOCK_RECORDS.map(r => JSON.stringify(r)), nextCursor: null, total: MOCK_RECORDS.length };
+          break;
+        case 'db_get_income_stats':
+          result = [];
+          break;
+        case 'db_get_record_history':
+          result = [];
+          break;
+        case 'db_get_trash':
+          result = [];
+          break;
+        case 'db_get_trash_retention_days':
+          result = 30;
+          break;
         case 'db_get_raids':
           result = [];
           break;
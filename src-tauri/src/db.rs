@@ -11,6 +11,11 @@ use crate::runtime_mode::{self, RuntimeMode};
 
 mod migration;
 pub mod migrations;
+pub mod income_stats;
+pub mod raid_record;
+pub mod record_history;
+pub mod trash;
+pub mod record_query;
 
 #[cfg(test)]
 mod upgrade_tests;
@@ -22,7 +27,7 @@ const LOG_FILE_NAME: &str = "jx3-raid-manager.log";
 const LEGACY_DATA_DIR_BOOTSTRAP_FILE: &str = "data-dir.json";
 
 /// 当前数据库 schema 版本
-pub const CURRENT_SCHEMA_VERSION: i32 = 17;
+pub const CURRENT_SCHEMA_VERSION: i32 = 29;
 
 /// 数据库连接单例
 static DB_INITIALIZED: Mutex<bool> = Mutex::new(false);
@@ -604,62 +609,6 @@ fn format_directory_delete_message(target_type: &str, display_path: &str, delete
     }
 }
 
-#[derive(Debug)]
-struct RecordLookupMetadata {
-    raid_name: Option<String>,
-    account_id: Option<String>,
-    role_id: Option<String>,
-    record_date: Option<i64>,
-    record_type: String,
-    #[allow(dead_code)]
-    source: String,
-    #[allow(dead_code)]
-    status: String,
-    #[allow(dead_code)]
-    drops: Option<String>,
-    #[allow(dead_code)]
-    jcl_files: Option<String>,
-}
-
-fn record_lookup_metadata_from_json(record: &serde_json::Value) -> RecordLookupMetadata {
-    RecordLookupMetadata {
-        raid_name: record["raidName"].as_str().map(|value| value.to_string()),
-        account_id: record["accountId"].as_str().map(|value| value.to_string()),
-        role_id: record["roleId"].as_str().map(|value| value.to_string()),
-        record_date: parse_record_date(&record["date"]),
-        record_type: record["type"]
-            .as_str()
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| "raid".to_string()),
-        source: record["source"]
-            .as_str()
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| "manual".to_string()),
-        status: record["status"]
-            .as_str()
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| "confirmed".to_string()),
-        drops: record.get("drops")
-            .filter(|v| !v.is_null())
-            .map(|v| v.to_string()),
-        jcl_files: record.get("jclFiles")
-            .filter(|v| !v.is_null())
-            .map(|v| v.to_string()),
-    }
-}
-
-fn parse_record_date(value: &serde_json::Value) -> Option<i64> {
-    value.as_i64().or_else(|| {
-        value.as_str().and_then(|date| {
-            date.parse::<i64>().ok().or_else(|| {
-                chrono::DateTime::parse_from_rfc3339(date)
-                    .ok()
-                    .map(|parsed| parsed.timestamp_millis())
-            })
-        })
-    })
-}
-
 #[tauri::command]
 pub fn db_delete_directory(
     path: String,
@@ -807,6 +756,14 @@ pub fn init_db() -> Result<Connection, String> {
     ensure_raid_bosses_table(&conn)?;
     ensure_records_columns(&conn)?;
     ensure_jcl_cache_table(&conn)?;
+    ensure_raid_attempts_table(&conn)?;
+    ensure_raid_boss_rules_table(&conn)?;
+    ensure_record_chatlog_items_table(&conn)?;
+    ensure_record_roster_table(&conn)?;
+    ensure_record_auction_ledger_table(&conn)?;
+    ensure_record_salary_sheets_table(&conn)?;
+    ensure_record_history_table(&conn)?;
+    ensure_trash_items_table(&conn)?;
     ensure_drop_items_table(&conn)?;
     ensure_critical_columns(&conn)?;
     migration::init_static_raids(&conn)?;
@@ -882,6 +839,14 @@ pub fn init_db_skip_migration() -> Result<Connection, String> {
     ensure_raid_bosses_table(&conn)?;
     ensure_records_columns(&conn)?;
     ensure_jcl_cache_table(&conn)?;
+    ensure_raid_attempts_table(&conn)?;
+    ensure_raid_boss_rules_table(&conn)?;
+    ensure_record_chatlog_items_table(&conn)?;
+    ensure_record_roster_table(&conn)?;
+    ensure_record_auction_ledger_table(&conn)?;
+    ensure_record_salary_sheets_table(&conn)?;
+    ensure_record_history_table(&conn)?;
+    ensure_trash_items_table(&conn)?;
     ensure_drop_items_table(&conn)?;
     ensure_critical_columns(&conn)?;
     migration::init_static_raids(&conn)?;
@@ -895,6 +860,18 @@ pub fn init_db_skip_migration() -> Result<Connection, String> {
     Ok(conn)
 }
 
+/// 以只读方式打开本机数据库（供 `--replay-jcl` 等诊断命令使用）
+///
+/// 不建表、不执行数据目录迁移与结构升级，也不写入任何数据；数据库需已由应用正常启动升级到当前版本。
+pub fn open_db_read_only() -> Result<Connection, String> {
+    let path = get_db_path()?;
+    if !path.exists() {
+        return Err(format!("数据库文件不存在: {}", path.display()));
+    }
+    Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("只读打开数据库失败 ({}): {}", path.display(), e))
+}
+
 /// 使用指定路径初始化数据库（用于测试）
 ///
 /// 此函数用于测试场景，允许指定数据库路径进行初始化和升级。
@@ -1047,6 +1024,9 @@ fn validate_post_upgrade(conn: &Connection) -> Result<(), String> {
         "raid_role_visibility",
         "trial_records", "baizhan_records",
         "game_versions", "seasons",
+        "raid_attempts", "raid_boss_rules",
+        "record_chatlog_items", "record_roster", "record_auction_ledger",
+        "record_salary_sheets", "record_history", "trash_items",
     ];
 
     for table_name in &expected_tables {
@@ -1313,6 +1293,7 @@ fn ensure_records_columns(conn: &Connection) -> Result<(), String> {
         ("jcl_files", "TEXT"),
         ("created_at", "TEXT"),
         ("updated_at", "TEXT"),
+        ("run_group_id", "TEXT"),
     ];
 
     for (col_name, col_type) in &columns_to_add {
@@ -1342,6 +1323,7 @@ fn ensure_records_columns(conn: &Connection) -> Result<(), String> {
         r#"
         CREATE INDEX IF NOT EXISTS idx_records_status ON records(status);
         CREATE INDEX IF NOT EXISTS idx_records_source ON records(source);
+        CREATE INDEX IF NOT EXISTS idx_records_run_group_id ON records(run_group_id);
         "#,
     )
     .map_err(|e| e.to_string())?;
@@ -1349,7 +1331,7 @@ fn ensure_records_columns(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
-/// 确保 jcl_cache 表存在（V14 新增，JCL 解析缓存）
+/// 确保 jcl_cache 表存在（V14 新增，JCL 解析缓存；V18 新增 boss_hp_percent 列；V20 新增 analyzer_version 列；V22 新增 players 列）
 fn ensure_jcl_cache_table(conn: &Connection) -> Result<(), String> {
     conn.execute_batch(
         r#"
@@ -1360,11 +1342,226 @@ fn ensure_jcl_cache_table(conn: &Connection) -> Result<(), String> {
             fight_start_ms INTEGER NOT NULL,
             fight_end_ms INTEGER NOT NULL,
             is_kill INTEGER NOT NULL,
-            cached_at TEXT NOT NULL
+            cached_at TEXT NOT NULL,
+            boss_hp_percent REAL,
+            analyzer_version INTEGER NOT NULL DEFAULT 0,
+            players TEXT NOT NULL DEFAULT '[]'
         );
         "#,
     )
     .map_err(|e| format!("创建 jcl_cache 表失败: {}", e))?;
+
+    let has_hp_col: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('jcl_cache') WHERE name='boss_hp_percent'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_hp_col {
+        conn.execute("ALTER TABLE jcl_cache ADD COLUMN boss_hp_percent REAL", [])
+            .map_err(|e| e.to_string())?;
+        log::info!("[ensure_jcl_cache_table] 已添加 jcl_cache.boss_hp_percent 列");
+    }
+
+    if !column_exists(conn, "jcl_cache", "analyzer_version")? {
+        conn.execute(
+            "ALTER TABLE jcl_cache ADD COLUMN analyzer_version INTEGER NOT NULL DEFAULT 0",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+        log::info!("[ensure_jcl_cache_table] 已添加 jcl_cache.analyzer_version 列");
+    }
+
+    if !column_exists(conn, "jcl_cache", "players")? {
+        conn.execute(
+            "ALTER TABLE jcl_cache ADD COLUMN players TEXT NOT NULL DEFAULT '[]'",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+        log::info!("[ensure_jcl_cache_table] 已添加 jcl_cache.players 列");
+    }
+    Ok(())
+}
+
+/// 确保 raid_attempts 表存在（V18 新增，BOSS 战斗尝试记录）
+fn ensure_raid_attempts_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS raid_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            boss_name TEXT NOT NULL,
+            jcl_file TEXT NOT NULL,
+            attempt_index INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            end_time INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            is_kill INTEGER NOT NULL,
+            boss_hp_percent REAL,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, jcl_file)
+        );
+        CREATE INDEX IF NOT EXISTS idx_raid_attempts_record_id ON raid_attempts(record_id);
+        "#,
+    )
+    .map_err(|e| format!("创建 raid_attempts 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 raid_boss_rules 表存在（V19 新增，BOSS 击杀判定规则）
+fn ensure_raid_boss_rules_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS raid_boss_rules (
+            raid_name TEXT NOT NULL,
+            boss_name TEXT NOT NULL,
+            aliases TEXT NOT NULL DEFAULT '[]',
+            companions TEXT NOT NULL DEFAULT '[]',
+            require_companion_death INTEGER NOT NULL DEFAULT 1,
+            chest_names TEXT NOT NULL DEFAULT '[]',
+            clone_handling TEXT NOT NULL DEFAULT 'sameName',
+            fight_time_signal INTEGER NOT NULL DEFAULT 0,
+            dead_face_direction INTEGER DEFAULT 192,
+            notes TEXT NOT NULL DEFAULT '',
+            is_static INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (raid_name, boss_name)
+        );
+        "#,
+    )
+    .map_err(|e| format!("创建 raid_boss_rules 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 record_chatlog_items 表存在（V21 新增，自动扫描记录的 chatlog 收支明细）
+fn ensure_record_chatlog_items_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_chatlog_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            time_sec INTEGER NOT NULL,
+            channel TEXT NOT NULL,
+            text TEXT NOT NULL,
+            item_name TEXT,
+            buyer TEXT,
+            gold INTEGER,
+            gold_copper INTEGER,
+            counted INTEGER NOT NULL DEFAULT 1,
+            note TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, seq)
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_chatlog_items_record_id ON record_chatlog_items(record_id);
+        "#,
+    )
+    .map_err(|e| format!("创建 record_chatlog_items 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 record_roster 表存在（V22 新增，自动扫描记录的团队名单）
+fn ensure_record_roster_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_roster (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            server TEXT NOT NULL DEFAULT '',
+            force_id INTEGER,
+            kungfu_id INTEGER,
+            sources TEXT NOT NULL DEFAULT '',
+            own_role_id TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, name, server)
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_roster_record_id ON record_roster(record_id);
+        CREATE INDEX IF NOT EXISTS idx_record_roster_name ON record_roster(name);
+        "#,
+    )
+    .map_err(|e| format!("创建 record_roster 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 record_auction_ledger 表存在（V24 新增，自动扫描记录的团队拍卖账本）
+fn ensure_record_auction_ledger_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_auction_ledger (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            time_sec INTEGER NOT NULL,
+            buyer TEXT NOT NULL,
+            item_name TEXT NOT NULL,
+            gold INTEGER NOT NULL,
+            gold_copper INTEGER,
+            source TEXT NOT NULL DEFAULT 'auction',
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, seq)
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_auction_ledger_record_id ON record_auction_ledger(record_id);
+        "#,
+    )
+    .map_err(|e| format!("创建 record_auction_ledger 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 record_salary_sheets 表存在（V25 新增，团长模式工资分配表）
+fn ensure_record_salary_sheets_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_salary_sheets (
+            record_id TEXT PRIMARY KEY,
+            input TEXT NOT NULL,
+            sheet TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .map_err(|e| format!("创建 record_salary_sheets 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 record_history 表存在（V27 新增，记录变更历史）
+fn ensure_record_history_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            before_data TEXT,
+            after_data TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_history_record_id ON record_history(record_id);
+        "#,
+    )
+    .map_err(|e| format!("创建 record_history 表失败: {}", e))?;
+    Ok(())
+}
+
+/// 确保 trash_items 表存在（V28 新增，回收站）
+fn ensure_trash_items_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS trash_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            label TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_trash_items_deleted_at ON trash_items(deleted_at);
+        "#,
+    )
+    .map_err(|e| format!("创建 trash_items 表失败: {}", e))?;
     Ok(())
 }
 
@@ -1819,7 +2016,8 @@ fn create_latest_schema(conn: &Connection) -> Result<(), String> {
             drops TEXT,
             jcl_files TEXT,
             created_at TEXT,
-            updated_at TEXT
+            updated_at TEXT,
+            run_group_id TEXT
         );
 
         CREATE TABLE IF NOT EXISTS config (
@@ -1847,7 +2045,10 @@ fn create_latest_schema(conn: &Connection) -> Result<(), String> {
             fight_start_ms INTEGER NOT NULL,
             fight_end_ms INTEGER NOT NULL,
             is_kill INTEGER NOT NULL,
-            cached_at TEXT NOT NULL
+            cached_at TEXT NOT NULL,
+            boss_hp_percent REAL,
+            analyzer_version INTEGER NOT NULL DEFAULT 0,
+            players TEXT NOT NULL DEFAULT '[]'
         );
 
         CREATE TABLE IF NOT EXISTS equipments (
@@ -2008,7 +2209,9 @@ fn create_latest_schema(conn: &Connection) -> Result<(), String> {
             gold_expense INTEGER DEFAULT 0,
             notes TEXT,
             record_type TEXT DEFAULT 'baizhan',
-            updated_at TEXT
+            updated_at TEXT,
+            gold_income_copper INTEGER,
+            gold_expense_copper INTEGER
         );
 
         -- ===== 索引 =====
@@ -2040,10 +2243,129 @@ fn create_latest_schema(conn: &Connection) -> Result<(), String> {
         -- 复合索引：覆盖手工记录查询
         CREATE INDEX IF NOT EXISTS idx_records_manual_cd
         ON records(account_id, status, record_date);
+        -- V23: 多开同团分组
+        CREATE INDEX IF NOT EXISTS idx_records_run_group_id ON records(run_group_id);
 
         CREATE INDEX IF NOT EXISTS idx_seasons_version_id ON seasons(version_id);
         CREATE INDEX IF NOT EXISTS idx_raids_season_id ON raids(season_id);
 
+        -- ===== V18: BOSS 战斗尝试记录 =====
+        CREATE TABLE IF NOT EXISTS raid_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            boss_name TEXT NOT NULL,
+            jcl_file TEXT NOT NULL,
+            attempt_index INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            end_time INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            is_kill INTEGER NOT NULL,
+            boss_hp_percent REAL,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, jcl_file)
+        );
+        CREATE INDEX IF NOT EXISTS idx_raid_attempts_record_id ON raid_attempts(record_id);
+
+        -- ===== V19: BOSS 击杀判定规则 =====
+        CREATE TABLE IF NOT EXISTS raid_boss_rules (
+            raid_name TEXT NOT NULL,
+            boss_name TEXT NOT NULL,
+            aliases TEXT NOT NULL DEFAULT '[]',
+            companions TEXT NOT NULL DEFAULT '[]',
+            require_companion_death INTEGER NOT NULL DEFAULT 1,
+            chest_names TEXT NOT NULL DEFAULT '[]',
+            clone_handling TEXT NOT NULL DEFAULT 'sameName',
+            fight_time_signal INTEGER NOT NULL DEFAULT 0,
+            dead_face_direction INTEGER DEFAULT 192,
+            notes TEXT NOT NULL DEFAULT '',
+            is_static INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (raid_name, boss_name)
+        );
+
+        -- ===== V21: chatlog 收支明细 =====
+        CREATE TABLE IF NOT EXISTS record_chatlog_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            time_sec INTEGER NOT NULL,
+            channel TEXT NOT NULL,
+            text TEXT NOT NULL,
+            item_name TEXT,
+            buyer TEXT,
+            gold INTEGER,
+            gold_copper INTEGER,
+            counted INTEGER NOT NULL DEFAULT 1,
+            note TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, seq)
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_chatlog_items_record_id ON record_chatlog_items(record_id);
+
+        -- ===== V22: 副本团队名单 =====
+        CREATE TABLE IF NOT EXISTS record_roster (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            server TEXT NOT NULL DEFAULT '',
+            force_id INTEGER,
+            kungfu_id INTEGER,
+            sources TEXT NOT NULL DEFAULT '',
+            own_role_id TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, name, server)
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_roster_record_id ON record_roster(record_id);
+        CREATE INDEX IF NOT EXISTS idx_record_roster_name ON record_roster(name);
+
+        -- ===== V24: 团队拍卖账本 =====
+        CREATE TABLE IF NOT EXISTS record_auction_ledger (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            time_sec INTEGER NOT NULL,
+            buyer TEXT NOT NULL,
+            item_name TEXT NOT NULL,
+            gold INTEGER NOT NULL,
+            gold_copper INTEGER,
+            source TEXT NOT NULL DEFAULT 'auction',
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, seq)
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_auction_ledger_record_id ON record_auction_ledger(record_id);
+
+        -- ===== V25: 团长模式工资分配表 =====
+        CREATE TABLE IF NOT EXISTS record_salary_sheets (
+            record_id TEXT PRIMARY KEY,
+            input TEXT NOT NULL,
+            sheet TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        -- ===== V27: 记录变更历史 =====
+        CREATE TABLE IF NOT EXISTS record_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            before_data TEXT,
+            after_data TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_history_record_id ON record_history(record_id);
+
+        -- ===== V28: 回收站 =====
+        CREATE TABLE IF NOT EXISTS trash_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            label TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_trash_items_deleted_at ON trash_items(deleted_at);
+
         -- ===== V14: 应用配置表（key-value 存储） =====
         CREATE TABLE IF NOT EXISTS app_config (
             key TEXT PRIMARY KEY,
@@ -2079,6 +2401,15 @@ fn create_latest_schema(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
+/// 测试用内存数据库：与全新安装相同的最新结构（含外键约束）
+#[cfg(test)]
+pub(crate) fn open_test_db() -> Connection {
+    let conn = Connection::open_in_memory().expect("打开内存数据库失败");
+    apply_connection_pragmas(&conn);
+    create_latest_schema(&conn).expect("创建最新数据库结构失败");
+    conn
+}
+
 /// 检查 localStorage 迁移是否已完成
 #[tauri::command]
 pub fn db_is_local_storage_migrated() -> Result<bool, String> {
@@ -2444,9 +2775,11 @@ pub fn db_get_trial_records() -> Result<String, String> {
 
 #[tauri::command]
 pub fn db_delete_trial_record(id: String) -> Result<(), String> {
-    let conn = init_db().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM trial_records WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    let mut conn = init_db().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // 移入回收站，可在回收站中恢复
+    trash::trash_trial_record(&tx, &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -2467,11 +2800,28 @@ pub struct BaizhanRecord {
     pub gold_income: i64,
     #[serde(rename = "goldExpense", default)]
     pub gold_expense: Option<i64>,
+    /// 铜精度收入，唯一的存储金额；gold_income 列由它派生
+    #[serde(rename = "goldIncomeCopper", default)]
+    pub gold_income_copper: Option<i64>,
+    #[serde(rename = "goldExpenseCopper", default)]
+    pub gold_expense_copper: Option<i64>,
     pub notes: Option<String>,
     #[serde(rename = "type", default = "default_baizhan_type")]
     pub record_type: String,
 }
 
+impl BaizhanRecord {
+    /// 铜精度收入 / 支出（铜字段为唯一的存储金额，缺失时按金字段换算）
+    fn money_amounts(&self) -> (crate::money::Money, crate::money::Money) {
+        let income = crate::money::stored_money(self.gold_income as f64, self.gold_income_copper);
+        let expense = crate::money::stored_money(
+            self.gold_expense.unwrap_or(0) as f64,
+            self.gold_expense_copper,
+        );
+        (income, expense)
+    }
+}
+
 fn default_baizhan_type() -> String {
     "baizhan".to_string()
 }
@@ -2479,14 +2829,16 @@ fn default_baizhan_type() -> String {
 #[tauri::command]
 pub fn db_add_baizhan_record(record: String) -> Result<(), String> {
     let item: BaizhanRecord = serde_json::from_str(&record).map_err(|e| e.to_string())?;
+    let (income, expense) = item.money_amounts();
     let conn = init_db().map_err(|e| e.to_string())?;
     let timestamp = get_local_timestamp();
 
     conn.execute(
         "INSERT INTO baizhan_records (
             id, account_id, role_id, role_name, server,
-            date, gold_income, gold_expense, notes, record_type, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            date, gold_income, gold_expense, gold_income_copper, gold_expense_copper,
+            notes, record_type, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
          ON CONFLICT(id) DO UPDATE SET
             account_id = excluded.account_id,
             role_id = excluded.role_id,
@@ -2495,6 +2847,8 @@ pub fn db_add_baizhan_record(record: String) -> Result<(), String> {
             date = excluded.date,
             gold_income = excluded.gold_income,
             gold_expense = excluded.gold_expense,
+            gold_income_copper = excluded.gold_income_copper,
+            gold_expense_copper = excluded.gold_expense_copper,
             notes = excluded.notes,
             record_type = excluded.record_type,
             updated_at = excluded.updated_at",
@@ -2505,8 +2859,10 @@ pub fn db_add_baizhan_record(record: String) -> Result<(), String> {
             item.role_name,
             item.server,
             item.date,
-            item.gold_income,
-            item.gold_expense.unwrap_or(0),
+            income.gold(),
+            expense.gold(),
+            income.copper(),
+            expense.copper(),
             item.notes,
             item.record_type,
             timestamp
@@ -2523,7 +2879,8 @@ pub fn db_get_baizhan_records() -> Result<String, String> {
     let mut stmt = conn
         .prepare(
             "SELECT id, account_id, role_id, role_name, server,
-                    date, gold_income, gold_expense, notes, record_type
+                    date, gold_income, gold_expense, notes, record_type,
+                    gold_income_copper, gold_expense_copper
              FROM baizhan_records
              ORDER BY date DESC",
         )
@@ -2540,6 +2897,8 @@ pub fn db_get_baizhan_records() -> Result<String, String> {
                 date: row.get(5)?,
                 gold_income: row.get(6)?,
                 gold_expense: row.get(7)?,
+                gold_income_copper: row.get(10)?,
+                gold_expense_copper: row.get(11)?,
                 notes: row.get(8)?,
                 record_type: row
                     .get::<_, Option<String>>(9)?
@@ -2560,23 +2919,26 @@ pub fn db_get_baizhan_records() -> Result<String, String> {
 
 #[tauri::command]
 pub fn db_delete_baizhan_record(id: String) -> Result<(), String> {
-    let conn = init_db().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM baizhan_records WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    let mut conn = init_db().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // 移入回收站，可在回收站中恢复
+    trash::trash_baizhan_record(&tx, &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
 pub fn db_update_baizhan_record(record: String) -> Result<(), String> {
     let item: BaizhanRecord = serde_json::from_str(&record).map_err(|e| e.to_string())?;
+    let (income, expense) = item.money_amounts();
     let conn = init_db().map_err(|e| e.to_string())?;
     let timestamp = get_local_timestamp();
 
     conn.execute(
         "UPDATE baizhan_records SET
             account_id = ?, role_id = ?, role_name = ?, server = ?,
-            date = ?, gold_income = ?, gold_expense = ?, notes = ?,
-            record_type = ?, updated_at = ?
+            date = ?, gold_income = ?, gold_expense = ?, gold_income_copper = ?,
+            gold_expense_copper = ?, notes = ?, record_type = ?, updated_at = ?
          WHERE id = ?",
         params![
             item.account_id,
@@ -2584,8 +2946,10 @@ pub fn db_update_baizhan_record(record: String) -> Result<(), String> {
             item.role_name,
             item.server,
             item.date,
-            item.gold_income,
-            item.gold_expense.unwrap_or(0),
+            income.gold(),
+            expense.gold(),
+            income.copper(),
+            expense.copper(),
             item.notes,
             item.record_type,
             timestamp,
@@ -2654,38 +3018,18 @@ pub fn db_save_accounts(accounts: String) -> Result<(), String> {
     let incoming_account_set: HashSet<String> = incoming_account_ids.into_iter().collect();
     let incoming_role_set: HashSet<String> = incoming_role_ids.into_iter().collect();
 
-    // 4. 删除不在传入数据中的账号及其关联数据（保留历史记录）
+    // 4. 不在传入数据中的账号及其关联数据移入回收站（保留历史记录）
     for account_id in &existing_account_ids {
         if !incoming_account_set.contains(account_id) {
-            let roles_to_delete: Vec<String> = {
-                let mut stmt = tx
-                    .prepare("SELECT id FROM roles WHERE account_id = ?")
-                    .map_err(|e| e.to_string())?;
-                let rows = stmt.query_map(params![account_id], |row| row.get(0)).map_err(|e| e.to_string())?;
-                rows.filter_map(|r| r.ok()).collect()
-            };
-            for role_id in &roles_to_delete {
-                tx.execute("DELETE FROM role_instance_visibility WHERE role_id = ?", params![role_id])
-                    .map_err(|e| e.to_string())?;
-                tx.execute("DELETE FROM raid_role_visibility WHERE role_id = ?", params![role_id])
-                    .map_err(|e| e.to_string())?;
-            }
-            tx.execute("DELETE FROM roles WHERE account_id = ?", params![account_id])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM accounts WHERE id = ?", params![account_id])
-                .map_err(|e| e.to_string())?;
+            trash::trash_account(&tx, account_id)?;
         }
     }
 
-    // 5. 删除不在传入数据中的角色及其关联数据（保留历史记录）
+    // 5. 不在传入数据中的角色及其关联数据移入回收站（保留历史记录）
+    //    随账号一起进入回收站的角色已不存在，trash_role 直接跳过
     for role_id in &existing_role_ids {
         if !incoming_role_set.contains(role_id) {
-            tx.execute("DELETE FROM role_instance_visibility WHERE role_id = ?", params![role_id])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM raid_role_visibility WHERE role_id = ?", params![role_id])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM roles WHERE id = ?", params![role_id])
-                .map_err(|e| e.to_string())?;
+            trash::trash_role(&tx, role_id)?;
         }
     }
 
@@ -3282,25 +3626,8 @@ pub fn db_delete_account_structured(account_id: String) -> Result<(), String> {
     let mut conn = init_db().map_err(|e| e.to_string())?;
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    let role_ids: Vec<String> = {
-        let mut stmt = tx
-            .prepare("SELECT id FROM roles WHERE account_id = ?")
-            .map_err(|e| e.to_string())?;
-        let rows = stmt.query_map(params![&account_id], |row| row.get(0)).map_err(|e| e.to_string())?;
-        rows.filter_map(|r| r.ok()).collect()
-    };
-
-    for role_id in &role_ids {
-        tx.execute("DELETE FROM role_instance_visibility WHERE role_id = ?", params![role_id])
-            .map_err(|e| e.to_string())?;
-        tx.execute("DELETE FROM raid_role_visibility WHERE role_id = ?", params![role_id])
-            .map_err(|e| e.to_string())?;
-    }
-
-    tx.execute("DELETE FROM roles WHERE account_id = ?", params![account_id])
-        .map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM accounts WHERE id = ?", params![account_id])
-        .map_err(|e| e.to_string())?;
+    // 账号、角色及可见性设置整体移入回收站，副本记录保留
+    trash::trash_account(&tx, &account_id)?;
 
     tx.commit().map_err(|e| e.to_string())?;
     Ok(())
@@ -3311,12 +3638,8 @@ pub fn db_delete_role_structured(role_id: String) -> Result<(), String> {
     let mut conn = init_db().map_err(|e| e.to_string())?;
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    tx.execute("DELETE FROM role_instance_visibility WHERE role_id = ?", params![role_id])
-        .map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM raid_role_visibility WHERE role_id = ?", params![role_id])
-        .map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM roles WHERE id = ?", params![role_id])
-        .map_err(|e| e.to_string())?;
+    // 角色及可见性设置移入回收站，副本记录保留
+    trash::trash_role(&tx, &role_id)?;
 
     tx.commit().map_err(|e| e.to_string())?;
     Ok(())
@@ -3361,36 +3684,33 @@ pub fn db_save_records(records: String) -> Result<(), String> {
     let mut conn = init_db().map_err(|e| e.to_string())?;
     let parsed: Vec<serde_json::Value> =
         serde_json::from_str(&records).map_err(|e| e.to_string())?;
+    // 先整体校验，任一记录不合法则不写入。
+    // 升级前遗留的不合法记录（V29 迁移无法修复的）若前端原样回传，跳过而不阻塞其他记录保存
+    let mut records = Vec::with_capacity(parsed.len());
+    for value in parsed {
+        match raid_record::RaidRecord::from_value(value.clone()) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                let id = value.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+                let stored: Option<serde_json::Value> = conn
+                    .query_row("SELECT data FROM records WHERE id = ?1", params![id], |row| {
+                        row.get::<_, String>(0)
+                    })
+                    .ok()
+                    .and_then(|data| serde_json::from_str(&data).ok());
+                if stored.as_ref() != Some(&value) {
+                    return Err(e);
+                }
+                log::warn!("[db_save_records] 跳过未修改的不合法记录 {}: {}", id, e);
+            }
+        }
+    }
 
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    // 增量同步：使用 INSERT ... ON CONFLICT DO UPDATE 更新记录
-    for record in parsed {
-        let id = record["id"].as_str().unwrap_or_default().to_string();
-        if id.is_empty() {
-            continue;
-        }
-        let metadata = record_lookup_metadata_from_json(&record);
-        tx.execute(
-            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, record_type)
-             VALUES (?, ?, ?, ?, ?, ?, ?)
-             ON CONFLICT(id) DO UPDATE SET
-                data = excluded.data,
-                account_id = excluded.account_id,
-                role_id = excluded.role_id,
-                record_date = excluded.record_date,
-                record_type = excluded.record_type",
-            params![
-                id,
-                record.to_string(),
-                metadata.raid_name,
-                metadata.account_id,
-                metadata.role_id,
-                metadata.record_date,
-                metadata.record_type,
-            ],
-        )
-        .map_err(|e| e.to_string())?;
+    // 增量同步：使用 INSERT ... ON CONFLICT DO UPDATE 更新记录，内容有变化的记录写入变更历史
+    for record in &records {
+        record_history::upsert_record_with_history(&tx, record, record_history::ACTION_UPDATE)?;
     }
 
     tx.commit().map_err(|e| e.to_string())?;
@@ -3980,41 +4300,17 @@ pub fn db_save_config(config: String) -> Result<(), String> {
 #[tauri::command]
 pub fn db_add_record(record: String) -> Result<(), String> {
     let conn = init_db().map_err(|e| e.to_string())?;
-    let parsed: serde_json::Value = serde_json::from_str(&record).map_err(|e| e.to_string())?;
-
-    let id = parsed["id"].as_str().unwrap_or_default().to_string();
-
-    let metadata = record_lookup_metadata_from_json(&parsed);
-
-    conn.execute(
-        "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, record_type)
-         VALUES (?, ?, ?, ?, ?, ?, ?)
-         ON CONFLICT(id) DO UPDATE SET
-            data = excluded.data,
-            account_id = excluded.account_id,
-            role_id = excluded.role_id,
-            record_date = excluded.record_date,
-            record_type = excluded.record_type",
-        params![
-            id,
-            record,
-            metadata.raid_name,
-            metadata.account_id,
-            metadata.role_id,
-            metadata.record_date,
-            metadata.record_type,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
-
-    Ok(())
+    let record = raid_record::RaidRecord::from_json_str(&record)?;
+    record_history::upsert_record_with_history(&conn, &record, record_history::ACTION_UPDATE)
 }
 
 #[tauri::command]
 pub fn db_delete_record(record_id: String) -> Result<(), String> {
-    let conn = init_db().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM records WHERE id = ?", params![record_id])
-        .map_err(|e| e.to_string())?;
+    let mut conn = init_db().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // 记录及战斗尝试、chatlog 明细、名单、拍卖账本、工资表一并移入回收站，并写入变更历史
+    trash::trash_record(&tx, &record_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -4884,8 +5180,8 @@ mod tests {
             "accounts", "app_config", "baizhan_records", "cache", "equipments",
             "favorite_raids", "game_versions", "instance_types",
             "jcl_cache",
-            "raid_bosses", "raid_role_visibility", "raids",
-            "records", "role_instance_visibility", "roles",
+            "raid_attempts", "raid_boss_rules", "raid_bosses", "raid_role_visibility", "raids",
+            "record_auction_ledger", "record_chatlog_items", "record_roster", "record_salary_sheets", "records", "role_instance_visibility", "roles",
             "schema_versions", "seasons", "trial_records",
         ];
 
@@ -4989,8 +5285,8 @@ mod tests {
             "accounts", "app_config", "baizhan_records", "cache", "equipments",
             "favorite_raids", "game_versions", "instance_types",
             "jcl_cache",
-            "raid_bosses", "raid_role_visibility", "raids",
-            "records", "role_instance_visibility", "roles",
+            "raid_attempts", "raid_boss_rules", "raid_bosses", "raid_role_visibility", "raids",
+            "record_auction_ledger", "record_chatlog_items", "record_roster", "record_salary_sheets", "records", "role_instance_visibility", "roles",
             "schema_versions", "seasons", "trial_records",
         ];
 
@@ -5167,8 +5463,8 @@ mod tests {
             "accounts", "app_config", "baizhan_records", "cache", "equipments",
             "favorite_raids", "game_versions", "instance_types",
             "jcl_cache",
-            "raid_bosses", "raid_role_visibility", "raids",
-            "records", "role_instance_visibility", "roles",
+            "raid_attempts", "raid_boss_rules", "raid_bosses", "raid_role_visibility", "raids",
+            "record_auction_ledger", "record_chatlog_items", "record_roster", "record_salary_sheets", "records", "role_instance_visibility", "roles",
             "schema_versions", "seasons", "trial_records",
         ];
 
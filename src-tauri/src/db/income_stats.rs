@@ -0,0 +1,566 @@
+//! 收入统计聚合
+//!
+//! 仪表盘原本在前端用全部记录现算合计，各页面口径不一致且数据量大时很慢。
+//! `db_get_income_stats` 在后端统一口径：副本记录（排除待确认 / 已拒绝 / 进行中的记录）、
+//! 百战记录和试炼记录按 CD 窗口、自然周、赛季、账号、角色或副本分组，
+//! 汇总收入、支出、净收入、记录数和特殊掉落数。
+//!
+//! 多开同团（见 `mingyi::run_groups`）的共享掉落合并全组的特殊掉落后只在主记录统计一次
+//! （主记录不在统计范围内时取组内最早的记录），金币按各账号分别统计。
+
+use chrono::{Datelike, Local, TimeZone};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::init_db;
+use super::record_query::RARE_DROP_FLAGS;
+use crate::mingyi::drop_scanner::calculate_cd_window;
+use crate::money::{self, Money};
+
+/// 百战记录在按副本分组时的名称
+const BAIZHAN_GROUP_NAME: &str = "百战";
+/// 试炼记录在按副本分组时的名称
+const TRIAL_GROUP_NAME: &str = "试炼之地";
+
+/// 分组维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StatsGroupBy {
+    /// 副本 CD 窗口（25人本按周，10人本按半周，见 `calculate_cd_window`）
+    CdWindow,
+    /// 自然周（周一 00:00 起）
+    Week,
+    Season,
+    Account,
+    Role,
+    Raid,
+}
+
+/// 单个分组的统计结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncomeStatsBucket {
+    pub key: String,
+    /// 展示名（赛季名、账号名、角色名、副本名等；时间分组为空，由前端按起止时间格式化）
+    pub label: String,
+    /// 时间分组的起止时间（毫秒，左闭右开）
+    pub start_ms: Option<i64>,
+    pub end_ms: Option<i64>,
+    pub income: Money,
+    pub expense: Money,
+    pub net_income: Money,
+    pub raid_count: u32,
+    pub baizhan_count: u32,
+    pub trial_count: u32,
+    /// 特殊掉落数（类别 → 次数，类别名同记录筛选）
+    pub rare_drops: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Raid,
+    Baizhan,
+    Trial,
+}
+
+/// 统一后的单条统计来源
+#[derive(Debug, Clone)]
+struct StatsEntry {
+    kind: EntryKind,
+    date_ms: i64,
+    account_id: String,
+    role_id: String,
+    role_name: String,
+    raid_name: String,
+    income: Money,
+    expense: Money,
+    /// 计入统计的特殊掉落类别（同团主记录为全组合并结果，非主记录为空）
+    rare_drops: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone)]
+struct SeasonRange {
+    id: i64,
+    name: String,
+    start_ms: i64,
+    end_ms: Option<i64>,
+}
+
+/// 查询条件中的时间范围片段（按 `column` 过滤）
+fn date_range_sql(column: &str, start_ms: Option<i64>, end_ms: Option<i64>) -> String {
+    let mut sql = String::new();
+    if let Some(start_ms) = start_ms {
+        sql.push_str(&format!(" AND {} >= {}", column, start_ms));
+    }
+    if let Some(end_ms) = end_ms {
+        sql.push_str(&format!(" AND {} < {}", column, end_ms));
+    }
+    sql
+}
+
+fn load_raid_entries(
+    conn: &Connection,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<Vec<StatsEntry>, String> {
+    let sql = format!(
+        "SELECT data, record_date, account_id, role_id, raid_name, id, run_group_id FROM records
+         WHERE COALESCE(status, 'confirmed') NOT IN ('pending', 'rejected', 'scanning')
+           AND record_date IS NOT NULL{}",
+        date_range_sql("record_date", start_ms, end_ms)
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("准备副本记录统计查询失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })
+        .map_err(|e| format!("查询副本记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取副本记录失败: {}", e))?;
+
+    let mut entries = Vec::with_capacity(rows.len());
+    // 同团分组 ID → (主记录在 entries 中的下标, 组内各记录的下标)
+    let mut run_groups: HashMap<String, (Option<usize>, Vec<usize>)> = HashMap::new();
+    for (data, date_ms, account_id, role_id, raid_name, id, run_group_id) in rows {
+        let data: serde_json::Value = match serde_json::from_str(&data) {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("[IncomeStats] 跳过无法解析的记录 data: {}", e);
+                continue;
+            }
+        };
+        let rare_drops = RARE_DROP_FLAGS
+            .iter()
+            .filter(|(_, key)| data.get(*key).and_then(|v| v.as_bool()) == Some(true))
+            .map(|(category, _)| *category)
+            .collect();
+        let group_id = run_group_id.or_else(|| {
+            data.get("runGroupId")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        });
+        if let Some(group_id) = group_id {
+            let group = run_groups.entry(group_id.clone()).or_default();
+            if group_id == id {
+                group.0 = Some(entries.len());
+            }
+            group.1.push(entries.len());
+        }
+        entries.push(StatsEntry {
+            kind: EntryKind::Raid,
+            date_ms,
+            account_id: account_id.unwrap_or_default(),
+            role_id: role_id.unwrap_or_default(),
+            role_name: data
+                .get("roleName")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            raid_name: raid_name.unwrap_or_default(),
+            income: money::record_income(&data),
+            expense: money::record_expense(&data),
+            rare_drops,
+        });
+    }
+
+    // 多开同团：合并全组的特殊掉落，只在主记录上统计一次
+    for (primary, members) in run_groups.into_values() {
+        let Some(primary) =
+            primary.or_else(|| members.iter().copied().min_by_key(|&i| entries[i].date_ms))
+        else {
+            continue;
+        };
+        let mut merged: Vec<&'static str> = Vec::new();
+        for &i in &members {
+            for category in std::mem::take(&mut entries[i].rare_drops) {
+                if !merged.contains(&category) {
+                    merged.push(category);
+                }
+            }
+        }
+        merged.sort_by_key(|category| {
+            RARE_DROP_FLAGS
+                .iter()
+                .position(|(name, _)| name == category)
+        });
+        entries[primary].rare_drops = merged;
+    }
+    Ok(entries)
+}
+
+fn load_baizhan_entries(
+    conn: &Connection,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<Vec<StatsEntry>, String> {
+    let sql = format!(
+        "SELECT date, account_id, role_id, role_name,
+                COALESCE(gold_income_copper, COALESCE(gold_income, 0) * 10000),
+                COALESCE(gold_expense_copper, COALESCE(gold_expense, 0) * 10000)
+         FROM baizhan_records WHERE 1 = 1{}",
+        date_range_sql("date", start_ms, end_ms)
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("准备百战记录统计查询失败: {}", e))?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(StatsEntry {
+                kind: EntryKind::Baizhan,
+                date_ms: row.get(0)?,
+                account_id: row.get(1)?,
+                role_id: row.get(2)?,
+                role_name: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                raid_name: BAIZHAN_GROUP_NAME.to_string(),
+                income: Money::from_copper(row.get(4)?),
+                expense: Money::from_copper(row.get(5)?),
+                rare_drops: Vec::new(),
+            })
+        })
+        .map_err(|e| format!("查询百战记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取百战记录失败: {}", e))?;
+    Ok(entries)
+}
+
+fn load_trial_entries(
+    conn: &Connection,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<Vec<StatsEntry>, String> {
+    let sql = format!(
+        "SELECT date, account_id, role_id, role_name FROM trial_records WHERE 1 = 1{}",
+        date_range_sql("date", start_ms, end_ms)
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("准备试炼记录统计查询失败: {}", e))?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(StatsEntry {
+                kind: EntryKind::Trial,
+                date_ms: row.get(0)?,
+                account_id: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                role_id: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                role_name: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                raid_name: TRIAL_GROUP_NAME.to_string(),
+                income: Money::ZERO,
+                expense: Money::ZERO,
+                rare_drops: Vec::new(),
+            })
+        })
+        .map_err(|e| format!("查询试炼记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取试炼记录失败: {}", e))?;
+    Ok(entries)
+}
+
+fn load_seasons(conn: &Connection) -> Result<Vec<SeasonRange>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, start_date, end_date FROM seasons ORDER BY sort_order DESC")
+        .map_err(|e| format!("准备赛季查询失败: {}", e))?;
+    let seasons = stmt
+        .query_map([], |row| {
+            // seasons 表的起止时间为秒
+            Ok(SeasonRange {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                start_ms: row.get::<_, i64>(2)? * 1000,
+                end_ms: row
+                    .get::<_, Option<i64>>(3)?
+                    .filter(|d| *d > 0)
+                    .map(|d| d * 1000),
+            })
+        })
+        .map_err(|e| format!("查询赛季失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取赛季失败: {}", e))?;
+    Ok(seasons)
+}
+
+fn load_names(conn: &Connection, sql: &str) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("准备名称查询失败: {}", e))?;
+    let names = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("查询名称失败: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("读取名称失败: {}", e))?;
+    Ok(names)
+}
+
+/// 自然周（周一 00:00 ~ 下周一 00:00，本地时间）
+fn week_range(date_ms: i64) -> Option<(String, i64, i64)> {
+    let dt = Local.timestamp_millis_opt(date_ms).single()?;
+    let monday =
+        dt.date_naive() - chrono::Duration::days(dt.weekday().num_days_from_monday() as i64);
+    let start = Local
+        .from_local_datetime(&monday.and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+    let end = Local
+        .from_local_datetime(&(monday + chrono::Duration::days(7)).and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+    let week = monday.iso_week();
+    Some((
+        format!("{}-W{:02}", week.year(), week.week()),
+        start.timestamp_millis(),
+        end.timestamp_millis(),
+    ))
+}
+
+/// 聚合统计来源，按 key 排序（时间分组按起始时间排序）
+fn aggregate(
+    entries: &[StatsEntry],
+    group_by: StatsGroupBy,
+    seasons: &[SeasonRange],
+    account_names: &HashMap<String, String>,
+    role_names: &HashMap<String, String>,
+) -> Vec<IncomeStatsBucket> {
+    let mut buckets: BTreeMap<String, IncomeStatsBucket> = BTreeMap::new();
+    for entry in entries {
+        let (key, label, range) = match group_by {
+            StatsGroupBy::CdWindow => {
+                let is_ten_person =
+                    entry.kind == EntryKind::Raid && entry.raid_name.contains("10人");
+                let (start, end) = calculate_cd_window(entry.date_ms, is_ten_person);
+                (
+                    format!("{}-{}", start, end),
+                    String::new(),
+                    Some((start, end)),
+                )
+            }
+            StatsGroupBy::Week => match week_range(entry.date_ms) {
+                Some((key, start, end)) => (key, String::new(), Some((start, end))),
+                None => continue,
+            },
+            StatsGroupBy::Season => {
+                // 按 sort_order 倒序取第一个覆盖该时间的赛季（与 db_get_season_for_date 一致）
+                match seasons.iter().find(|s| {
+                    entry.date_ms >= s.start_ms && s.end_ms.is_none_or(|end| entry.date_ms < end)
+                }) {
+                    Some(season) => (
+                        season.id.to_string(),
+                        season.name.clone(),
+                        Some((season.start_ms, season.end_ms.unwrap_or(i64::MAX))),
+                    ),
+                    None => ("none".to_string(), "无赛季".to_string(), None),
+                }
+            }
+            StatsGroupBy::Account => (
+                entry.account_id.clone(),
+                account_names
+                    .get(&entry.account_id)
+                    .cloned()
+                    .unwrap_or_else(|| entry.account_id.clone()),
+                None,
+            ),
+            StatsGroupBy::Role => (
+                entry.role_id.clone(),
+                role_names
+                    .get(&entry.role_id)
+                    .cloned()
+                    .unwrap_or_else(|| entry.role_name.clone()),
+                None,
+            ),
+            StatsGroupBy::Raid => (entry.raid_name.clone(), entry.raid_name.clone(), None),
+        };
+
+        let bucket = buckets
+            .entry(key.clone())
+            .or_insert_with(|| IncomeStatsBucket {
+                key,
+                label,
+                start_ms: range.map(|(start, _)| start),
+                end_ms: range.map(|(_, end)| end),
+                ..Default::default()
+            });
+        bucket.income += entry.income;
+        bucket.expense += entry.expense;
+        bucket.net_income = bucket.income - bucket.expense;
+        match entry.kind {
+            EntryKind::Raid => bucket.raid_count += 1,
+            EntryKind::Baizhan => bucket.baizhan_count += 1,
+            EntryKind::Trial => bucket.trial_count += 1,
+        }
+        for category in &entry.rare_drops {
+            *bucket.rare_drops.entry(category.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    let mut result: Vec<IncomeStatsBucket> = buckets.into_values().collect();
+    if result.iter().all(|b| b.start_ms.is_some()) {
+        result.sort_by_key(|b| b.start_ms);
+    }
+    result
+}
+
+/// 统计 [start_ms, end_ms) 范围内的收入，按 `group_by` 分组
+pub(crate) fn compute_income_stats(
+    conn: &Connection,
+    group_by: StatsGroupBy,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<Vec<IncomeStatsBucket>, String> {
+    let mut entries = load_raid_entries(conn, start_ms, end_ms)?;
+    entries.extend(load_baizhan_entries(conn, start_ms, end_ms)?);
+    entries.extend(load_trial_entries(conn, start_ms, end_ms)?);
+
+    let seasons = if group_by == StatsGroupBy::Season {
+        load_seasons(conn)?
+    } else {
+        Vec::new()
+    };
+    let account_names = if group_by == StatsGroupBy::Account {
+        load_names(conn, "SELECT id, account_name FROM accounts")?
+    } else {
+        HashMap::new()
+    };
+    let role_names = if group_by == StatsGroupBy::Role {
+        load_names(conn, "SELECT id, name FROM roles")?
+    } else {
+        HashMap::new()
+    };
+
+    Ok(aggregate(
+        &entries,
+        group_by,
+        &seasons,
+        &account_names,
+        &role_names,
+    ))
+}
+
+/// Tauri 命令：按维度统计收入（副本 + 百战 + 试炼）
+///
+/// `start_ms` / `end_ms` 为可选的时间范围（毫秒，左闭右开）。
+#[tauri::command]
+pub fn db_get_income_stats(
+    group_by: StatsGroupBy,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<Vec<IncomeStatsBucket>, String> {
+    let conn = init_db()?;
+    compute_income_stats(&conn, group_by, start_ms, end_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    fn setup() -> Connection {
+        let conn = crate::db::open_test_db();
+        conn.execute_batch(
+            "INSERT INTO game_versions (id, name, created_at) VALUES (1, '丝路风语', '');
+             INSERT INTO seasons (id, name, version_id, start_date, end_date, sort_order, created_at)
+             VALUES (1, '太极秘录', 1, 1730329200, 1745449200, 2, '');
+             INSERT INTO accounts (id, account_name) VALUES ('acc-2', '小号');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn insert_record(
+        conn: &Connection,
+        id: &str,
+        account: &str,
+        status: &str,
+        data: serde_json::Value,
+    ) {
+        conn.execute(
+            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, status)
+             VALUES (?1, ?2, '25人英雄阆风悬城', ?3, ?3, ?4, ?5)",
+            params![id, data.to_string(), account, 1_735_000_000_000_i64, status],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_income_stats_dedups_shared_drops_and_skips_pending() {
+        let conn = setup();
+        // 多开同团：两条记录都有玄晶，只在主记录统计
+        insert_record(
+            &conn,
+            "main",
+            "acc-1",
+            "confirmed",
+            serde_json::json!({ "goldIncome": 3000, "goldExpense": 500, "hasXuanjing": true, "runGroupPrimary": true }),
+        );
+        insert_record(
+            &conn,
+            "alt",
+            "acc-2",
+            "confirmed",
+            serde_json::json!({ "goldIncome": 147, "goldIncomeCopper": 1_477_653, "hasXuanjing": true, "hasPet": true, "runGroupPrimary": false }),
+        );
+        // 分组写在 run_group_id 列上；只有小号记录带的宠物合并到主记录统计
+        conn.execute(
+            "UPDATE records SET run_group_id = 'main' WHERE id IN ('main', 'alt')",
+            [],
+        )
+        .unwrap();
+        insert_record(
+            &conn,
+            "pending",
+            "acc-1",
+            "pending",
+            serde_json::json!({ "goldIncome": 9999 }),
+        );
+        conn.execute(
+            "INSERT INTO baizhan_records (id, account_id, role_id, date, gold_income, gold_expense)
+             VALUES ('bz', 'acc-1', 'acc-1', 1735000000000, 800, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO trial_records (id, account_id, role_id, date) VALUES ('tr', 'acc-1', 'acc-1', 1735000000000)",
+            [],
+        )
+        .unwrap();
+
+        let season = compute_income_stats(&conn, StatsGroupBy::Season, None, None).unwrap();
+        assert_eq!(season.len(), 1);
+        let bucket = &season[0];
+        assert_eq!(bucket.label, "太极秘录");
+        assert_eq!(
+            bucket.income,
+            Money::from_gold(3800) + Money::from_parts(147, 76, 53)
+        );
+        assert_eq!(bucket.expense, Money::from_gold(500));
+        assert_eq!(bucket.net_income, bucket.income - bucket.expense);
+        assert_eq!(
+            (bucket.raid_count, bucket.baizhan_count, bucket.trial_count),
+            (2, 1, 1)
+        );
+        assert_eq!(bucket.rare_drops.get("xuanjing"), Some(&1));
+        assert_eq!(bucket.rare_drops.get("pet"), Some(&1));
+
+        let by_raid = compute_income_stats(&conn, StatsGroupBy::Raid, None, None).unwrap();
+        let keys: Vec<&str> = by_raid.iter().map(|b| b.key.as_str()).collect();
+        assert_eq!(keys, vec!["25人英雄阆风悬城", "百战", "试炼之地"]);
+
+        let by_account = compute_income_stats(&conn, StatsGroupBy::Account, None, None).unwrap();
+        assert_eq!(by_account.len(), 2);
+        assert_eq!(by_account[1].label, "小号");
+        assert_eq!(by_account[1].income, Money::from_parts(147, 76, 53));
+        assert!(by_account[1].rare_drops.is_empty());
+        assert_eq!(by_account[0].rare_drops.get("pet"), Some(&1));
+
+        let cd = compute_income_stats(&conn, StatsGroupBy::CdWindow, None, None).unwrap();
+        assert_eq!(cd.len(), 1);
+        let (start, end) = (cd[0].start_ms.unwrap(), cd[0].end_ms.unwrap());
+        assert!(start <= 1_735_000_000_000 && 1_735_000_000_000 < end);
+    }
+}
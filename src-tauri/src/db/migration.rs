@@ -32,6 +32,18 @@ pub fn apply_migration(conn: &Connection, version: i32) -> Result<(), String> {
         15 => migrations::v15::migrate(conn),
         16 => migrations::v16::migrate(conn),
         17 => migrations::v17::migrate(conn),
+        18 => migrations::v18::migrate(conn),
+        19 => migrations::v19::migrate(conn),
+        20 => migrations::v20::migrate(conn),
+        21 => migrations::v21::migrate(conn),
+        22 => migrations::v22::migrate(conn),
+        23 => migrations::v23::migrate(conn),
+        24 => migrations::v24::migrate(conn),
+        25 => migrations::v25::migrate(conn),
+        26 => migrations::v26::migrate(conn),
+        27 => migrations::v27::migrate(conn),
+        28 => migrations::v28::migrate(conn),
+        29 => migrations::v29::migrate(conn),
         _ => Err(format!("未知的迁移版本: {}", version)),
     }
 }
@@ -142,9 +154,18 @@ pub fn init_static_raids(conn: &Connection) -> Result<(), String> {
         log::info!("成功注入 {} 条预制副本数据", inserted_count);
     }
 
+    init_static_boss_rules(conn)?;
+
     Ok(())
 }
 
+/// 同步预置 BOSS 击杀判定规则（从 static_boss_rules.json 读取）
+/// 预置规则随版本更新覆盖，用户自定义规则保持不变
+fn init_static_boss_rules(conn: &Connection) -> Result<(), String> {
+    let rules_json = include_str!("static_boss_rules.json");
+    crate::mingyi::boss_rules::sync_static_boss_rules(conn, rules_json)
+}
+
 fn init_static_versions_and_seasons(conn: &Connection) -> Result<(), String> {
     let timestamp = chrono::Utc::now().to_rfc3339();
 
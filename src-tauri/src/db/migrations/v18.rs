@@ -0,0 +1,149 @@
+use rusqlite::{params, Connection};
+
+/// V18 迁移：BOSS 战斗尝试记录
+///
+/// 设计要点：
+/// 1. 新增 `raid_attempts` 表：保存副本实例内每一次 BOSS 战斗（击杀与灭团），
+///    通过 `record_id` 关联 records 表。扫描时按记录整体重建，`(record_id, jcl_file)` 唯一。
+/// 2. `jcl_cache` 新增 `boss_hp_percent` 列：JCL 中 BOSS 的最低血量百分比（灭团进度）。
+/// 3. 清空 `jcl_cache`：旧缓存没有血量信息，清空后下次扫描重新解析（缓存可随时重建）。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V18 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS raid_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            boss_name TEXT NOT NULL,
+            jcl_file TEXT NOT NULL,
+            attempt_index INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            end_time INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            is_kill INTEGER NOT NULL,
+            boss_hp_percent REAL,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, jcl_file)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_raid_attempts_record_id ON raid_attempts(record_id);
+
+        CREATE TABLE IF NOT EXISTS jcl_cache (
+            file_path TEXT PRIMARY KEY,
+            file_mtime INTEGER NOT NULL,
+            boss_name TEXT,
+            fight_start_ms INTEGER NOT NULL,
+            fight_end_ms INTEGER NOT NULL,
+            is_kill INTEGER NOT NULL,
+            cached_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .map_err(|e| format!("V18 创建 raid_attempts 表失败: {}", e))?;
+
+    let has_hp_col: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('jcl_cache') WHERE name='boss_hp_percent'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_hp_col {
+        conn.execute("ALTER TABLE jcl_cache ADD COLUMN boss_hp_percent REAL", [])
+            .map_err(|e| format!("V18 添加 jcl_cache.boss_hp_percent 列失败: {}", e))?;
+    }
+
+    let cleared = conn
+        .execute("DELETE FROM jcl_cache", [])
+        .map_err(|e| format!("V18 清空 jcl_cache 失败: {}", e))?;
+    log::info!("[V18] 已清空 {} 条 JCL 缓存，下次扫描重新解析", cleared);
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v18_raid_attempts_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V18 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V18 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE jcl_cache (
+                file_path TEXT PRIMARY KEY,
+                file_mtime INTEGER NOT NULL,
+                boss_name TEXT,
+                fight_start_ms INTEGER NOT NULL,
+                fight_end_ms INTEGER NOT NULL,
+                is_kill INTEGER NOT NULL,
+                cached_at TEXT NOT NULL
+            );
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v18_creates_attempts_table_and_resets_cache() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+        conn.execute(
+            "INSERT INTO jcl_cache VALUES ('a.jcl', 1, '唐醉', 0, 0, 1, 'now')",
+            [],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        let cache_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM jcl_cache", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cache_count, 0, "旧 JCL 缓存应被清空");
+
+        let has_hp_col: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('jcl_cache') WHERE name='boss_hp_percent'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(has_hp_col);
+
+        conn.execute(
+            "INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, boss_hp_percent, created_at)
+             VALUES ('rec1', '唐醉', 'a.jcl', 1, 0, 0, 0, 0, 35.5, 'now')",
+            [],
+        )
+        .unwrap();
+        let duplicate = conn.execute(
+            "INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, created_at)
+             VALUES ('rec1', '唐醉', 'a.jcl', 2, 0, 0, 0, 1, 'now')",
+            [],
+        );
+        assert!(duplicate.is_err(), "同一记录同一 JCL 只应有一条尝试");
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v18_raid_attempts_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
@@ -0,0 +1,105 @@
+use rusqlite::{params, Connection};
+
+/// V19 迁移：BOSS 击杀判定规则表
+///
+/// 设计要点：
+/// 1. 新增 `raid_boss_rules` 表：与 `raid_bosses` 一样以 raids.name 关联，
+///    `(raid_name, boss_name)` 唯一。列表类字段（别名/伴生 NPC/宝箱名）以 JSON 数组存储。
+/// 2. `dead_face_direction` 为 NULL 表示不使用朝向辅助判定，默认 192（实测击杀特征值）。
+/// 3. `is_static=1` 为预置规则，由 init_static_raids 从 static_boss_rules.json 同步；
+///    用户自定义规则（is_static=0）不会被预置数据覆盖。
+/// 4. 预置规则不完全等价于旧版硬编码判定：`fight_time_signal` 的 BOSS（赵八嫂）在有 BOSS 配置时
+///    也以全局 FIGHT_TIME 作为进战信号。jcl_cache 由 `JCL_ANALYZER_VERSION` 递增失效，无需在此清空。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V19 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS raid_boss_rules (
+            raid_name TEXT NOT NULL,
+            boss_name TEXT NOT NULL,
+            aliases TEXT NOT NULL DEFAULT '[]',
+            companions TEXT NOT NULL DEFAULT '[]',
+            require_companion_death INTEGER NOT NULL DEFAULT 1,
+            chest_names TEXT NOT NULL DEFAULT '[]',
+            clone_handling TEXT NOT NULL DEFAULT 'sameName',
+            fight_time_signal INTEGER NOT NULL DEFAULT 0,
+            dead_face_direction INTEGER DEFAULT 192,
+            notes TEXT NOT NULL DEFAULT '',
+            is_static INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (raid_name, boss_name)
+        );
+        "#,
+    )
+    .map_err(|e| format!("V19 创建 raid_boss_rules 表失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v19_boss_rules_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V19 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V19 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v19_creates_boss_rules_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO raid_boss_rules (raid_name, boss_name, updated_at) VALUES ('阆风悬城', '唐怀仁', 'now')",
+            [],
+        )
+        .unwrap();
+        let (clone_handling, face_dir, require_companion): (String, Option<i64>, i64) = conn
+            .query_row(
+                "SELECT clone_handling, dead_face_direction, require_companion_death FROM raid_boss_rules",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(clone_handling, "sameName");
+        assert_eq!(face_dir, Some(192));
+        assert_eq!(require_companion, 1);
+
+        let duplicate = conn.execute(
+            "INSERT INTO raid_boss_rules (raid_name, boss_name, updated_at) VALUES ('阆风悬城', '唐怀仁', 'now')",
+            [],
+        );
+        assert!(duplicate.is_err(), "同一副本同一 BOSS 只应有一条规则");
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v19_boss_rules_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
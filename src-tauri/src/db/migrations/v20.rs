@@ -0,0 +1,99 @@
+use rusqlite::{params, Connection};
+
+/// V20 迁移：JCL 缓存按分析器版本失效
+///
+/// 设计要点：
+/// 1. `jcl_cache` 新增 `analyzer_version` 列，作为缓存键的一部分
+///    （file_path + file_mtime + analyzer_version）。
+/// 2. 旧缓存行的版本为 0，与当前分析器版本不匹配，读取时视为未命中并在下次扫描时覆盖，
+///    无需清空表；之后 analyze_jcl 判定逻辑变化只需递增 `JCL_ANALYZER_VERSION`。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V20 迁移开始 ==========");
+
+    let has_version_col: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('jcl_cache') WHERE name='analyzer_version'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_version_col {
+        conn.execute(
+            "ALTER TABLE jcl_cache ADD COLUMN analyzer_version INTEGER NOT NULL DEFAULT 0",
+            [],
+        )
+        .map_err(|e| format!("V20 添加 jcl_cache.analyzer_version 列失败: {}", e))?;
+    }
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v20_jcl_cache_version_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V20 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V20 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE jcl_cache (
+                file_path TEXT PRIMARY KEY,
+                file_mtime INTEGER NOT NULL,
+                boss_name TEXT,
+                fight_start_ms INTEGER NOT NULL,
+                fight_end_ms INTEGER NOT NULL,
+                is_kill INTEGER NOT NULL,
+                cached_at TEXT NOT NULL,
+                boss_hp_percent REAL
+            );
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v20_adds_analyzer_version_to_jcl_cache() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+        conn.execute(
+            "INSERT INTO jcl_cache VALUES ('a.jcl', 1, '唐醉', 0, 0, 1, 'now', NULL)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        let (count, version): (i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(*), MAX(analyzer_version) FROM jcl_cache",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 1, "旧缓存保留，由版本号判定失效");
+        assert_eq!(version, 0);
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v20_jcl_cache_version_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
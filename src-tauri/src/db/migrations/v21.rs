@@ -0,0 +1,98 @@
+use rusqlite::{params, Connection};
+
+/// V21 迁移：chatlog 收支明细
+///
+/// 设计要点：
+/// 1. 新增 `record_chatlog_items` 表：保存自动扫描时每一条参与计算的 chatlog 消息
+///    （掉落、底薪、收入、BOSS 奖励、支出、分配记录），含时间、频道、买家与金额，
+///    用于待确认记录审核时展示收入/支出的来源。
+/// 2. 通过 `record_id` 关联 records 表，扫描时按记录整体重建，`seq` 为记录内的消息顺序。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V21 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_chatlog_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            time_sec INTEGER NOT NULL,
+            channel TEXT NOT NULL,
+            text TEXT NOT NULL,
+            item_name TEXT,
+            buyer TEXT,
+            gold INTEGER,
+            counted INTEGER NOT NULL DEFAULT 1,
+            note TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, seq)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_record_chatlog_items_record_id ON record_chatlog_items(record_id);
+        "#,
+    )
+    .map_err(|e| format!("V21 创建 record_chatlog_items 表失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v21_chatlog_items_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V21 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V21 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v21_creates_record_chatlog_items() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO record_chatlog_items (record_id, seq, kind, time_sec, channel, text, gold, created_at)
+             VALUES ('r1', 0, 'salary', 1750000000, 'MSG_ROOM', '每人底薪：3000金', 3000, 'now')",
+            [],
+        )
+        .unwrap();
+        let counted: i64 = conn
+            .query_row(
+                "SELECT counted FROM record_chatlog_items WHERE record_id = 'r1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(counted, 1);
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v21_chatlog_items_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
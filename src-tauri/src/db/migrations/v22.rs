@@ -0,0 +1,128 @@
+use rusqlite::{params, Connection};
+
+/// V22 迁移：副本团队名单
+///
+/// 设计要点：
+/// 1. 新增 `record_roster` 表：保存自动扫描记录的队友（角色名、服务器、门派、心法、来源），
+///    `own_role_id` 标记本地已添加的角色（同团小号）。通过 `record_id` 关联 records 表，
+///    扫描时按记录整体重建，`(record_id, name, server)` 唯一（服务器未知时为空字符串）。
+/// 2. `jcl_cache` 新增 `players` 列：JCL 中队伍成员的 JSON 数组。旧缓存行由分析器版本号判定失效，
+///    下次扫描重新解析时补齐。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V22 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_roster (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            server TEXT NOT NULL DEFAULT '',
+            force_id INTEGER,
+            kungfu_id INTEGER,
+            sources TEXT NOT NULL DEFAULT '',
+            own_role_id TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, name, server)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_record_roster_record_id ON record_roster(record_id);
+        CREATE INDEX IF NOT EXISTS idx_record_roster_name ON record_roster(name);
+        "#,
+    )
+    .map_err(|e| format!("V22 创建 record_roster 表失败: {}", e))?;
+
+    let has_players_col: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('jcl_cache') WHERE name='players'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_players_col {
+        conn.execute(
+            "ALTER TABLE jcl_cache ADD COLUMN players TEXT NOT NULL DEFAULT '[]'",
+            [],
+        )
+        .map_err(|e| format!("V22 添加 jcl_cache.players 列失败: {}", e))?;
+    }
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v22_record_roster_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V22 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V22 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE jcl_cache (
+                file_path TEXT PRIMARY KEY,
+                file_mtime INTEGER NOT NULL,
+                boss_name TEXT,
+                fight_start_ms INTEGER NOT NULL,
+                fight_end_ms INTEGER NOT NULL,
+                is_kill INTEGER NOT NULL,
+                cached_at TEXT NOT NULL,
+                boss_hp_percent REAL,
+                analyzer_version INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v22_creates_record_roster_and_cache_players() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+        conn.execute(
+            "INSERT INTO jcl_cache VALUES ('a.jcl', 1, '唐醉', 0, 0, 1, 'now', NULL, 1)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        let players: String = conn
+            .query_row("SELECT players FROM jcl_cache", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(players, "[]");
+
+        conn.execute(
+            "INSERT INTO record_roster (record_id, name, created_at) VALUES ('r1', '糯闪', 'now')",
+            [],
+        )
+        .unwrap();
+        let duplicate = conn.execute(
+            "INSERT INTO record_roster (record_id, name, created_at) VALUES ('r1', '糯闪', 'now')",
+            [],
+        );
+        assert!(duplicate.is_err(), "同一记录内角色唯一");
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v22_record_roster_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
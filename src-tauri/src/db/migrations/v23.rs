@@ -0,0 +1,98 @@
+use rusqlite::{params, Connection};
+
+/// V23 迁移：多开同团分组
+///
+/// 设计要点：
+/// 1. `records` 新增 `run_group_id` 列：多开时不同账号在同一场副本产生的自动扫描记录写入相同的分组 ID
+///    （取组内最早的记录 ID），未分组为 NULL。
+/// 2. 分组由扫描后的同团识别整体重算，旧记录不做回填，下次扫描对应 CD 时补齐。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V23 迁移开始 ==========");
+
+    let has_run_group_col: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('records') WHERE name='run_group_id'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_run_group_col {
+        conn.execute("ALTER TABLE records ADD COLUMN run_group_id TEXT", [])
+            .map_err(|e| format!("V23 添加 records.run_group_id 列失败: {}", e))?;
+    }
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_records_run_group_id ON records(run_group_id)",
+        [],
+    )
+    .map_err(|e| format!("V23 创建 run_group_id 索引失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v23_run_groups_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V23 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V23 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE records (
+                id TEXT PRIMARY KEY,
+                data TEXT,
+                account_id TEXT,
+                record_date INTEGER,
+                source TEXT DEFAULT 'manual',
+                status TEXT DEFAULT 'confirmed'
+            );
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v23_adds_run_group_id() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+        conn.execute(
+            "INSERT INTO records (id, data, account_id, record_date) VALUES ('r1', '{}', 'acc-1', 0)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        let group: Option<String> = conn
+            .query_row(
+                "SELECT run_group_id FROM records WHERE id = 'r1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(group.is_none(), "旧记录默认未分组");
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v23_run_groups_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
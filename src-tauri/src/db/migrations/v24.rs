@@ -0,0 +1,93 @@
+use rusqlite::{params, Connection};
+
+/// V24 迁移：团队拍卖账本
+///
+/// 设计要点：
+/// 1. 新增 `record_auction_ledger` 表：保存自动扫描记录整场副本的团队频道成交流水
+///    （拍卖购买与团长分配记录，含买家、物品、金额、时间），不限当前角色，用于团长核对总金额。
+/// 2. 通过 `record_id` 关联 records 表，扫描时按记录整体重建，`seq` 为记录内的成交顺序。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V24 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_auction_ledger (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            time_sec INTEGER NOT NULL,
+            buyer TEXT NOT NULL,
+            item_name TEXT NOT NULL,
+            gold INTEGER NOT NULL,
+            source TEXT NOT NULL DEFAULT 'auction',
+            created_at TEXT NOT NULL,
+            UNIQUE(record_id, seq)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_record_auction_ledger_record_id ON record_auction_ledger(record_id);
+        "#,
+    )
+    .map_err(|e| format!("V24 创建 record_auction_ledger 表失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v24_auction_ledger_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V24 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V24 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v24_creates_record_auction_ledger() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO record_auction_ledger (record_id, seq, time_sec, buyer, item_name, gold, created_at)
+             VALUES ('r1', 0, 1750000000, '糯闪·梦江南', '玄晶', 60000, 'now')",
+            [],
+        )
+        .unwrap();
+        let source: String = conn
+            .query_row(
+                "SELECT source FROM record_auction_ledger WHERE record_id = 'r1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(source, "auction");
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v24_auction_ledger_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
@@ -0,0 +1,84 @@
+use rusqlite::{params, Connection};
+
+/// V25 迁移：团长模式工资分配表
+///
+/// 设计要点：
+/// 1. 新增 `record_salary_sheets` 表：每条记录一张工资分配表，`input` 为团长填写的分配规则
+///    （提成、补贴、罚款、人数），`sheet` 为按拍卖账本计算出的分配结果，均为 JSON。
+/// 2. 以 `record_id` 为主键，重新保存时整体覆盖。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V25 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_salary_sheets (
+            record_id TEXT PRIMARY KEY,
+            input TEXT NOT NULL,
+            sheet TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .map_err(|e| format!("V25 创建 record_salary_sheets 表失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v25_salary_sheets_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V25 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V25 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v25_creates_record_salary_sheets() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO record_salary_sheets (record_id, input, sheet, created_at, updated_at)
+             VALUES ('r1', '{}', '{}', 'now', 'now')",
+            [],
+        )
+        .unwrap();
+        let duplicate = conn.execute(
+            "INSERT INTO record_salary_sheets (record_id, input, sheet, created_at, updated_at)
+             VALUES ('r1', '{}', '{}', 'now', 'now')",
+            [],
+        );
+        assert!(duplicate.is_err(), "每条记录只有一张分配表");
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v25_salary_sheets_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
@@ -0,0 +1,205 @@
+use rusqlite::{params, Connection};
+
+/// 新增的铜精度列 (表, 列)
+const COPPER_COLUMNS: [(&str, &str); 4] = [
+    ("baizhan_records", "gold_income_copper"),
+    ("baizhan_records", "gold_expense_copper"),
+    ("record_chatlog_items", "gold_copper"),
+    ("record_auction_ledger", "gold_copper"),
+];
+
+/// V26 迁移：记录金额铜精度
+///
+/// 设计要点：
+/// 1. 记录 JSON 新增 `goldIncomeCopper` / `goldExpenseCopper`（铜），自动扫描按 chatlog 原始金额写入，
+///    不再截断银铜部分；铜字段为存储金额，`goldIncome` / `goldExpense`（金）由铜字段派生，仅供显示。
+/// 2. 已有记录按金额字段 × 10000 回填铜字段（旧数据本就只有金精度）。已有铜字段的记录不覆盖。
+/// 3. 百战记录（`baizhan_records`）新增 `gold_income_copper` / `gold_expense_copper` 列，按金额列 × 10000 回填；
+///    写入时由 `db_add_baizhan_record` / `db_update_baizhan_record` 一并写入。
+/// 4. chatlog 收支明细（`record_chatlog_items`）与团队拍卖账本（`record_auction_ledger`）新增 `gold_copper` 列，
+///    按 `gold` 列 × 10000 回填；扫描时按 chatlog 原始金额写入，`gold` 列只保留整金部分。
+/// 5. 试炼记录（`trial_records`）没有金额字段，无需回填。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V26 迁移开始 ==========");
+
+    let updated = conn
+        .execute(
+            "UPDATE records SET data = json_set(
+                data,
+                '$.goldIncomeCopper', CAST(ROUND(COALESCE(json_extract(data, '$.goldIncome'), 0) * 10000) AS INTEGER),
+                '$.goldExpenseCopper', CAST(ROUND(COALESCE(json_extract(data, '$.goldExpense'), 0) * 10000) AS INTEGER)
+             )
+             WHERE json_valid(data) AND json_extract(data, '$.goldIncomeCopper') IS NULL",
+            [],
+        )
+        .map_err(|e| format!("V26 回填记录铜精度金额失败: {}", e))?;
+    log::info!("[V26] 回填 {} 条记录的铜精度金额", updated);
+
+    for (table, column) in COPPER_COLUMNS {
+        let has_col: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+                params![table, column],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !has_col {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} INTEGER", table, column),
+                [],
+            )
+            .map_err(|e| format!("V26 添加 {}.{} 列失败: {}", table, column, e))?;
+        }
+    }
+    let backfilled = conn
+        .execute(
+            "UPDATE baizhan_records SET
+                gold_income_copper = COALESCE(gold_income, 0) * 10000,
+                gold_expense_copper = COALESCE(gold_expense, 0) * 10000
+             WHERE gold_income_copper IS NULL",
+            [],
+        )
+        .map_err(|e| format!("V26 回填百战记录铜精度金额失败: {}", e))?;
+    log::info!("[V26] 回填 {} 条百战记录的铜精度金额", backfilled);
+
+    for table in ["record_chatlog_items", "record_auction_ledger"] {
+        let backfilled = conn
+            .execute(
+                &format!(
+                    "UPDATE {} SET gold_copper = gold * 10000
+                     WHERE gold_copper IS NULL AND gold IS NOT NULL",
+                    table
+                ),
+                [],
+            )
+            .map_err(|e| format!("V26 回填 {} 铜精度金额失败: {}", table, e))?;
+        log::info!("[V26] 回填 {} 条 {} 的铜精度金额", backfilled, table);
+    }
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v26_money_copper_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V26 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V26 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE records (
+                id TEXT PRIMARY KEY,
+                data TEXT
+            );
+            CREATE TABLE baizhan_records (
+                id TEXT PRIMARY KEY,
+                gold_income INTEGER DEFAULT 0,
+                gold_expense INTEGER DEFAULT 0
+            );
+            CREATE TABLE record_chatlog_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                gold INTEGER
+            );
+            CREATE TABLE record_auction_ledger (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                gold INTEGER NOT NULL
+            );
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v26_backfills_copper_amounts() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+        conn.execute_batch(
+            r#"
+            INSERT INTO records VALUES ('legacy', '{"goldIncome":3200,"goldExpense":2000}');
+            INSERT INTO records VALUES ('no-expense', '{"goldIncome":147}');
+            INSERT INTO records VALUES ('scanned', '{"goldIncome":147,"goldIncomeCopper":1477653,"goldExpenseCopper":0}');
+            INSERT INTO records VALUES ('broken', 'not json');
+            INSERT INTO baizhan_records VALUES ('bz', 3000, 120);
+            INSERT INTO record_chatlog_items (gold) VALUES (3200), (NULL);
+            INSERT INTO record_auction_ledger (gold) VALUES (60000);
+            "#,
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        let copper = |id: &str, key: &str| -> Option<i64> {
+            conn.query_row(
+                &format!(
+                    "SELECT json_extract(data, '$.{}') FROM records WHERE id = ?1",
+                    key
+                ),
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(copper("legacy", "goldIncomeCopper"), Some(32_000_000));
+        assert_eq!(copper("legacy", "goldExpenseCopper"), Some(20_000_000));
+        assert_eq!(copper("no-expense", "goldExpenseCopper"), Some(0));
+        assert_eq!(
+            copper("scanned", "goldIncomeCopper"),
+            Some(1_477_653),
+            "已有铜字段不覆盖"
+        );
+
+        let broken: String = conn
+            .query_row("SELECT data FROM records WHERE id = 'broken'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(broken, "not json");
+
+        let baizhan: (i64, i64) = conn
+            .query_row(
+                "SELECT gold_income_copper, gold_expense_copper FROM baizhan_records WHERE id = 'bz'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(baizhan, (30_000_000, 1_200_000));
+
+        let items: Vec<Option<i64>> = conn
+            .prepare("SELECT gold_copper FROM record_chatlog_items ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(items, vec![Some(32_000_000), None]);
+        let ledger: i64 = conn
+            .query_row("SELECT gold_copper FROM record_auction_ledger", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(ledger, 600_000_000);
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v26_money_copper_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
@@ -0,0 +1,88 @@
+use rusqlite::{params, Connection};
+
+/// V27 迁移：记录变更历史
+///
+/// 设计要点：
+/// 1. 新增 `record_history` 表：确认、驳回、编辑、删除、批量保存记录时写入一行，
+///    `before_data` / `after_data` 为变更前后的 records.data 快照（新建时 before 为 NULL，删除时 after 为 NULL）。
+/// 2. 历史只追加不修改，撤销操作本身也作为一条历史写入，可以再次撤销。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V27 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS record_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            record_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            before_data TEXT,
+            after_data TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_record_history_record_id ON record_history(record_id);
+        "#,
+    )
+    .map_err(|e| format!("V27 创建 record_history 表失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v27_record_history_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V27 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V27 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v27_creates_record_history() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO record_history (record_id, action, before_data, after_data, created_at)
+             VALUES ('r1', 'create', NULL, '{}', 'now')",
+            [],
+        )
+        .unwrap();
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM record_history WHERE record_id = 'r1' AND before_data IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v27_record_history_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
@@ -0,0 +1,85 @@
+use rusqlite::{params, Connection};
+
+/// V28 迁移：回收站
+///
+/// 设计要点：
+/// 1. 新增 `trash_items` 表：删除副本记录、试炼记录、百战记录、账号、角色时，
+///    先把被删除的行（含级联删除的角色、可见性、记录附属明细）以 JSON 快照写入回收站，再执行删除。
+/// 2. `payload` 按父表在前的顺序保存各表的行，恢复时原样插回；`deleted_at` 为删除时间（毫秒），
+///    超过保留天数的条目在启动时清除。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V28 迁移开始 ==========");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS trash_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            label TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_trash_items_deleted_at ON trash_items(deleted_at);
+        "#,
+    )
+    .map_err(|e| format!("V28 创建 trash_items 表失败: {}", e))?;
+
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v28_trash_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V28 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V28 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_v28_creates_trash_items() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO trash_items (entity_type, entity_id, label, payload, deleted_at)
+             VALUES ('record', 'r1', '阆风悬城', '[]', 0)",
+            [],
+        )
+        .unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM trash_items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v28_trash_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
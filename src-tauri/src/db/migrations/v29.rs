@@ -0,0 +1,344 @@
+use rusqlite::{params, Connection};
+use serde_json::{Map, Value};
+
+use crate::db::raid_record::{json_list, RaidRecord};
+
+/// 必填字符串字段与对应的索引列
+const REQUIRED_TEXT_FIELDS: [(&str, usize); 4] =
+    [("id", 0), ("accountId", 2), ("roleId", 3), ("raidName", 4)];
+const NUMBER_FIELDS: [&str; 5] = [
+    "goldIncome",
+    "goldExpense",
+    "goldIncomeCopper",
+    "goldExpenseCopper",
+    "mapId",
+];
+const INTEGER_FIELDS: [&str; 3] = ["goldIncomeCopper", "goldExpenseCopper", "mapId"];
+const BOOL_FIELDS: [&str; 10] = [
+    "isCleared",
+    "hasXuanjing",
+    "hasMaJu",
+    "hasPet",
+    "hasPendant",
+    "hasMount",
+    "hasAppearance",
+    "hasTitle",
+    "hasSecretBook",
+    "runGroupPrimary",
+];
+
+/// 一行记录：(id, data, account_id, role_id, raid_name, record_date)
+type RecordRow = (
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+);
+
+/// 修复升级前写入的常见格式问题，返回是否有修改
+///
+/// - null 字段移除（可选字段按缺省处理）
+/// - 缺失的 id / accountId / roleId / raidName / date 从索引列补齐
+/// - 字符串形式的金额、MapID 转为数值，铜与 MapID 取整
+/// - 字符串 / 0 / 1 形式的布尔标记转为布尔
+/// - 缺失的 goldIncome 补 0
+fn repair_record_json(obj: &mut Map<String, Value>, row: &RecordRow) -> bool {
+    let before = obj.clone();
+    obj.retain(|_, value| !value.is_null());
+
+    let columns = [
+        Some(row.0.as_str()),
+        None,
+        row.2.as_deref(),
+        row.3.as_deref(),
+        row.4.as_deref(),
+    ];
+    for (field, column) in REQUIRED_TEXT_FIELDS {
+        let missing = obj
+            .get(field)
+            .and_then(|v| v.as_str())
+            .is_none_or(|v| v.trim().is_empty());
+        if let (true, Some(value)) = (missing, columns[column].filter(|v| !v.trim().is_empty())) {
+            obj.insert(field.to_string(), Value::from(value));
+        }
+    }
+    if let (false, Some(date)) = (obj.contains_key("date"), row.5) {
+        obj.insert("date".to_string(), Value::from(date));
+    }
+
+    for field in NUMBER_FIELDS {
+        let parsed = match obj.get(field) {
+            Some(Value::String(text)) => text.trim().parse::<f64>().ok(),
+            Some(Value::Number(number)) if INTEGER_FIELDS.contains(&field) => number.as_f64(),
+            _ => None,
+        };
+        let Some(number) = parsed.filter(|n| n.is_finite()) else {
+            continue;
+        };
+        let value = if INTEGER_FIELDS.contains(&field) {
+            Value::from(number.round() as i64)
+        } else if number.fract() == 0.0 {
+            Value::from(number as i64)
+        } else {
+            Value::from(number)
+        };
+        obj.insert(field.to_string(), value);
+    }
+    obj.entry("goldIncome").or_insert(Value::from(0));
+
+    for field in BOOL_FIELDS {
+        let parsed = match obj.get(field) {
+            Some(Value::String(text)) => match text.trim() {
+                "true" | "1" => Some(true),
+                "false" | "0" | "" => Some(false),
+                _ => None,
+            },
+            Some(Value::Number(number)) => match number.as_i64() {
+                Some(0) => Some(false),
+                Some(1) => Some(true),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(flag) = parsed {
+            obj.insert(field.to_string(), Value::from(flag));
+        }
+    }
+
+    *obj != before
+}
+
+/// V29 迁移：校验已有副本记录
+///
+/// 设计要点：
+/// 1. 记录的所有写入路径改为经 `RaidRecord` 校验后写入。升级前写入的记录可能含字符串金额、
+///    null 标记、缺失的必填字段，前端整表保存（db_save_records）时会因一条记录不合法而整体失败。
+/// 2. 逐条按 `RaidRecord` 解析：可修复的问题（见 `repair_record_json`）修复后写回 data；
+///    合法记录按结构体同步索引列（raid_name 统一为完整副本名，旧版扫描写入的是短名）。
+/// 3. 仍无法解析的记录保持原样，ID 与错误写入 app_config `v29_invalid_records` 并输出日志；
+///    `db_save_records` 对前端原样回传的此类记录跳过校验，不阻塞其他记录保存。
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    log::info!("========== V29 迁移开始 ==========");
+
+    let rows: Vec<RecordRow> = {
+        let mut stmt = conn
+            .prepare("SELECT id, data, account_id, role_id, raid_name, record_date FROM records")
+            .map_err(|e| format!("V29 准备记录查询失败: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })
+            .map_err(|e| format!("V29 查询记录失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("V29 读取记录失败: {}", e))?;
+        rows
+    };
+
+    let mut repaired = 0usize;
+    let mut synced = 0usize;
+    let mut invalid: Vec<Value> = Vec::new();
+    for row in &rows {
+        let id = &row.0;
+        let mut json: Value = match row.1.as_deref().map(serde_json::from_str::<Value>) {
+            Some(Ok(json)) if json.is_object() => json,
+            _ => {
+                log::warn!("[V29] 记录 {} 的 data 不是 JSON 对象，保持原样", id);
+                invalid.push(serde_json::json!({ "id": id, "error": "data 不是 JSON 对象" }));
+                continue;
+            }
+        };
+        let changed = json
+            .as_object_mut()
+            .is_some_and(|obj| repair_record_json(obj, row));
+        let record = match RaidRecord::from_value(json) {
+            Ok(record) => record,
+            Err(e) => {
+                log::warn!("[V29] 记录 {} 无法修复，保持原样: {}", id, e);
+                invalid.push(serde_json::json!({ "id": id, "error": e }));
+                continue;
+            }
+        };
+        if record.id != *id {
+            log::warn!("[V29] 记录 {} 的 data.id 不一致: {}", id, record.id);
+            invalid.push(serde_json::json!({ "id": id, "error": "data.id 与记录 ID 不一致" }));
+            continue;
+        }
+
+        let data = if changed {
+            repaired += 1;
+            record.to_json_string()?
+        } else {
+            row.1.clone().unwrap_or_default()
+        };
+        let updated = conn
+            .execute(
+                "UPDATE records SET
+                    data = ?2, raid_name = ?3, account_id = ?4, role_id = ?5, record_date = ?6,
+                    record_type = ?7, status = ?8, drops = ?9, jcl_files = ?10, run_group_id = ?11
+                 WHERE id = ?1 AND NOT (
+                    data IS ?2 AND raid_name IS ?3 AND account_id IS ?4 AND role_id IS ?5
+                    AND record_date IS ?6 AND record_type IS ?7 AND status IS ?8
+                    AND drops IS ?9 AND jcl_files IS ?10 AND run_group_id IS ?11
+                 )",
+                params![
+                    id,
+                    data,
+                    record.raid_name,
+                    record.account_id,
+                    record.role_id,
+                    record.record_date_ms(),
+                    record.record_type_str(),
+                    record.status_str(),
+                    json_list(&record.drops),
+                    json_list(&record.jcl_files),
+                    record.run_group_id,
+                ],
+            )
+            .map_err(|e| format!("V29 写回记录 {} 失败: {}", id, e))?;
+        synced += updated;
+    }
+    log::info!(
+        "[V29] 校验 {} 条记录: 修复 {} 条, 同步索引列 {} 条, 无法修复 {} 条",
+        rows.len(),
+        repaired,
+        synced,
+        invalid.len()
+    );
+
+    let now = chrono::Local::now().to_rfc3339();
+    if !invalid.is_empty() {
+        conn.execute(
+            "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v29_invalid_records', ?1, ?2)",
+            params![Value::from(invalid).to_string(), now],
+        )
+        .map_err(|e| format!("V29 写入不合法记录报告失败: {}", e))?;
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES ('v29_record_validation_migrated', 'true', ?1)",
+        params![now],
+    )
+    .map_err(|e| format!("V29 写入迁移标记失败: {}", e))?;
+
+    log::info!("========== V29 迁移完成 ==========");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_prerequisites(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE app_config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE records (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                raid_name TEXT,
+                account_id TEXT,
+                role_id TEXT,
+                record_date INTEGER,
+                record_type TEXT,
+                source TEXT DEFAULT 'manual',
+                status TEXT DEFAULT 'confirmed',
+                drops TEXT,
+                jcl_files TEXT,
+                created_at TEXT,
+                updated_at TEXT,
+                run_group_id TEXT
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    fn data_of(conn: &Connection, id: &str) -> Value {
+        let data: String = conn
+            .query_row(
+                "SELECT data FROM records WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        serde_json::from_str(&data).unwrap()
+    }
+
+    #[test]
+    fn test_v29_repairs_and_reports_records() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup_prerequisites(&conn);
+        conn.execute_batch(
+            r#"
+            INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, source, status)
+            VALUES
+              ('scan', '{"id":"scan","accountId":"a1","roleId":"r1","raidName":"25人英雄阆风悬城","date":1710000000000,"goldIncome":3000,"hasXuanjing":false,"source":"auto","status":"pending","drops":["玄晶"]}',
+               '阆风悬城', 'a1', 'r1', 1710000000000, 'auto_scan', 'pending'),
+              ('legacy', '{"accountId":"a1","raidName":"25人英雄阆风悬城","date":"1710000000000","goldIncome":"1200","goldIncomeCopper":12000000.0,"hasXuanjing":null,"hasMount":"true","notes":null}',
+               '25人英雄阆风悬城', 'a1', 'r1', 1710000000000, 'manual', 'confirmed'),
+              ('bad', '{"id":"bad","accountId":"a1","roleId":"r1","raidName":"25人英雄阆风悬城","date":1710000000000,"goldIncome":-5,"hasXuanjing":false,"legacyField":1}',
+               '25人英雄阆风悬城', 'a1', 'r1', 1710000000000, 'manual', 'confirmed'),
+              ('broken', 'not json', NULL, NULL, NULL, NULL, 'manual', 'confirmed');
+            "#,
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // 幂等：重复执行不报错
+        migrate(&conn).unwrap();
+
+        // 合法记录只同步索引列，raid_name 统一为完整副本名
+        let (raid_name, drops): (String, String) = conn
+            .query_row(
+                "SELECT raid_name, drops FROM records WHERE id = 'scan'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(raid_name, "25人英雄阆风悬城");
+        assert_eq!(drops, r#"["玄晶"]"#);
+
+        let legacy = data_of(&conn, "legacy");
+        assert!(RaidRecord::from_value(legacy.clone()).is_ok());
+        assert_eq!(legacy["id"], "legacy");
+        assert_eq!(legacy["roleId"], "r1");
+        assert_eq!(legacy["goldIncome"], 1200);
+        assert_eq!(legacy["goldIncomeCopper"], 12_000_000);
+        assert_eq!(legacy["hasXuanjing"], false);
+        assert_eq!(legacy["hasMount"], true);
+
+        // 无法修复的记录保持原样并写入报告
+        assert_eq!(data_of(&conn, "bad")["legacyField"], 1);
+        let report: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v29_invalid_records'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let report: Vec<Value> = serde_json::from_str(&report).unwrap();
+        let ids: Vec<&str> = report.iter().filter_map(|r| r["id"].as_str()).collect();
+        assert_eq!(ids, vec!["bad", "broken"]);
+
+        let flag: String = conn
+            .query_row(
+                "SELECT value FROM app_config WHERE key = 'v29_record_validation_migrated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(flag, "true");
+    }
+}
@@ -0,0 +1,445 @@
+//! 副本记录（records.data）的强类型模型
+//!
+//! records.data 以 JSON 存储，字段与前端 `RaidRecord` 类型一致，另含扫描 / GKP 导入写入的
+//! `region`、`mapId`、`dropTable`、`gkp`。所有写入路径（前端的 `db_add_record`、`db_save_records`，
+//! 掉落扫描、确认 / 驳回、多开同团分组、重新扫描、GKP 导入、撤销）都先构造 [`RaidRecord`]：
+//! 未知字段或类型不符直接拒绝，再经 [`upsert_record`] 写入，索引列（raid_name 为完整副本名、
+//! account_id、role_id、record_date、run_group_id 等）统一由结构体生成，保证 data 与列一致。
+//! 铜字段为唯一的存储金额，金字段在解析时由铜字段派生（[`RaidRecord::sync_gold`]）。
+//! 升级前写入的不合法记录由 V29 迁移修复或报告。
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::money;
+
+/// 记录确认状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordStatus {
+    Pending,
+    Confirmed,
+    Rejected,
+    /// 副本进行中，UI 锁定不可确认
+    Scanning,
+}
+
+impl RecordStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RecordStatus::Pending => "pending",
+            RecordStatus::Confirmed => "confirmed",
+            RecordStatus::Rejected => "rejected",
+            RecordStatus::Scanning => "scanning",
+        }
+    }
+}
+
+/// 记录来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordSource {
+    Auto,
+    Manual,
+    /// GKP 拍卖账本导入（source 列为 gkp_import）
+    Gkp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordType {
+    Raid,
+    Trial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionType {
+    Income,
+    Expense,
+    Combined,
+}
+
+/// 记录日期：毫秒时间戳，或字符串形式的时间戳 / RFC3339 时间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecordDate {
+    Millis(i64),
+    Text(String),
+}
+
+impl RecordDate {
+    /// 转换为毫秒时间戳，无法解析时返回 None
+    pub fn to_millis(&self) -> Option<i64> {
+        match self {
+            RecordDate::Millis(ms) => Some(*ms),
+            RecordDate::Text(text) => text.parse::<i64>().ok().or_else(|| {
+                chrono::DateTime::parse_from_rfc3339(text)
+                    .ok()
+                    .map(|parsed| parsed.timestamp_millis())
+            }),
+        }
+    }
+}
+
+/// 副本记录（records.data）
+///
+/// 金额字段保留 JSON 原始数值（整数仍写为整数），其余读取方按 `as_i64` / `as_f64` 读取不受影响。
+/// 可选字段为 None 时不写入 JSON。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RaidRecord {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    pub account_id: String,
+    pub role_id: String,
+    pub raid_name: String,
+    pub date: RecordDate,
+    pub gold_income: serde_json::Number,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gold_expense: Option<serde_json::Number>,
+    /// 铜精度收入，唯一的存储金额；goldIncome 由它派生（见 [`RaidRecord::sync_gold`]）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gold_income_copper: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gold_expense_copper: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_cleared: Option<bool>,
+    #[serde(default)]
+    pub has_xuanjing: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<TransactionType>,
+    #[serde(default, rename = "hasMaJu", skip_serializing_if = "Option::is_none")]
+    pub has_maju: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_pet: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_pendant: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_mount: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_appearance: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_title: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_secret_book: Option<bool>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<RecordType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boss_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boss_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boss_ids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boss_names: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<RecordSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<RecordStatus>,
+    /// 掉落物原始物品名（自动扫描 / GKP 导入）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drops: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jcl_files: Option<Vec<String>>,
+    /// JCL 文件名中的副本 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map_id: Option<i64>,
+    /// 旧版理论掉落表，新扫描固定为 null
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_table: Option<serde_json::Value>,
+    /// GKP 导入的拍卖账本（结构由 gkp_parser 维护）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gkp: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_group_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_group_primary: Option<bool>,
+}
+
+/// 校验金额：必须为非负有限数
+fn validate_gold(field: &str, value: &serde_json::Number) -> Result<(), String> {
+    match value.as_f64() {
+        Some(gold) if gold.is_finite() && gold >= 0.0 => Ok(()),
+        _ => Err(format!("{} 必须为非负数: {}", field, value)),
+    }
+}
+
+impl RaidRecord {
+    /// 从 JSON 值解析并校验记录
+    pub fn from_value(value: serde_json::Value) -> Result<Self, String> {
+        let id = value
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let mut record: RaidRecord = serde_json::from_value(value)
+            .map_err(|e| format!("记录 {} 数据格式错误: {}", id, e))?;
+        record.validate()?;
+        record.sync_gold();
+        Ok(record)
+    }
+
+    /// 从 JSON 字符串解析并校验记录
+    pub fn from_json_str(data: &str) -> Result<Self, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(data).map_err(|e| format!("解析记录 JSON 失败: {}", e))?;
+        Self::from_value(value)
+    }
+
+    /// 类型之外的约束：必填字符串非空、日期可解析、金额非负
+    pub fn validate(&self) -> Result<(), String> {
+        let required = [
+            ("id", &self.id),
+            ("accountId", &self.account_id),
+            ("roleId", &self.role_id),
+            ("raidName", &self.raid_name),
+        ];
+        for (field, value) in required {
+            if value.trim().is_empty() {
+                return Err(format!("记录 {} 缺少 {}", self.id, field));
+            }
+        }
+        if self.date.to_millis().is_none() {
+            return Err(format!(
+                "记录 {} 的 date 无法解析: {:?}",
+                self.id, self.date
+            ));
+        }
+        validate_gold("goldIncome", &self.gold_income)?;
+        if let Some(expense) = &self.gold_expense {
+            validate_gold("goldExpense", expense)?;
+        }
+        for (field, copper) in [
+            ("goldIncomeCopper", self.gold_income_copper),
+            ("goldExpenseCopper", self.gold_expense_copper),
+        ] {
+            if copper.is_some_and(|c| c < 0) {
+                return Err(format!("记录 {} 的 {} 不能为负数", self.id, field));
+            }
+        }
+        Ok(())
+    }
+
+    /// 由铜字段派生金字段，使写入的 goldIncome / goldExpense 始终与铜精度金额一致
+    ///
+    /// 缺少铜字段的旧记录先按金字段补全铜字段；之后金字段只是铜金额的整金部分。
+    pub fn sync_gold(&mut self) {
+        let income = money::stored_money(
+            self.gold_income.as_f64().unwrap_or(0.0),
+            self.gold_income_copper,
+        );
+        let expense = money::stored_money(
+            self.gold_expense
+                .as_ref()
+                .and_then(|n| n.as_f64())
+                .unwrap_or(0.0),
+            self.gold_expense_copper,
+        );
+        self.gold_income_copper = Some(income.copper());
+        self.gold_income = income.gold().into();
+        self.gold_expense_copper = Some(expense.copper());
+        if self.gold_expense.is_some() || expense != money::Money::ZERO {
+            self.gold_expense = Some(expense.gold().into());
+        }
+    }
+
+    pub fn to_json_string(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("序列化记录失败: {}", e))
+    }
+
+    pub fn record_date_ms(&self) -> Option<i64> {
+        self.date.to_millis()
+    }
+
+    /// records.record_type 列（缺省为 raid）
+    pub fn record_type_str(&self) -> &'static str {
+        match self.record_type {
+            Some(RecordType::Trial) => "trial",
+            _ => "raid",
+        }
+    }
+
+    /// records.status 列（缺省为 confirmed，与表默认值一致）
+    pub fn status_str(&self) -> &'static str {
+        self.status.unwrap_or(RecordStatus::Confirmed).as_str()
+    }
+
+    /// records.source 列（自动扫描写入的列值为 auto_scan，GKP 导入为 gkp_import）
+    pub fn source_column(&self) -> &'static str {
+        match self.source {
+            Some(RecordSource::Auto) => "auto_scan",
+            Some(RecordSource::Gkp) => "gkp_import",
+            _ => "manual",
+        }
+    }
+}
+
+pub(crate) fn json_list(list: &Option<Vec<String>>) -> Option<String> {
+    list.as_ref()
+        .map(|items| serde_json::to_string(items).unwrap_or_else(|_| "[]".to_string()))
+}
+
+/// 写入（新增或覆盖）记录，data 与索引列均由结构体生成
+///
+/// 已有记录的 source 列保持不变（GKP 导入的 gkp_import 等列值不会被前端保存覆盖）。
+pub(crate) fn upsert_record(conn: &Connection, record: &RaidRecord) -> Result<(), String> {
+    let data = record.to_json_string()?;
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, record_type,
+                              source, status, drops, jcl_files, run_group_id, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13)
+         ON CONFLICT(id) DO UPDATE SET
+            data = excluded.data,
+            raid_name = excluded.raid_name,
+            account_id = excluded.account_id,
+            role_id = excluded.role_id,
+            record_date = excluded.record_date,
+            record_type = excluded.record_type,
+            status = excluded.status,
+            drops = excluded.drops,
+            jcl_files = excluded.jcl_files,
+            run_group_id = excluded.run_group_id,
+            updated_at = excluded.updated_at",
+        params![
+            record.id,
+            data,
+            record.raid_name,
+            record.account_id,
+            record.role_id,
+            record.record_date_ms(),
+            record.record_type_str(),
+            record.source_column(),
+            record.status_str(),
+            json_list(&record.drops),
+            json_list(&record.jcl_files),
+            record.run_group_id,
+            now,
+        ],
+    )
+    .map_err(|e| format!("写入记录 {} 失败: {}", record.id, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raid_record_validation_and_columns() {
+        let conn = crate::db::open_test_db();
+
+        let scanned = serde_json::json!({
+            "id": "r1", "accountId": "a1", "roleId": "role1", "raidName": "25人英雄阆风悬城",
+            "date": 1_710_000_000_000i64, "goldIncome": 147, "goldIncomeCopper": 1_477_653,
+            "hasXuanjing": true, "hasMaJu": false, "type": "raid", "source": "auto",
+            "status": "pending", "drops": ["玄晶"], "dropTable": null, "mapId": 650,
+        });
+        let record = RaidRecord::from_value(scanned).unwrap();
+        upsert_record(&conn, &record).unwrap();
+
+        let (data, source, status, date, drops): (String, String, String, i64, String) = conn
+            .query_row(
+                "SELECT data, source, status, record_date, drops FROM records WHERE id = 'r1'",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!((source.as_str(), status.as_str()), ("auto_scan", "pending"));
+        assert_eq!(date, 1_710_000_000_000);
+        assert_eq!(drops, r#"["玄晶"]"#);
+        let stored: serde_json::Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(stored["goldIncome"].as_i64(), Some(147));
+        assert_eq!(stored["hasMaJu"], serde_json::json!(false));
+        assert!(stored.get("dropTable").is_none());
+
+        // 手动记录：RFC3339 日期，缺省状态为 confirmed
+        let manual = RaidRecord::from_json_str(
+            r#"{"id":"r2","accountId":"a1","roleId":"role1","raidName":"10人普通弓月城",
+                "date":"2024-03-10T00:00:00+08:00","goldIncome":12.5,"hasXuanjing":false}"#,
+        )
+        .unwrap();
+        assert_eq!(manual.status_str(), "confirmed");
+        assert_eq!(manual.record_date_ms(), Some(1_710_000_000_000));
+
+        let unknown = serde_json::json!({
+            "id": "r3", "accountId": "a1", "roleId": "role1", "raidName": "x",
+            "date": 0, "goldIncome": 0, "goldIncom": 100,
+        });
+        assert!(RaidRecord::from_value(unknown)
+            .unwrap_err()
+            .contains("goldIncom"));
+
+        let ill_typed = serde_json::json!({
+            "id": "r4", "accountId": "a1", "roleId": "role1", "raidName": "x",
+            "date": 0, "goldIncome": "100",
+        });
+        assert!(RaidRecord::from_value(ill_typed).is_err());
+
+        let bad_status = serde_json::json!({
+            "id": "r5", "accountId": "a1", "roleId": "role1", "raidName": "x",
+            "date": 0, "goldIncome": 0, "status": "done",
+        });
+        assert!(RaidRecord::from_value(bad_status).is_err());
+
+        let negative = serde_json::json!({
+            "id": "r6", "accountId": "a1", "roleId": "role1", "raidName": "x",
+            "date": 0, "goldIncome": -1,
+        });
+        assert!(RaidRecord::from_value(negative)
+            .unwrap_err()
+            .contains("goldIncome"));
+    }
+
+    #[test]
+    fn test_sync_gold_follows_copper() {
+        let base = |copper: serde_json::Value| {
+            serde_json::json!({
+                "id": "r1", "accountId": "a1", "roleId": "role1", "raidName": "x",
+                "date": 0, "goldIncome": 147, "goldIncomeCopper": copper,
+            })
+        };
+
+        let scanned = RaidRecord::from_value(base(serde_json::json!(1_477_653))).unwrap();
+        assert_eq!(scanned.gold_income_copper, Some(1_477_653));
+        assert_eq!(scanned.gold_expense_copper, Some(0));
+        assert_eq!(scanned.gold_expense, None);
+
+        // 只修改了银铜部分：铜字段保留编辑结果
+        let edited = RaidRecord::from_value(base(serde_json::json!(1_470_001))).unwrap();
+        assert_eq!(edited.gold_income_copper, Some(1_470_001));
+
+        // 修改后的铜金额跨越整金：金字段随之重算
+        let raised = RaidRecord::from_value(base(serde_json::json!(2_000_000))).unwrap();
+        let stored: serde_json::Value =
+            serde_json::from_str(&raised.to_json_string().unwrap()).unwrap();
+        assert_eq!(stored["goldIncome"], serde_json::json!(200));
+        assert_eq!(stored["goldIncomeCopper"], serde_json::json!(2_000_000));
+
+        // 没有铜字段的旧记录按金字段补全
+        let mut legacy = base(serde_json::json!(0));
+        legacy.as_object_mut().unwrap().remove("goldIncomeCopper");
+        legacy["goldExpense"] = serde_json::json!(3000);
+        let legacy = RaidRecord::from_value(legacy).unwrap();
+        assert_eq!(legacy.gold_income_copper, Some(1_470_000));
+        assert_eq!(legacy.gold_expense_copper, Some(30_000_000));
+    }
+}
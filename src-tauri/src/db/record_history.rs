@@ -0,0 +1,306 @@
+//! 记录变更历史与撤销
+//!
+//! 确认、驳回、编辑、删除、批量保存记录时，把变更前后的 records.data 快照写入 `record_history`，
+//! `db_undo_record_change` 把记录恢复到某条历史变更之前的版本。
+//! 前端会整表调用 `db_save_records`，内容未变化的记录不写历史。
+//! 历史与回收站共用保留天数，过期条目在清理回收站时一并清除。
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::init_db;
+use super::raid_record::{self, RaidRecord};
+use super::trash;
+
+/// 历史动作
+pub const ACTION_CREATE: &str = "create";
+pub const ACTION_UPDATE: &str = "update";
+pub const ACTION_CONFIRM: &str = "confirm";
+pub const ACTION_REJECT: &str = "reject";
+pub const ACTION_DELETE: &str = "delete";
+pub const ACTION_RESCAN: &str = "rescan";
+pub const ACTION_UNDO: &str = "undo";
+pub const ACTION_RESTORE: &str = "restore";
+
+/// 单条变更历史
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordHistoryEntry {
+    pub id: i64,
+    pub record_id: String,
+    /// create / update / confirm / reject / delete / rescan / undo / restore
+    pub action: String,
+    /// 变更前的 data JSON，新建时为 None
+    pub before_data: Option<String>,
+    /// 变更后的 data JSON，删除时为 None
+    pub after_data: Option<String>,
+    pub created_at: String,
+}
+
+/// 读取记录当前的 data JSON，记录不存在时返回 None
+pub(crate) fn current_record_data(
+    conn: &Connection,
+    record_id: &str,
+) -> Result<Option<String>, String> {
+    match conn.query_row(
+        "SELECT data FROM records WHERE id = ?1",
+        params![record_id],
+        |row| row.get::<_, Option<String>>(0),
+    ) {
+        Ok(data) => Ok(data),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("查询记录 {} 失败: {}", record_id, e)),
+    }
+}
+
+/// 两个快照内容是否一致（按 JSON 比较，忽略字段顺序）
+fn same_snapshot(before: Option<&str>, after: Option<&str>) -> bool {
+    match (before, after) {
+        (None, None) => true,
+        (Some(before), Some(after)) => {
+            before == after
+                || matches!(
+                    (
+                        serde_json::from_str::<serde_json::Value>(before),
+                        serde_json::from_str::<serde_json::Value>(after),
+                    ),
+                    (Ok(b), Ok(a)) if a == b
+                )
+        }
+        _ => false,
+    }
+}
+
+/// 写入一条变更历史，前后快照一致时跳过
+pub(crate) fn append_history(
+    conn: &Connection,
+    record_id: &str,
+    action: &str,
+    before: Option<&str>,
+    after: Option<&str>,
+) -> Result<(), String> {
+    if same_snapshot(before, after) {
+        return Ok(());
+    }
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO record_history (record_id, action, before_data, after_data, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![record_id, action, before, after, now],
+    )
+    .map_err(|e| format!("写入记录 {} 变更历史失败: {}", record_id, e))?;
+    Ok(())
+}
+
+/// 写入记录并追加历史（新建为 create，已有记录为 `update_action`）
+pub(crate) fn upsert_record_with_history(
+    conn: &Connection,
+    record: &RaidRecord,
+    update_action: &str,
+) -> Result<(), String> {
+    let before = current_record_data(conn, &record.id)?;
+    raid_record::upsert_record(conn, record)?;
+    let after = current_record_data(conn, &record.id)?;
+    let action = if before.is_some() {
+        update_action
+    } else {
+        ACTION_CREATE
+    };
+    append_history(
+        conn,
+        &record.id,
+        action,
+        before.as_deref(),
+        after.as_deref(),
+    )
+}
+
+/// 清除早于 `cutoff_ms` 的变更历史，返回清除数量
+pub(crate) fn prune_history(conn: &Connection, cutoff_ms: i64) -> Result<usize, String> {
+    let cutoff = chrono::DateTime::from_timestamp_millis(cutoff_ms)
+        .ok_or_else(|| format!("无效的清除时间: {}", cutoff_ms))?
+        .to_rfc3339();
+    conn.execute(
+        "DELETE FROM record_history WHERE julianday(created_at) < julianday(?1)",
+        params![cutoff],
+    )
+    .map_err(|e| format!("清除过期变更历史失败: {}", e))
+}
+
+/// 查询记录的变更历史（最新在前）
+pub(crate) fn query_record_history(
+    conn: &Connection,
+    record_id: &str,
+) -> Result<Vec<RecordHistoryEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, record_id, action, before_data, after_data, created_at
+             FROM record_history WHERE record_id = ?1
+             ORDER BY id DESC",
+        )
+        .map_err(|e| format!("准备变更历史查询失败: {}", e))?;
+    let entries = stmt
+        .query_map(params![record_id], |row| {
+            Ok(RecordHistoryEntry {
+                id: row.get(0)?,
+                record_id: row.get(1)?,
+                action: row.get(2)?,
+                before_data: row.get(3)?,
+                after_data: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("查询变更历史失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取变更历史失败: {}", e))?;
+    Ok(entries)
+}
+
+/// 撤销一条历史变更：把记录恢复为该变更之前的版本
+///
+/// 恢复的版本经 `RaidRecord` 校验后写回；记录当前已被删除且在回收站中时，
+/// 先从回收站插回记录及其战斗尝试、chatlog 明细等附属数据，再覆盖为变更前的版本。
+/// 变更前记录不存在（create）时把记录连同附属数据移入回收站，而不是直接删除。
+pub(crate) fn undo_record_change(conn: &mut Connection, history_id: i64) -> Result<String, String> {
+    let (record_id, before_data): (String, Option<String>) = conn
+        .query_row(
+            "SELECT record_id, before_data FROM record_history WHERE id = ?1",
+            params![history_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("变更历史不存在: {}", history_id),
+            e => format!("查询变更历史失败: {}", e),
+        })?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    let current = current_record_data(&tx, &record_id)?;
+    match &before_data {
+        Some(data) => {
+            let record = RaidRecord::from_json_str(data)?;
+            if record.id != record_id {
+                return Err(format!("历史快照的记录 ID 不匹配: {}", record.id));
+            }
+            if current.is_none() {
+                trash::take_record_from_trash(&tx, &record_id)?;
+            }
+            raid_record::upsert_record(&tx, &record)?;
+            let restored = current_record_data(&tx, &record_id)?;
+            append_history(
+                &tx,
+                &record_id,
+                ACTION_UNDO,
+                current.as_deref(),
+                restored.as_deref(),
+            )?;
+        }
+        None => {
+            trash::trash_record_as(&tx, &record_id, ACTION_UNDO)?;
+        }
+    }
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+
+    log::info!(
+        "[RecordHistory] 记录 {} 已撤销变更 #{}",
+        record_id,
+        history_id
+    );
+    Ok(record_id)
+}
+
+/// Tauri 命令：查询记录的变更历史（最新在前）
+#[tauri::command]
+pub fn db_get_record_history(record_id: String) -> Result<Vec<RecordHistoryEntry>, String> {
+    let conn = init_db()?;
+    query_record_history(&conn, &record_id)
+}
+
+/// Tauri 命令：撤销一条历史变更，返回被恢复的记录 ID
+#[tauri::command]
+pub fn db_undo_record_change(history_id: i64) -> Result<String, String> {
+    let mut conn = init_db()?;
+    undo_record_change(&mut conn, history_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(gold: i64) -> RaidRecord {
+        RaidRecord::from_value(serde_json::json!({
+            "id": "r1", "accountId": "a1", "roleId": "role1", "raidName": "25人英雄阆风悬城",
+            "date": 1_710_000_000_000i64, "goldIncome": gold, "hasXuanjing": false,
+        }))
+        .unwrap()
+    }
+
+    fn gold_of(conn: &Connection) -> Option<i64> {
+        current_record_data(conn, "r1")
+            .unwrap()
+            .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+            .and_then(|json| json["goldIncome"].as_i64())
+    }
+
+    #[test]
+    fn test_record_history_and_undo() {
+        let mut conn = crate::db::open_test_db();
+
+        upsert_record_with_history(&conn, &record(3000), ACTION_UPDATE).unwrap();
+        // 批量保存未变化的记录不写历史
+        upsert_record_with_history(&conn, &record(3000), ACTION_UPDATE).unwrap();
+        // 批量保存覆盖了手动修改的工资
+        upsert_record_with_history(&conn, &record(0), ACTION_UPDATE).unwrap();
+
+        let history = query_record_history(&conn, "r1").unwrap();
+        let actions: Vec<&str> = history.iter().map(|h| h.action.as_str()).collect();
+        assert_eq!(actions, vec![ACTION_UPDATE, ACTION_CREATE]);
+        assert!(history[1].before_data.is_none());
+
+        undo_record_change(&mut conn, history[0].id).unwrap();
+        assert_eq!(gold_of(&conn), Some(3000));
+
+        // 撤销 create 把记录连同附属数据移入回收站，撤销本身也可再撤销
+        conn.execute(
+            "INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, boss_hp_percent, created_at)
+             VALUES ('r1', '笑妆娘', 'a.jcl', 1, 0, 1000, 1000, 1, 0.0, '')",
+            [],
+        )
+        .unwrap();
+        let count = |conn: &Connection, sql: &str| -> i64 {
+            conn.query_row(sql, [], |row| row.get(0)).unwrap()
+        };
+        undo_record_change(&mut conn, history[1].id).unwrap();
+        assert_eq!(gold_of(&conn), None);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM raid_attempts"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM trash_items"), 1);
+        let latest = query_record_history(&conn, "r1").unwrap();
+        assert_eq!(latest[0].action, ACTION_UNDO);
+        undo_record_change(&mut conn, latest[0].id).unwrap();
+        assert_eq!(gold_of(&conn), Some(3000));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM raid_attempts"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM trash_items"), 0);
+
+        assert!(undo_record_change(&mut conn, 999).is_err());
+    }
+
+    #[test]
+    fn test_prune_history() {
+        let conn = crate::db::open_test_db();
+        upsert_record_with_history(&conn, &record(3000), ACTION_UPDATE).unwrap();
+        upsert_record_with_history(&conn, &record(0), ACTION_UPDATE).unwrap();
+        conn.execute(
+            "UPDATE record_history SET created_at = '2024-03-01T08:00:00.123456789+08:00'
+             WHERE action = ?1",
+            params![ACTION_CREATE],
+        )
+        .unwrap();
+
+        let cutoff = chrono::Local::now().timestamp_millis() - 24 * 60 * 60 * 1000;
+        assert_eq!(prune_history(&conn, cutoff).unwrap(), 1);
+        let history = query_record_history(&conn, "r1").unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].action, ACTION_UPDATE);
+    }
+}
@@ -0,0 +1,497 @@
+//! 记录筛选与分页查询
+//!
+//! `db_get_records` 一次返回全部记录、由前端过滤，多开多年的数据量下很慢。
+//! `db_query_records` 在 SQLite 中按筛选条件过滤，并按 (排序键, id) 做游标分页，
+//! 翻页结果不受新增记录影响。
+//!
+//! 按副本时间排序时直接以 record_date 列排序，可沿 idx_records_record_date 顺序读取
+//! （record_date 为空的记录不参与该排序）；
+//! 按收入排序需逐行读取 data JSON，不走索引。总数统计与分页使用同一筛选条件，
+//! 时间、账号、角色等列条件可使用对应的 idx_records_* 索引，JSON 字段条件为逐行过滤。
+
+use rusqlite::{Connection, ToSql};
+use serde::{Deserialize, Serialize};
+
+use super::init_db;
+
+/// 特殊掉落类别 → 记录 JSON 中的标记字段
+pub(crate) const RARE_DROP_FLAGS: [(&str, &str); 8] = [
+    ("xuanjing", "hasXuanjing"),
+    ("mount", "hasMount"),
+    ("secretBook", "hasSecretBook"),
+    ("pet", "hasPet"),
+    ("maju", "hasMaJu"),
+    ("pendant", "hasPendant"),
+    ("appearance", "hasAppearance"),
+    ("title", "hasTitle"),
+];
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// 记录筛选条件，未设置的条件不过滤
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecordFilter {
+    /// 副本时间下限（毫秒，闭区间）
+    pub start_ms: Option<i64>,
+    /// 副本时间上限（毫秒，开区间）
+    pub end_ms: Option<i64>,
+    /// 赛季 ID（按 seasons 表的起止时间过滤）
+    pub season_id: Option<i64>,
+    pub account_ids: Vec<String>,
+    pub role_ids: Vec<String>,
+    /// 完整副本名（如 "25人英雄阆风悬城"）
+    pub raid_names: Vec<String>,
+    /// 难度（如 "英雄"），匹配副本名中人数之后的难度部分
+    pub difficulty: Option<String>,
+    pub statuses: Vec<String>,
+    pub sources: Vec<String>,
+    /// 特殊掉落类别，命中任一即可：xuanjing / mount / secretBook / pet / maju / pendant / appearance / title
+    pub rare_drops: Vec<String>,
+    /// 在备注和掉落物中搜索的文本
+    pub text: Option<String>,
+}
+
+/// 排序字段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordSortField {
+    #[default]
+    Date,
+    GoldIncome,
+}
+
+impl RecordSortField {
+    fn key_sql(self) -> &'static str {
+        match self {
+            RecordSortField::Date => "record_date",
+            // 按铜精度金额排序，缺少铜字段的旧记录按金字段换算
+            RecordSortField::GoldIncome => {
+                "COALESCE(json_extract(data, '$.goldIncomeCopper'), \
+                 CAST(json_extract(data, '$.goldIncome') AS REAL) * 10000, 0)"
+            }
+        }
+    }
+}
+
+/// 排序方式，默认按副本时间倒序
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecordSort {
+    pub field: RecordSortField,
+    pub ascending: bool,
+}
+
+/// 一页查询结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordPage {
+    /// 记录 data JSON（与 db_get_records 相同）
+    pub records: Vec<String>,
+    /// 下一页游标，None 表示已是最后一页
+    pub next_cursor: Option<String>,
+    /// 满足筛选条件的记录总数
+    pub total: i64,
+}
+
+/// 分页游标：上一页最后一条记录的排序键和 id
+#[derive(Debug, Serialize, Deserialize)]
+struct PageCursor {
+    key: f64,
+    id: String,
+}
+
+#[derive(Default)]
+struct WhereClause {
+    conditions: Vec<String>,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl WhereClause {
+    fn push(&mut self, condition: impl Into<String>, params: Vec<Box<dyn ToSql>>) {
+        self.conditions.push(condition.into());
+        self.params.extend(params);
+    }
+
+    fn push_in(&mut self, column: &str, values: &[String]) {
+        if values.is_empty() {
+            return;
+        }
+        let placeholders = vec!["?"; values.len()].join(", ");
+        self.push(
+            format!("{} IN ({})", column, placeholders),
+            values
+                .iter()
+                .map(|v| Box::new(v.clone()) as Box<dyn ToSql>)
+                .collect(),
+        );
+    }
+
+    fn sql(&self) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", self.conditions.join(" AND "))
+        }
+    }
+}
+
+/// 转义 LIKE 通配符，配合 `ESCAPE '\'` 使用
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+fn build_where(conn: &Connection, filter: &RecordFilter) -> Result<WhereClause, String> {
+    let mut clause = WhereClause::default();
+
+    if let Some(start_ms) = filter.start_ms {
+        clause.push("record_date >= ?", vec![Box::new(start_ms)]);
+    }
+    if let Some(end_ms) = filter.end_ms {
+        clause.push("record_date < ?", vec![Box::new(end_ms)]);
+    }
+    if let Some(season_id) = filter.season_id {
+        // seasons 表的起止时间为秒
+        let (start_date, end_date): (i64, Option<i64>) = match conn.query_row(
+            "SELECT start_date, end_date FROM seasons WHERE id = ?1",
+            [season_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(range) => range,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Err(format!("赛季不存在: {}", season_id))
+            }
+            Err(e) => return Err(format!("查询赛季失败: {}", e)),
+        };
+        clause.push("record_date >= ?", vec![Box::new(start_date * 1000)]);
+        if let Some(end_date) = end_date.filter(|d| *d > 0) {
+            clause.push("record_date < ?", vec![Box::new(end_date * 1000)]);
+        }
+    }
+
+    clause.push_in("account_id", &filter.account_ids);
+    clause.push_in("role_id", &filter.role_ids);
+    clause.push_in("raid_name", &filter.raid_names);
+    clause.push_in("status", &filter.statuses);
+    clause.push_in("source", &filter.sources);
+
+    if let Some(difficulty) = filter.difficulty.as_deref().filter(|d| !d.is_empty()) {
+        // 副本名格式 "{人数}人{难度}{副本名}"
+        clause.push(
+            "raid_name LIKE ? ESCAPE '\\'",
+            vec![Box::new(like_pattern(&format!("人{}", difficulty)))],
+        );
+    }
+
+    if !filter.rare_drops.is_empty() {
+        let mut flags = Vec::with_capacity(filter.rare_drops.len());
+        for category in &filter.rare_drops {
+            let key = RARE_DROP_FLAGS
+                .iter()
+                .find(|(name, _)| name == category)
+                .map(|(_, key)| *key)
+                .ok_or_else(|| format!("未知的特殊掉落类别: {}", category))?;
+            flags.push(format!("json_extract(data, '$.{}') = 1", key));
+        }
+        clause.push(format!("({})", flags.join(" OR ")), Vec::new());
+    }
+
+    if let Some(text) = filter
+        .text
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    {
+        let pattern = like_pattern(text);
+        clause.push(
+            "(json_extract(data, '$.notes') LIKE ? ESCAPE '\\' \
+             OR json_extract(data, '$.drops') LIKE ? ESCAPE '\\')",
+            vec![Box::new(pattern.clone()), Box::new(pattern)],
+        );
+    }
+
+    Ok(clause)
+}
+
+/// 按筛选条件查询一页记录
+pub(crate) fn query_records(
+    conn: &Connection,
+    filter: &RecordFilter,
+    sort: RecordSort,
+    cursor: Option<&str>,
+    limit: usize,
+) -> Result<RecordPage, String> {
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+    let mut clause = build_where(conn, filter)?;
+    if sort.field == RecordSortField::Date {
+        // 排序键直接使用列值以便走索引；空值无法参与游标比较
+        clause.push("record_date IS NOT NULL", Vec::new());
+    }
+    let where_sql = clause.sql();
+    let mut params: Vec<&dyn ToSql> = clause.params.iter().map(|p| p.as_ref()).collect();
+
+    let total: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM records{}", where_sql),
+            params.as_slice(),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("统计记录数失败: {}", e))?;
+
+    let key = sort.field.key_sql();
+    let (direction, comparison) = if sort.ascending {
+        ("ASC", ">")
+    } else {
+        ("DESC", "<")
+    };
+    let cursor = cursor
+        .map(|c| serde_json::from_str::<PageCursor>(c).map_err(|e| format!("分页游标无效: {}", e)))
+        .transpose()?;
+    let mut sql = format!("SELECT data, id, {} FROM records{}", key, where_sql);
+    if let Some(cursor) = cursor.as_ref() {
+        sql.push_str(if where_sql.is_empty() {
+            " WHERE "
+        } else {
+            " AND "
+        });
+        sql.push_str(&format!(
+            "({key} {cmp} ? OR ({key} = ? AND id {cmp} ?))",
+            key = key,
+            cmp = comparison
+        ));
+        params.push(&cursor.key);
+        params.push(&cursor.key);
+        params.push(&cursor.id);
+    }
+    // 多取一条判断是否还有下一页
+    let fetch = (limit + 1) as i64;
+    sql.push_str(&format!(
+        " ORDER BY {} {}, id {} LIMIT ?",
+        key, direction, direction
+    ));
+    params.push(&fetch);
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("准备记录查询失败: {}", e))?;
+    let mut rows: Vec<(String, String, f64)> = stmt
+        .query_map(params.as_slice(), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| format!("查询记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取记录失败: {}", e))?;
+
+    let next_cursor = if rows.len() > limit {
+        rows.truncate(limit);
+        rows.last()
+            .map(|(_, id, key)| {
+                serde_json::to_string(&PageCursor {
+                    key: *key,
+                    id: id.clone(),
+                })
+            })
+            .transpose()
+            .map_err(|e| format!("生成分页游标失败: {}", e))?
+    } else {
+        None
+    };
+
+    Ok(RecordPage {
+        records: rows.into_iter().map(|(data, _, _)| data).collect(),
+        next_cursor,
+        total,
+    })
+}
+
+/// Tauri 命令：按筛选条件分页查询记录
+///
+/// `cursor` 为上一页返回的 `nextCursor`，首页传 None；`limit` 默认 50，最大 500。
+#[tauri::command]
+pub fn db_query_records(
+    filter: RecordFilter,
+    sort: Option<RecordSort>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+) -> Result<RecordPage, String> {
+    let conn = init_db()?;
+    query_records(
+        &conn,
+        &filter,
+        sort.unwrap_or_default(),
+        cursor.as_deref(),
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    fn setup() -> Connection {
+        let conn = crate::db::open_test_db();
+        conn.execute_batch(
+            "INSERT INTO game_versions (id, name, created_at) VALUES (1, '丝路风语', '');
+             INSERT INTO seasons (id, name, version_id, start_date, end_date, sort_order, created_at)
+             VALUES (1, '太极秘录', 1, 1730329200, 1745449200, 1, '');",
+        )
+        .unwrap();
+        let base = 1_735_000_000_000_i64;
+        for i in 0..5_i64 {
+            let raid_name = if i % 2 == 0 {
+                "25人英雄阆风悬城"
+            } else {
+                "25人普通阆风悬城"
+            };
+            let data = serde_json::json!({
+                "id": format!("r{}", i),
+                "raidName": raid_name,
+                "goldIncome": 1000 * (5 - i),
+                "hasXuanjing": i == 3,
+                "notes": if i == 1 { "毕业 50%" } else { "" },
+                "drops": if i == 4 { vec!["五行石·百分百"] } else { Vec::new() },
+            });
+            conn.execute(
+                "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date)
+                 VALUES (?1, ?2, ?3, 'acc', 'role', ?4)",
+                params![
+                    format!("r{}", i),
+                    data.to_string(),
+                    raid_name,
+                    base + i * 1000
+                ],
+            )
+            .unwrap();
+        }
+        // 赛季之外的记录
+        conn.execute(
+            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date)
+             VALUES ('old', '{}', '25人英雄阆风悬城', 'acc', 'role', 1)",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn ids(page: &RecordPage) -> Vec<String> {
+        page.records
+            .iter()
+            .map(|data| {
+                serde_json::from_str::<serde_json::Value>(data).unwrap()["id"]
+                    .as_str()
+                    .unwrap_or("old")
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_query_records_filters_and_paginates() {
+        let conn = setup();
+        let filter = RecordFilter {
+            season_id: Some(1),
+            ..Default::default()
+        };
+
+        let first = query_records(&conn, &filter, RecordSort::default(), None, 2).unwrap();
+        assert_eq!(first.total, 5);
+        assert_eq!(ids(&first), vec!["r4", "r3"]);
+        let second = query_records(
+            &conn,
+            &filter,
+            RecordSort::default(),
+            first.next_cursor.as_deref(),
+            2,
+        )
+        .unwrap();
+        assert_eq!(ids(&second), vec!["r2", "r1"]);
+        let last = query_records(
+            &conn,
+            &filter,
+            RecordSort::default(),
+            second.next_cursor.as_deref(),
+            2,
+        )
+        .unwrap();
+        assert_eq!(ids(&last), vec!["r0"]);
+        assert!(last.next_cursor.is_none());
+
+        let by_income = RecordSort {
+            field: RecordSortField::GoldIncome,
+            ascending: true,
+        };
+        let page = query_records(&conn, &filter, by_income, None, 2).unwrap();
+        assert_eq!(ids(&page), vec!["r4", "r3"]);
+
+        let heroic = RecordFilter {
+            difficulty: Some("英雄".to_string()),
+            ..filter.clone()
+        };
+        let page = query_records(&conn, &heroic, RecordSort::default(), None, 10).unwrap();
+        assert_eq!(ids(&page), vec!["r4", "r2", "r0"]);
+
+        let rare = RecordFilter {
+            rare_drops: vec!["xuanjing".to_string()],
+            ..Default::default()
+        };
+        let page = query_records(&conn, &rare, RecordSort::default(), None, 10).unwrap();
+        assert_eq!(ids(&page), vec!["r3"]);
+
+        // % 按字面匹配，不作为通配符
+        let text = RecordFilter {
+            text: Some("50%".to_string()),
+            ..Default::default()
+        };
+        let page = query_records(&conn, &text, RecordSort::default(), None, 10).unwrap();
+        assert_eq!(ids(&page), vec!["r1"]);
+        let text = RecordFilter {
+            text: Some("五行石".to_string()),
+            ..Default::default()
+        };
+        let page = query_records(&conn, &text, RecordSort::default(), None, 10).unwrap();
+        assert_eq!(ids(&page), vec!["r4"]);
+    }
+
+    #[test]
+    fn test_query_records_date_sort_uses_index() {
+        let conn = setup();
+        let plan: Vec<String> = conn
+            .prepare(
+                "EXPLAIN QUERY PLAN SELECT data, id, record_date FROM records
+                 WHERE record_date IS NOT NULL ORDER BY record_date DESC, id DESC LIMIT 10",
+            )
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(3))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(
+            plan.iter()
+                .any(|line| line.contains("idx_records_record_date")),
+            "{:?}",
+            plan
+        );
+
+        // 无日期的记录不参与按时间排序
+        conn.execute(
+            "INSERT INTO records (id, data, account_id) VALUES ('nodate', '{}', 'acc')",
+            [],
+        )
+        .unwrap();
+        let page = query_records(
+            &conn,
+            &RecordFilter::default(),
+            RecordSort::default(),
+            None,
+            10,
+        )
+        .unwrap();
+        assert_eq!(page.total, 6);
+    }
+}
@@ -0,0 +1,735 @@
+//! 回收站
+//!
+//! 删除副本记录、试炼记录、百战记录、账号、角色时不再直接丢弃数据：被删除的行
+//! （含级联删除的角色、可见性设置、记录的战斗尝试 / chatlog 明细 / 名单 / 拍卖账本 / 工资表）
+//! 先以 JSON 快照写入 `trash_items`，再从原表删除，因此其余查询无需额外过滤。
+//! 回收站条目可以恢复（按父表在前的顺序原样插回）或彻底清除，
+//! 超过保留天数（app_config.trash_retention_days，默认 30 天）的条目在启动时自动清除，
+//! 同时清除超过保留天数的记录变更历史。
+
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::init_db;
+use super::record_history;
+
+/// 默认保留天数
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+const RETENTION_CONFIG_KEY: &str = "trash_retention_days";
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// 可进入回收站的实体
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrashEntityType {
+    Record,
+    TrialRecord,
+    BaizhanRecord,
+    Account,
+    Role,
+}
+
+impl TrashEntityType {
+    fn as_str(self) -> &'static str {
+        match self {
+            TrashEntityType::Record => "record",
+            TrashEntityType::TrialRecord => "trialRecord",
+            TrashEntityType::BaizhanRecord => "baizhanRecord",
+            TrashEntityType::Account => "account",
+            TrashEntityType::Role => "role",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "record" => Some(TrashEntityType::Record),
+            "trialRecord" => Some(TrashEntityType::TrialRecord),
+            "baizhanRecord" => Some(TrashEntityType::BaizhanRecord),
+            "account" => Some(TrashEntityType::Account),
+            "role" => Some(TrashEntityType::Role),
+            _ => None,
+        }
+    }
+}
+
+/// 快照涉及的表：(表名, 关联列, 是否为自增主键 id)
+///
+/// 自增 id 恢复时不写回，由 SQLite 重新分配，避免与删除后新增的行冲突。
+type TableSpec = (&'static str, &'static str, bool);
+
+const RECORD_TABLES: [TableSpec; 6] = [
+    ("records", "id", false),
+    ("raid_attempts", "record_id", true),
+    ("record_chatlog_items", "record_id", true),
+    ("record_roster", "record_id", true),
+    ("record_auction_ledger", "record_id", true),
+    ("record_salary_sheets", "record_id", false),
+];
+const ROLE_TABLES: [TableSpec; 3] = [
+    ("roles", "id", false),
+    ("role_instance_visibility", "role_id", false),
+    ("raid_role_visibility", "role_id", false),
+];
+const ACCOUNT_TABLE: TableSpec = ("accounts", "id", false);
+const TRIAL_TABLE: TableSpec = ("trial_records", "id", false);
+const BAIZHAN_TABLE: TableSpec = ("baizhan_records", "id", false);
+
+/// 快照中的表是否可恢复（Some 表示已知表，值为是否为自增主键 id）
+fn is_surrogate_id(table: &str) -> Option<bool> {
+    RECORD_TABLES
+        .iter()
+        .chain(ROLE_TABLES.iter())
+        .chain([ACCOUNT_TABLE, TRIAL_TABLE, BAIZHAN_TABLE].iter())
+        .find(|(name, _, _)| *name == table)
+        .map(|(_, _, surrogate)| *surrogate)
+}
+
+/// 单表的行快照（列名 → 值）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TableRows {
+    table: String,
+    rows: Vec<serde_json::Map<String, Value>>,
+}
+
+/// 回收站条目
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub id: i64,
+    pub entity_type: TrashEntityType,
+    pub entity_id: String,
+    /// 展示名（账号名、角色名@服务器、副本名等）
+    pub label: String,
+    /// 删除时间（毫秒）
+    pub deleted_at: i64,
+    /// 到期自动清除的时间（毫秒）
+    pub expires_at: i64,
+    /// 快照中各表的行数（表名 → 行数），用于展示级联删除的范围
+    pub row_counts: Vec<(String, usize)>,
+}
+
+fn sql_to_json(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => Value::from(blob.to_vec()),
+    }
+}
+
+fn json_to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        Value::Array(items) => SqlValue::Blob(
+            items
+                .iter()
+                .filter_map(|v| v.as_u64().map(|b| b as u8))
+                .collect(),
+        ),
+        Value::Object(_) => SqlValue::Text(value.to_string()),
+    }
+}
+
+/// 读取 `table` 中 `column = value` 的全部行
+fn capture_rows(
+    conn: &Connection,
+    (table, column, _): TableSpec,
+    value: &str,
+) -> Result<TableRows, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {} WHERE {} = ?1", table, column))
+        .map_err(|e| format!("准备读取 {} 失败: {}", table, e))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = stmt
+        .query(params![value])
+        .map_err(|e| format!("读取 {} 失败: {}", table, e))?;
+    let mut captured = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("读取 {} 失败: {}", table, e))?
+    {
+        let mut map = serde_json::Map::new();
+        for (index, name) in columns.iter().enumerate() {
+            let value = row
+                .get_ref(index)
+                .map_err(|e| format!("读取 {}.{} 失败: {}", table, name, e))?;
+            map.insert(name.clone(), sql_to_json(value));
+        }
+        captured.push(map);
+    }
+    Ok(TableRows {
+        table: table.to_string(),
+        rows: captured,
+    })
+}
+
+fn delete_rows(
+    conn: &Connection,
+    (table, column, _): TableSpec,
+    value: &str,
+) -> Result<(), String> {
+    conn.execute(
+        &format!("DELETE FROM {} WHERE {} = ?1", table, column),
+        params![value],
+    )
+    .map_err(|e| format!("删除 {} 失败: {}", table, e))?;
+    Ok(())
+}
+
+/// 把快照写回原表（按快照顺序，父表在前）
+fn restore_rows(conn: &Connection, snapshot: &[TableRows]) -> Result<(), String> {
+    for table_rows in snapshot {
+        let table = table_rows.table.as_str();
+        let surrogate_id =
+            is_surrogate_id(table).ok_or_else(|| format!("回收站快照包含未知的表: {}", table))?;
+        for row in &table_rows.rows {
+            let entries: Vec<(&String, &Value)> = row
+                .iter()
+                .filter(|(name, _)| !(surrogate_id && name.as_str() == "id"))
+                .collect();
+            if let Some((name, _)) = entries
+                .iter()
+                .find(|(name, _)| !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+            {
+                return Err(format!("回收站快照包含非法列名: {}.{}", table, name));
+            }
+            let columns: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+            let placeholders: Vec<String> =
+                (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+            let values: Vec<SqlValue> = entries
+                .iter()
+                .map(|(_, value)| json_to_sql(value))
+                .collect();
+            conn.execute(
+                &format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table,
+                    columns.join(", "),
+                    placeholders.join(", ")
+                ),
+                params_from_iter(values),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(err, _)
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    format!(
+                        "恢复 {} 失败：数据已存在或所属账号 / 角色已被删除（{}）",
+                        table, e
+                    )
+                }
+                e => format!("恢复 {} 失败: {}", table, e),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_trash_item(
+    conn: &Connection,
+    entity_type: TrashEntityType,
+    entity_id: &str,
+    label: &str,
+    snapshot: &[TableRows],
+) -> Result<(), String> {
+    let payload =
+        serde_json::to_string(snapshot).map_err(|e| format!("序列化回收站快照失败: {}", e))?;
+    conn.execute(
+        "INSERT INTO trash_items (entity_type, entity_id, label, payload, deleted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            entity_type.as_str(),
+            entity_id,
+            label,
+            payload,
+            chrono::Local::now().timestamp_millis()
+        ],
+    )
+    .map_err(|e| format!("写入回收站失败: {}", e))?;
+    Ok(())
+}
+
+fn text_column(rows: &TableRows, column: &str) -> String {
+    rows.rows
+        .first()
+        .and_then(|row| row.get(column))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// 副本记录移入回收站（含战斗尝试、chatlog 明细、名单、拍卖账本、工资表），记录不存在时返回 false
+pub(crate) fn trash_record(conn: &Connection, record_id: &str) -> Result<bool, String> {
+    trash_record_as(conn, record_id, record_history::ACTION_DELETE)
+}
+
+/// 同 `trash_record`，变更历史记为 `action`（撤销新建时为 undo）
+pub(crate) fn trash_record_as(
+    conn: &Connection,
+    record_id: &str,
+    action: &str,
+) -> Result<bool, String> {
+    let snapshot = RECORD_TABLES
+        .iter()
+        .map(|spec| capture_rows(conn, *spec, record_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(record) = snapshot[0].rows.first() else {
+        return Ok(false);
+    };
+    let before = record
+        .get("data")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+    let data: Value = before
+        .as_deref()
+        .and_then(|data| serde_json::from_str(data).ok())
+        .unwrap_or_default();
+    let label = format!(
+        "{} · {}",
+        data["raidName"].as_str().unwrap_or("副本记录"),
+        data["roleName"].as_str().unwrap_or("未知角色")
+    );
+    insert_trash_item(conn, TrashEntityType::Record, record_id, &label, &snapshot)?;
+    for spec in RECORD_TABLES.iter().rev() {
+        delete_rows(conn, *spec, record_id)?;
+    }
+    record_history::append_history(conn, record_id, action, before.as_deref(), None)?;
+    Ok(true)
+}
+
+/// 把回收站中该记录最近的条目原样插回并移出回收站（不写变更历史），没有条目时返回 false
+pub(crate) fn take_record_from_trash(conn: &Connection, record_id: &str) -> Result<bool, String> {
+    let (trash_id, payload): (i64, String) = match conn.query_row(
+        "SELECT id, payload FROM trash_items
+         WHERE entity_type = ?1 AND entity_id = ?2
+         ORDER BY deleted_at DESC, id DESC
+         LIMIT 1",
+        params![TrashEntityType::Record.as_str(), record_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ) {
+        Ok(row) => row,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+        Err(e) => return Err(format!("查询回收站条目失败: {}", e)),
+    };
+    let snapshot: Vec<TableRows> =
+        serde_json::from_str(&payload).map_err(|e| format!("解析回收站快照失败: {}", e))?;
+    restore_rows(conn, &snapshot)?;
+    conn.execute("DELETE FROM trash_items WHERE id = ?1", params![trash_id])
+        .map_err(|e| format!("移除回收站条目失败: {}", e))?;
+    Ok(true)
+}
+
+/// 试炼 / 百战记录移入回收站，记录不存在时返回 false
+fn trash_single_row(
+    conn: &Connection,
+    entity_type: TrashEntityType,
+    spec: TableSpec,
+    id: &str,
+    label_prefix: &str,
+) -> Result<bool, String> {
+    let rows = capture_rows(conn, spec, id)?;
+    if rows.rows.is_empty() {
+        return Ok(false);
+    }
+    let label = format!("{} · {}", label_prefix, text_column(&rows, "role_name"));
+    insert_trash_item(conn, entity_type, id, &label, &[rows])?;
+    delete_rows(conn, spec, id)?;
+    Ok(true)
+}
+
+pub(crate) fn trash_trial_record(conn: &Connection, id: &str) -> Result<bool, String> {
+    trash_single_row(
+        conn,
+        TrashEntityType::TrialRecord,
+        TRIAL_TABLE,
+        id,
+        "试炼之地",
+    )
+}
+
+pub(crate) fn trash_baizhan_record(conn: &Connection, id: &str) -> Result<bool, String> {
+    trash_single_row(
+        conn,
+        TrashEntityType::BaizhanRecord,
+        BAIZHAN_TABLE,
+        id,
+        "百战异闻录",
+    )
+}
+
+fn capture_role(conn: &Connection, role_id: &str) -> Result<Vec<TableRows>, String> {
+    ROLE_TABLES
+        .iter()
+        .map(|spec| capture_rows(conn, *spec, role_id))
+        .collect()
+}
+
+fn delete_role_rows(conn: &Connection, role_id: &str) -> Result<(), String> {
+    for spec in ROLE_TABLES.iter().rev() {
+        delete_rows(conn, *spec, role_id)?;
+    }
+    Ok(())
+}
+
+/// 角色移入回收站（含可见性设置），角色不存在时返回 false。角色的副本记录不受影响。
+pub(crate) fn trash_role(conn: &Connection, role_id: &str) -> Result<bool, String> {
+    let snapshot = capture_role(conn, role_id)?;
+    if snapshot[0].rows.is_empty() {
+        return Ok(false);
+    }
+    let label = format!(
+        "{}@{}",
+        text_column(&snapshot[0], "name"),
+        text_column(&snapshot[0], "server")
+    );
+    insert_trash_item(conn, TrashEntityType::Role, role_id, &label, &snapshot)?;
+    delete_role_rows(conn, role_id)?;
+    Ok(true)
+}
+
+/// 账号移入回收站（含全部角色及可见性设置），账号不存在时返回 false。账号的副本记录不受影响。
+pub(crate) fn trash_account(conn: &Connection, account_id: &str) -> Result<bool, String> {
+    let account = capture_rows(conn, ACCOUNT_TABLE, account_id)?;
+    if account.rows.is_empty() {
+        return Ok(false);
+    }
+    let label = text_column(&account, "account_name");
+
+    let role_ids: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM roles WHERE account_id = ?1")
+            .map_err(|e| format!("查询账号角色失败: {}", e))?;
+        let rows = stmt
+            .query_map(params![account_id], |row| row.get(0))
+            .map_err(|e| format!("查询账号角色失败: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("读取账号角色失败: {}", e))?
+    };
+
+    // 快照按表合并：accounts → roles → 可见性，恢复时父表先插入
+    let mut snapshot = vec![account];
+    snapshot.extend(ROLE_TABLES.iter().map(|(table, _, _)| TableRows {
+        table: table.to_string(),
+        rows: Vec::new(),
+    }));
+    for role_id in &role_ids {
+        for (index, rows) in capture_role(conn, role_id)?.into_iter().enumerate() {
+            snapshot[index + 1].rows.extend(rows.rows);
+        }
+    }
+
+    insert_trash_item(
+        conn,
+        TrashEntityType::Account,
+        account_id,
+        &label,
+        &snapshot,
+    )?;
+    for role_id in &role_ids {
+        delete_role_rows(conn, role_id)?;
+    }
+    delete_rows(conn, ACCOUNT_TABLE, account_id)?;
+    Ok(true)
+}
+
+fn retention_days(conn: &Connection) -> i64 {
+    conn.query_row(
+        "SELECT value FROM app_config WHERE key = ?1",
+        params![RETENTION_CONFIG_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| value.parse::<i64>().ok())
+    .filter(|days| *days > 0)
+    .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+pub(crate) fn list_trash(conn: &Connection) -> Result<Vec<TrashItem>, String> {
+    let retention_ms = retention_days(conn) * DAY_MS;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, entity_type, entity_id, label, payload, deleted_at
+             FROM trash_items ORDER BY deleted_at DESC, id DESC",
+        )
+        .map_err(|e| format!("准备回收站查询失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(|e| format!("查询回收站失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取回收站失败: {}", e))?;
+
+    let mut items = Vec::with_capacity(rows.len());
+    for (id, entity_type, entity_id, label, payload, deleted_at) in rows {
+        let Some(entity_type) = TrashEntityType::parse(&entity_type) else {
+            log::warn!("[Trash] 未知的回收站条目类型: {}", entity_type);
+            continue;
+        };
+        let row_counts = serde_json::from_str::<Vec<TableRows>>(&payload)
+            .map(|snapshot| {
+                snapshot
+                    .into_iter()
+                    .filter(|t| !t.rows.is_empty())
+                    .map(|t| (t.table, t.rows.len()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        items.push(TrashItem {
+            id,
+            entity_type,
+            entity_id,
+            label,
+            deleted_at,
+            expires_at: deleted_at + retention_ms,
+            row_counts,
+        });
+    }
+    Ok(items)
+}
+
+/// 恢复回收站条目，返回条目类型与实体 ID
+pub(crate) fn restore_trash_item(
+    conn: &mut Connection,
+    trash_id: i64,
+) -> Result<(TrashEntityType, String), String> {
+    let (entity_type, entity_id, payload): (String, String, String) = conn
+        .query_row(
+            "SELECT entity_type, entity_id, payload FROM trash_items WHERE id = ?1",
+            params![trash_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("回收站条目不存在: {}", trash_id),
+            e => format!("查询回收站条目失败: {}", e),
+        })?;
+    let entity_type = TrashEntityType::parse(&entity_type)
+        .ok_or_else(|| format!("未知的回收站条目类型: {}", entity_type))?;
+    let snapshot: Vec<TableRows> =
+        serde_json::from_str(&payload).map_err(|e| format!("解析回收站快照失败: {}", e))?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    restore_rows(&tx, &snapshot)?;
+    if entity_type == TrashEntityType::Record {
+        let restored = record_history::current_record_data(&tx, &entity_id)?;
+        record_history::append_history(
+            &tx,
+            &entity_id,
+            record_history::ACTION_RESTORE,
+            None,
+            restored.as_deref(),
+        )?;
+    }
+    tx.execute("DELETE FROM trash_items WHERE id = ?1", params![trash_id])
+        .map_err(|e| format!("移除回收站条目失败: {}", e))?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+
+    log::info!(
+        "[Trash] 已恢复 {} {}（条目 #{}）",
+        entity_type.as_str(),
+        entity_id,
+        trash_id
+    );
+    Ok((entity_type, entity_id))
+}
+
+/// 清除早于 `now_ms - retention_days` 的条目和记录变更历史，返回清除的回收站条目数量
+pub(crate) fn purge_expired(conn: &Connection, now_ms: i64) -> Result<usize, String> {
+    let cutoff = now_ms - retention_days(conn) * DAY_MS;
+    let pruned = record_history::prune_history(conn, cutoff)?;
+    if pruned > 0 {
+        log::info!("[RecordHistory] 已清除 {} 条过期变更历史", pruned);
+    }
+    conn.execute(
+        "DELETE FROM trash_items WHERE deleted_at < ?1",
+        params![cutoff],
+    )
+    .map_err(|e| format!("清除过期回收站条目失败: {}", e))
+}
+
+/// 启动时清除过期的回收站条目
+pub fn purge_expired_on_startup() -> Result<usize, String> {
+    let conn = init_db()?;
+    let purged = purge_expired(&conn, chrono::Local::now().timestamp_millis())?;
+    if purged > 0 {
+        log::info!("[Trash] 已自动清除 {} 条过期回收站条目", purged);
+    }
+    Ok(purged)
+}
+
+/// Tauri 命令：查询回收站（最近删除在前）
+#[tauri::command]
+pub fn db_get_trash() -> Result<Vec<TrashItem>, String> {
+    let conn = init_db()?;
+    list_trash(&conn)
+}
+
+/// Tauri 命令：恢复回收站条目
+///
+/// 恢复角色前需所属账号存在；恢复后前端需重新加载对应数据，避免旧状态再次保存时把恢复的账号 / 角色删掉。
+#[tauri::command]
+pub fn db_restore_trash_item(trash_id: i64) -> Result<(), String> {
+    let mut conn = init_db()?;
+    restore_trash_item(&mut conn, trash_id).map(|_| ())
+}
+
+/// Tauri 命令：彻底清除回收站条目，`trash_id` 为空时清空回收站
+#[tauri::command]
+pub fn db_purge_trash(trash_id: Option<i64>) -> Result<usize, String> {
+    let conn = init_db()?;
+    let purged = match trash_id {
+        Some(id) => conn.execute("DELETE FROM trash_items WHERE id = ?1", params![id]),
+        None => conn.execute("DELETE FROM trash_items", []),
+    }
+    .map_err(|e| format!("清除回收站失败: {}", e))?;
+    log::info!("[Trash] 已彻底清除 {} 条回收站条目", purged);
+    Ok(purged)
+}
+
+/// Tauri 命令：查询回收站保留天数
+#[tauri::command]
+pub fn db_get_trash_retention_days() -> Result<i64, String> {
+    let conn = init_db()?;
+    Ok(retention_days(&conn))
+}
+
+/// Tauri 命令：设置回收站保留天数（至少 1 天），并立即清除已过期的条目
+#[tauri::command]
+pub fn db_set_trash_retention_days(days: i64) -> Result<usize, String> {
+    if days < 1 {
+        return Err("保留天数至少为 1 天".to_string());
+    }
+    let conn = init_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_config (key, value, updated_at) VALUES (?1, ?2, ?3)",
+        params![
+            RETENTION_CONFIG_KEY,
+            days.to_string(),
+            chrono::Local::now().to_rfc3339()
+        ],
+    )
+    .map_err(|e| format!("保存回收站保留天数失败: {}", e))?;
+    purge_expired(&conn, chrono::Local::now().timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = crate::db::open_test_db();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, account_name) VALUES ('a1', '大号');
+            INSERT INTO roles (id, account_id, name, server) VALUES ('role1', 'a1', '剑心', '梦江南');
+            INSERT INTO role_instance_visibility (id, role_id, instance_type_id, visible)
+            VALUES ('v1', 'role1', 1, 1);
+            INSERT INTO raid_role_visibility (id, role_id, raid_key)
+            VALUES ('rv1', 'role1', '25人英雄阆风悬城');
+            INSERT INTO records (id, data, record_date)
+            VALUES ('r1', '{\"raidName\":\"25人英雄阆风悬城\",\"roleName\":\"剑心\"}', 1710000000000);
+            INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, boss_hp_percent, created_at)
+            VALUES ('r1', '笑妆娘', 'a.jcl', 1, 0, 1000, 1000, 0, 0.5, '');
+            INSERT INTO record_salary_sheets (record_id, input, sheet, created_at, updated_at) VALUES ('r1', '{}', '{}', '', '');
+            INSERT INTO trial_records (id, role_name, date) VALUES ('t1', '剑心', 1710000000000);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_trash_restore_and_purge() {
+        let mut conn = setup();
+
+        assert!(trash_account(&conn, "a1").unwrap());
+        assert!(trash_record(&conn, "r1").unwrap());
+        assert!(trash_trial_record(&conn, "t1").unwrap());
+        assert!(
+            !trash_role(&conn, "role1").unwrap(),
+            "角色已随账号进入回收站"
+        );
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM roles"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM raid_attempts"), 0);
+
+        let items = list_trash(&conn).unwrap();
+        assert_eq!(items.len(), 3);
+        let account = items
+            .iter()
+            .find(|item| item.entity_type == TrashEntityType::Account)
+            .unwrap();
+        assert_eq!(account.label, "大号");
+        assert!(account
+            .row_counts
+            .contains(&("raid_role_visibility".to_string(), 1)));
+        let record = items
+            .iter()
+            .find(|item| item.entity_type == TrashEntityType::Record)
+            .unwrap();
+        assert_eq!(record.label, "25人英雄阆风悬城 · 剑心");
+
+        restore_trash_item(&mut conn, account.id).unwrap();
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM roles WHERE account_id = 'a1'"),
+            1
+        );
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM role_instance_visibility"),
+            1
+        );
+
+        restore_trash_item(&mut conn, record.id).unwrap();
+        let hp: f64 = conn
+            .query_row(
+                "SELECT boss_hp_percent FROM raid_attempts WHERE record_id = 'r1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(hp, 0.5);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM record_salary_sheets"), 1);
+        assert!(
+            restore_trash_item(&mut conn, record.id).is_err(),
+            "条目恢复后已移出回收站"
+        );
+
+        // 所属账号不存在时角色恢复失败，条目保留
+        trash_role(&conn, "role1").unwrap();
+        conn.execute("DELETE FROM accounts", []).unwrap();
+        let role_item = list_trash(&conn)
+            .unwrap()
+            .into_iter()
+            .find(|item| item.entity_type == TrashEntityType::Role)
+            .unwrap();
+        assert!(restore_trash_item(&mut conn, role_item.id).is_err());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM trash_items"), 2);
+
+        // 超过保留天数的条目被清除
+        let now = chrono::Local::now().timestamp_millis();
+        assert_eq!(purge_expired(&conn, now).unwrap(), 0);
+        assert_eq!(
+            purge_expired(&conn, now + (DEFAULT_RETENTION_DAYS + 1) * DAY_MS).unwrap(),
+            2
+        );
+    }
+}
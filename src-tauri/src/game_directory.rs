@@ -14,6 +14,9 @@ use crate::db;
 // 引入 kungfu_data 模块获取统一的心法数据
 use crate::kungfu_data;
 
+// 引入 klua 模块解析茗伊 EncodeByteData BLOB
+use crate::klua;
+
 const GKP_BASE_PATH: &str = r"interface\my#data";
 const USERDATA_BASE_PATH: &str = "userdata";
 const GAME_RUNTIME_SUFFIX: [&str; 4] = ["Game", "JX3", "bin", "zhcn_hd"];
@@ -280,177 +283,70 @@ struct RoleStatData {
     version: String,
 }
 
-/// KLua BLOB 类型标记字节
-/// 这些标记紧跟在 key 字符串之后，标识字段值的类型
-const KLUA_NUMBER: u8 = 0x6e; // 'n' — 8 字节 little-endian double
-const KLUA_STRING: u8 = 0x73; // 's' — 无长度前缀，以下一个 0x73 标记结束
-
-/// 在 haystack 中查找 needle 子序列，返回起始位置
-fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-    haystack
-        .windows(needle.len())
-        .position(|window| window == needle)
-}
-
-/// 从 KLua 序列化的 BLOB 中提取数值字段
-///
-/// KLua 格式：key 字符串 + 'n'(0x6e) + 8字节 little-endian double
-/// 注意：key 可能是其他字段名的子串（如 "name" 在 "contribution_remain" 中），
-/// 因此需要遍历所有匹配位置，找到真正紧跟 'n' 标记的那个
-fn extract_double_from_blob(buf: &[u8], key: &str) -> Option<f64> {
-    let key_bytes = key.as_bytes();
-    let mut search_start = 0;
-    while let Some(idx) = find_subsequence(&buf[search_start..], key_bytes) {
-        let abs_idx = search_start + idx;
-        let after = &buf[abs_idx + key_bytes.len()..];
-        if !after.is_empty() && after[0] == KLUA_NUMBER && after.len() >= 9 {
-            let bytes: [u8; 8] = after[1..9].try_into().ok()?;
-            return Some(f64::from_le_bytes(bytes));
-        }
-        search_start = abs_idx + 1;
-    }
-    None
-}
-
-/// 从 KLua 序列化的 BLOB 中提取字符串字段
-///
-/// KLua 格式：key 字符串 + 's'(0x73) + 类型字节(0x06/0x08等) + 字符串内容 + 下一个 's'(0x73) 标记
-/// 字符串无长度前缀，以下一个字段起始标记 's'(0x73) 作为结束
-fn extract_string_from_blob(buf: &[u8], key: &str) -> Option<String> {
-    let key_bytes = key.as_bytes();
-    let mut search_start = 0;
-    while let Some(idx) = find_subsequence(&buf[search_start..], key_bytes) {
-        let abs_idx = search_start + idx;
-        let after = &buf[abs_idx + key_bytes.len()..];
-        // 字符串标记：'s'(0x73) + 非零类型字节（0x06/0x08 等均可能出现）
-        if after.len() >= 2 && after[0] == KLUA_STRING && after[1] != 0x00 {
-            let content_start = 2;
-            // 查找下一个 's'(0x73) 作为字符串结束
-            let content_end = after[content_start..]
-                .iter()
-                .position(|&b| b == KLUA_STRING)
-                .map(|p| content_start + p)
-                .unwrap_or(after.len());
-            let bytes = &after[content_start..content_end];
-            if bytes.is_empty() {
-                return None;
-            }
-            // 茗伊中文字符串以 GBK 或 UTF-8 存储，尝试 UTF-8 解码失败时回退 GBK
-            // 注意：BLOB 中字符串内容可能包含前导 0x00 空字节，需同时去除首尾空字节
-            return match std::str::from_utf8(bytes) {
-                Ok(s) => Some(s.trim_matches('\0').to_string()),
-                Err(_) => {
-                    let (cow, _, _) = encoding_rs::GBK.decode(bytes);
-                    Some(cow.trim_matches('\0').to_string())
-                }
-            };
-        }
-        search_start = abs_idx + 1;
-    }
-    None
-}
-
-/// 在父字段之后搜索嵌套 table 中的数值子字段
-///
-/// 用于解析 KLua 嵌套 table，如 `account_stamina = { current=100, max=200 }`
-/// 先定位父字段 parent_key 的位置，再在其后搜索 child_key + 'n' + double
-fn extract_nested_double(buf: &[u8], parent_key: &str, child_key: &str) -> Option<f64> {
-    let parent_bytes = parent_key.as_bytes();
-    let parent_pos = find_subsequence(buf, parent_bytes)?;
-    let search_start = parent_pos + parent_bytes.len();
-    if search_start >= buf.len() {
-        return None;
-    }
-    let child_bytes = child_key.as_bytes();
-    let child_idx = find_subsequence(&buf[search_start..], child_bytes)?;
-    let abs_idx = search_start + child_idx;
-    let after = &buf[abs_idx + child_bytes.len()..];
-    if !after.is_empty() && after[0] == KLUA_NUMBER && after.len() >= 9 {
-        let bytes: [u8; 8] = after[1..9].try_into().ok()?;
-        return Some(f64::from_le_bytes(bytes));
-    }
-    None
-}
-
 /// 解析 MY_RoleStatistics_RoleStat.tAlertTodayVal 的 BLOB 内容
 ///
 /// BLOB 顶层结构为 `{ d=<数据 table>, v=<版本号 string> }`（由茗伊 Storage.UserSettings.lua 确认）
-/// d 包含所有角色统计字段，v 为数据格式版本号
+/// d 包含所有角色统计字段，v 为数据格式版本号。BLOB 无法解码或缺少角色名时返回 None。
 fn parse_role_stat_blob(blob: &[u8]) -> Option<RoleStatData> {
-    // 顶层版本号 v（字符串），仅用于日志和兼容性校验
-    // 注意："v" 是单字节 key(0x76)，在 double 数据流中极易误匹配
-    // 因此对解析结果做版本号格式校验（仅接受数字和点），不合法则丢弃
-    let version = extract_string_from_blob(blob, "v")
-        .filter(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || c == '.'));
+    let root = match klua::decode_byte_data(blob) {
+        Ok(root) => root,
+        Err(e) => {
+            log::warn!("[AnalyzeRoles] RoleStat BLOB 解析失败: {}", e);
+            return None;
+        }
+    };
+    let data = root.get("d")?;
+
+    let string_field = |key: &str| klua::get_string(data, key).unwrap_or_default();
+    let number_field = |key: &str| klua::get_f64(data, key).unwrap_or(0.0);
+    // money / account_stamina / role_stamina 为嵌套 table
+    let nested_field = |parent: &str, child: &str| {
+        data.get(parent)
+            .and_then(|table| klua::get_f64(table, child))
+            .unwrap_or(0.0)
+    };
 
     // 标识字段
-    let name = extract_string_from_blob(blob, "name")?;
-    let guid = extract_string_from_blob(blob, "guid").unwrap_or_default();
-    let account = extract_string_from_blob(blob, "account").unwrap_or_default();
-    let region = extract_string_from_blob(blob, "region").unwrap_or_default();
-    let server = extract_string_from_blob(blob, "server").unwrap_or_default();
-
-    // 基础属性
-    let force_id = extract_double_from_blob(blob, "force").unwrap_or(0.0) as i32;
-    let level = extract_double_from_blob(blob, "level").unwrap_or(0.0) as i32;
-    let equip_score = extract_double_from_blob(blob, "equip_score").unwrap_or(0.0) as i32;
-    let achievement_score = extract_double_from_blob(blob, "achievement_score").unwrap_or(0.0) as i32;
-    let pet_score = extract_double_from_blob(blob, "pet_score").unwrap_or(0.0) as i32;
-
-    // 货币（money 为嵌套 table { nGold, nSilver, nCopper }，通过父字段定位子字段避免误匹配）
-    let money_gold = extract_nested_double(blob, "money", "nGold").unwrap_or(0.0) as i64;
-    let money_silver = extract_nested_double(blob, "money", "nSilver").unwrap_or(0.0) as i64;
-    let money_copper = extract_nested_double(blob, "money", "nCopper").unwrap_or(0.0) as i64;
-    let justice = extract_double_from_blob(blob, "justice").unwrap_or(0.0) as i32;
-
-    // 体力/精力（嵌套 table { current, max }，current/max 子字段名太通用，必须通过父字段定位）
-    let account_stamina_current =
-        extract_nested_double(blob, "account_stamina", "current").unwrap_or(0.0) as i32;
-    let account_stamina_max =
-        extract_nested_double(blob, "account_stamina", "max").unwrap_or(0.0) as i32;
-    let role_stamina_current =
-        extract_nested_double(blob, "role_stamina", "current").unwrap_or(0.0) as i32;
-    let role_stamina_max = extract_nested_double(blob, "role_stamina", "max").unwrap_or(0.0) as i32;
-
-    // 元数据
-    let time = extract_double_from_blob(blob, "time").unwrap_or(0.0) as i64;
+    let name = klua::get_string(data, "name").filter(|name| !name.is_empty())?;
 
-    if name.is_empty() {
-        return None;
-    }
+    let parsed = RoleStatData {
+        guid: string_field("guid"),
+        account: string_field("account"),
+        region: string_field("region"),
+        server: string_field("server"),
+        name,
+        // 基础属性
+        force_id: number_field("force") as i32,
+        level: number_field("level") as i32,
+        equip_score: number_field("equip_score") as i32,
+        achievement_score: number_field("achievement_score") as i32,
+        pet_score: number_field("pet_score") as i32,
+        // 货币（money = { nGold, nSilver, nCopper }）
+        money_gold: nested_field("money", "nGold") as i64,
+        money_silver: nested_field("money", "nSilver") as i64,
+        money_copper: nested_field("money", "nCopper") as i64,
+        justice: number_field("justice") as i32,
+        // 体力/精力（{ current, max }）
+        account_stamina_current: nested_field("account_stamina", "current") as i32,
+        account_stamina_max: nested_field("account_stamina", "max") as i32,
+        role_stamina_current: nested_field("role_stamina", "current") as i32,
+        role_stamina_max: nested_field("role_stamina", "max") as i32,
+        // 元数据
+        time: number_field("time") as i64,
+        version: klua::get_string(&root, "v").unwrap_or_default(),
+    };
 
     log::debug!(
         "[AnalyzeRoles] 解析角色: {} (server={}, force={}, level={}, equip={}, v={})",
-        name,
-        server,
-        force_id,
-        level,
-        equip_score,
-        version.as_deref().unwrap_or("")
+        parsed.name,
+        parsed.server,
+        parsed.force_id,
+        parsed.level,
+        parsed.equip_score,
+        parsed.version
     );
 
-    Some(RoleStatData {
-        guid,
-        account,
-        region,
-        server,
-        name,
-        force_id,
-        level,
-        equip_score,
-        achievement_score,
-        pet_score,
-        money_gold,
-        money_silver,
-        money_copper,
-        justice,
-        account_stamina_current,
-        account_stamina_max,
-        role_stamina_current,
-        role_stamina_max,
-        time,
-        version: version.unwrap_or_default(),
-    })
+    Some(parsed)
 }
 
 /// 遍历所有 *@zhcn_hd/userdata/userdata.db，从 RoleStat BLOB 读取角色装分
@@ -2375,3 +2271,39 @@ pub fn scan_jx3_clients() -> ScanClientsResult {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_role_stat_blob_fixture() {
+        // 按 RoleStat BLOB 结构构造的样本（EncodeByteData 编码的 { d = {...}, v = "29.0.0" }）
+        let blob = include_bytes!("../tests/fixtures/klua/role_stat.blob");
+        let role = parse_role_stat_blob(blob).expect("解析失败");
+        assert_eq!(role.name, "叶未晓");
+        assert_eq!(role.server, "梦江南");
+        assert_eq!(role.region, "电信五区");
+        assert_eq!(role.account, "jx3demo");
+        assert_eq!(role.guid, "432345564243886337");
+        assert_eq!(role.force_id, 4);
+        assert_eq!(role.level, 130);
+        assert_eq!(role.equip_score, 412345);
+        assert_eq!(role.money_gold, 123456);
+        assert_eq!(role.money_silver, 78);
+        assert_eq!(role.money_copper, 9);
+        assert_eq!(role.account_stamina_current, 3200);
+        assert_eq!(role.account_stamina_max, 4500);
+        assert_eq!(role.role_stamina_max, 1200);
+        assert_eq!(role.time, 1760000000);
+        assert_eq!(role.version, "29.0.0");
+    }
+
+    #[test]
+    fn test_parse_role_stat_blob_rejects_invalid_blob() {
+        let blob = include_bytes!("../tests/fixtures/klua/role_stat.blob");
+        // 截断的 BLOB 不再按键名拼凑字段
+        assert!(parse_role_stat_blob(&blob[..blob.len() / 2]).is_none());
+        assert!(parse_role_stat_blob(b"name").is_none());
+    }
+}
@@ -1,5 +1,18 @@
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::db;
+use crate::db::raid_record::{self, RaidRecord};
+use crate::game_directory::GkpFileInfo;
+use crate::klua;
+use crate::mingyi::drop_scanner::{
+    calculate_cd_window, get_cached_raids, get_game_directory, match_raid_name,
+    normalize_role_name, resolve_db_role_identity, DbRoleIdentity, RaidEntry,
+};
+use crate::money::{self, Money};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GkpParseRequest {
@@ -14,103 +27,767 @@ pub struct GkpParseResponse {
     pub error: Option<String>,
 }
 
+/// 茗伊 GKP 二进制文件头（MY#DATA 二进制 jx3dat 标识）
+const GKP_BINARY_SIGNATURE: [u8; 8] = [0x20, 0x61, 0x74, 0x17, 0x46, 0x14, 0x46, 0x17];
+
+/// 单条拍卖记录（GKP_Record 中的一项）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GkpItemRecord {
+    /// 记录唯一标识（茗伊生成的 key）
+    pub key: Option<String>,
+    /// 物品名称
+    pub item_name: String,
+    /// 购买者角色名
+    pub buyer: String,
+    /// 成交价（金，补贴/罚款等可能为负数）
+    pub price: i64,
+    /// 堆叠数量
+    pub stack_num: i32,
+    /// 掉落来源（BOSS/宝箱名称）
+    pub source_name: Option<String>,
+    /// 购买者门派 ID
+    pub buyer_force_id: Option<i32>,
+    /// 记录时间（Unix 秒）
+    pub time: i64,
+    /// 是否已被团长标记删除
+    pub deleted: bool,
+}
+
+/// 单条付款记录（GKP_Account 中的一项）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GkpPayment {
+    /// 付款角色名
+    pub payer: String,
+    /// 付款金额（金）
+    pub gold: i64,
+    /// 付款者门派 ID
+    pub payer_force_id: Option<i32>,
+    /// 付款时间（Unix 秒）
+    pub time: i64,
+}
+
+/// 解析后的 GKP 拍卖账本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GkpLedger {
+    /// 地图名称（GKP_Map）
+    pub map_name: Option<String>,
+    /// 账本时间（GKP_Time，Unix 秒）
+    pub time: Option<i64>,
+    /// 拍卖记录（按时间排序）
+    pub items: Vec<GkpItemRecord>,
+    /// 付款记录（按时间排序）
+    pub payments: Vec<GkpPayment>,
+    /// 拍卖总额（金，不含已删除记录）
+    pub total_price: i64,
+    /// 已付款总额（金）
+    pub total_paid: i64,
+}
+
+impl GkpLedger {
+    /// 实际分工资人数：GKP_Account 中的不同角色数，没有付款记录时为 None
+    pub fn payee_count(&self) -> Option<i64> {
+        let payees: HashSet<String> = self
+            .payments
+            .iter()
+            .map(|payment| normalize_role_name(&payment.payer))
+            .collect();
+        (!payees.is_empty()).then_some(payees.len() as i64)
+    }
+}
+
+/// 读取 table 的列表项：数组直接返回，对象按值返回（茗伊部分版本以 key 作为索引）
+fn table_entries(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(Value::Object(map)) => map.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn field_str(entry: &Value, key: &str) -> Option<String> {
+    entry
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn field_i64(entry: &Value, key: &str) -> Option<i64> {
+    entry
+        .get(key)
+        .and_then(|v| v.as_f64())
+        .map(|f| f.round() as i64)
+}
+
+fn field_bool(entry: &Value, key: &str) -> bool {
+    match entry.get(key) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::Number(n)) => n.as_f64().unwrap_or(0.0) != 0.0,
+        _ => false,
+    }
+}
+
+/// 从解码后的 JSON 树中提取 GKP 账本
+///
+/// 茗伊 MY_GKP 保存结构：`{ GKP_Record = {...}, GKP_Account = {...}, GKP_Map = "...", GKP_Time = n }`
+pub fn ledger_from_value(root: &Value) -> Result<GkpLedger, String> {
+    if !root.is_object() {
+        return Err("GKP 数据顶层不是 table".to_string());
+    }
+    let record_table = root.get("GKP_Record").or_else(|| root.get("record"));
+    let account_table = root.get("GKP_Account").or_else(|| root.get("account"));
+    if record_table.is_none() && account_table.is_none() {
+        return Err("GKP 数据缺少 GKP_Record/GKP_Account".to_string());
+    }
+
+    let mut items: Vec<GkpItemRecord> = table_entries(record_table)
+        .into_iter()
+        .filter_map(|entry| {
+            let item_name = field_str(entry, "szName")?;
+            Some(GkpItemRecord {
+                key: field_str(entry, "key"),
+                item_name,
+                buyer: field_str(entry, "szPlayer").unwrap_or_default(),
+                price: field_i64(entry, "nMoney").unwrap_or(0),
+                stack_num: field_i64(entry, "nStackNum").unwrap_or(1).max(1) as i32,
+                source_name: field_str(entry, "szNpcName"),
+                buyer_force_id: field_i64(entry, "dwForceID").map(|v| v as i32),
+                time: field_i64(entry, "nTime").unwrap_or(0),
+                deleted: field_bool(entry, "bDelete"),
+            })
+        })
+        .collect();
+    items.sort_by_key(|item| item.time);
+
+    let mut payments: Vec<GkpPayment> = table_entries(account_table)
+        .into_iter()
+        .filter(|entry| !field_bool(entry, "bDelete"))
+        .filter_map(|entry| {
+            Some(GkpPayment {
+                payer: field_str(entry, "szPlayer")?,
+                gold: field_i64(entry, "nGold").unwrap_or(0),
+                payer_force_id: field_i64(entry, "dwForceID").map(|v| v as i32),
+                time: field_i64(entry, "nTime").unwrap_or(0),
+            })
+        })
+        .collect();
+    payments.sort_by_key(|payment| payment.time);
+
+    let total_price = items
+        .iter()
+        .filter(|item| !item.deleted)
+        .map(|item| item.price)
+        .sum();
+    let total_paid = payments.iter().map(|payment| payment.gold).sum();
+
+    Ok(GkpLedger {
+        map_name: field_str(root, "GKP_Map"),
+        time: field_i64(root, "GKP_Time"),
+        items,
+        payments,
+        total_price,
+        total_paid,
+    })
+}
+
+/// 解析 GKP 文件内容（二进制 KLua、Lua 文本或 JSON 文本）为账本
+pub fn decode_gkp_buffer(buffer: &[u8]) -> Result<GkpLedger, String> {
+    if buffer.len() < GKP_BINARY_SIGNATURE.len() {
+        return Err("文件太小".to_string());
+    }
+
+    let root = if buffer.starts_with(&GKP_BINARY_SIGNATURE) {
+        klua::decode_byte_data(&buffer[GKP_BINARY_SIGNATURE.len()..])?
+    } else {
+        // 明文 jx3dat 为 GBK 编码的 Lua return 语句，旧版导出为 JSON
+        let text = match std::str::from_utf8(buffer) {
+            Ok(text) => text.to_string(),
+            Err(_) => encoding_rs::GBK.decode(buffer).0.into_owned(),
+        };
+        match klua::parse_lua_text(&text) {
+            Ok(value) => value,
+            Err(lua_err) => serde_json::from_str(text.trim())
+                .map_err(|_| format!("不是Lua表格式: {}", lua_err))?,
+        }
+    };
+
+    ledger_from_value(&root)
+}
+
 #[tauri::command]
 pub fn parse_binary_gkp(request: GkpParseRequest) -> Result<GkpParseResponse, String> {
-    // 简单的二进制检测
-    if request.buffer.len() < 8 {
-        return Ok(GkpParseResponse {
+    match decode_gkp_buffer(&request.buffer) {
+        Ok(ledger) => {
+            log::info!(
+                "[GkpParser] 解析成功: {} 条拍卖记录, {} 条付款记录, 总额 {} 金",
+                ledger.items.len(),
+                ledger.payments.len(),
+                ledger.total_price
+            );
+            let data = serde_json::to_value(&ledger).map_err(|e| format!("序列化失败: {}", e))?;
+            Ok(GkpParseResponse {
+                success: true,
+                data: Some(data),
+                error: None,
+            })
+        }
+        Err(e) => Ok(GkpParseResponse {
             success: false,
             data: None,
-            error: Some("文件太小".to_string()),
-        });
+            error: Some(format!("解析失败: {}", e)),
+        }),
     }
+}
 
-    // 检查是否是二进制格式
-    let mut is_binary = false;
-    if request.buffer.len() >= 8 {
-        // 检查二进制标识
-        let mut sig = [0u8; 8];
-        for i in 0..8 {
-            if i < request.buffer.len() {
-                sig[i] = request.buffer[i];
-            }
+/// GKP 导入结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GkpImportResult {
+    /// 写入的 records.id
+    pub record_id: String,
+    /// true 表示新建记录，false 表示合并到已有记录
+    pub created: bool,
+    /// 匹配到的副本完整名（raids.id，如 "25人普通河阳之战"）
+    pub raid_name: String,
+    /// 拍卖总额（金）
+    pub auction_total: i64,
+    /// 按人数均分后的每人工资（金）
+    pub per_person_salary: i64,
+    /// 当前角色在账本中的消费（金）
+    pub role_expense: i64,
+}
+
+/// 读取并解析 GKP 文件
+pub fn read_gkp_file(path: &Path) -> Result<GkpLedger, String> {
+    let buffer = std::fs::read(path)
+        .map_err(|e| format!("读取 GKP 文件失败: {} ({})", path.display(), e))?;
+    decode_gkp_buffer(&buffer)
+}
+
+/// GKP 文件所在的茗伊账号目录（`{uid}@zhcn_hd/userdata/gkp/xxx.gkp.jx3dat` 向上三级）
+fn gkp_account_dir(file_path: &Path) -> Option<PathBuf> {
+    file_path
+        .parent()
+        .and_then(Path::parent)
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+}
+
+/// 解析 GKP 文件对应的数据库角色
+///
+/// 优先通过账号目录的 info.jx3dat 映射（与掉落扫描一致）；
+/// 失败时回退到 scan_game_directory 匹配到的角色名（同名角色仅取第一个）。
+fn resolve_gkp_role(conn: &Connection, file_info: &GkpFileInfo) -> Result<DbRoleIdentity, String> {
+    let file_path = PathBuf::from(&file_info.file_path);
+    if let (Some(account_dir), Ok(game_dir)) = (gkp_account_dir(&file_path), get_game_directory()) {
+        if let Some(identity) =
+            resolve_db_role_identity(conn, &account_dir, &PathBuf::from(game_dir), true)?
+        {
+            return Ok(identity);
         }
+    }
+
+    let role_name = file_info
+        .role_name
+        .as_deref()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| format!("无法确定 GKP 文件所属角色: {}", file_info.file_name))?;
+
+    conn.query_row(
+        "SELECT id, account_id, name, COALESCE(server, ''), COALESCE(region, '') FROM roles \
+         WHERE REPLACE(REPLACE(name, ' ', ''), '\u{3000}', '') = ?1 LIMIT 1",
+        params![normalize_role_name(role_name)],
+        |row| {
+            Ok(DbRoleIdentity {
+                role_id: row.get(0)?,
+                account_id: row.get(1)?,
+                role_name: row.get(2)?,
+                server: row.get(3)?,
+                region: row.get(4)?,
+            })
+        },
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("角色未在数据库中登记: {}", role_name),
+        other => format!("查询 roles 表失败: {}", other),
+    })
+}
+
+/// GKP 记录时间（毫秒）
+///
+/// 优先使用账本内的 GKP_Time；缺失时使用文件名时间。
+/// 注意：parse_gkp_file_name 将文件名中的本地时间按 UTC 解析，这里需还原为本地时间。
+fn gkp_record_date_ms(file_info: &GkpFileInfo, ledger: &GkpLedger) -> i64 {
+    use chrono::TimeZone;
+
+    if let Some(time) = ledger.time.filter(|&t| t > 0) {
+        return time * 1000;
+    }
+    chrono::DateTime::from_timestamp_millis(file_info.timestamp)
+        .and_then(|utc| {
+            chrono::Local
+                .from_local_datetime(&utc.naive_utc())
+                .earliest()
+        })
+        .map(|local| local.timestamp_millis())
+        .unwrap_or(file_info.timestamp)
+}
 
-        // 检查常见二进制标识
-        if sig == [0x20, 0x61, 0x74, 0x17, 0x46, 0x14, 0x46, 0x17] {
-            // " aF\x14F\x17"
-            is_binary = true;
+/// 构建写入 records JSON 的 gkp 字段
+fn build_gkp_block(
+    file_info: &GkpFileInfo,
+    ledger: &GkpLedger,
+    headcount: i64,
+    per_person_salary: i64,
+) -> Value {
+    let lines: Vec<Value> = ledger
+        .items
+        .iter()
+        .filter(|item| !item.deleted)
+        .map(|item| {
+            serde_json::json!({
+                "itemName": item.item_name,
+                "buyer": item.buyer,
+                "price": item.price,
+                "stackNum": item.stack_num,
+                "sourceName": item.source_name,
+                "time": item.time * 1000,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "fileName": file_info.file_name,
+        "playerCount": file_info.player_count,
+        "headcount": headcount,
+        "auctionTotal": ledger.total_price,
+        "perPersonSalary": per_person_salary,
+        "items": lines,
+    })
+}
+
+/// 将 GKP 拍卖账本导入为副本记录
+///
+/// 1. 解析 GKP 文件，按文件名中的 `{人数}人{副本名}` 匹配 raids 表
+/// 2. 通过账号目录 info.jx3dat（或扫描到的角色名）映射到数据库角色
+/// 3. 写入记录，见 `save_gkp_import`
+#[tauri::command]
+pub fn import_gkp_record(file_info: GkpFileInfo) -> Result<GkpImportResult, String> {
+    let ledger = read_gkp_file(Path::new(&file_info.file_path))?;
+    let conn = db::init_db()?;
+
+    let raids = get_cached_raids(&conn)?;
+    let gkp_raid_name = format!("{}人{}", file_info.player_count, file_info.map_name);
+    let raid = match_raid_name(&gkp_raid_name, &raids)
+        .ok_or_else(|| format!("未找到匹配的副本配置: {}", gkp_raid_name))?;
+
+    let role = resolve_gkp_role(&conn, &file_info)?;
+    save_gkp_import(&conn, &file_info, &ledger, &role, raid)
+}
+
+/// 将 GKP 账本写入角色的副本记录
+///
+/// 1. 同角色、同副本、同 CD 窗口内已有待确认记录（pending/scanning）时合并：
+///    写入 gkp 字段，收入/支出为 0 时用账本数据补齐，掉落取并集；记录来源保持不变
+/// 2. 已有确认过的记录时拒绝导入，避免改动用户确认后的金额，也不重复占用 CD
+/// 3. 否则新建 pending 记录（data.source='gkp'，source 列为 gkp_import），等待用户确认
+///
+/// 每人工资 = 拍卖总额 / 分工资人数（向下取整），人数取 GKP_Account 中的不同角色数，
+/// 账本没有付款记录时回退到文件名中的团队人数；角色支出 = 账本中该角色购买物品的总价。
+fn save_gkp_import(
+    conn: &Connection,
+    file_info: &GkpFileInfo,
+    ledger: &GkpLedger,
+    role: &DbRoleIdentity,
+    raid: &RaidEntry,
+) -> Result<GkpImportResult, String> {
+    let normalized_role = normalize_role_name(&role.role_name);
+
+    let headcount = ledger
+        .payee_count()
+        .unwrap_or(file_info.player_count as i64)
+        .max(1);
+    let per_person_salary = (ledger.total_price / headcount).max(0);
+    let role_expense: i64 = ledger
+        .items
+        .iter()
+        .filter(|item| !item.deleted && normalize_role_name(&item.buyer) == normalized_role)
+        .map(|item| item.price)
+        .sum();
+    let item_names: Vec<String> = ledger
+        .items
+        .iter()
+        .filter(|item| !item.deleted)
+        .map(|item| item.item_name.clone())
+        .collect();
+
+    let record_date = gkp_record_date_ms(file_info, ledger);
+    let (window_start, window_end) =
+        calculate_cd_window(record_date, raid.raid_id.contains("10人"));
+    let gkp_block = build_gkp_block(file_info, ledger, headcount, per_person_salary);
+
+    let existing: Option<(String, String, String)> = match conn.query_row(
+        "SELECT id, status, data FROM records
+         WHERE role_id = ?1
+           AND record_date >= ?2 AND record_date < ?3
+           AND status != 'rejected'
+           AND json_extract(data, '$.raidName') = ?4
+         ORDER BY CASE status WHEN 'confirmed' THEN 1 ELSE 0 END
+         LIMIT 1",
+        params![role.role_id, window_start, window_end, raid.raid_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ) {
+        Ok(row) => Some(row),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("查询已有记录失败: {}", e)),
+    };
+
+    if let Some((record_id, status, _)) = &existing {
+        if status == "confirmed" {
+            return Err(format!(
+                "CD 内已有确认过的 {} 记录（{}），请直接编辑该记录",
+                raid.raid_id, record_id
+            ));
         }
     }
 
-    if is_binary {
-        // 尝试解析为Lua表
-        match parse_lua_from_binary(&request.buffer) {
-            Ok(data) => {
-                return Ok(GkpParseResponse {
-                    success: true,
-                    data: Some(data),
-                    error: None,
-                });
-            }
-            Err(e) => {
-                return Ok(GkpParseResponse {
-                    success: false,
-                    data: None,
-                    error: Some(format!("解析失败: {}", e)),
-                });
-            }
+    if let Some((record_id, _, data)) = existing {
+        let mut json: Value =
+            serde_json::from_str(&data).map_err(|e| format!("解析记录 data JSON 失败: {}", e))?;
+        if money::record_income(&json) == Money::ZERO {
+            json["goldIncome"] = serde_json::json!(per_person_salary);
+            json["goldIncomeCopper"] =
+                serde_json::json!(Money::from_gold(per_person_salary).copper());
         }
-    } else {
-        // 尝试解析为文本
-        match String::from_utf8(request.buffer.clone()) {
-            Ok(text) => {
-                // 简单检查是否是Lua表
-                if text.trim().starts_with('{') && text.trim().ends_with('}') {
-                    match serde_json::from_str(&text) {
-                        Ok(data) => {
-                            return Ok(GkpParseResponse {
-                                success: true,
-                                data: Some(data),
-                                error: None,
-                            });
-                        }
-                        Err(_) => {
-                            return Ok(GkpParseResponse {
-                                success: false,
-                                data: None,
-                                error: Some("无法解析JSON".to_string()),
-                            });
-                        }
-                    }
-                } else {
-                    return Ok(GkpParseResponse {
-                        success: false,
-                        data: None,
-                        error: Some("不是Lua表格式".to_string()),
-                    });
-                }
-            }
-            Err(_) => {
-                return Ok(GkpParseResponse {
-                    success: false,
-                    data: None,
-                    error: Some("UTF-8解码失败".to_string()),
-                });
+        if money::record_expense(&json) == Money::ZERO {
+            json["goldExpense"] = serde_json::json!(role_expense);
+            json["goldExpenseCopper"] = serde_json::json!(Money::from_gold(role_expense).copper());
+        }
+        let mut drops: Vec<String> = json["drops"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for name in &item_names {
+            if !drops.contains(name) {
+                drops.push(name.clone());
             }
         }
+        json["drops"] = serde_json::json!(drops);
+        json["gkp"] = gkp_block;
+
+        let record = RaidRecord::from_value(json)?;
+        raid_record::upsert_record(conn, &record)?;
+
+        log::info!(
+            "[GkpParser] GKP 已合并到已有记录: {} -> {} ({})",
+            file_info.file_name,
+            record_id,
+            raid.raid_id
+        );
+        return Ok(GkpImportResult {
+            record_id,
+            created: false,
+            raid_name: raid.raid_id.clone(),
+            auction_total: ledger.total_price,
+            per_person_salary,
+            role_expense,
+        });
     }
+
+    let record_id = uuid::Uuid::new_v4().to_string();
+    let record = serde_json::json!({
+        "id": record_id,
+        "accountId": role.account_id,
+        "roleId": role.role_id,
+        "roleName": role.role_name,
+        "server": role.server,
+        "region": role.region,
+        "raidName": raid.raid_id,
+        "date": record_date,
+        "goldIncome": per_person_salary,
+        "goldExpense": role_expense,
+        "goldIncomeCopper": Money::from_gold(per_person_salary).copper(),
+        "goldExpenseCopper": Money::from_gold(role_expense).copper(),
+        "hasXuanjing": false,
+        "hasMount": false,
+        "hasSecretBook": false,
+        "hasPet": false,
+        "hasMaJu": false,
+        "hasPendant": false,
+        "hasAppearance": false,
+        "hasTitle": false,
+        "bossIds": [],
+        "bossNames": [],
+        "type": "raid",
+        "source": "gkp",
+        "status": "pending",
+        "drops": item_names,
+        "dropTable": null,
+        "jclFiles": [],
+        "notes": format!("GKP 导入 - {}", file_info.file_name),
+        "gkp": gkp_block,
+    });
+    raid_record::upsert_record(conn, &RaidRecord::from_value(record)?)?;
+
+    log::info!(
+        "[GkpParser] GKP 新建 pending 记录: {} -> {} ({}, 总额 {} 金, 每人 {} 金)",
+        file_info.file_name,
+        record_id,
+        raid.raid_id,
+        ledger.total_price,
+        per_person_salary
+    );
+    Ok(GkpImportResult {
+        record_id,
+        created: true,
+        raid_name: raid.raid_id.clone(),
+        auction_total: ledger.total_price,
+        per_person_salary,
+        role_expense,
+    })
 }
 
-// 简单的Lua解析函数
-fn parse_lua_from_binary(buffer: &[u8]) -> Result<serde_json::Value, String> {
-    let text = String::from_utf8(buffer.to_vec()).map_err(|_| "UTF-8解码失败".to_string())?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_binary_gkp_fixture() {
+        // 按茗伊 GKP 导出结构构造的样本（文件头 + EncodeByteData 编码的账本 table，bDelete 为布尔值）
+        let buf = include_bytes!("../tests/fixtures/klua/sample.gkp.jx3dat");
+        let ledger = decode_gkp_buffer(buf).expect("解析失败");
+        assert_eq!(ledger.map_name.as_deref(), Some("河阳之战"));
+        assert_eq!(ledger.time, Some(1760000000));
+        assert_eq!(ledger.items.len(), 3);
+        // 按时间排序
+        let first = &ledger.items[0];
+        assert_eq!(first.item_name, "五行石（六级）");
+        assert_eq!(first.buyer, "李四");
+        assert_eq!(first.stack_num, 5);
+        assert_eq!(first.source_name.as_deref(), Some("风乌"));
+        assert_eq!(first.key.as_deref(), Some("r1"));
+        assert_eq!(ledger.items[1].price, 52000);
+        assert_eq!(ledger.items[1].buyer_force_id, Some(4));
+        assert!(ledger.items[2].deleted);
+        assert_eq!(ledger.items[2].price, -3000);
+        // 已删除记录不计入总额
+        assert_eq!(ledger.total_price, 60000);
+        assert_eq!(ledger.payments.len(), 2);
+        assert_eq!(ledger.payments[0].payer, "张三");
+        assert_eq!(ledger.total_paid, 60000);
+    }
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.push(b's');
+        buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn push_number(buf: &mut Vec<u8>, n: f64) {
+        buf.push(b'n');
+        buf.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn push_table(buf: &mut Vec<u8>, pairs: u32) {
+        buf.push(b't');
+        buf.extend_from_slice(&pairs.to_le_bytes());
+    }
+
+    #[test]
+    fn test_decode_binary_gkp_keeps_incomplete_records_apart() {
+        // 第一条缺少 szPlayer/nMoney，字段含 "key" 子串；结构化解码后仍是两条独立记录
+        let mut buf = GKP_BINARY_SIGNATURE.to_vec();
+        push_table(&mut buf, 1);
+        push_string(&mut buf, "GKP_Record");
+        push_table(&mut buf, 2);
+        push_number(&mut buf, 1.0);
+        push_table(&mut buf, 3);
+        push_string(&mut buf, "szName");
+        push_string(&mut buf, "monkey's key");
+        push_string(&mut buf, "nTime");
+        push_number(&mut buf, 1.0);
+        push_string(&mut buf, "bDelete");
+        buf.extend_from_slice(b"b\x00");
+        push_number(&mut buf, 2.0);
+        push_table(&mut buf, 4);
+        push_string(&mut buf, "szName");
+        push_string(&mut buf, "sword");
+        push_string(&mut buf, "szPlayer");
+        push_string(&mut buf, "buyer");
+        push_string(&mut buf, "nMoney");
+        push_number(&mut buf, 3000.0);
+        push_string(&mut buf, "nTime");
+        push_number(&mut buf, 2.0);
+
+        let ledger = decode_gkp_buffer(&buf).unwrap();
+        assert_eq!(ledger.items.len(), 2);
+        assert_eq!(ledger.items[0].item_name, "monkey's key");
+        assert_eq!(ledger.items[0].buyer, "");
+        assert_eq!(ledger.items[0].price, 0);
+        assert!(!ledger.items[0].deleted);
+        assert_eq!(ledger.items[1].buyer, "buyer");
+        assert_eq!(ledger.total_price, 3000);
+    }
+
+    fn import_fixture() -> (GkpFileInfo, GkpLedger, DbRoleIdentity, RaidEntry) {
+        let buf = include_bytes!("../tests/fixtures/klua/sample.gkp.jx3dat");
+        let file_info = GkpFileInfo {
+            file_path: String::new(),
+            file_name: "2025-10-09-16-53-20_25人河阳之战.gkp.jx3dat".to_string(),
+            timestamp: 0,
+            player_count: 25,
+            map_name: "河阳之战".to_string(),
+            role_name: Some("张三".to_string()),
+        };
+        let role = DbRoleIdentity {
+            role_id: "role1".to_string(),
+            account_id: "acc1".to_string(),
+            role_name: "张三".to_string(),
+            server: "梦江南".to_string(),
+            region: "电信五区".to_string(),
+        };
+        let raid = RaidEntry {
+            name: "河阳之战".to_string(),
+            raid_id: "25人普通河阳之战".to_string(),
+            bosses: Vec::new(),
+            boss_rules: Vec::new(),
+        };
+        (file_info, decode_gkp_buffer(buf).unwrap(), role, raid)
+    }
+
+    fn record_row(conn: &Connection, id: &str) -> (String, String, Value) {
+        conn.query_row(
+            "SELECT source, status, data FROM records WHERE id = ?1",
+            params![id],
+            |row| {
+                let data: String = row.get(2)?;
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    serde_json::from_str(&data).unwrap(),
+                ))
+            },
+        )
+        .unwrap()
+    }
 
-    // 简单的Lua表解析
-    if !text.trim().starts_with('{') || !text.trim().ends_with('}') {
-        return Err("不是Lua表格式".to_string());
+    fn insert_existing(conn: &Connection, status: &str, gold: i64) {
+        let data = serde_json::json!({
+            "id": "r1", "accountId": "acc1", "roleId": "role1", "raidName": "25人普通河阳之战",
+            "date": 1_760_000_000_000i64, "goldIncome": gold, "goldExpense": 0,
+            "hasXuanjing": false, "source": "auto", "status": status, "drops": ["玄晶·翻天卷"],
+        });
+        conn.execute(
+            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, source, status)
+             VALUES ('r1', ?1, '河阳之战', 'acc1', 'role1', 1760000000000, 'auto_scan', ?2)",
+            params![data.to_string(), status],
+        )
+        .unwrap();
     }
 
-    // 尝试解析为JSON
-    serde_json::from_str(&text).map_err(|e| format!("JSON解析失败: {}", e))
+    #[test]
+    fn test_gkp_import_creates_pending_record() {
+        let conn = crate::db::open_test_db();
+        let (file_info, ledger, role, raid) = import_fixture();
+
+        let result = save_gkp_import(&conn, &file_info, &ledger, &role, &raid).unwrap();
+        assert!(result.created);
+        // 60000 金由 GKP_Account 中的 2 人分
+        assert_eq!(result.per_person_salary, 30000);
+        assert_eq!(result.role_expense, 52000);
+
+        // data.source 与 source 列一致，且能通过记录模型校验
+        let (source, status, data) = record_row(&conn, &result.record_id);
+        assert_eq!(
+            (source.as_str(), status.as_str()),
+            ("gkp_import", "pending")
+        );
+        assert_eq!(data["source"], "gkp");
+        assert!(RaidRecord::from_value(data.clone()).is_ok());
+        assert_eq!(data["gkp"]["auctionTotal"], 60000);
+    }
+
+    #[test]
+    fn test_gkp_import_salary_uses_payee_count() {
+        let conn = crate::db::open_test_db();
+        let (file_info, mut ledger, role, raid) = import_fixture();
+        // 25 人本实际只有 20 人分工资，同名付款只算一人
+        ledger.payments = (0..20)
+            .flat_map(|i| {
+                let payment = GkpPayment {
+                    payer: format!("队员{}", i),
+                    gold: 0,
+                    payer_force_id: None,
+                    time: 0,
+                };
+                [payment.clone(), payment]
+            })
+            .collect();
+
+        let result = save_gkp_import(&conn, &file_info, &ledger, &role, &raid).unwrap();
+        assert_eq!(result.per_person_salary, 3000);
+        let (_, _, data) = record_row(&conn, &result.record_id);
+        assert_eq!(data["gkp"]["headcount"], 20);
+        assert_eq!(data["gkp"]["playerCount"], 25);
+
+        // 没有付款记录时按文件名中的团队人数分
+        let conn = crate::db::open_test_db();
+        ledger.payments.clear();
+        let result = save_gkp_import(&conn, &file_info, &ledger, &role, &raid).unwrap();
+        assert_eq!(result.per_person_salary, 2400);
+    }
+
+    #[test]
+    fn test_gkp_import_merges_into_pending_record() {
+        let conn = crate::db::open_test_db();
+        let (file_info, ledger, role, raid) = import_fixture();
+        insert_existing(&conn, "pending", 0);
+
+        let result = save_gkp_import(&conn, &file_info, &ledger, &role, &raid).unwrap();
+        assert!(!result.created);
+        assert_eq!(result.record_id, "r1");
+
+        let (source, status, data) = record_row(&conn, "r1");
+        assert_eq!((source.as_str(), status.as_str()), ("auto_scan", "pending"));
+        assert_eq!(data["source"], "auto");
+        assert_eq!(data["goldIncome"], 30000);
+        assert_eq!(data["goldExpense"], 52000);
+        // 已删除的账本条目不进入掉落，重复物品只保留一次
+        assert_eq!(
+            data["drops"],
+            serde_json::json!(["玄晶·翻天卷", "五行石（六级）"])
+        );
+        assert!(data.get("gkp").is_some());
+    }
+
+    #[test]
+    fn test_gkp_import_rejects_confirmed_record() {
+        let conn = crate::db::open_test_db();
+        let (file_info, ledger, role, raid) = import_fixture();
+        insert_existing(&conn, "confirmed", 3000);
+
+        assert!(save_gkp_import(&conn, &file_info, &ledger, &role, &raid).is_err());
+        let (_, status, data) = record_row(&conn, "r1");
+        assert_eq!(status, "confirmed");
+        assert_eq!(data["goldIncome"], 3000);
+        assert!(data.get("gkp").is_none());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM records", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_decode_truncated_binary_fails() {
+        let buf = include_bytes!("../tests/fixtures/klua/sample.gkp.jx3dat");
+        let map_end = buf.windows(7).position(|w| w == b"GKP_Map").unwrap() + 7;
+        assert!(decode_gkp_buffer(&buf[..map_end]).is_err());
+    }
 }
@@ -0,0 +1,590 @@
+//! KLua / jx3dat 数据反序列化
+//!
+//! 茗伊插件的数据有两种落盘形式：
+//! - 二进制：JX3 引擎 EncodeByteData 序列化的 table（userdata.db BLOB、二进制 .jx3dat）
+//! - 文本：Lua `return {...}` 语句（info.jx3dat 等明文文件）
+//!
+//! 两种形式都解码为 `serde_json::Value` 树：Lua table 的键为 1..n 连续整数时转为数组，
+//! 否则转为对象（数值键转为字符串）。
+
+use serde_json::{Map, Value};
+
+/// 嵌套 table 最大深度，防止损坏文件导致栈溢出
+const MAX_DEPTH: usize = 64;
+
+// ===== 二进制 EncodeByteData =====
+
+/// 数值：'n' + 8 字节 little-endian double
+const TAG_NUMBER: u8 = b'n';
+/// 字符串：'s' + u32 little-endian 字节长度 + 内容（GBK 或 UTF-8）
+const TAG_STRING: u8 = b's';
+/// table：'t' + u32 little-endian 键值对数量 + 依次排列的键、值
+const TAG_TABLE: u8 = b't';
+/// 布尔：'b' + 1 字节（0 为 false）
+const TAG_BOOL: u8 = b'b';
+
+/// EncodeByteData 顺序解码器
+///
+/// 每个值为类型标记 + 定长或带长度前缀的内容，table 的键和值都是完整的值，
+/// 因此可以从头到尾顺序读取，不需要按键名搜索。
+struct ByteDataDecoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteDataDecoder<'a> {
+    fn error(&self, offset: usize, message: &str) -> String {
+        format!("EncodeByteData 解析失败（偏移 {}）: {}", offset, message)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.buf.len())
+            .ok_or_else(|| self.error(self.pos, "数据意外结束"))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<usize, String> {
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap_or_default();
+        Ok(u32::from_le_bytes(bytes) as usize)
+    }
+
+    fn read_value(&mut self, depth: usize) -> Result<Value, String> {
+        let start = self.pos;
+        if depth > MAX_DEPTH {
+            return Err(self.error(start, "table 嵌套层级过深"));
+        }
+        match self.take(1)?[0] {
+            TAG_NUMBER => {
+                let bytes: [u8; 8] = self.take(8)?.try_into().unwrap_or_default();
+                Ok(number_value(f64::from_le_bytes(bytes)))
+            }
+            TAG_STRING => {
+                let len = self.read_u32()?;
+                Ok(Value::String(decode_string_bytes(self.take(len)?)))
+            }
+            TAG_BOOL => Ok(Value::Bool(self.take(1)?[0] != 0)),
+            TAG_TABLE => {
+                let count = self.read_u32()?;
+                let mut pairs: Vec<(Value, Value)> = Vec::new();
+                for _ in 0..count {
+                    let key = self.read_value(depth + 1)?;
+                    let value = self.read_value(depth + 1)?;
+                    // 与 Lua 文本一致：nil（NaN 等无法表示的数值）视为不存在
+                    if !key.is_null() && !value.is_null() {
+                        pairs.push((key, value));
+                    }
+                }
+                Ok(table_pairs_to_value(pairs))
+            }
+            other => Err(self.error(start, &format!("未知的类型标记 0x{:02x}", other))),
+        }
+    }
+}
+
+/// 解码 EncodeByteData 二进制数据为 JSON 树（规则同 `parse_lua_text`）
+///
+/// 数据必须恰好是一个完整的值，截断、未知类型标记或尾部多余字节都返回错误。
+pub fn decode_byte_data(buf: &[u8]) -> Result<Value, String> {
+    let mut decoder = ByteDataDecoder { buf, pos: 0 };
+    let value = decoder.read_value(0)?;
+    if decoder.pos < buf.len() {
+        return Err(decoder.error(decoder.pos, "值之后存在多余内容"));
+    }
+    Ok(value)
+}
+
+// ===== Lua 文本 =====
+
+/// Lua 字面量文本解析器（仅支持数据表达式：table/字符串/数值/布尔/nil）
+struct LuaTextParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> LuaTextParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Lua 解析失败（偏移 {}）: {}", self.pos, message)
+    }
+
+    /// 跳过空白和注释（`-- 行注释`、`--[[ 块注释 ]]`）
+    fn skip_trivia(&mut self) {
+        loop {
+            while matches!(self.peek(), Some(b) if b.is_ascii_whitespace()) {
+                self.pos += 1;
+            }
+            if !self.rest().starts_with("--") {
+                return;
+            }
+            self.pos += 2;
+            if let Some(level) = self.long_bracket_level() {
+                let close = format!("]{}]", "=".repeat(level));
+                match self.rest().find(&close) {
+                    Some(end) => self.pos += end + close.len(),
+                    None => self.pos = self.text.len(),
+                }
+            } else {
+                match self.rest().find('\n') {
+                    Some(end) => self.pos += end + 1,
+                    None => self.pos = self.text.len(),
+                }
+            }
+        }
+    }
+
+    /// 当前位置为长括号 `[[` / `[==[` 时返回等号数量，并跳过开括号
+    fn long_bracket_level(&mut self) -> Option<usize> {
+        let bytes = self.rest().as_bytes();
+        if bytes.first() != Some(&b'[') {
+            return None;
+        }
+        let level = bytes[1..].iter().take_while(|&&b| b == b'=').count();
+        if bytes.get(level + 1) != Some(&b'[') {
+            return None;
+        }
+        self.pos += level + 2;
+        Some(level)
+    }
+
+    fn read_name(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        let bytes = self.text.as_bytes();
+        if !matches!(bytes.get(start), Some(b) if b.is_ascii_alphabetic() || *b == b'_') {
+            return None;
+        }
+        let len = bytes[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+            .count();
+        self.pos += len;
+        Some(&self.text[start..start + len])
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("table 嵌套层级过深"));
+        }
+        self.skip_trivia();
+        match self.peek() {
+            Some(b'{') => self.parse_table(depth),
+            Some(b'"') | Some(b'\'') => self.parse_quoted_string().map(Value::String),
+            Some(b'[') => {
+                let level = self
+                    .long_bracket_level()
+                    .ok_or_else(|| self.error("无效的长字符串"))?;
+                self.parse_long_string(level).map(Value::String)
+            }
+            Some(b) if b == b'-' || b == b'.' || b.is_ascii_digit() => self.parse_number(),
+            Some(_) => {
+                let name = self.read_name().ok_or_else(|| self.error("无法识别的值"))?;
+                match name {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "nil" => Ok(Value::Null),
+                    other => Err(self.error(&format!("不支持的表达式 {}", other))),
+                }
+            }
+            None => Err(self.error("数据意外结束")),
+        }
+    }
+
+    fn parse_table(&mut self, depth: usize) -> Result<Value, String> {
+        self.pos += 1; // '{'
+        let mut pairs: Vec<(Value, Value)> = Vec::new();
+        let mut array_index = 1.0;
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(table_pairs_to_value(pairs));
+                }
+                None => return Err(self.error("table 未闭合")),
+                _ => {}
+            }
+
+            let checkpoint = self.pos;
+            let key = if self.peek() == Some(b'[') && self.long_bracket_level().is_none() {
+                // [expr] = value
+                self.pos = checkpoint + 1;
+                let key = self.parse_value(depth + 1)?;
+                self.skip_trivia();
+                if self.peek() != Some(b']') {
+                    return Err(self.error("缺少 ]"));
+                }
+                self.pos += 1;
+                self.expect_assign()?;
+                Some(key)
+            } else {
+                self.pos = checkpoint;
+                // name = value，否则回退为数组项
+                match self.read_name() {
+                    Some(name) if !matches!(name, "true" | "false" | "nil") => {
+                        self.skip_trivia();
+                        if self.peek() == Some(b'=') {
+                            self.pos += 1;
+                            Some(Value::String(name.to_string()))
+                        } else {
+                            return Err(self.error(&format!("不支持的表达式 {}", name)));
+                        }
+                    }
+                    _ => {
+                        self.pos = checkpoint;
+                        None
+                    }
+                }
+            };
+
+            let value = self.parse_value(depth + 1)?;
+            let key = key.unwrap_or_else(|| {
+                let k = number_value(array_index);
+                array_index += 1.0;
+                k
+            });
+            // Lua 中 value 为 nil 的键等同于不存在
+            if !value.is_null() {
+                pairs.push((key, value));
+            }
+
+            self.skip_trivia();
+            match self.peek() {
+                Some(b',') | Some(b';') => self.pos += 1,
+                Some(b'}') => {}
+                _ => return Err(self.error("table 字段之间缺少分隔符")),
+            }
+        }
+    }
+
+    fn expect_assign(&mut self) -> Result<(), String> {
+        self.skip_trivia();
+        if self.peek() != Some(b'=') {
+            return Err(self.error("缺少 ="));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_quoted_string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap_or(b'"');
+        self.pos += 1;
+        let mut out: Vec<u8> = Vec::new();
+        let bytes = self.text.as_bytes();
+        loop {
+            let b = *bytes
+                .get(self.pos)
+                .ok_or_else(|| self.error("字符串未闭合"))?;
+            self.pos += 1;
+            if b == quote {
+                break;
+            }
+            if b != b'\\' {
+                out.push(b);
+                continue;
+            }
+            let escaped = *bytes
+                .get(self.pos)
+                .ok_or_else(|| self.error("字符串未闭合"))?;
+            self.pos += 1;
+            match escaped {
+                b'n' => out.push(b'\n'),
+                b't' => out.push(b'\t'),
+                b'r' => out.push(b'\r'),
+                b'a' => out.push(0x07),
+                b'b' => out.push(0x08),
+                b'f' => out.push(0x0c),
+                b'v' => out.push(0x0b),
+                b'\n' => out.push(b'\n'),
+                b'x' => {
+                    let hex = self
+                        .text
+                        .get(self.pos..self.pos + 2)
+                        .and_then(|h| u8::from_str_radix(h, 16).ok())
+                        .ok_or_else(|| self.error("无效的 \\x 转义"))?;
+                    self.pos += 2;
+                    out.push(hex);
+                }
+                b'0'..=b'9' => {
+                    let len = bytes[self.pos - 1..]
+                        .iter()
+                        .take(3)
+                        .take_while(|b| b.is_ascii_digit())
+                        .count();
+                    let code: u32 = self.text[self.pos - 1..self.pos - 1 + len]
+                        .parse()
+                        .map_err(|_| self.error("无效的数字转义"))?;
+                    self.pos += len - 1;
+                    out.push(u8::try_from(code).map_err(|_| self.error("数字转义超出范围"))?);
+                }
+                other => out.push(other),
+            }
+        }
+        Ok(decode_string_bytes(&out))
+    }
+
+    fn parse_long_string(&mut self, level: usize) -> Result<String, String> {
+        let close = format!("]{}]", "=".repeat(level));
+        let end = self
+            .rest()
+            .find(&close)
+            .ok_or_else(|| self.error("长字符串未闭合"))?;
+        let content = &self.rest()[..end];
+        // Lua 规定紧跟开括号的换行不计入内容
+        let content = content
+            .strip_prefix("\r\n")
+            .or_else(|| content.strip_prefix('\n'))
+            .unwrap_or(content)
+            .to_string();
+        self.pos += end + close.len();
+        Ok(content)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.pos += 1;
+            self.skip_trivia();
+        }
+        let digits_start = self.pos;
+        let rest = self.rest();
+        let number = if rest.starts_with("0x") || rest.starts_with("0X") {
+            let len = rest[2..]
+                .bytes()
+                .take_while(|b| b.is_ascii_hexdigit())
+                .count();
+            self.pos += 2 + len;
+            i64::from_str_radix(&rest[2..2 + len], 16)
+                .map(|n| n as f64)
+                .map_err(|_| self.error("无效的十六进制数"))?
+        } else {
+            let bytes = rest.as_bytes();
+            let mut len = 0;
+            while len < bytes.len() {
+                let b = bytes[len];
+                let is_exponent_sign =
+                    (b == b'-' || b == b'+') && len > 0 && matches!(bytes[len - 1], b'e' | b'E');
+                if b.is_ascii_digit() || b == b'.' || b == b'e' || b == b'E' || is_exponent_sign {
+                    len += 1;
+                } else {
+                    break;
+                }
+            }
+            self.pos += len;
+            rest[..len]
+                .parse::<f64>()
+                .map_err(|_| self.error(&format!("无效的数值 {}", &self.text[start..self.pos])))?
+        };
+        if self.pos == digits_start {
+            return Err(self.error("无效的数值"));
+        }
+        Ok(number_value(if negative { -number } else { number }))
+    }
+}
+
+/// 解析 Lua `return {...}` 文本（也接受不带 return 的单个字面量）为 JSON 树
+pub fn parse_lua_text(text: &str) -> Result<Value, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut parser = LuaTextParser { text, pos: 0 };
+    parser.skip_trivia();
+    if parser.rest().starts_with("return")
+        && !matches!(
+            parser.text.as_bytes().get(parser.pos + 6),
+            Some(b) if b.is_ascii_alphanumeric() || *b == b'_'
+        )
+    {
+        parser.pos += 6;
+    }
+    let value = parser.parse_value(0)?;
+    parser.skip_trivia();
+    if parser.peek() == Some(b';') {
+        parser.pos += 1;
+        parser.skip_trivia();
+    }
+    if parser.pos < parser.text.len() {
+        return Err(parser.error("表达式之后存在多余内容"));
+    }
+    Ok(value)
+}
+
+// ===== 公共工具 =====
+
+/// 茗伊中文字符串以 GBK 或 UTF-8 存储，UTF-8 解码失败时回退 GBK
+fn decode_string_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.trim_matches('\0').to_string(),
+        Err(_) => {
+            let (cow, _, _) = encoding_rs::GBK.decode(bytes);
+            cow.trim_matches('\0').to_string()
+        }
+    }
+}
+
+/// f64 转 JSON 数值：整数值保持整数表示，NaN/Inf 转为 null
+fn number_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        return Value::from(number as i64);
+    }
+    serde_json::Number::from_f64(number)
+        .map(Value::Number)
+        .unwrap_or(Value::Null)
+}
+
+/// 将 Lua table 的键值对转换为 JSON
+///
+/// 键为 1..n 连续整数时视为数组，否则转为对象（数值键转为字符串）
+fn table_pairs_to_value(mut pairs: Vec<(Value, Value)>) -> Value {
+    pairs.sort_by(|a, b| match (a.0.as_f64(), b.0.as_f64()) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+        _ => std::cmp::Ordering::Equal,
+    });
+    let is_array = !pairs.is_empty()
+        && pairs
+            .iter()
+            .enumerate()
+            .all(|(i, (key, _))| key.as_f64() == Some((i + 1) as f64));
+    if is_array {
+        return Value::Array(pairs.into_iter().map(|(_, v)| v).collect());
+    }
+
+    let mut map = Map::new();
+    for (key, value) in pairs {
+        let key = match key {
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => continue,
+        };
+        map.insert(key, value);
+    }
+    Value::Object(map)
+}
+
+/// 读取 table 字段的字符串值（数值字段格式化为字符串）
+pub fn get_string(table: &Value, key: &str) -> Option<String> {
+    match table.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// 读取 table 字段的数值
+pub fn get_f64(table: &Value, key: &str) -> Option<f64> {
+    table.get(key)?.as_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_string(buf: &mut Vec<u8>, bytes: &[u8]) {
+        buf.push(TAG_STRING);
+        buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        buf.extend_from_slice(bytes);
+    }
+
+    fn push_number(buf: &mut Vec<u8>, number: f64) {
+        buf.push(TAG_NUMBER);
+        buf.extend_from_slice(&number.to_le_bytes());
+    }
+
+    #[test]
+    fn test_decode_byte_data_fixture() {
+        // 按 RoleStat BLOB 结构构造的样本：{ d = { ... }, v = "29.0.0" }
+        let blob = include_bytes!("../tests/fixtures/klua/role_stat.blob");
+        let root = decode_byte_data(blob).unwrap();
+        assert_eq!(get_string(&root, "v").as_deref(), Some("29.0.0"));
+        let data = &root["d"];
+        assert_eq!(get_string(data, "name").as_deref(), Some("叶未晓"));
+        assert_eq!(get_f64(data, "contribution_remain"), Some(1200.0));
+        assert_eq!(get_f64(&data["money"], "nGold"), Some(123456.0));
+        assert_eq!(get_f64(&data["role_stamina"], "current"), Some(800.0));
+        assert_eq!(get_f64(&data["account_stamina"], "current"), Some(3200.0));
+        assert_eq!(get_f64(data, "missing"), None);
+    }
+
+    #[test]
+    fn test_decode_byte_data_lengths_and_bools() {
+        // 含 's'(0x73) 字节的字符串、长度低字节为 0 的字符串、布尔值、数组 table
+        let long = "玄".repeat(128);
+        let (gbk, _, _) = encoding_rs::GBK.encode(&long);
+        assert_eq!(gbk.len(), 256);
+
+        let mut buf = vec![TAG_TABLE];
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        push_string(&mut buf, b"szName");
+        push_string(&mut buf, b"assassins");
+        push_string(&mut buf, b"szLong");
+        push_string(&mut buf, &gbk);
+        push_string(&mut buf, b"bDelete");
+        buf.extend_from_slice(&[TAG_BOOL, 1]);
+        push_string(&mut buf, b"list");
+        buf.push(TAG_TABLE);
+        buf.extend_from_slice(&2u32.to_le_bytes());
+        push_number(&mut buf, 2.0);
+        buf.extend_from_slice(&[TAG_BOOL, 0]);
+        push_number(&mut buf, 1.0);
+        push_number(&mut buf, 8000.0);
+
+        let root = decode_byte_data(&buf).unwrap();
+        assert_eq!(root["szName"], serde_json::json!("assassins"));
+        assert_eq!(root["szLong"], serde_json::json!(long));
+        assert_eq!(root["bDelete"], serde_json::json!(true));
+        assert_eq!(root["list"], serde_json::json!([8000, false]));
+    }
+
+    #[test]
+    fn test_decode_byte_data_invalid() {
+        let blob = include_bytes!("../tests/fixtures/klua/role_stat.blob");
+        assert!(decode_byte_data(&blob[..blob.len() - 1]).is_err());
+        assert!(decode_byte_data(&[blob.as_slice(), &[0]].concat()).is_err());
+        assert!(decode_byte_data(b"x").is_err());
+        assert!(decode_byte_data(&[]).is_err());
+        // 长度前缀超出数据范围
+        assert!(decode_byte_data(&[TAG_STRING, 0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn test_parse_lua_text_return_table() {
+        let text = r#"return {region="电信五区",uid="432345564243886337",szName="别名",name="角色名",server="梦江南"}"#;
+        let root = parse_lua_text(text).unwrap();
+        assert_eq!(get_string(&root, "name").as_deref(), Some("角色名"));
+        assert_eq!(get_string(&root, "szName").as_deref(), Some("别名"));
+        assert_eq!(
+            get_string(&root, "uid").as_deref(),
+            Some("432345564243886337")
+        );
+    }
+
+    #[test]
+    fn test_parse_lua_text_mixed_syntax() {
+        let text = "-- comment\nreturn {\n  [1] = 'a\\'b', [2] = [[long\nstr]];\n  n = -1.5e2, h = 0x10, ok = false, none = nil,\n  nested = { 1, 2, { x = \"\\65\\x42\" } },\n}\n";
+        let root = parse_lua_text(text).unwrap();
+        assert_eq!(root["1"], serde_json::json!("a'b"));
+        assert_eq!(root["2"], serde_json::json!("long\nstr"));
+        assert_eq!(get_f64(&root, "n"), Some(-150.0));
+        assert_eq!(get_f64(&root, "h"), Some(16.0));
+        assert_eq!(root["ok"], serde_json::json!(false));
+        assert!(root.get("none").is_none());
+        assert_eq!(root["nested"], serde_json::json!([1, 2, { "x": "AB" }]));
+    }
+
+    #[test]
+    fn test_parse_lua_text_invalid() {
+        assert!(parse_lua_text("return {a=1").is_err());
+        assert!(parse_lua_text("return {a=foo()}").is_err());
+        assert!(parse_lua_text("return {a=1} extra").is_err());
+    }
+}
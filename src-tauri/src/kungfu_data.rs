@@ -12,7 +12,6 @@ pub struct Force {
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Kungfu {
-    #[allow(dead_code)]
     pub id: i32,
     pub name: String,
     // 前端展示配置字段（后端当前仅用于数据序列化一致性）
@@ -44,7 +43,6 @@ pub fn get_kungfu_data() -> &'static KungfuData {
     &KUNGFU_DATA
 }
 
-#[allow(dead_code)]
 static FORCE_ID_TO_NAME: LazyLock<HashMap<i32, String>> = LazyLock::new(|| {
     KUNGFU_DATA
         .forces
@@ -61,6 +59,14 @@ static FORCE_NAME_TO_ID: LazyLock<HashMap<String, i32>> = LazyLock::new(|| {
         .collect()
 });
 
+static KUNGFU_ID_TO_NAME: LazyLock<HashMap<i32, String>> = LazyLock::new(|| {
+    KUNGFU_DATA
+        .forces
+        .iter()
+        .flat_map(|force| force.kungfus.iter().map(|k| (k.id, k.name.clone())))
+        .collect()
+});
+
 static MARTIAL_TO_SECT: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     KUNGFU_DATA
         .forces
@@ -88,11 +94,15 @@ static KUNGFU_FORCE_NAME_TO_ID: LazyLock<HashMap<(i32, String), i32>> = LazyLock
         .collect()
 });
 
-#[allow(dead_code)]
 pub fn get_force_name(force_id: i32) -> Option<String> {
     FORCE_ID_TO_NAME.get(&force_id).cloned()
 }
 
+/// 按心法 ID（JCL PLAYER_INFO 的 dwMountKungfuID）获取心法名
+pub fn get_kungfu_name(kungfu_id: i32) -> Option<String> {
+    KUNGFU_ID_TO_NAME.get(&kungfu_id).cloned()
+}
+
 pub fn get_force_id_by_name(force_name: &str) -> Option<i32> {
     FORCE_NAME_TO_ID.get(force_name).copied()
 }
@@ -123,6 +133,13 @@ mod tests {
         assert_eq!(get_force_name(999), None);
     }
 
+    #[test]
+    fn test_get_kungfu_name() {
+        assert_eq!(get_kungfu_name(10002), Some("洗髓经".to_string()));
+        assert_eq!(get_kungfu_name(10756), Some("山海心诀".to_string()));
+        assert_eq!(get_kungfu_name(0), None);
+    }
+
     #[test]
     fn test_get_force_id_by_name() {
         assert_eq!(get_force_id_by_name("万灵"), Some(214));
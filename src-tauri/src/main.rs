@@ -7,12 +7,15 @@ mod equip_sync;
 mod game_directory;
 mod gkp_parser;
 mod jx3_process;
+mod klua;
 mod kungfu_data;
 mod mingyi;
+mod money;
 mod runtime_mode;
 mod updater;
 
 const PREPARE_INSTALL_DATA_FLAG: &str = "--prepare-install-data";
+const REPLAY_JCL_FLAG: &str = "--replay-jcl";
 
 #[cfg(target_os = "windows")]
 fn check_webview2() -> Result<String, String> {
@@ -96,6 +99,32 @@ fn is_prepare_install_data_mode() -> bool {
         .any(|arg| arg == PREPARE_INSTALL_DATA_FLAG)
 }
 
+/// JCL 回放模式参数（`--replay-jcl` 之后的所有参数），非回放模式返回 None
+fn replay_jcl_args() -> Option<Vec<String>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let pos = args.iter().position(|arg| arg == REPLAY_JCL_FLAG)?;
+    Some(args[pos + 1..].to_vec())
+}
+
+/// 连接到启动进程（命令行）的控制台
+///
+/// release 版本为 `windows_subsystem = "windows"`，没有控制台，命令行模式的输出会丢失。
+#[cfg(target_os = "windows")]
+fn attach_parent_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(dw_process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // 从资源管理器启动时没有父控制台，调用失败可忽略
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_parent_console() {}
+
 fn run_prepare_install_data() -> Result<(), String> {
     // 使用跳过迁移的版本，避免安装时迁移副作用导致数据丢失。
     // 迁移逻辑留给应用正常启动时的 init_db 处理。
@@ -111,6 +140,18 @@ fn main() {
         return;
     }
 
+    if let Some(args) = replay_jcl_args() {
+        attach_parent_console();
+        match mingyi::jcl_replay::run_cli(&args) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(error) => {
+                eprintln!("JCL 回放失败: {}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
     // 设置 panic hook
     std::panic::set_hook(Box::new(|info| {
         log::error!("Panic occurred: {:?}", info);
@@ -125,6 +166,11 @@ fn main() {
         Err(e) => log::error!("[INIT] 数据库预初始化失败: {}", e),
     }
 
+    // 清除超过保留天数的回收站条目
+    if let Err(e) = db::trash::purge_expired_on_startup() {
+        log::error!("[Trash] 清除过期回收站条目失败: {}", e);
+    }
+
     // 启动后台装备同步任务
     std::thread::spawn(|| {
         // 等待 2 秒让应用完全启动
@@ -189,8 +235,23 @@ fn main() {
     let builder = builder.plugin(tauri_plugin_mcp_bridge::init());
 
     builder
+        .setup(|app| {
+            // 目录监听扫描结果转发为前端事件
+            let handle = app.handle().clone();
+            mingyi::scan_watcher::set_update_listener(move |update| {
+                use tauri::Emitter;
+                let _ = handle.emit(mingyi::scan_watcher::RAID_SCAN_UPDATED_EVENT, update);
+            });
+            let handle = app.handle().clone();
+            mingyi::session_tracker::set_event_listener(move |event| {
+                use tauri::Emitter;
+                let _ = handle.emit(mingyi::session_tracker::RAID_SESSION_EVENT, event);
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             gkp_parser::parse_binary_gkp,
+            gkp_parser::import_gkp_record,
             game_directory::scan_game_directory,
             game_directory::auto_parse_game_directory,
             game_directory::import_local_accounts,
@@ -217,6 +278,15 @@ fn main() {
             db::db_delete_role_structured,
             // 记录相关
             db::db_get_records,
+            db::record_query::db_query_records,
+            db::income_stats::db_get_income_stats,
+            db::record_history::db_get_record_history,
+            db::record_history::db_undo_record_change,
+            db::trash::db_get_trash,
+            db::trash::db_restore_trash_item,
+            db::trash::db_purge_trash,
+            db::trash::db_get_trash_retention_days,
+            db::trash::db_set_trash_retention_days,
             db::db_get_pending_records,
             db::db_save_records,
             db::db_add_record,
@@ -257,6 +327,36 @@ fn main() {
             mingyi::drop_scanner::confirm_record,
             mingyi::drop_scanner::reject_record,
             mingyi::drop_scanner::clear_jcl_cache,
+            mingyi::drop_scanner::list_raid_attempts,
+            mingyi::drop_scanner::list_record_chatlog_items,
+            // 扫描解释（dry-run）
+            mingyi::scan_explain::scan_raid_drops_explain,
+            // 已确认记录重新扫描
+            mingyi::record_rescan::rescan_record,
+            mingyi::record_rescan::accept_rescan_fields,
+            // BOSS 击杀判定规则
+            mingyi::boss_rules::get_boss_kill_rules,
+            mingyi::boss_rules::save_boss_kill_rule,
+            // 团队名单
+            mingyi::roster::list_record_roster,
+            mingyi::roster::list_raid_companions,
+            // 目录监听自动扫描
+            mingyi::scan_watcher::start_raid_scan_watcher,
+            mingyi::scan_watcher::stop_raid_scan_watcher,
+            // 进行中副本时间线
+            mingyi::session_tracker::get_raid_session_timeline,
+            // 多开同团
+            mingyi::run_groups::list_run_group_members,
+            // 团队拍卖账本
+            mingyi::auction_ledger::get_record_auction_ledger,
+            // 团长模式工资分配
+            mingyi::salary_sheet::preview_salary_sheet,
+            mingyi::salary_sheet::save_salary_sheet,
+            mingyi::salary_sheet::get_salary_sheet,
+            mingyi::salary_sheet::export_salary_sheet,
+            // JCL 战斗统计
+            mingyi::combat_stats::analyze_jcl_combat_stats,
+            mingyi::combat_stats::analyze_jcl_deaths,
             // 缓存相关
             db::db_get_cache,
             db::db_save_cache,
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::game_directory::MINGYI_ACCOUNTS_BASE_PATH;
 use crate::jx3_process;
+use crate::klua;
 
 /// 活跃等级
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -184,7 +185,7 @@ fn get_path_mtime(path: &Path) -> Option<SystemTime> {
 /// - `extension`: 文件扩展名过滤（如 "jcl"、"gkp.jx3dat"）；None 表示不过滤
 /// - `window_start`: 时间窗口下限（仅返回窗口内的 mtime）
 /// - `window_end`: 时间窗口上限
-fn get_latest_file_mtime_in_dir(
+pub(crate) fn get_latest_file_mtime_in_dir(
     dir: &Path,
     extension_filter: Option<&str>,
     window_start: SystemTime,
@@ -240,6 +241,9 @@ pub struct AccountActivityScan {
 /// 若两者都不在时间窗口内（说明本会话未登录该账号），跳过 chat_log/gkp/combat_logs
 /// 的 read_dir 文件遍历（性能瓶颈），仅保留账号目录兜底 stat。
 /// 实测：29 个账号目录从 3.7s 降至 <0.5s（27 个未登录账号快速跳过）。
+///
+/// 目录监听（`scan_watcher`）运行期间，chat_log / combat_logs 的最新文件 mtime
+/// 直接取监听器记录的写入时间，不再遍历这两个目录。
 pub fn scan_account_activity(
     account_dir: &Path,
     window_start: SystemTime,
@@ -274,15 +278,20 @@ pub fn scan_account_activity(
     let need_full_scan = info_in_window || userdata_in_window;
 
     if need_full_scan {
+        let watched = crate::mingyi::scan_watcher::watched_activity(account_dir);
+
         // 数据源 3: userdata/chat_log/ 目录 mtime + 最新 chatlog_*.v2.db 文件 mtime
         let chat_log_dir = account_dir.join("userdata").join("chat_log");
         let chat_log_dir_mtime = get_path_mtime(&chat_log_dir).filter(in_window);
-        let latest_chatlog_db_mtime = get_latest_file_mtime_in_dir(
-            &chat_log_dir,
-            Some(".v2.db"),
-            window_start,
-            window_end,
-        );
+        let latest_chatlog_db_mtime = match &watched {
+            Some(w) => w.chat_log.filter(in_window),
+            None => get_latest_file_mtime_in_dir(
+                &chat_log_dir,
+                Some(".v2.db"),
+                window_start,
+                window_end,
+            ),
+        };
         let chat_log_candidate = match (chat_log_dir_mtime, latest_chatlog_db_mtime) {
             (Some(dir_m), Some(db_m)) => Some(if dir_m > db_m { dir_m } else { db_m }),
             (Some(m), None) | (None, Some(m)) => Some(m),
@@ -313,12 +322,15 @@ pub fn scan_account_activity(
         // 数据源 5: userdata/combat_logs/ 目录 mtime + 最新 .jcl mtime
         let combat_logs_dir = account_dir.join("userdata").join("combat_logs");
         let combat_logs_mtime = get_path_mtime(&combat_logs_dir).filter(in_window);
-        let latest_jcl_mtime = get_latest_file_mtime_in_dir(
-            &combat_logs_dir,
-            Some(".jcl"),
-            window_start,
-            window_end,
-        );
+        let latest_jcl_mtime = match &watched {
+            Some(w) => w.combat_logs.filter(in_window),
+            None => get_latest_file_mtime_in_dir(
+                &combat_logs_dir,
+                Some(".jcl"),
+                window_start,
+                window_end,
+            ),
+        };
         let combat_logs_candidate = match (combat_logs_mtime, latest_jcl_mtime) {
             (Some(dir_m), Some(jcl_m)) => Some(if dir_m > jcl_m { dir_m } else { jcl_m }),
             (Some(m), None) | (None, Some(m)) => Some(m),
@@ -341,20 +353,49 @@ pub fn scan_account_activity(
     }
 }
 
-/// 从 Lua return 格式的 info.jx3dat 中提取字段值
+/// 读取 info.jx3dat 顶层 table 的字符串字段
 ///
 /// 文件格式示例：
 /// ```text
 /// return {region="电信五区",uid="432345564243886337",name="角色名",server="梦江南",...}
 /// ```
 ///
-/// 提取 `key="value"` 中的 value 部分
-fn extract_lua_string_field(text: &str, key: &str) -> Option<String> {
-    let pattern = format!("{}=\"", key);
-    let start = text.find(&pattern)? + pattern.len();
-    let rest = &text[start..];
-    let end = rest.find('"')?;
-    Some(rest[..end].to_string())
+/// 由 klua 解析为完整的 table 后按键读取，避免子串匹配误命中（如 `szName=` 中的 `name=`）
+fn lua_string_field(table: &serde_json::Value, key: &str) -> Option<String> {
+    klua::get_string(table, key).filter(|value| !value.is_empty())
+}
+
+/// 整体解析失败时（如某个字段的值不是字面量）逐字段恢复 `key="value"`
+///
+/// 键前必须是 table 分隔符或空白，避免 `szName=` 命中 `name=`
+fn recover_lua_string_field(text: &str, key: &str) -> Option<String> {
+    text.match_indices(key).find_map(|(pos, _)| {
+        let at_boundary = text[..pos]
+            .chars()
+            .next_back()
+            .is_none_or(|c| matches!(c, '{' | ',' | ';') || c.is_whitespace());
+        if !at_boundary {
+            return None;
+        }
+        let value = text[pos + key.len()..]
+            .trim_start()
+            .strip_prefix('=')?
+            .trim_start();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let end = value[1..].find(quote)?;
+        Some(value[1..1 + end].to_string())
+    })
+}
+
+/// 逐字段恢复 info.jx3dat 的身份字段，结果与 `klua::parse_lua_text` 的 table 格式一致
+fn recover_info_fields(text: &str) -> serde_json::Value {
+    let mut table = serde_json::Map::new();
+    for key in ["uid", "name", "server", "region"] {
+        if let Some(value) = recover_lua_string_field(text, key) {
+            table.insert(key.to_string(), serde_json::Value::String(value));
+        }
+    }
+    serde_json::Value::Object(table)
 }
 
 /// 解析 info.jx3dat 文件，提取角色身份信息
@@ -372,12 +413,22 @@ pub fn parse_info_jx3dat(path: &Path, game_directory: Option<&Path>) -> Option<M
     if had_errors {
         log::warn!("[ActiveDetector] info.jx3dat GBK 解码存在异常: {:?}", path);
     }
-    let text = text.as_ref();
+    let table = match klua::parse_lua_text(text.as_ref()) {
+        Ok(table) => table,
+        Err(e) => {
+            log::warn!(
+                "[ActiveDetector] info.jx3dat 整体解析失败，逐字段恢复: {:?} ({})",
+                path,
+                e
+            );
+            recover_info_fields(text.as_ref())
+        }
+    };
 
-    let uid = extract_lua_string_field(text, "uid")?;
-    let mut role_name = extract_lua_string_field(text, "name").unwrap_or_default();
-    let server = extract_lua_string_field(text, "server").unwrap_or_default();
-    let region = extract_lua_string_field(text, "region").unwrap_or_default();
+    let uid = lua_string_field(&table, "uid")?;
+    let mut role_name = lua_string_field(&table, "name").unwrap_or_default();
+    let server = lua_string_field(&table, "server").unwrap_or_default();
+    let region = lua_string_field(&table, "region").unwrap_or_default();
 
     // 回退：info.jx3dat 缺失 name 字段时，从茗伊装备数据库查 ownername
     // 实测部分账号（如糯闪 uid=342273571708094124）的 info.jx3dat 完全缺失 name/id 字段，
@@ -416,7 +467,7 @@ pub fn parse_info_jx3dat(path: &Path, game_directory: Option<&Path>) -> Option<M
 /// - `!all-users@zhcn_hd`（全局共享目录）
 /// - `#cache`（缓存目录）
 /// - `#电信区_梦江南@zhcn_hd` 等（以 # 开头但包含下划线的区服目录）
-fn is_mingyi_account_dir(dir_name: &str) -> bool {
+pub(crate) fn is_mingyi_account_dir(dir_name: &str) -> bool {
     if !dir_name.ends_with("@zhcn_hd") {
         return false;
     }
@@ -838,26 +889,47 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_lua_string_field() {
-        let text = r#"return {region="电信五区",uid="432345564243886337",name="角色名",server="梦江南"}"#;
+    fn test_lua_string_field() {
+        let text = r#"return {region="电信五区",uid="432345564243886337",szName="别名",name="角色名",server="梦江南"}"#;
+        let table = klua::parse_lua_text(text).unwrap();
         assert_eq!(
-            extract_lua_string_field(text, "uid"),
+            lua_string_field(&table, "uid"),
             Some("432345564243886337".to_string())
         );
         assert_eq!(
-            extract_lua_string_field(text, "name"),
+            lua_string_field(&table, "name"),
             Some("角色名".to_string())
         );
         assert_eq!(
-            extract_lua_string_field(text, "server"),
+            lua_string_field(&table, "server"),
             Some("梦江南".to_string())
         );
         assert_eq!(
-            extract_lua_string_field(text, "region"),
+            lua_string_field(&table, "region"),
             Some("电信五区".to_string())
         );
         // 不存在的字段
-        assert_eq!(extract_lua_string_field(text, "nonexistent"), None);
+        assert_eq!(lua_string_field(&table, "nonexistent"), None);
+    }
+
+    #[test]
+    fn test_parse_info_jx3dat_recovers_fields_when_parse_fails() {
+        // 含非字面量字段的文件整体解析失败，身份字段仍能逐字段恢复
+        let text = r#"return {szName="别名",uid="432345564243886337",extra=GetClientPlayer(),name="角色名", server = '梦江南',region="电信五区"}"#;
+        assert!(klua::parse_lua_text(text).is_err());
+
+        let temp_dir = std::env::temp_dir().join("jx3-test-info-recover");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("info.jx3dat");
+        let (gbk_bytes, _, _) = encoding_rs::GBK.encode(text);
+        std::fs::write(&path, gbk_bytes.as_ref()).unwrap();
+
+        let identity = parse_info_jx3dat(&path, None).expect("应能逐字段恢复");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        assert_eq!(identity.uid, "432345564243886337");
+        assert_eq!(identity.role_name, "角色名");
+        assert_eq!(identity.server, "梦江南");
+        assert_eq!(identity.region, "电信五区");
     }
 
     #[test]
@@ -0,0 +1,434 @@
+//! 团队拍卖账本（团长视角的整场副本拍卖流水）
+//!
+//! 来源为 chatlog 团队频道（MSG_ROOM）：
+//! - 拍卖成交："[买家]花费[金额]购买了[物品]"（`EXPENSE_MSG_RE`，不限当前角色）
+//! - 团长分配："[团长]将[物品]以[金额]记录给了[接收者]"
+//!
+//! 两者都计入团队总金额（pot）。账本按记录保存在 `record_auction_ledger` 表，
+//! 金额均为铜精度（`Money`），不截断银铜部分。
+//! 查询时用"每人底薪 × 人数"核对总金额：差额为团长补贴/未分配部分，为负说明账本缺少拍卖消息。
+//! 人数优先取底薪消息中宣布的人数，团队名单（可能缺少未进组的成员）只作兜底。
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use crate::db;
+use crate::mingyi::drop_scanner::{ChatlogItemKind, ChatlogLineItem, ChatlogRowKey};
+use crate::money::Money;
+
+/// 底薪消息中宣布的人数（如"可分配：70000金，20人"、"分配人数：25人"）
+static SALARY_HEADCOUNT_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(\d+)\s*人").unwrap());
+
+/// 拍卖账本中的一笔成交
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuctionEntry {
+    /// chatlog 时间（秒）
+    pub time_sec: i64,
+    /// 买家（"角色名·服务器"，与 chatlog 原文一致）
+    pub buyer: String,
+    pub item_name: String,
+    pub amount: Money,
+    /// 成交来源：auction（拍卖购买）/ allocation（团长分配记录）
+    pub source: String,
+}
+
+/// 总金额核对结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PotCheckStatus {
+    /// 总金额 = 底薪 × 人数（差额小于人数 × 1 金，为整金底薪的整除余数）
+    Balanced,
+    /// 总金额多于底薪总额（团长补贴、罚款或未分配）
+    Surplus,
+    /// 总金额少于底薪总额（账本缺少拍卖消息）
+    Shortfall,
+    /// 没有底薪消息或无法确定人数
+    Unknown,
+}
+
+/// 记录的拍卖账本与总金额核对
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuctionLedger {
+    pub record_id: String,
+    pub entries: Vec<AuctionEntry>,
+    /// 团队总金额（全部成交之和）
+    pub pot: Money,
+    /// 每人底薪（最后一条底薪消息）
+    pub base_salary: Option<Money>,
+    /// 核对用人数
+    pub headcount: Option<u32>,
+    /// 底薪 × 人数
+    pub salary_total: Option<Money>,
+    /// 总金额 - 底薪总额
+    pub difference: Option<Money>,
+    pub status: PotCheckStatus,
+}
+
+const SOURCE_AUCTION: &str = "auction";
+const SOURCE_ALLOCATION: &str = "allocation";
+
+/// 从 chatlog 明细中提取整场拍卖账本
+///
+/// 同一 chatlog 行（文件 + rowid）重复读入时只计一次；同一秒内容相同的不同行
+/// （两次相同价格的购买）分别计入。
+pub(crate) fn build_auction_ledger(line_items: &[ChatlogLineItem]) -> Vec<AuctionEntry> {
+    let mut seen: HashSet<ChatlogRowKey> = HashSet::new();
+    let mut entries = Vec::new();
+    for item in line_items {
+        let source = match item.kind {
+            ChatlogItemKind::AuctionItem => SOURCE_AUCTION,
+            ChatlogItemKind::Allocation => SOURCE_ALLOCATION,
+            _ => continue,
+        };
+        let (Some(buyer), Some(item_name), Some(amount)) =
+            (&item.buyer, &item.item_name, item.amount)
+        else {
+            continue;
+        };
+        if item.row_key.is_some_and(|key| !seen.insert(key)) {
+            continue;
+        }
+        entries.push(AuctionEntry {
+            time_sec: item.time_sec,
+            buyer: buyer.clone(),
+            item_name: item_name.clone(),
+            amount,
+            source: source.to_string(),
+        });
+    }
+    entries
+}
+
+/// 解析底薪消息中宣布的人数（取"每人底薪"之前最后一个"N人"）
+pub(crate) fn parse_salary_headcount(text: &str) -> Option<u32> {
+    let before_salary = text.split("每人底薪").next().unwrap_or(text);
+    SALARY_HEADCOUNT_RE
+        .captures_iter(before_salary)
+        .last()
+        .and_then(|caps| caps[1].parse::<u32>().ok())
+        .filter(|&count| count > 0)
+}
+
+/// 用"每人底薪 × 人数"核对总金额，返回 (底薪总额, 差额, 状态)
+pub(crate) fn check_pot(
+    pot: Money,
+    base_salary: Option<Money>,
+    headcount: Option<u32>,
+) -> (Option<Money>, Option<Money>, PotCheckStatus) {
+    let (Some(salary), Some(headcount)) = (base_salary, headcount.filter(|&h| h > 0)) else {
+        return (None, None, PotCheckStatus::Unknown);
+    };
+    let salary_total = Money::from_copper(salary.copper() * headcount as i64);
+    let difference = pot - salary_total;
+    let status = if difference < Money::ZERO {
+        PotCheckStatus::Shortfall
+    } else if difference < Money::from_gold(headcount as i64) {
+        PotCheckStatus::Balanced
+    } else {
+        PotCheckStatus::Surplus
+    };
+    (Some(salary_total), Some(difference), status)
+}
+
+/// 重建记录的拍卖账本（扫描时每次以最新提取结果整体覆盖）
+pub(crate) fn save_record_auction_ledger(
+    conn: &Connection,
+    record_id: &str,
+    entries: &[AuctionEntry],
+) -> Result<(), String> {
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "DELETE FROM record_auction_ledger WHERE record_id = ?1",
+        params![record_id],
+    )
+    .map_err(|e| format!("清理拍卖账本失败: {}", e))?;
+    for (seq, entry) in entries.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO record_auction_ledger (record_id, seq, time_sec, buyer, item_name, gold, gold_copper, source, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                record_id,
+                seq as i64,
+                entry.time_sec,
+                entry.buyer,
+                entry.item_name,
+                entry.amount.gold(),
+                entry.amount.copper(),
+                entry.source,
+                now
+            ],
+        )
+        .map_err(|e| format!("写入拍卖账本失败: {}", e))?;
+    }
+    log::info!(
+        "[AuctionLedger] 记录 {} 保存拍卖账本 {} 笔，总金额 {}",
+        record_id,
+        entries.len(),
+        entries.iter().map(|e| e.amount).sum::<Money>()
+    );
+    Ok(())
+}
+
+/// 查询记录的拍卖账本并核对总金额
+///
+/// 底薪取 chatlog 明细中计入的底薪消息；人数未指定时优先取该底薪消息中宣布的人数，
+/// 没有时取团队名单人数。
+pub(crate) fn query_auction_ledger(
+    conn: &Connection,
+    record_id: &str,
+    headcount: Option<u32>,
+) -> Result<AuctionLedger, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT time_sec, buyer, item_name, COALESCE(gold_copper, gold * 10000), source
+             FROM record_auction_ledger
+             WHERE record_id = ?1 ORDER BY seq ASC",
+        )
+        .map_err(|e| format!("准备拍卖账本查询失败: {}", e))?;
+    let entries = stmt
+        .query_map(params![record_id], |row| {
+            Ok(AuctionEntry {
+                time_sec: row.get(0)?,
+                buyer: row.get(1)?,
+                item_name: row.get(2)?,
+                amount: Money::from_copper(row.get(3)?),
+                source: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("查询拍卖账本失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取拍卖账本失败: {}", e))?;
+
+    let salary_line: Option<(Option<i64>, String)> = match conn.query_row(
+        "SELECT COALESCE(gold_copper, gold * 10000), text FROM record_chatlog_items
+         WHERE record_id = ?1 AND kind = ?2 AND counted = 1
+         ORDER BY seq DESC LIMIT 1",
+        params![record_id, ChatlogItemKind::Salary.as_str()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ) {
+        Ok(line) => Some(line),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("查询底薪失败: {}", e)),
+    };
+    let base_salary = salary_line
+        .as_ref()
+        .and_then(|(copper, _)| copper.map(Money::from_copper));
+    let announced_headcount = salary_line
+        .as_ref()
+        .and_then(|(_, text)| parse_salary_headcount(text));
+
+    let headcount = match headcount.or(announced_headcount) {
+        Some(h) => Some(h),
+        None => {
+            let count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM record_roster WHERE record_id = ?1",
+                    params![record_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("查询团队人数失败: {}", e))?;
+            Some(count as u32).filter(|&c| c > 0)
+        }
+    };
+
+    let pot = entries.iter().map(|e| e.amount).sum();
+    let (salary_total, difference, status) = check_pot(pot, base_salary, headcount);
+    Ok(AuctionLedger {
+        record_id: record_id.to_string(),
+        entries,
+        pot,
+        base_salary,
+        headcount,
+        salary_total,
+        difference,
+        status,
+    })
+}
+
+/// Tauri 命令：查询记录的团队拍卖账本（headcount 为空时按底薪消息人数或团队名单人数核对）
+#[tauri::command]
+pub fn get_record_auction_ledger(
+    record_id: String,
+    headcount: Option<u32>,
+) -> Result<AuctionLedger, String> {
+    let conn = db::init_db()?;
+    query_auction_ledger(&conn, &record_id, headcount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(kind: ChatlogItemKind, time_sec: i64, text: &str) -> ChatlogLineItem {
+        ChatlogLineItem {
+            kind,
+            time_sec,
+            channel: "MSG_ROOM".to_string(),
+            text: text.to_string(),
+            item_name: None,
+            buyer: None,
+            amount: None,
+            counted: false,
+            note: None,
+            row_key: None,
+        }
+    }
+
+    #[test]
+    fn test_build_auction_ledger_and_check_pot() {
+        let auction =
+            |rowid: i64, time_sec: i64, buyer: &str, item: &str, gold: i64| ChatlogLineItem {
+                row_key: Some(ChatlogRowKey { file: 1, rowid }),
+                item_name: Some(item.to_string()),
+                buyer: Some(buyer.to_string()),
+                amount: Some(Money::from_gold(gold)),
+                ..line(
+                    ChatlogItemKind::AuctionItem,
+                    time_sec,
+                    &format!("[{}]花费[{}金]购买了[{}]", buyer, gold, item),
+                )
+            };
+        let items = vec![
+            auction(1, 100, "糯闪·梦江南", "玄晶", 60000),
+            // 同一行重复读入只计一次
+            auction(1, 100, "糯闪·梦江南", "玄晶", 60000),
+            // 同一秒内容相同的两次购买是不同的行，都计入
+            auction(2, 160, "唐小糖·梦江南", "五行石", 1000),
+            auction(3, 160, "唐小糖·梦江南", "五行石", 1000),
+            ChatlogLineItem {
+                item_name: Some("天乙玄晶".to_string()),
+                buyer: Some("阿白·梦江南".to_string()),
+                amount: Some(Money::from_gold(8000)),
+                ..line(
+                    ChatlogItemKind::Allocation,
+                    200,
+                    "[团长]将[天乙玄晶]以[8000金]记录给了[阿白·梦江南]",
+                )
+            },
+            // 没有买家的"购买了"（其他格式）不计入
+            ChatlogLineItem {
+                item_name: Some("挂件".to_string()),
+                ..line(ChatlogItemKind::AuctionItem, 300, "购买了[挂件]")
+            },
+            ChatlogLineItem {
+                amount: Some(Money::from_gold(3500)),
+                ..line(ChatlogItemKind::Salary, 400, "每人底薪：3500金")
+            },
+        ];
+
+        let entries = build_auction_ledger(&items);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[3].source, "allocation");
+        let pot: Money = entries.iter().map(|e| e.amount).sum();
+        assert_eq!(pot, Money::from_gold(70000));
+
+        let gold = |g: i64| Some(Money::from_gold(g));
+        assert_eq!(
+            check_pot(pot, gold(3500), Some(20)),
+            (gold(70000), Some(Money::ZERO), PotCheckStatus::Balanced)
+        );
+        assert_eq!(
+            check_pot(pot, gold(2800), Some(25)),
+            (gold(70000), Some(Money::ZERO), PotCheckStatus::Balanced)
+        );
+        assert_eq!(
+            check_pot(pot, gold(2500), Some(25)).2,
+            PotCheckStatus::Surplus
+        );
+        assert_eq!(
+            check_pot(pot, gold(3000), Some(25)).2,
+            PotCheckStatus::Shortfall
+        );
+        assert_eq!(check_pot(pot, None, Some(25)).2, PotCheckStatus::Unknown);
+        assert_eq!(
+            check_pot(pot, gold(3000), Some(0)).2,
+            PotCheckStatus::Unknown
+        );
+
+        // 银铜部分参与核对：底薪带银时不再按整金截断
+        // 20 人 × 50 银 = 10 金
+        let silver_pot = pot + Money::from_gold(10);
+        assert_eq!(
+            check_pot(silver_pot, Some(Money::from_parts(3500, 50, 0)), Some(20)),
+            (
+                Some(silver_pot),
+                Some(Money::ZERO),
+                PotCheckStatus::Balanced
+            )
+        );
+        assert_eq!(
+            check_pot(pot, Some(Money::from_parts(3500, 50, 0)), Some(20)).2,
+            PotCheckStatus::Shortfall
+        );
+    }
+
+    #[test]
+    fn test_ledger_headcount_prefers_salary_line() {
+        assert_eq!(parse_salary_headcount("每人底薪：3500金"), None);
+        assert_eq!(
+            parse_salary_headcount("可分配：70000金，20人\n每人底薪：3500金"),
+            Some(20)
+        );
+        assert_eq!(
+            parse_salary_headcount("总金额：70000金，分配人数：25人，每人底薪：2800金"),
+            Some(25)
+        );
+
+        let conn = db::open_test_db();
+        save_record_auction_ledger(
+            &conn,
+            "r1",
+            &[AuctionEntry {
+                time_sec: 100,
+                buyer: "糯闪·梦江南".to_string(),
+                item_name: "玄晶".to_string(),
+                amount: Money::from_parts(70000, 12, 34),
+                source: SOURCE_AUCTION.to_string(),
+            }],
+        )
+        .unwrap();
+        // 团队名单只有 2 人（其他成员未识别到）
+        conn.execute_batch(
+            "INSERT INTO record_roster (record_id, name, server, created_at)
+             VALUES ('r1', '糯闪', '梦江南', 'now'), ('r1', '唐小糖', '梦江南', 'now');",
+        )
+        .unwrap();
+        let insert_salary = |seq: i64, text: &str, gold: i64| {
+            conn.execute(
+                "INSERT INTO record_chatlog_items (record_id, seq, kind, time_sec, channel, text, gold, gold_copper, counted, created_at)
+                 VALUES ('r1', ?1, 'salary', 400, 'MSG_ROOM', ?2, ?3, ?4, 1, 'now')",
+                params![seq, text, gold, Money::from_gold(gold).copper()],
+            )
+            .unwrap();
+        };
+
+        // 底薪消息没有人数：按团队名单人数核对
+        insert_salary(0, "每人底薪：3500金", 3500);
+        let ledger = query_auction_ledger(&conn, "r1", None).unwrap();
+        assert_eq!(
+            ledger.pot,
+            Money::from_parts(70000, 12, 34),
+            "账本保留银铜部分"
+        );
+        assert_eq!(ledger.headcount, Some(2));
+        assert_eq!(ledger.status, PotCheckStatus::Surplus);
+
+        // 底薪消息宣布了人数：优先使用
+        conn.execute("DELETE FROM record_chatlog_items", [])
+            .unwrap();
+        insert_salary(0, "可分配：70000金，20人\n每人底薪：3500金", 3500);
+        let ledger = query_auction_ledger(&conn, "r1", None).unwrap();
+        assert_eq!(ledger.base_salary, Some(Money::from_gold(3500)));
+        assert_eq!(ledger.headcount, Some(20));
+        assert_eq!(ledger.status, PotCheckStatus::Balanced);
+
+        // 指定人数优先
+        let ledger = query_auction_ledger(&conn, "r1", Some(25)).unwrap();
+        assert_eq!(ledger.headcount, Some(25));
+    }
+}
@@ -0,0 +1,355 @@
+//! BOSS 击杀判定规则
+//!
+//! `analyze_jcl` 的通用信号（宝箱/DEATH_NOTIFY/HP=0/bFight 变化等）之外，部分 BOSS
+//! 需要专属知识：战斗 JCL 以伴生 NPC 命名、存在同名分身、不产生 NPC_FIGHT_HINT 等。
+//! 这些知识以数据形式保存在 `raid_boss_rules` 表中（与 `raid_bosses` 一样按 raids.name 关联），
+//! 预置规则来自 `db/static_boss_rules.json`，启动时同步；用户自定义规则不会被预置数据覆盖。
+//! 未配置规则的 BOSS 使用 `BossKillRule::default_for` 的通用判定。
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db;
+
+/// 通用判定：击杀时 BOSS 的 NPC_INFO nFaceDirection 停留在该值（团灭时会回到 0）
+pub const DEFAULT_DEAD_FACE_DIRECTION: i64 = 192;
+
+/// 同名 NPC（分身/幻影）处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CloneHandling {
+    /// 追踪与 BOSS 同名的 NPC，其死亡与离场计入 BOSS 击杀信号
+    #[default]
+    SameName,
+    /// 只追踪文件名 BOSS 本体
+    Ignore,
+}
+
+impl CloneHandling {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CloneHandling::SameName => "sameName",
+            CloneHandling::Ignore => "ignore",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "ignore" => CloneHandling::Ignore,
+            _ => CloneHandling::SameName,
+        }
+    }
+}
+
+/// 单个 BOSS 的击杀判定规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BossKillRule {
+    /// raids.name（如 "阆风悬城"）
+    pub raid_name: String,
+    /// BOSS 名（与 raid_bosses.name 一致）
+    pub boss_name: String,
+    /// BOSS 在 JCL 文件名 / NPC_INFO 中的其他名字
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// 伴生 NPC：JCL 以该 NPC 命名但实际是本 BOSS 的战斗（如"须罗巨傀"→"唐怀仁"）
+    #[serde(default)]
+    pub companions: Vec<String>,
+    /// 是否要求伴生 NPC 也死亡才算击杀
+    #[serde(default = "default_require_companion_death")]
+    pub require_companion_death: bool,
+    /// 宝箱 NPC 名（为空时为 "BOSS名宝箱" 及各别名 + "宝箱"）
+    #[serde(default)]
+    pub chest_names: Vec<String>,
+    /// 同名分身处理方式
+    #[serde(default)]
+    pub clone_handling: CloneHandling,
+    /// 全局 FIGHT_TIME 是否作为 BOSS 进入战斗信号（BOSS 不产生 NPC_FIGHT_HINT 时使用）
+    #[serde(default)]
+    pub fight_time_signal: bool,
+    /// 击杀时 nFaceDirection 特征值（None 表示不使用朝向辅助判定）
+    #[serde(default = "default_dead_face_direction")]
+    pub dead_face_direction: Option<i64>,
+    /// 备注
+    #[serde(default)]
+    pub notes: String,
+}
+
+fn default_require_companion_death() -> bool {
+    true
+}
+
+fn default_dead_face_direction() -> Option<i64> {
+    Some(DEFAULT_DEAD_FACE_DIRECTION)
+}
+
+impl BossKillRule {
+    /// 未配置规则时的通用判定
+    pub fn default_for(raid_name: &str, boss_name: &str) -> Self {
+        BossKillRule {
+            raid_name: raid_name.to_string(),
+            boss_name: boss_name.to_string(),
+            aliases: Vec::new(),
+            companions: Vec::new(),
+            require_companion_death: default_require_companion_death(),
+            chest_names: Vec::new(),
+            clone_handling: CloneHandling::default(),
+            fight_time_signal: false,
+            dead_face_direction: default_dead_face_direction(),
+            notes: String::new(),
+        }
+    }
+
+    /// NPC 名是否为该 BOSS（本名或别名）
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.boss_name == name || self.aliases.iter().any(|a| a == name)
+    }
+
+    /// 该 BOSS 的宝箱 NPC 名
+    pub fn chest_npc_names(&self) -> Vec<String> {
+        if !self.chest_names.is_empty() {
+            return self.chest_names.clone();
+        }
+        std::iter::once(&self.boss_name)
+            .chain(self.aliases.iter())
+            .map(|name| format!("{}宝箱", name))
+            .collect()
+    }
+}
+
+/// 按 BOSS 名或别名查找规则
+pub fn find_rule<'a>(rules: &'a [BossKillRule], name: &str) -> Option<&'a BossKillRule> {
+    rules.iter().find(|r| r.matches_name(name))
+}
+
+/// 按伴生 NPC 名查找规则（JCL 文件名 BOSS 为伴生 NPC 时使用）
+pub fn find_rule_by_companion<'a>(
+    rules: &'a [BossKillRule],
+    name: &str,
+) -> Option<&'a BossKillRule> {
+    rules
+        .iter()
+        .find(|r| r.companions.iter().any(|c| c == name))
+}
+
+/// 查找 BOSS 规则，未配置时返回通用判定规则
+pub fn resolve_rule(rules: &[BossKillRule], name: &str) -> BossKillRule {
+    find_rule(rules, name)
+        .cloned()
+        .unwrap_or_else(|| BossKillRule::default_for("", name))
+}
+
+/// 别名归一为规则中的 BOSS 名，无匹配规则时原样返回
+pub fn canonical_boss_name<'a>(name: &'a str, rules: &'a [BossKillRule]) -> &'a str {
+    find_rule(rules, name)
+        .map(|r| r.boss_name.as_str())
+        .unwrap_or(name)
+}
+
+fn rule_from_row(row: &rusqlite::Row) -> rusqlite::Result<BossKillRule> {
+    let json_list = |idx: usize| -> rusqlite::Result<Vec<String>> {
+        let text: String = row.get(idx)?;
+        Ok(serde_json::from_str(&text).unwrap_or_default())
+    };
+    Ok(BossKillRule {
+        raid_name: row.get(0)?,
+        boss_name: row.get(1)?,
+        aliases: json_list(2)?,
+        companions: json_list(3)?,
+        require_companion_death: row.get::<_, i64>(4)? != 0,
+        chest_names: json_list(5)?,
+        clone_handling: CloneHandling::parse(&row.get::<_, String>(6)?),
+        fight_time_signal: row.get::<_, i64>(7)? != 0,
+        dead_face_direction: row.get(8)?,
+        notes: row.get(9)?,
+    })
+}
+
+const SELECT_RULE_COLUMNS: &str = "SELECT raid_name, boss_name, aliases, companions, require_companion_death, chest_names, clone_handling, fight_time_signal, dead_face_direction, notes FROM raid_boss_rules";
+
+/// 加载全部规则，按 raid_name 分组
+pub(crate) fn load_boss_rules(
+    conn: &Connection,
+) -> Result<HashMap<String, Vec<BossKillRule>>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} ORDER BY raid_name, boss_name",
+            SELECT_RULE_COLUMNS
+        ))
+        .map_err(|e| format!("准备 BOSS 规则查询失败: {}", e))?;
+    let rules = stmt
+        .query_map([], rule_from_row)
+        .map_err(|e| format!("查询 BOSS 规则失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取 BOSS 规则失败: {}", e))?;
+
+    let mut grouped: HashMap<String, Vec<BossKillRule>> = HashMap::new();
+    for rule in rules {
+        grouped
+            .entry(rule.raid_name.clone())
+            .or_default()
+            .push(rule);
+    }
+    Ok(grouped)
+}
+
+/// 写入一条规则
+///
+/// 预置规则（is_static=true）只覆盖预置行，不覆盖用户自定义的同名规则；
+/// 用户规则直接覆盖，并标记为非预置。
+pub(crate) fn upsert_boss_rule(
+    conn: &Connection,
+    rule: &BossKillRule,
+    is_static: bool,
+) -> Result<usize, String> {
+    let to_json =
+        |list: &Vec<String>| serde_json::to_string(list).unwrap_or_else(|_| "[]".to_string());
+    let now = chrono::Local::now().to_rfc3339();
+    let conflict_guard = if is_static {
+        "WHERE raid_boss_rules.is_static = 1"
+    } else {
+        ""
+    };
+    conn.execute(
+        &format!(
+            "INSERT INTO raid_boss_rules (raid_name, boss_name, aliases, companions, require_companion_death, chest_names, clone_handling, fight_time_signal, dead_face_direction, notes, is_static, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT(raid_name, boss_name) DO UPDATE SET
+                aliases = excluded.aliases,
+                companions = excluded.companions,
+                require_companion_death = excluded.require_companion_death,
+                chest_names = excluded.chest_names,
+                clone_handling = excluded.clone_handling,
+                fight_time_signal = excluded.fight_time_signal,
+                dead_face_direction = excluded.dead_face_direction,
+                notes = excluded.notes,
+                is_static = excluded.is_static,
+                updated_at = excluded.updated_at
+             {}",
+            conflict_guard
+        ),
+        params![
+            rule.raid_name,
+            rule.boss_name,
+            to_json(&rule.aliases),
+            to_json(&rule.companions),
+            rule.require_companion_death as i64,
+            to_json(&rule.chest_names),
+            rule.clone_handling.as_str(),
+            rule.fight_time_signal as i64,
+            rule.dead_face_direction,
+            rule.notes,
+            is_static as i64,
+            now
+        ],
+    )
+    .map_err(|e| format!("写入 BOSS 规则失败 ({}/{}): {}", rule.raid_name, rule.boss_name, e))
+}
+
+/// 同步预置规则（static_boss_rules.json）
+pub(crate) fn sync_static_boss_rules(conn: &Connection, static_json: &str) -> Result<(), String> {
+    let rules: Vec<BossKillRule> =
+        serde_json::from_str(static_json).map_err(|e| format!("解析预置 BOSS 规则失败: {}", e))?;
+    let mut updated = 0;
+    for rule in &rules {
+        updated += upsert_boss_rule(conn, rule, true)?;
+    }
+    log::debug!(
+        "[BossRules] 同步预置 BOSS 规则 {} 条（生效 {} 条）",
+        rules.len(),
+        updated
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_boss_kill_rules() -> Result<Vec<BossKillRule>, String> {
+    let conn = db::init_db()?;
+    let mut rules: Vec<BossKillRule> = load_boss_rules(&conn)?.into_values().flatten().collect();
+    rules.sort_by(|a, b| (&a.raid_name, &a.boss_name).cmp(&(&b.raid_name, &b.boss_name)));
+    Ok(rules)
+}
+
+#[tauri::command]
+pub fn save_boss_kill_rule(rule: BossKillRule) -> Result<(), String> {
+    if rule.raid_name.trim().is_empty() || rule.boss_name.trim().is_empty() {
+        return Err("副本名和 BOSS 名不能为空".to_string());
+    }
+    let conn = db::init_db()?;
+    upsert_boss_rule(&conn, &rule, false)?;
+    log::info!(
+        "[BossRules] 保存 BOSS 规则: {}/{}",
+        rule.raid_name,
+        rule.boss_name
+    );
+    // 规则变化会影响 JCL 分析结果，清空副本配置缓存与 JCL 缓存
+    crate::mingyi::drop_scanner::invalidate_raids_cache();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(conn: &Connection) {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE raid_boss_rules (
+                raid_name TEXT NOT NULL,
+                boss_name TEXT NOT NULL,
+                aliases TEXT NOT NULL DEFAULT '[]',
+                companions TEXT NOT NULL DEFAULT '[]',
+                require_companion_death INTEGER NOT NULL DEFAULT 1,
+                chest_names TEXT NOT NULL DEFAULT '[]',
+                clone_handling TEXT NOT NULL DEFAULT 'sameName',
+                fight_time_signal INTEGER NOT NULL DEFAULT 0,
+                dead_face_direction INTEGER DEFAULT 192,
+                notes TEXT NOT NULL DEFAULT '',
+                is_static INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (raid_name, boss_name)
+            );
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_static_rules_parse_with_defaults() {
+        let rules: Vec<BossKillRule> =
+            serde_json::from_str(include_str!("../db/static_boss_rules.json")).unwrap();
+        let tang = find_rule_by_companion(&rules, "须罗巨傀").expect("须罗巨傀应为唐怀仁伴生 NPC");
+        assert_eq!(tang.boss_name, "唐怀仁");
+        assert!(tang.require_companion_death);
+        assert_eq!(tang.dead_face_direction, Some(DEFAULT_DEAD_FACE_DIRECTION));
+        assert_eq!(tang.chest_npc_names(), vec!["唐怀仁宝箱".to_string()]);
+
+        let zhao = find_rule(&rules, "赵八嫂").unwrap();
+        assert!(zhao.fight_time_signal);
+        assert_eq!(zhao.clone_handling, CloneHandling::SameName);
+    }
+
+    #[test]
+    fn test_static_sync_keeps_user_rules() {
+        let conn = Connection::open_in_memory().unwrap();
+        setup(&conn);
+
+        let mut custom = BossKillRule::default_for("阆风悬城", "唐怀仁");
+        custom.aliases = vec!["唐怀仁·机关".to_string()];
+        custom.dead_face_direction = None;
+        upsert_boss_rule(&conn, &custom, false).unwrap();
+
+        sync_static_boss_rules(&conn, include_str!("../db/static_boss_rules.json")).unwrap();
+        sync_static_boss_rules(&conn, include_str!("../db/static_boss_rules.json")).unwrap();
+
+        let rules = load_boss_rules(&conn).unwrap();
+        let langfeng = rules.get("阆风悬城").unwrap();
+        assert_eq!(langfeng.len(), 4);
+        let tang = find_rule(langfeng, "唐怀仁·机关").expect("用户规则不应被预置数据覆盖");
+        assert!(tang.companions.is_empty());
+        assert_eq!(tang.dead_face_direction, None);
+        assert_eq!(canonical_boss_name("唐怀仁·机关", langfeng), "唐怀仁");
+        assert_eq!(canonical_boss_name("唐醉", langfeng), "唐醉");
+    }
+}
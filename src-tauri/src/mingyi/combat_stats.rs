@@ -0,0 +1,591 @@
+//! 单场 BOSS 战斗的伤害/治疗统计与重伤回顾
+//!
+//! 基于 `jcl::JclReader` 遍历 JCL 事件，按玩家汇总输出、治疗和承伤，
+//! 战斗时长取自 FIGHT_TIME 事件（进战 → 脱战）。
+//! 重伤回顾基于 SYS_MSG_UI_OME_DEATH_NOTIFY 事件，凶手名通过同一 JCL 的 NPC_INFO / PLAYER_INFO 解析。
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::game_directory::MINGYI_ACCOUNTS_BASE_PATH;
+use crate::mingyi::drop_scanner::{get_game_directory, parse_jcl_filename, JclFileInfo};
+use crate::mingyi::jcl::{log_type, JclEvent, JclReader};
+
+/// 单个玩家的战斗统计
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerCombatStats {
+    pub player_id: i64,
+    pub name: String,
+    pub force_id: i64,
+    pub kungfu_id: i64,
+    /// 原始伤害（含被化解/吸收部分）
+    pub damage: i64,
+    /// 有效伤害
+    pub effective_damage: i64,
+    /// 每秒有效伤害
+    pub dps: f64,
+    /// 原始治疗量（含溢出）
+    pub therapy: i64,
+    /// 有效治疗量
+    pub effective_therapy: i64,
+    /// 每秒有效治疗
+    pub hps: f64,
+    /// 承受的有效伤害
+    pub damage_taken: i64,
+    /// 重伤次数
+    pub deaths: u32,
+}
+
+/// 单个 JCL 的战斗统计结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JclCombatStats {
+    pub file_name: String,
+    pub boss_name: String,
+    /// 进战时间（Unix 毫秒），无 FIGHT_TIME 时为 0
+    pub fight_start_ms: i64,
+    /// 脱战时间（Unix 毫秒），无 FIGHT_TIME 时为 0
+    pub fight_end_ms: i64,
+    /// 战斗时长（毫秒）
+    pub duration_ms: i64,
+    /// 按有效伤害降序排列
+    pub players: Vec<PlayerCombatStats>,
+}
+
+/// 一次玩家重伤
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerDeath {
+    pub player_id: i64,
+    pub player_name: String,
+    /// 重伤时间（Unix 毫秒，秒级精度）
+    pub time_ms: i64,
+    /// 相对开怪时间（毫秒），开怪前的重伤为负数；无 FIGHT_TIME 时为 None
+    pub relative_ms: Option<i64>,
+    /// 凶手 ID（DEATH_NOTIFY 的 dwKiller，缺失时取最后一次伤害来源）
+    pub killer_id: i64,
+    /// 凶手名（NPC_INFO / PLAYER_INFO 中未出现时为空）
+    pub killer_name: String,
+    pub killer_is_player: bool,
+    /// 致死技能/BUFF ID（重伤前最后一次对该玩家造成伤害的效果）
+    pub skill_id: Option<i64>,
+    pub skill_level: Option<i64>,
+    /// 效果类型（游戏 SKILL_EFFECT_TYPE：技能 / BUFF）
+    pub effect_type: Option<i64>,
+    /// 致死一击的有效伤害
+    pub damage: Option<i64>,
+}
+
+/// 单个 JCL 的重伤回顾
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JclDeathRecap {
+    pub file_name: String,
+    pub boss_name: String,
+    /// 开怪时间（Unix 毫秒），无 FIGHT_TIME 时为 0
+    pub fight_start_ms: i64,
+    /// 按时间顺序排列
+    pub deaths: Vec<PlayerDeath>,
+}
+
+/// 玩家最近一次受到的伤害
+struct LastHit {
+    caster_id: i64,
+    effect_id: i64,
+    effect_level: i64,
+    effect_type: i64,
+    damage: i64,
+}
+
+/// 同一施放者对同一目标的技能效果累计
+#[derive(Default)]
+struct EffectTotals {
+    damage: i64,
+    effective_damage: i64,
+    therapy: i64,
+    effective_therapy: i64,
+}
+
+/// 战斗窗口跟踪：优先使用 client_ms（毫秒精度），缺失时回退到 Unix 秒
+#[derive(Default)]
+struct FightWindow {
+    start_ms: i64,
+    end_ms: i64,
+    start_client_ms: i64,
+    end_client_ms: i64,
+}
+
+impl FightWindow {
+    fn duration_ms(&self) -> i64 {
+        if self.start_client_ms > 0 && self.end_client_ms > self.start_client_ms {
+            self.end_client_ms - self.start_client_ms
+        } else if self.start_ms > 0 && self.end_ms > self.start_ms {
+            self.end_ms - self.start_ms
+        } else {
+            0
+        }
+    }
+}
+
+/// 汇总 JCL 事件为玩家战斗统计
+///
+/// 宠物/召唤物（NPC_INFO 的 dwEmployer 为玩家）造成的伤害和治疗计入其主人。
+/// 玩家对玩家的伤害（如切磋、BOSS 控制下的误伤）不计入输出，但计入承伤。
+/// 技能效果和重伤先按 ID 累计，读完后再按 PLAYER_INFO / NPC_INFO 归属，与事件先后顺序无关。
+pub(crate) fn compute_combat_stats<R: BufRead>(reader: JclReader<R>) -> JclCombatStats {
+    let reader = reader.only(&[
+        log_type::FIGHT_TIME,
+        log_type::PLAYER_INFO,
+        log_type::NPC_INFO,
+        log_type::SKILL_EFFECT_LOG,
+        log_type::SYS_MSG_UI_OME_DEATH_NOTIFY,
+    ]);
+
+    let mut players: HashMap<i64, PlayerCombatStats> = HashMap::new();
+    let mut employers: HashMap<i64, i64> = HashMap::new();
+    let mut effects: HashMap<(i64, i64), EffectTotals> = HashMap::new();
+    let mut deaths: HashMap<i64, u32> = HashMap::new();
+    let mut window = FightWindow::default();
+    let mut last_event_ms: i64 = 0;
+    let mut last_client_ms: i64 = 0;
+
+    for entry in reader {
+        last_event_ms = entry.timestamp_ms();
+        last_client_ms = entry.client_ms;
+
+        match entry.event {
+            JclEvent::FightTime { fighting, .. } => {
+                if fighting {
+                    if window.start_ms == 0 {
+                        window.start_ms = entry.timestamp_ms();
+                        window.start_client_ms = entry.client_ms;
+                    }
+                } else if window.start_ms != 0 {
+                    window.end_ms = entry.timestamp_ms();
+                    window.end_client_ms = entry.client_ms;
+                }
+            }
+            JclEvent::PlayerInfo {
+                id,
+                name,
+                force_id,
+                kungfu_id,
+                ..
+            } => {
+                let stats = players.entry(id).or_default();
+                stats.player_id = id;
+                stats.name = name;
+                stats.force_id = force_id;
+                stats.kungfu_id = kungfu_id;
+            }
+            JclEvent::NpcInfo {
+                id, employer_id, ..
+            } if employer_id != 0 => {
+                employers.insert(id, employer_id);
+            }
+            JclEvent::SkillEffect {
+                caster_id,
+                target_id,
+                results,
+                ..
+            } => {
+                let totals = effects.entry((caster_id, target_id)).or_default();
+                totals.damage += results.damage();
+                totals.effective_damage += results.effective_damage();
+                totals.therapy += results.therapy();
+                totals.effective_therapy += results.effective_therapy();
+            }
+            JclEvent::DeathNotify { character_id, .. } => {
+                *deaths.entry(character_id).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    for ((caster_id, target_id), totals) in effects {
+        let owner = employers.get(&caster_id).copied().unwrap_or(caster_id);
+        let target_is_player = players.contains_key(&target_id);
+        if let Some(stats) = players.get_mut(&owner) {
+            if !target_is_player {
+                stats.damage += totals.damage;
+                stats.effective_damage += totals.effective_damage;
+            }
+            stats.therapy += totals.therapy;
+            stats.effective_therapy += totals.effective_therapy;
+        }
+        if let Some(stats) = players.get_mut(&target_id) {
+            stats.damage_taken += totals.effective_damage;
+        }
+    }
+    for (character_id, count) in deaths {
+        if let Some(stats) = players.get_mut(&character_id) {
+            stats.deaths += count;
+        }
+    }
+
+    // 日志在战斗中途截断（掉线、游戏崩溃）时没有脱战事件，以最后一条事件作为结束
+    if window.start_ms != 0 && window.end_ms == 0 {
+        window.end_ms = last_event_ms;
+        window.end_client_ms = last_client_ms;
+    }
+    let duration_ms = window.duration_ms();
+    let seconds = duration_ms as f64 / 1000.0;
+
+    let mut players: Vec<PlayerCombatStats> = players
+        .into_values()
+        .filter(|p| p.damage > 0 || p.therapy > 0 || p.damage_taken > 0 || p.deaths > 0)
+        .map(|mut p| {
+            if seconds > 0.0 {
+                p.dps = p.effective_damage as f64 / seconds;
+                p.hps = p.effective_therapy as f64 / seconds;
+            }
+            p
+        })
+        .collect();
+    players.sort_by(|a, b| {
+        b.effective_damage
+            .cmp(&a.effective_damage)
+            .then(b.effective_therapy.cmp(&a.effective_therapy))
+            .then(a.player_id.cmp(&b.player_id))
+    });
+
+    JclCombatStats {
+        fight_start_ms: window.start_ms,
+        fight_end_ms: window.end_ms,
+        duration_ms,
+        players,
+        ..Default::default()
+    }
+}
+
+/// 提取 JCL 中的玩家重伤列表
+///
+/// 凶手优先取 DEATH_NOTIFY 的 dwKiller，为 0 时（如环境伤害、DOT 结算）回退到最后一次伤害来源；
+/// 宠物/召唤物凶手归属其主人。致死技能取重伤前最后一次对该玩家造成有效伤害的效果。
+pub(crate) fn compute_death_recap<R: BufRead>(reader: JclReader<R>) -> JclDeathRecap {
+    let reader = reader.only(&[
+        log_type::FIGHT_TIME,
+        log_type::PLAYER_INFO,
+        log_type::NPC_INFO,
+        log_type::SKILL_EFFECT_LOG,
+        log_type::SYS_MSG_UI_OME_DEATH_NOTIFY,
+    ]);
+
+    let mut player_names: HashMap<i64, String> = HashMap::new();
+    let mut npc_names: HashMap<i64, String> = HashMap::new();
+    let mut employers: HashMap<i64, i64> = HashMap::new();
+    let mut last_hits: HashMap<i64, LastHit> = HashMap::new();
+    let mut fight_start: Option<(i64, i64)> = None;
+    let mut deaths: Vec<PlayerDeath> = Vec::new();
+
+    for entry in reader {
+        match entry.event {
+            JclEvent::FightTime { fighting: true, .. } if fight_start.is_none() => {
+                fight_start = Some((entry.timestamp_ms(), entry.client_ms));
+            }
+            JclEvent::PlayerInfo { id, name, .. } => {
+                player_names.insert(id, name);
+            }
+            JclEvent::NpcInfo {
+                id,
+                name,
+                employer_id,
+                ..
+            } => {
+                npc_names.insert(id, name);
+                if employer_id != 0 {
+                    employers.insert(id, employer_id);
+                }
+            }
+            JclEvent::SkillEffect {
+                caster_id,
+                target_id,
+                effect_type,
+                effect_id,
+                effect_level,
+                results,
+                ..
+            } if results.effective_damage() > 0 => {
+                last_hits.insert(
+                    target_id,
+                    LastHit {
+                        caster_id,
+                        effect_id,
+                        effect_level,
+                        effect_type,
+                        damage: results.effective_damage(),
+                    },
+                );
+            }
+            JclEvent::DeathNotify {
+                character_id,
+                killer_id,
+            } => {
+                let Some(player_name) = player_names.get(&character_id) else {
+                    continue;
+                };
+                let last_hit = last_hits.remove(&character_id);
+                let raw_killer = if killer_id != 0 {
+                    killer_id
+                } else {
+                    last_hit.as_ref().map(|h| h.caster_id).unwrap_or(0)
+                };
+                let killer = employers.get(&raw_killer).copied().unwrap_or(raw_killer);
+                let killer_is_player = player_names.contains_key(&killer);
+                let killer_name = player_names
+                    .get(&killer)
+                    .or_else(|| npc_names.get(&killer))
+                    .cloned()
+                    .unwrap_or_default();
+
+                let time_ms = entry.timestamp_ms();
+                let relative_ms = fight_start.map(|(start_ms, start_client_ms)| {
+                    if start_client_ms > 0 && entry.client_ms > 0 {
+                        entry.client_ms - start_client_ms
+                    } else {
+                        time_ms - start_ms
+                    }
+                });
+
+                deaths.push(PlayerDeath {
+                    player_id: character_id,
+                    player_name: player_name.clone(),
+                    time_ms,
+                    relative_ms,
+                    killer_id: killer,
+                    killer_name,
+                    killer_is_player,
+                    skill_id: last_hit.as_ref().map(|h| h.effect_id),
+                    skill_level: last_hit.as_ref().map(|h| h.effect_level),
+                    effect_type: last_hit.as_ref().map(|h| h.effect_type),
+                    damage: last_hit.as_ref().map(|h| h.damage),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    JclDeathRecap {
+        fight_start_ms: fight_start.map(|(ms, _)| ms).unwrap_or(0),
+        deaths,
+        ..Default::default()
+    }
+}
+
+/// 账号 ID 只能是单个路径片段：不含路径分隔符、盘符冒号或 `..`
+fn is_valid_account_id(account_id: &str) -> bool {
+    !account_id.trim().is_empty()
+        && !account_id.contains(['/', '\\', ':', '\0'])
+        && !account_id.contains("..")
+}
+
+/// 定位账号 combat_logs 目录下的 JCL 文件
+///
+/// 只接受合法的账号 ID 与符合 JCL 命名规则的纯文件名，避免访问 combat_logs 之外的路径
+fn resolve_jcl_path(account_id: &str, file_name: &str) -> Result<(PathBuf, JclFileInfo), String> {
+    if !is_valid_account_id(account_id) {
+        return Err(format!("无效的账号 ID: {}", account_id));
+    }
+    let info = parse_jcl_filename(file_name)
+        .filter(|_| !file_name.contains(['/', '\\']))
+        .ok_or_else(|| format!("无效的 JCL 文件名: {}", file_name))?;
+
+    let game_dir = get_game_directory()?;
+    let jcl_path = PathBuf::from(&game_dir)
+        .join(MINGYI_ACCOUNTS_BASE_PATH)
+        .join(format!("{}@zhcn_hd", account_id))
+        .join("userdata")
+        .join("combat_logs")
+        .join(file_name);
+    if !jcl_path.exists() {
+        return Err(format!("JCL 文件不存在: {}", jcl_path.display()));
+    }
+    Ok((jcl_path, info))
+}
+
+/// 统计单个 JCL（一场 BOSS 战斗）中每个玩家的 DPS / HPS / 承伤
+///
+/// `file_name` 为记录 `jclFiles` 中保存的文件名，在账号的 combat_logs 目录下查找。
+#[tauri::command]
+pub async fn analyze_jcl_combat_stats(
+    account_id: String,
+    file_name: String,
+) -> Result<JclCombatStats, String> {
+    tokio::task::spawn_blocking(move || {
+        let (jcl_path, info) = resolve_jcl_path(&account_id, &file_name)?;
+
+        let mut stats = compute_combat_stats(JclReader::open(&jcl_path)?);
+        stats.file_name = file_name;
+        stats.boss_name = info.boss_name;
+
+        log::info!(
+            "[CombatStats] {} 统计完成: 时长 {}ms, 玩家 {} 人",
+            stats.file_name,
+            stats.duration_ms,
+            stats.players.len()
+        );
+        Ok(stats)
+    })
+    .await
+    .map_err(|e| format!("统计线程异常: {}", e))?
+}
+
+/// 提取单个 JCL（一场 BOSS 战斗）的玩家重伤列表，供团长复盘灭团
+#[tauri::command]
+pub async fn analyze_jcl_deaths(
+    account_id: String,
+    file_name: String,
+) -> Result<JclDeathRecap, String> {
+    tokio::task::spawn_blocking(move || {
+        let (jcl_path, info) = resolve_jcl_path(&account_id, &file_name)?;
+
+        let mut recap = compute_death_recap(JclReader::open(&jcl_path)?);
+        recap.file_name = file_name;
+        recap.boss_name = info.boss_name;
+
+        log::info!(
+            "[CombatStats] {} 重伤回顾: {} 次重伤",
+            recap.file_name,
+            recap.deaths.len()
+        );
+        Ok(recap)
+    })
+    .await
+    .map_err(|e| format!("统计线程异常: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_combat_stats() {
+        let lines = [
+            "A\t1\t1750000000\t10000\t4\t{1,\"奶妈\",5,10028,300000}",
+            "A\t2\t1750000000\t10000\t4\t{2,\"输出\",10,10014,310000}",
+            "A\t3\t1750000000\t10000\t8\t{900,\"唐醉\",137005,0,0,0,0,0}",
+            "A\t4\t1750000000\t10000\t8\t{901,\"宠物\",1234,2,0,0,0,0}",
+            "A\t5\t1750000001\t11000\t1\t{true,\"uuid\",0}",
+            "A\t6\t1750000002\t12000\t21\t{2,900,false,1,1,1,false,1,{[0]=1200,[13]=1000}}",
+            "A\t7\t1750000003\t13000\t21\t{901,900,false,1,1,1,false,1,{[0]=600,[13]=500}}",
+            "A\t8\t1750000004\t14000\t21\t{900,2,false,1,1,1,false,1,{[0]=800,[13]=700}}",
+            "A\t9\t1750000005\t15000\t21\t{1,2,false,1,1,1,false,1,{[6]=900,[14]=700}}",
+            "A\t10\t1750000006\t16000\t28\t{2,900}",
+            "A\t11\t1750000011\t21000\t1\t{false,\"uuid\",10000}",
+        ];
+        let mut data = Vec::new();
+        for line in lines {
+            data.extend_from_slice(&encoding_rs::GBK.encode(line).0);
+            data.push(b'\n');
+        }
+
+        let stats = compute_combat_stats(JclReader::new(&data[..]));
+        assert_eq!(stats.fight_start_ms, 1_750_000_001_000);
+        assert_eq!(stats.fight_end_ms, 1_750_000_011_000);
+        assert_eq!(stats.duration_ms, 10_000);
+        assert_eq!(stats.players.len(), 2);
+
+        let dps = &stats.players[0];
+        assert_eq!(dps.name, "输出");
+        assert_eq!(dps.damage, 1800);
+        assert_eq!(dps.effective_damage, 1500);
+        assert_eq!(dps.dps, 150.0);
+        assert_eq!(dps.damage_taken, 700);
+        assert_eq!(dps.deaths, 1);
+
+        let healer = &stats.players[1];
+        assert_eq!(healer.name, "奶妈");
+        assert_eq!(healer.effective_damage, 0);
+        assert_eq!(healer.therapy, 900);
+        assert_eq!(healer.effective_therapy, 700);
+        assert_eq!(healer.hps, 70.0);
+    }
+
+    #[test]
+    fn test_compute_combat_stats_events_before_player_info() {
+        // 中途开启记录时，技能效果和重伤可能早于对应的 PLAYER_INFO / NPC_INFO
+        let lines = [
+            "A\t1\t1750000001\t11000\t1\t{true,\"uuid\",0}",
+            "A\t2\t1750000002\t12000\t21\t{2,900,false,1,1,1,false,1,{[0]=1200,[13]=1000}}",
+            "A\t3\t1750000002\t12000\t21\t{901,900,false,1,1,1,false,1,{[0]=600,[13]=500}}",
+            "A\t4\t1750000003\t13000\t21\t{1,2,false,1,1,1,false,1,{[0]=300,[13]=300,[6]=900,[14]=700}}",
+            "A\t5\t1750000004\t14000\t28\t{2,900}",
+            "A\t6\t1750000005\t15000\t4\t{1,\"奶妈\",5,10028,300000}",
+            "A\t7\t1750000005\t15000\t4\t{2,\"输出\",10,10014,310000}",
+            "A\t8\t1750000005\t15000\t8\t{901,\"宠物\",1234,2,0,0,0,0}",
+            "A\t9\t1750000011\t21000\t1\t{false,\"uuid\",10000}",
+        ];
+        let mut data = Vec::new();
+        for line in lines {
+            data.extend_from_slice(&encoding_rs::GBK.encode(line).0);
+            data.push(b'\n');
+        }
+
+        let stats = compute_combat_stats(JclReader::new(&data[..]));
+        let dps = stats.players.iter().find(|p| p.name == "输出").unwrap();
+        assert_eq!(dps.damage, 1800);
+        assert_eq!(dps.effective_damage, 1500);
+        assert_eq!(dps.damage_taken, 300);
+        assert_eq!(dps.deaths, 1);
+
+        // 对玩家的伤害不计入输出
+        let healer = stats.players.iter().find(|p| p.name == "奶妈").unwrap();
+        assert_eq!(healer.damage, 0);
+        assert_eq!(healer.effective_therapy, 700);
+    }
+
+    #[test]
+    fn test_compute_death_recap() {
+        let lines = [
+            "A\t1\t1750000000\t10000\t4\t{1,\"奶妈\",5,10028,300000}",
+            "A\t2\t1750000000\t10000\t4\t{2,\"输出\",10,10014,310000}",
+            "A\t3\t1750000000\t10000\t8\t{900,\"唐醉\",137005,0,0,0,0,0}",
+            "A\t4\t1750000001\t11000\t1\t{true,\"uuid\",0}",
+            "A\t5\t1750000005\t15000\t21\t{900,2,false,1,30001,2,false,1,{[0]=5000,[13]=4800}}",
+            "A\t6\t1750000005\t15200\t28\t{2,900}",
+            "A\t7\t1750000009\t19000\t21\t{900,1,false,2,40002,1,false,1,{[3]=900,[13]=900}}",
+            "A\t8\t1750000009\t19500\t28\t{1,0}",
+            "A\t9\t1750000010\t20000\t28\t{900,2}",
+        ];
+        let mut data = Vec::new();
+        for line in lines {
+            data.extend_from_slice(&encoding_rs::GBK.encode(line).0);
+            data.push(b'\n');
+        }
+
+        let recap = compute_death_recap(JclReader::new(&data[..]));
+        assert_eq!(recap.fight_start_ms, 1_750_000_001_000);
+        // BOSS 死亡不计入玩家重伤
+        assert_eq!(recap.deaths.len(), 2);
+
+        let first = &recap.deaths[0];
+        assert_eq!(first.player_name, "输出");
+        assert_eq!(first.relative_ms, Some(4_200));
+        assert_eq!(first.killer_name, "唐醉");
+        assert!(!first.killer_is_player);
+        assert_eq!(first.skill_id, Some(30001));
+        assert_eq!(first.damage, Some(4800));
+
+        // dwKiller 缺失时回退到最后一次伤害来源
+        let second = &recap.deaths[1];
+        assert_eq!(second.player_name, "奶妈");
+        assert_eq!(second.killer_id, 900);
+        assert_eq!(second.killer_name, "唐醉");
+        assert_eq!(second.skill_id, Some(40002));
+        assert_eq!(second.effect_type, Some(2));
+    }
+
+    #[test]
+    fn test_resolve_jcl_path_rejects_unsafe_account_id() {
+        assert!(is_valid_account_id("12345678"));
+        for account_id in ["", "..", "..\\..", "../other", "a/b", "C:", "a\0b"] {
+            assert!(!is_valid_account_id(account_id), "{:?}", account_id);
+            let err = resolve_jcl_path(account_id, "2025-01-01-00-00-00-x.jcl").unwrap_err();
+            assert!(err.contains("账号 ID"), "{}", err);
+        }
+    }
+}
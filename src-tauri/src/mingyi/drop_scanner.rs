@@ -1,7 +1,7 @@
 use rusqlite::{params, Connection};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::{LazyLock, Mutex, OnceLock};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use chrono::Datelike;
@@ -10,6 +10,12 @@ use crate::app_config;
 use crate::db;
 use crate::game_directory::MINGYI_ACCOUNTS_BASE_PATH;
 use crate::mingyi::active_detector::parse_info_jx3dat;
+use crate::mingyi::auction_ledger;
+use crate::mingyi::boss_rules::{self, BossKillRule, CloneHandling};
+use crate::mingyi::jcl::{log_type, JclEvent, JclReader};
+use crate::mingyi::roster::{self, JclPlayer, RosterMember};
+use crate::mingyi::scan_explain::{InstanceTrace, JclFileTrace, JclVerdict, ScanTrace};
+use crate::money::Money;
 
 // === 正则表达式预编译 ===
 
@@ -18,19 +24,10 @@ static JCL_FILENAME_RE: LazyLock<regex::Regex> = LazyLock::new(||
     regex::Regex::new(r"^(\d{4})-(\d{2})-(\d{2})-(\d{2})-(\d{2})-(\d{2})-(.+?)\((\d+)\)-(.*?)\((\d+)\)\.jcl$").unwrap()
 );
 
-// parse_money_from_msg 用
-static GOLDB_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_GoldB""#).unwrap());
-static GOLD_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_Gold""#).unwrap());
-static SILVER_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_Silver""#).unwrap());
-static COPPER_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_Copper""#).unwrap());
-
-// parse_expense_amount 用
-static BRICK_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"(\d+)金砖").unwrap());
-static EXPENSE_GOLD_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"(\d+)金").unwrap());
-
 // extract_drops_from_chatlog 用
 static ITEM_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"分配者将：\[([^\]]+)\]分配给").unwrap());
-static SALARY_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"每人底薪：(\d+)金").unwrap());
+static ITEM_RECEIVER_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"分配给([^。\s]+)").unwrap());
+pub(crate) static SALARY_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"每人底薪：((?:\d+金砖)?\d+金(?:\d+银)?(?:\d+铜)?)").unwrap());
 static EXPENSE_MSG_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"\[([^\]]+)\]花费\[([^\]]+)\]购买了\[([^\]]+)\]").unwrap());
 static PURCHASED_ITEM_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"购买了\[([^\]]+)\]").unwrap());
 /// "记录给了"格式：[分配者]将[物品名]以[金额]记录给了[接收者]
@@ -39,19 +36,19 @@ static ALLOCATE_TO_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::n
 
 /// JCL 文件名解析结果
 #[derive(Debug, Clone)]
-struct JclFileInfo {
+pub(crate) struct JclFileInfo {
     /// 文件名（不含路径）
-    file_name: String,
+    pub(crate) file_name: String,
     /// 时间戳（毫秒）
-    timestamp: i64,
+    pub(crate) timestamp: i64,
     /// JCL 副本名（如 "25人英雄阆风悬城"）
-    raid_display_name: String,
+    pub(crate) raid_display_name: String,
     /// 副本 ID（与 JX3Box 掉落接口的 MapID 同源，如阆风悬城=795）
-    raid_id: i64,
+    pub(crate) raid_id: i64,
     /// BOSS 名
-    boss_name: String,
+    pub(crate) boss_name: String,
     /// BOSS 模板 ID（来自文件名，用于 JCL 内容中 NPC_INFO 的 dwTemplateID 匹配）
-    boss_id: i64,
+    pub(crate) boss_id: i64,
 }
 
 /// JCL 文件内容分析结果
@@ -61,26 +58,39 @@ struct JclFileInfo {
 /// - 战斗开始/结束时间（来自 FIGHT_TIME 事件，比文件名时间更精确）
 /// - BOSS 击杀状态（基于 NPC_FIGHT_HINT 的 bFight True→False 转变 + 宝箱信号判定通关）
 #[derive(Debug, Clone)]
-struct JclAnalysis {
+pub(crate) struct JclAnalysis {
     /// 真实 BOSS 名（从 JCL 内容解析，当文件名 BOSS 不在配置中时使用）
-    boss_name: Option<String>,
+    pub(crate) boss_name: Option<String>,
     /// 战斗开始时间（毫秒，Unix timestamp）
     /// 来自首个 FIGHT_TIME 事件（bFighting=true）的时间戳
-    fight_start_ms: i64,
+    pub(crate) fight_start_ms: i64,
     /// 战斗结束时间（毫秒，Unix timestamp）
     /// 来自最后 FIGHT_TIME 事件（bFighting=false）的时间戳
-    fight_end_ms: i64,
+    pub(crate) fight_end_ms: i64,
     /// 是否击杀 BOSS（true=通关，false=拉托）
     /// 判定依据：宝箱路径 或 NPC_FIGHT_HINT 的 bFight True→False 转变
-    is_kill: bool,
+    pub(crate) is_kill: bool,
+    /// BOSS 战斗中的最低血量百分比（0~100，灭团进度），无血量事件时为 None
+    /// 来自 NPC_FIGHT_HINT 的 fCurrentLife / fMaxLife
+    pub(crate) boss_hp_percent: Option<f64>,
+    /// JCL 中的队伍成员（PLAYER_INFO 中有 PARTY_ADD_MEMBER 的玩家，没有队伍事件时为空），用于团队名单
+    pub(crate) players: Vec<JclPlayer>,
 }
 
+/// JCL 分析器版本（写入 jcl_cache.analyzer_version，作为缓存键的一部分）
+///
+/// 修改 analyze_jcl 判定逻辑时递增：旧版本缓存自动失效，无需手动 clear_jcl_cache。
+/// 递增前用 `--replay-jcl <目录>` 回放回归样本，确认判定变化都符合预期。
+pub(crate) const JCL_ANALYZER_VERSION: i64 = 5;
+
 /// chatlog 预加载记录（单条 ChatLog 行的内存表示）
 ///
 /// 用于 scan_raid_drops_with_raids 入口处一次性加载所有 chatlog 文件中的相关记录，
 /// 避免后续每个副本实例重复打开 SQLite 连接和全表扫描。
 #[derive(Clone, Debug)]
 struct ChatlogRecord {
+    /// 行标识（文件 + rowid），同一行被重复读入时据此识别
+    row_key: ChatlogRowKey,
     /// 消息类型：MSG_ITEM / MSG_MONEY / MSG_ROOM / MSG_WHISPER
     msg_type: String,
     /// text 字段（消息文本）
@@ -91,6 +101,27 @@ struct ChatlogRecord {
     time_sec: i64,
 }
 
+/// chatlog 行标识：所在文件（路径哈希）与 ChatLog 表的 rowid
+///
+/// 同一秒内内容相同的两条消息（如两次相同价格的购买）是不同的行，只有同一行才视为重复。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChatlogRowKey {
+    pub file: u64,
+    pub rowid: i64,
+}
+
+impl ChatlogRowKey {
+    fn new(path: &Path, rowid: i64) -> Self {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.hash(&mut hasher);
+        ChatlogRowKey {
+            file: hasher.finish(),
+            rowid,
+        }
+    }
+}
+
 /// 副本实例（聚类后的一场副本）
 #[derive(Debug)]
 struct RaidInstance {
@@ -119,10 +150,139 @@ struct RaidInstance {
     /// 聊天记录分析的起始时间（毫秒）
     /// 取首个成功 JCL 的战斗开始时间（fight_start_ms），比文件名时间更精确
     first_gold_time: i64,
+    /// 组内全部 BOSS 战斗尝试（含灭团），按时间顺序
+    attempts: Vec<RaidAttempt>,
+    /// 组内全部 JCL 中出现的队伍成员（按角色名去重）
+    party: Vec<JclPlayer>,
+}
+
+/// 单次 BOSS 战斗尝试（击杀或灭团），持久化到 raid_attempts 表
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RaidAttempt {
+    /// BOSS 名（优先使用 JCL 内容识别结果，回退到文件名 BOSS）
+    pub boss_name: String,
+    /// JCL 文件名
+    pub jcl_file: String,
+    /// 该 BOSS 的第几次尝试（从 1 开始）
+    pub attempt_index: u32,
+    /// 战斗开始时间（毫秒），无 FIGHT_TIME 时回退到文件名时间
+    pub start_time: i64,
+    /// 战斗结束时间（毫秒）
+    pub end_time: i64,
+    /// 战斗时长（毫秒）
+    pub duration_ms: i64,
+    /// 是否击杀（与通关判定一致，含组级别兜底）
+    pub is_kill: bool,
+    /// BOSS 最低血量百分比（灭团进度），无血量事件时为 None
+    pub boss_hp_percent: Option<f64>,
+}
+
+/// chatlog 明细条目类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChatlogItemKind {
+    /// 物品分配（MSG_ITEM）
+    ItemDrop,
+    /// BOSS 击杀奖励 10 金（不计入收入）
+    BossReward,
+    /// 金币收入（非 BOSS 10金）
+    Income,
+    /// 拍团底薪
+    Salary,
+    /// 当前角色"花费...购买了"支出
+    Expense,
+    /// 团长分配记录（仅提取物品名，不计入支出）
+    Allocation,
+    /// 团队频道拍卖物品（含帮别人购买）
+    AuctionItem,
+}
+
+impl ChatlogItemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatlogItemKind::ItemDrop => "itemDrop",
+            ChatlogItemKind::BossReward => "bossReward",
+            ChatlogItemKind::Income => "income",
+            ChatlogItemKind::Salary => "salary",
+            ChatlogItemKind::Expense => "expense",
+            ChatlogItemKind::Allocation => "allocation",
+            ChatlogItemKind::AuctionItem => "auctionItem",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "itemDrop" => Some(ChatlogItemKind::ItemDrop),
+            "bossReward" => Some(ChatlogItemKind::BossReward),
+            "income" => Some(ChatlogItemKind::Income),
+            "salary" => Some(ChatlogItemKind::Salary),
+            "expense" => Some(ChatlogItemKind::Expense),
+            "allocation" => Some(ChatlogItemKind::Allocation),
+            "auctionItem" => Some(ChatlogItemKind::AuctionItem),
+            _ => None,
+        }
+    }
+}
+
+/// 单条参与计算的 chatlog 消息（收支来源明细），持久化到 record_chatlog_items 表
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatlogLineItem {
+    pub kind: ChatlogItemKind,
+    /// 消息时间（秒级时间戳）
+    pub time_sec: i64,
+    /// 频道（chatlog type，如 MSG_ROOM / MSG_WHISPER / MSG_MONEY）
+    pub channel: String,
+    /// 消息原文
+    pub text: String,
+    pub item_name: Option<String>,
+    /// 购买者 / 分配接收者
+    pub buyer: Option<String>,
+    /// 金额（铜精度）
+    pub amount: Option<Money>,
+    /// 是否计入统计（重复消息、被覆盖的底薪为 false）
+    pub counted: bool,
+    /// 计算说明（如"最终收入"、"重复消息已去重"）
+    pub note: Option<String>,
+    /// 来源 chatlog 行（扫描时提取才有，从 record_chatlog_items 读回时为 None）
+    #[serde(skip)]
+    pub row_key: Option<ChatlogRowKey>,
+}
+
+/// chatlog 提取结果
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChatlogExtraction {
+    pub drops: Vec<String>,
+    /// 拍团底薪（多条时取最后一条，铜精度）
+    pub base_salary: Option<Money>,
+    /// 非底薪的金币收入总和（排除 BOSS 10金）
+    pub other_income: Money,
+    pub expense: Money,
+    pub boss_kill_count: u32,
+    /// 非 BOSS 10金的收入记录 (time_sec, 金额)，用于精确收入匹配
+    pub income_records: Vec<(i64, Money)>,
+    /// 每个 BOSS 击杀（10金收入）的 time_sec，用于击杀数验证
+    pub boss_kill_times: Vec<i64>,
+    /// 当前角色花钱购买的物品名（用于 notes 显示）
+    pub purchased_items: Vec<String>,
+    /// 按消息顺序排列的收支来源明细
+    pub line_items: Vec<ChatlogLineItem>,
+}
+
+impl ChatlogExtraction {
+    /// 标记被选为最终收入的收入消息
+    pub(crate) fn mark_selected_income(&mut self, time_sec: i64, amount: Money, note: &str) {
+        if let Some(item) = self.line_items.iter_mut().find(|item| {
+            item.kind == ChatlogItemKind::Income && item.time_sec == time_sec && item.amount == Some(amount)
+        }) {
+            item.note = Some(note.to_string());
+        }
+    }
 }
 
 /// 从 app_config 获取游戏目录（自动补全为 bin/zhcn_hd 运行目录）
-fn get_game_directory() -> Result<String, String> {
+pub(crate) fn get_game_directory() -> Result<String, String> {
     let config = app_config::get_app_config_internal()?;
     let raw = config
         .game_directory
@@ -137,17 +297,17 @@ fn get_game_directory() -> Result<String, String> {
 }
 
 /// 角色在数据库中的 UUID 身份
-struct DbRoleIdentity {
+pub(crate) struct DbRoleIdentity {
     /// roles.id（UUID）
-    role_id: String,
+    pub(crate) role_id: String,
     /// accounts.id（UUID）
-    account_id: String,
+    pub(crate) account_id: String,
     /// 角色名（用于匹配 chatlog 支出消息中的买家）
-    role_name: String,
+    pub(crate) role_name: String,
     /// 服务器（用于扫描记录 JSON 的 server 字段）
-    server: String,
+    pub(crate) server: String,
     /// 大区（用于扫描记录 JSON 的 region 字段）
-    region: String,
+    pub(crate) region: String,
 }
 
 /// 从茗伊账号目录解析 info.jx3dat 获取角色身份，
@@ -157,11 +317,15 @@ struct DbRoleIdentity {
 /// 但 records 表的 account_id/role_id 是 UUID 格式。
 /// 必须通过 role_name + server 查询 roles 表得到 UUID。
 ///
+/// `repair_orphans` 为 true 时顺带修复 account_id 不一致的孤儿记录；
+/// 只读流程（扫描解释）传 false，不修改数据库。
+///
 /// 返回 None 表示角色未在数据库中登记，调用方应跳过此账号。
-fn resolve_db_role_identity(
+pub(crate) fn resolve_db_role_identity(
     conn: &Connection,
     account_dir: &Path,
     game_path: &Path,
+    repair_orphans: bool,
 ) -> Result<Option<DbRoleIdentity>, String> {
     let info_path = account_dir.join("info.jx3dat");
     let identity = match parse_info_jx3dat(&info_path, Some(game_path)) {
@@ -207,19 +371,25 @@ fn resolve_db_role_identity(
                 )
                 .unwrap_or(0);
 
-            if orphan_count > 0 {
-                // 更新表列
-                let updated = conn.execute(
-                    "UPDATE records SET account_id = ?1 WHERE role_id = ?2 AND account_id != ?1",
-                    params![&account_id, &role_id],
-                ).map_err(|e| format!("更新孤儿记录 account_id 失败: {}", e))?;
-                // 同步更新 JSON data 中的 accountId 字段（前端从 data 读取）
-                // 过滤条件改用 account_id 列（已存在），避免 json_extract 全表扫描
+            if orphan_count > 0 && !repair_orphans {
+                log::info!(
+                    "[DropScanner] 只读模式，跳过 {} 条孤儿记录修复: role_id={}",
+                    orphan_count,
+                    role_id
+                );
+            } else if orphan_count > 0 {
+                // 先同步 JSON data 中的 accountId 字段（前端从 data 读取）
+                // 过滤条件用 account_id 列，避免 json_extract 全表扫描；因此必须在更新表列之前执行
                 conn.execute(
                     "UPDATE records SET data = json_set(data, '$.accountId', ?1) \
                      WHERE role_id = ?2 AND account_id != ?1",
                     params![&account_id, &role_id],
                 ).map_err(|e| format!("更新孤儿记录 JSON accountId 失败: {}", e))?;
+                // 再更新表列
+                let updated = conn.execute(
+                    "UPDATE records SET account_id = ?1 WHERE role_id = ?2 AND account_id != ?1",
+                    params![&account_id, &role_id],
+                ).map_err(|e| format!("更新孤儿记录 account_id 失败: {}", e))?;
                 log::info!(
                     "[DropScanner] 修复 {} 条孤儿记录: role_id={} -> account_id={} (rows affected: {})",
                     orphan_count,
@@ -298,7 +468,7 @@ fn days_to_ymd(days: i64) -> (i32, u32, u32) {
     (date.year(), date.month(), date.day())
 }
 
-fn parse_jcl_filename(file_name: &str) -> Option<JclFileInfo> {
+pub(crate) fn parse_jcl_filename(file_name: &str) -> Option<JclFileInfo> {
     // 先尝试 UTF-8 解析，如果不是则可能是 GBK 编码的 bytes
     // 但由于 Rust 的 OsString 在 Windows 上可能是 WTF-8/UTF-8，
     // 我们直接用正则解析字符串形式
@@ -782,6 +952,52 @@ fn build_raid_instance(
     let bosses_killed = jcl_boss_names.clone();
     let boss_kill_count = success_jcls.len() as u32;
 
+    // 全部战斗尝试（含灭团），is_kill 以上面的通关判定为准
+    let success_files: HashSet<&str> = success_jcls.iter().map(|j| j.file_name.as_str()).collect();
+    let mut boss_attempt_counts: HashMap<String, u32> = HashMap::new();
+    let attempts: Vec<RaidAttempt> = group
+        .iter()
+        .map(|jcl| {
+            let analysis = jcl_analyses.get(&jcl.file_name);
+            let boss_name = get_effective_boss_name(jcl, jcl_analyses);
+            let attempt_index = boss_attempt_counts.entry(boss_name.clone()).or_insert(0);
+            *attempt_index += 1;
+            let start_time = analysis
+                .map(|a| a.fight_start_ms)
+                .filter(|t| *t > 0)
+                .unwrap_or(jcl.timestamp);
+            let end_time = analysis
+                .map(|a| a.fight_end_ms)
+                .filter(|t| *t >= start_time)
+                .unwrap_or(start_time);
+            RaidAttempt {
+                boss_name,
+                jcl_file: jcl.file_name.clone(),
+                attempt_index: *attempt_index,
+                start_time,
+                end_time,
+                duration_ms: end_time - start_time,
+                is_kill: success_files.contains(jcl.file_name.as_str()),
+                boss_hp_percent: analysis.and_then(|a| a.boss_hp_percent),
+            }
+        })
+        .collect();
+
+    // 队伍成员：合并组内全部 JCL（含灭团），同名角色优先保留已知心法
+    let mut party_by_name: HashMap<&str, &JclPlayer> = HashMap::new();
+    for player in group
+        .iter()
+        .filter_map(|jcl| jcl_analyses.get(&jcl.file_name))
+        .flat_map(|a| a.players.iter())
+    {
+        let entry = party_by_name.entry(player.name.as_str()).or_insert(player);
+        if entry.kungfu_id == 0 && player.kungfu_id != 0 {
+            *entry = player;
+        }
+    }
+    let mut party: Vec<JclPlayer> = party_by_name.into_values().cloned().collect();
+    party.sort_by(|a, b| a.name.cmp(&b.name));
+
     log::info!(
         "[DropScanner] 副本 {} 聚类完成: 组内 {} 个JCL, 通关 {} 个, start={}, end={}, first_gold={}",
         raid_display_name,
@@ -804,6 +1020,8 @@ fn build_raid_instance(
         jcl_boss_names,
         boss_kill_count,
         first_gold_time,
+        attempts,
+        party,
     })
 }
 
@@ -816,12 +1034,14 @@ pub struct RaidEntry {
     pub raid_id: String,
     /// BOSS 列表（可能为空）
     pub bosses: Vec<(String, String)>,
+    /// BOSS 击杀判定规则（raid_boss_rules 表，可能为空）
+    pub boss_rules: Vec<BossKillRule>,
 }
 
 /// 副本配置缓存（应用生命周期内有效，副本配置不会频繁变化）
 static RAIDS_CACHE: OnceLock<Mutex<Option<Vec<RaidEntry>>>> = OnceLock::new();
 
-fn get_cached_raids(conn: &Connection) -> Result<Vec<RaidEntry>, String> {
+pub(crate) fn get_cached_raids(conn: &Connection) -> Result<Vec<RaidEntry>, String> {
     let cache = RAIDS_CACHE.get_or_init(|| Mutex::new(None));
     let mut guard = cache.lock().unwrap();
     if let Some(raids) = guard.as_ref() {
@@ -881,7 +1101,10 @@ fn load_raids_with_bosses(conn: &Connection) -> Result<Vec<RaidEntry>, String> {
             .push((boss_id, boss_name));
     }
 
-    // 2. 从 raids 表加载所有副本
+    // 2. 从 raid_boss_rules 表加载 BOSS 击杀判定规则（同样按 raids.name 关联）
+    let rules_map = boss_rules::load_boss_rules(conn)?;
+
+    // 3. 从 raids 表加载所有副本
     let mut entries: Vec<RaidEntry> = Vec::new();
 
     let mut stmt = conn
@@ -902,14 +1125,16 @@ fn load_raids_with_bosses(conn: &Connection) -> Result<Vec<RaidEntry>, String> {
 
         // 用 raids.name 作为 key 查找 BOSS 配置（同名副本共享）
         let bosses = bosses_map.get(&name).cloned().unwrap_or_default();
+        let boss_rules = rules_map.get(&name).cloned().unwrap_or_default();
         entries.push(RaidEntry {
             name: name.clone(),
             raid_id,
             bosses,
+            boss_rules,
         });
     }
 
-    // 3. Fallback：如果某个 raid 仍无 BOSS 配置（数据库未配置），
+    // 4. Fallback：如果某个 raid 仍无 BOSS 配置（数据库未配置），
     // 尝试从同 name 的其他 raid 复制 BOSS 列表。
     // 新表结构下同名副本已天然共享，此分支通常不会触发，仅作兜底。
     let mut fallback_count = 0u32;
@@ -1012,7 +1237,7 @@ fn detect_jcl_difficulty(jcl_raid_name: &str) -> Option<u8> {
 ///    - JCL 无难度前缀 → 选择最低难度的候选
 ///    - JCL 有人数前缀 → 优先匹配相同人数
 ///    - JCL 有难度前缀 → 优先匹配相同难度
-fn match_raid_name<'a>(jcl_raid_name: &str, raids: &'a [RaidEntry]) -> Option<&'a RaidEntry> {
+pub(crate) fn match_raid_name<'a>(jcl_raid_name: &str, raids: &'a [RaidEntry]) -> Option<&'a RaidEntry> {
     // 1. 精确匹配 raids.id
     for entry in raids {
         if entry.raid_id == jcl_raid_name {
@@ -1184,9 +1409,9 @@ fn map_boss_names_to_ids(
 /// 避免出场对话 JCL 被误判为该 BOSS 的战斗。
 ///
 /// 性能优化：
-/// - 逐行读取（BufReader），避免一次性读入大文件（45MB+）
-/// - 字节级事件类型预过滤，跳过不相关的事件类型
-/// - GBK 解码仅对相关事件行执行
+/// - 逐行读取（`jcl::JclReader`），避免一次性读入大文件（45MB+）
+/// - 字节级事件类型预过滤（`JclReader::only`），跳过不相关的事件类型
+/// - GBK 解码和 Lua 解析仅对相关事件行执行
 ///
 /// 参数：
 /// - jcl_path: JCL 文件完整路径
@@ -1194,30 +1419,27 @@ fn map_boss_names_to_ids(
 /// - raid_bosses: raid_bosses 表的 (boss_id, boss_name) 列表
 ///
 /// 从 SQLite 缓存读取 JCL 解析结果（跨会话复用）
-/// 缓存键：file_path + file_mtime，文件未修改时直接返回缓存
+/// 缓存键：file_path + file_mtime + analyzer_version，文件未修改且分析器版本一致时直接返回缓存
 fn get_jcl_cache(conn: &Connection, file_path: &str, file_mtime: i64) -> Option<JclAnalysis> {
-    let result: rusqlite::Result<(Option<String>, i64, i64, i64)> = conn.query_row(
-        "SELECT boss_name, fight_start_ms, fight_end_ms, is_kill FROM jcl_cache WHERE file_path = ?1 AND file_mtime = ?2",
-        params![file_path, file_mtime],
+    let result = conn.query_row(
+        "SELECT boss_name, fight_start_ms, fight_end_ms, is_kill, boss_hp_percent, players FROM jcl_cache WHERE file_path = ?1 AND file_mtime = ?2 AND analyzer_version = ?3",
+        params![file_path, file_mtime, JCL_ANALYZER_VERSION],
         |row| {
-            Ok((
-                row.get::<_, Option<String>>(0)?,
-                row.get::<_, i64>(1)?,
-                row.get::<_, i64>(2)?,
-                row.get::<_, i64>(3)?,
-            ))
+            Ok(JclAnalysis {
+                boss_name: row.get::<_, Option<String>>(0)?,
+                fight_start_ms: row.get::<_, i64>(1)?,
+                fight_end_ms: row.get::<_, i64>(2)?,
+                is_kill: row.get::<_, i64>(3)? != 0,
+                boss_hp_percent: row.get::<_, Option<f64>>(4)?,
+                players: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+            })
         },
     );
 
     match result {
-        Ok((boss_name, fight_start_ms, fight_end_ms, is_kill)) => {
+        Ok(analysis) => {
             log::debug!("[DropScanner] JCL 缓存命中: {}", file_path);
-            Some(JclAnalysis {
-                boss_name,
-                fight_start_ms,
-                fight_end_ms,
-                is_kill: is_kill != 0,
-            })
+            Some(analysis)
         }
         Err(rusqlite::Error::QueryReturnedNoRows) => None,
         Err(e) => {
@@ -1230,8 +1452,9 @@ fn get_jcl_cache(conn: &Connection, file_path: &str, file_mtime: i64) -> Option<
 /// 将 JCL 解析结果写入 SQLite 缓存
 fn set_jcl_cache(conn: &Connection, file_path: &str, file_mtime: i64, analysis: &JclAnalysis) {
     let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let players_json = serde_json::to_string(&analysis.players).unwrap_or_else(|_| "[]".to_string());
     if let Err(e) = conn.execute(
-        "INSERT OR REPLACE INTO jcl_cache (file_path, file_mtime, boss_name, fight_start_ms, fight_end_ms, is_kill, cached_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        "INSERT OR REPLACE INTO jcl_cache (file_path, file_mtime, boss_name, fight_start_ms, fight_end_ms, is_kill, cached_at, boss_hp_percent, analyzer_version, players) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
             file_path,
             file_mtime,
@@ -1239,7 +1462,10 @@ fn set_jcl_cache(conn: &Connection, file_path: &str, file_mtime: i64, analysis:
             analysis.fight_start_ms,
             analysis.fight_end_ms,
             if analysis.is_kill { 1 } else { 0 },
-            now
+            now,
+            analysis.boss_hp_percent,
+            JCL_ANALYZER_VERSION,
+            players_json
         ],
     ) {
         log::warn!("[DropScanner] JCL 缓存写入失败: {}", e);
@@ -1265,6 +1491,7 @@ fn analyze_jcl_cached(
     jcl_boss_name: &str,
     boss_template_id: i64,
     raid_bosses: &[(String, String)],
+    boss_rules: &[BossKillRule],
 ) -> Option<JclAnalysis> {
     let path_str = jcl_path.to_string_lossy().to_string();
     let file_mtime = get_file_mtime(jcl_path);
@@ -1277,7 +1504,7 @@ fn analyze_jcl_cached(
     }
 
     // 2. 缓存未命中，解析文件
-    let analysis = analyze_jcl(jcl_path, jcl_boss_name, boss_template_id, raid_bosses)?;
+    let analysis = analyze_jcl(jcl_path, jcl_boss_name, boss_template_id, raid_bosses, boss_rules)?;
 
     // 3. 写回缓存
     if file_mtime > 0 {
@@ -1287,33 +1514,94 @@ fn analyze_jcl_cached(
     Some(analysis)
 }
 
+/// 按副本配置分析单个 JCL（不经过缓存），BOSS 名处理与扫描流程一致
+///
+/// 供 `--replay-jcl` 回放使用，保证回放结果与实际扫描的判定相同。
+pub(crate) fn analyze_jcl_for_raid(jcl_path: &Path, jcl: &JclFileInfo, raid_entry: &RaidEntry) -> Option<JclAnalysis> {
+    let mut analysis = analyze_jcl(
+        jcl_path,
+        &jcl.boss_name,
+        jcl.boss_id,
+        &raid_entry.bosses,
+        &raid_entry.boss_rules,
+    )?;
+    apply_file_boss_name(&mut analysis, jcl, raid_entry);
+    Some(analysis)
+}
+
+/// BOSS 名优先级：文件名 BOSS 在 raid_bosses 配置中时使用文件名，
+/// 否则使用 JCL 内容搜索结果（处理"须罗巨傀"→"唐怀仁"等伴生 NPC 命名的 JCL）
+/// 文件名为规则中的别名时归一为 BOSS 名
+fn apply_file_boss_name(analysis: &mut JclAnalysis, jcl: &JclFileInfo, raid_entry: &RaidEntry) {
+    let file_boss_name = boss_rules::canonical_boss_name(&jcl.boss_name, &raid_entry.boss_rules);
+    if is_valid_boss(file_boss_name, &raid_entry.bosses) {
+        analysis.boss_name = Some(file_boss_name.to_string());
+    }
+}
+
 /// 解析 JCL 文件，提取 BOSS 击杀状态和战斗时间
 fn analyze_jcl(
     jcl_path: &Path,
     jcl_boss_name: &str,
     boss_template_id: i64,
     raid_bosses: &[(String, String)],
+    boss_rules: &[BossKillRule],
 ) -> Option<JclAnalysis> {
-    use std::io::BufRead;
-    let file = std::fs::File::open(jcl_path).ok()?;
-    let reader = std::io::BufReader::new(file);
-    let gbk = encoding_rs::GBK;
+    analyze_jcl_with_signals(jcl_path, jcl_boss_name, boss_template_id, raid_bosses, boss_rules)
+        .map(|(analysis, _)| analysis)
+}
 
-    // 预编码所有 BOSS 名为 GBK 字节串，避免在循环中重复编码
-    let needles: Vec<(&str, Vec<u8>)> = raid_bosses
-        .iter()
-        .filter(|(_, name)| !name.is_empty())
-        .map(|(_, name)| {
-            let (bytes, _, _) = gbk.encode(name);
-            (name.as_str(), bytes.to_vec())
-        })
-        .collect();
+/// `analyze_jcl` 的完整版本，额外返回击杀判定中成立的信号名（供扫描解释展示判定依据）
+fn analyze_jcl_with_signals(
+    jcl_path: &Path,
+    jcl_boss_name: &str,
+    boss_template_id: i64,
+    raid_bosses: &[(String, String)],
+    boss_rules: &[BossKillRule],
+) -> Option<(JclAnalysis, Vec<&'static str>)> {
+    // 击杀判定只关心 FIGHT_TIME / NPC_LEAVE_SCENE / NPC_INFO / NPC_FIGHT_HINT / DEATH_NOTIFY 五类事件，
+    // PLAYER_INFO / PARTY_ADD_MEMBER 仅用于收集队伍成员（团队名单）
+    let reader = JclReader::open(jcl_path).ok()?.only(&[
+        log_type::FIGHT_TIME,
+        log_type::NPC_LEAVE_SCENE,
+        log_type::NPC_INFO,
+        log_type::NPC_FIGHT_HINT,
+        log_type::SYS_MSG_UI_OME_DEATH_NOTIFY,
+        log_type::PLAYER_INFO,
+        log_type::PARTY_ADD_MEMBER,
+    ]);
 
     // 判断文件名 BOSS 是否在 raid_bosses 配置中（基于 BOSS 名称匹配）。
     // boss_id 是字符串格式（如 "langfengxuancheng_1"），不能用数值 template_id 比对。
     // 不在配置中说明是"小怪 JCL"（如"须罗巨傀"对应唐怀仁战斗），需要从 JCL 内容
     // 搜索配置中的真实 BOSS 名，并跟踪其状态。
-    let file_boss_in_config = is_valid_boss(jcl_boss_name, raid_bosses);
+    // 文件名 BOSS 可能是规则中的别名，先归一为 BOSS 名再与配置比对。
+    let file_boss_name = boss_rules::canonical_boss_name(jcl_boss_name, boss_rules);
+    let file_boss_in_config = is_valid_boss(file_boss_name, raid_bosses);
+
+    // BOSS 专属判定知识（伴生 NPC、宝箱名、分身处理、朝向特征值等）来自 raid_boss_rules 表，
+    // 未配置规则的 BOSS 使用通用判定（BossKillRule::default_for）。
+    let file_rule = boss_rules::resolve_rule(boss_rules, file_boss_name);
+    // 路径 B：文件名 BOSS 是某个配置 BOSS 的伴生 NPC 时，只在该规则中查找真实 BOSS
+    let companion_rule = if file_boss_in_config {
+        None
+    } else {
+        boss_rules::find_rule_by_companion(boss_rules, jcl_boss_name)
+            .filter(|rule| is_valid_boss(&rule.boss_name, raid_bosses))
+    };
+
+    // 宝箱 NPC 名 → BOSS 名（配置中的 BOSS + 文件名 BOSS）
+    let mut chest_owners: HashMap<String, String> = HashMap::new();
+    for (_, boss_name) in raid_bosses {
+        for chest in boss_rules::resolve_rule(boss_rules, boss_name).chest_npc_names() {
+            chest_owners.entry(chest).or_insert_with(|| boss_name.clone());
+        }
+    }
+    for chest in file_rule.chest_npc_names() {
+        chest_owners
+            .entry(chest)
+            .or_insert_with(|| file_boss_name.to_string());
+    }
 
     let mut fight_start_ms: i64 = 0;
     let mut fight_end_ms: i64 = 0;
@@ -1335,6 +1623,7 @@ fn analyze_jcl(
     // - 或"唐怀仁宝箱"NPC 出现
     let mut real_boss_dwid: i64 = 0;
     let mut real_boss_name: Option<String> = None;
+    let mut real_boss_rule: Option<BossKillRule> = None;
     let mut real_boss_fight_true_seen = false;
     let mut real_boss_fight_false_seen = false;
     // 真实 BOSS HP=0 信号（NPC_FIGHT_HINT fCurrentLife=0）
@@ -1376,77 +1665,20 @@ fn analyze_jcl(
     let mut same_name_dwids: std::collections::HashSet<i64> = std::collections::HashSet::new();
     let mut same_name_leave_scene_count: u32 = 0;
 
-    for line in reader.split(b'\n') {
-        let line_bytes: Vec<u8> = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
+    // BOSS 最低血量百分比（灭团进度），路径 A 取文件名 BOSS，路径 B 取真实 BOSS
+    let mut boss_min_hp_percent: Option<f64> = None;
 
-        // 按 TAB 分割列：CRC\tframe\tunix_ts\tclient_ms\tevent_type\t{LUA_DATA}
-        // 只需提取第3列（unix_ts）、第5列（event_type）、第6列（LUA_DATA）
-        let mut parts: Vec<&[u8]> = Vec::with_capacity(6);
-        let mut start = 0;
-        for (i, &b) in line_bytes.iter().enumerate() {
-            if b == b'\t' {
-                parts.push(&line_bytes[start..i]);
-                start = i + 1;
-                if parts.len() >= 5 {
-                    break;
-                }
-            }
-        }
-        if parts.len() >= 5 {
-            parts.push(&line_bytes[start..]);
-        }
-        if parts.len() < 6 {
-            continue;
-        }
+    // 队伍成员收集：dwID → 玩家信息，以及 PARTY_ADD_MEMBER 中出现的成员 dwID
+    let mut player_infos: HashMap<i64, JclPlayer> = HashMap::new();
+    let mut party_member_ids: HashSet<i64> = HashSet::new();
+    let mut real_boss_min_hp_percent: Option<f64> = None;
 
-        // 解析事件类型，处理相关类型（基于茗伊 MY_CombatLogs.lua 源码）：
-        // 1=FIGHT_TIME, 7=NPC_LEAVE_SCENE, 8=NPC_INFO, 9=NPC_FIGHT_HINT, 28=SYS_MSG_UI_OME_DEATH_NOTIFY
-        let event_type: u8 = match std::str::from_utf8(parts[4])
-            .ok()
-            .and_then(|s| s.parse().ok())
-        {
-            Some(t) => t,
-            None => continue,
-        };
-        if event_type != 1
-            && event_type != 7
-            && event_type != 8
-            && event_type != 9
-            && event_type != 28
-        {
-            continue;
-        }
+    for entry in reader {
+        let timestamp_sec = entry.timestamp_sec;
 
-        // 解析 Unix 时间戳（秒）
-        let timestamp_sec: i64 = match std::str::from_utf8(parts[2])
-            .ok()
-            .and_then(|s| s.parse().ok())
-        {
-            Some(t) => t,
-            None => continue,
-        };
-
-        // GBK 解码 LUA_DATA 部分
-        let lua_str = gbk.decode(parts[5]).0;
-        // 去除首尾花括号和空白
-        let lua_trimmed = lua_str.trim();
-        let lua_inner = lua_trimmed
-            .strip_prefix('{')
-            .and_then(|s| s.strip_suffix('}'))
-            .unwrap_or(lua_trimmed);
-        // 按逗号分割（简单分割，BOSS 名不含逗号）
-        let fields: Vec<&str> = lua_inner.split(',').collect();
-
-        match event_type {
-            1 => {
+        match entry.event {
+            JclEvent::FightTime { fighting: bfight, .. } => {
                 // FIGHT_TIME: { bFighting, szUUID, nDuring }
-                if fields.is_empty() {
-                    continue;
-                }
-                let bfight = fields[0].trim() == "true";
                 if bfight && fight_start_ms == 0 {
                     fight_start_ms = timestamp_sec * 1000;
                 }
@@ -1454,25 +1686,26 @@ fn analyze_jcl(
                     fight_end_ms = timestamp_sec * 1000;
                 }
                 // 无 BOSS 配置时，全局战斗信号也作为 BOSS 战斗信号
-                // 某些 BOSS（如白帝江关的赵八嫂）不产生 NPC_FIGHT_HINT 事件，
+                // 规则 fight_time_signal=true 的 BOSS（如白帝江关的赵八嫂）不产生 NPC_FIGHT_HINT 事件，
                 // 但 JCL 文件以该 BOSS 命名，说明游戏客户端认为是该 BOSS 的战斗，
                 // 因此全局 FIGHT_TIME 的 bFight=true 可作为 BOSS 进入战斗的信号。
-                if raid_bosses.is_empty() && bfight {
+                // 有 BOSS 配置时同样适用（旧版仅在无 BOSS 配置时使用，赵八嫂击杀会被判为拉托）。
+                if (file_rule.fight_time_signal || raid_bosses.is_empty()) && bfight {
                     boss_fight_true_seen = true;
                 }
             }
-            8 => {
-                // NPC_INFO: { dwID, szName, dwTemplateID, dwEmployer, nX, nY, nZ }
+            JclEvent::NpcInfo {
+                id: dwid,
+                name,
+                template_id,
+                face_direction: face_dir,
+                ..
+            } => {
+                // NPC_INFO: { dwID, szName, dwTemplateID, dwEmployer, nX, nY, nZ, nFaceDirection }
                 // 用途 1：在未找到 BOSS dwID 时，通过 template_id 匹配找到 BOSS 运行时 dwID
                 // 用途 2：检测"BOSS名+宝箱"NPC（击杀掉落的可靠信号，**精确匹配**配置 BOSS）
                 // 用途 3：当文件名 BOSS 不在配置中时，从 NPC 名中匹配 raid_bosses 配置的 BOSS
                 //        找到"真实 BOSS"dwID（如"须罗巨傀"JCL 中的"唐怀仁"）
-                if fields.len() < 3 {
-                    continue;
-                }
-                let dwid: i64 = fields[0].trim().parse().unwrap_or(0);
-                let name = fields[1].trim();
-                let template_id: i64 = fields[2].trim().parse().unwrap_or(0);
 
                 // 用途 1：通过 template_id 匹配文件名 BOSS 的 dwID
                 if boss_dwid == 0 && boss_template_id != 0 && template_id == boss_template_id {
@@ -1480,35 +1713,31 @@ fn analyze_jcl(
                 }
 
                 // 用途 3：当文件名 BOSS 不在配置中时，通过 NPC 名匹配配置中的"真实 BOSS"
+                // 有伴生规则时只匹配规则 BOSS（含别名），否则匹配任一配置 BOSS
                 if !file_boss_in_config && real_boss_dwid == 0 {
-                    for (boss_name, needle) in &needles {
-                        if needle.is_empty() || needle.len() > line_bytes.len() {
-                            continue;
-                        }
-                        // NPC 名为 "XXX"（带引号），需检查 name 是否等于或包含配置 BOSS 名
-                        let name_trimmed = name.trim_matches('"');
-                        if name_trimmed == *boss_name {
-                            real_boss_dwid = dwid;
-                            real_boss_name = Some((*boss_name).to_string());
-                            break;
+                    let matched_rule = match companion_rule {
+                        Some(rule) => rule.matches_name(&name).then(|| rule.clone()),
+                        None => {
+                            let npc_boss_name = boss_rules::canonical_boss_name(&name, boss_rules);
+                            raid_bosses
+                                .iter()
+                                .find(|(_, boss_name)| !boss_name.is_empty() && boss_name == npc_boss_name)
+                                .map(|(_, boss_name)| boss_rules::resolve_rule(boss_rules, boss_name))
                         }
+                    };
+                    if let Some(rule) = matched_rule {
+                        real_boss_dwid = dwid;
+                        real_boss_name = Some(rule.boss_name.clone());
+                        real_boss_rule = Some(rule);
                     }
                 }
 
-                // 用途 2：检测"BOSS名+宝箱"NPC（**精确匹配配置中的 BOSS**）
-                // 例如：NPC 名为 "笑妆娘宝箱" 时，提取前缀 "笑妆娘"，
-                //       若在 raid_bosses 配置中则记为对应 BOSS 的宝箱已出现
-                let name_trimmed = name.trim_matches('"');
-                if name_trimmed.ends_with("宝箱") {
-                    let prefix_len = name_trimmed.len() - "宝箱".len();
-                    let prefix = &name_trimmed[..prefix_len];
-                    // 检查前缀是否正好是配置中的某个 BOSS 名
-                    for (_boss_id, boss_name) in raid_bosses {
-                        if prefix == *boss_name {
-                            treasure_box_bosses.insert(boss_name.clone());
-                            break;
-                        }
-                    }
+                // 用途 2：检测 BOSS 宝箱 NPC（**精确匹配配置中的 BOSS**）
+                // 宝箱名默认为 "BOSS名宝箱"（如 "笑妆娘宝箱"），规则可指定其他宝箱名
+                if let Some(owner) = chest_owners.get(&name) {
+                    treasure_box_bosses.insert(owner.clone());
+                }
+                if let Some(prefix) = name.strip_suffix("宝箱") {
                     // 无 BOSS 配置时，用文件名 BOSS 名做模糊匹配
                     // 例如 "罗芬和胡汤宝箱" 能匹配文件名 BOSS "罗芬"
                     if raid_bosses.is_empty()
@@ -1520,22 +1749,21 @@ fn analyze_jcl(
                 }
 
                 // 用途 4：当文件名 BOSS 在配置中时，追踪所有同名 NPC 的 dwID（多阶段 BOSS）
-                // 某些 BOSS（如"唐醉"）有多个 templateId 的同名 NPC（分身/幻影），
+                // 部分 BOSS 有多个 templateId 的同名 NPC（分身/幻影，规则 clone_handling=sameName），
                 // 主 BOSS 被击杀后可能不触发 LEAVE_SCENE，但分身/幻影会离场。
-                if file_boss_in_config && name_trimmed == jcl_boss_name {
+                if file_boss_in_config
+                    && file_rule.clone_handling == CloneHandling::SameName
+                    && (name == jcl_boss_name || file_rule.matches_name(&name))
+                {
                     same_name_dwids.insert(dwid);
                 }
                 // 用途 5：nFaceDirection 辅助判定
-                // 茗伊源码确认 fields[7] 是 nFaceDirection（朝向方向），不是存活/死亡状态。
-                // 但实测发现：击杀时 face_dir=192 后不再回 0，团灭时 face_dir 192→0（BOSS 重置）。
+                // 茗伊源码确认第 8 个字段是 nFaceDirection（朝向方向），不是存活/死亡状态。
+                // 但实测发现：击杀时 face_dir 停在规则的 dead_face_direction（通用值 192）不再回 0，
+                // 团灭时 face_dir 从该值回到 0（BOSS 重置）。
                 // 在缺乏 DEATH_NOTIFY/HP=0 时，作为辅助区分击杀/团灭的信号。
-                let face_dir: i64 = if fields.len() >= 8 {
-                    fields[7].trim().parse().unwrap_or(-1)
-                } else {
-                    -1
-                };
                 if file_boss_in_config && boss_dwid != 0 && dwid == boss_dwid {
-                    if face_dir == 192 {
+                    if file_rule.dead_face_direction == Some(face_dir) {
                         boss_face_dir_dead = true;
                         if boss_face_dir_dead_ms == 0 {
                             boss_face_dir_dead_ms = timestamp_sec * 1000;
@@ -1545,7 +1773,9 @@ fn analyze_jcl(
                     }
                 }
                 if !file_boss_in_config && real_boss_dwid != 0 && dwid == real_boss_dwid {
-                    if face_dir == 192 {
+                    let real_dead_face_direction =
+                        real_boss_rule.as_ref().and_then(|rule| rule.dead_face_direction);
+                    if real_dead_face_direction == Some(face_dir) {
                         real_boss_face_dir_dead = true;
                         if real_boss_face_dir_dead_ms == 0 {
                             real_boss_face_dir_dead_ms = timestamp_sec * 1000;
@@ -1555,22 +1785,22 @@ fn analyze_jcl(
                     }
                 }
             }
-            9 => {
+            JclEvent::NpcFightHint {
+                id: dwid,
+                fighting: bfight,
+                current_life: hp,
+                max_life,
+                ..
+            } => {
                 // NPC_FIGHT_HINT: { dwID, bFight, fCurrentLife, fMaxLife, nCurrentMana, nMaxMana }
-                // 茗伊源码: D.InsertLog(LOG_TYPE.NPC_FIGHT_HINT, { dwID, bFight, fCurrentLife, fMaxLife, ... })
                 // 跟踪 BOSS 的战斗开始/结束信号 + HP=0 击杀信号
-                if fields.len() < 2 {
-                    continue;
-                }
-                let dwid: i64 = fields[0].trim().parse().unwrap_or(0);
-                let bfight = fields[1].trim() == "true";
                 // fCurrentLife=0 是可靠击杀信号（BOSS 血量归零）
-                let hp: f64 = if fields.len() >= 3 {
-                    fields[2].trim().parse().unwrap_or(-1.0)
+                let hp_zero = hp == 0.0;
+                let hp_percent = if hp >= 0.0 && max_life > 0.0 {
+                    Some((hp / max_life * 100.0).min(100.0))
                 } else {
-                    -1.0
+                    None
                 };
-                let hp_zero = hp == 0.0;
 
                 // 文件名 BOSS（小怪路径下是小怪 dwID）的 bFight 状态
                 if boss_dwid != 0 && dwid == boss_dwid {
@@ -1582,6 +1812,7 @@ fn analyze_jcl(
                     if hp_zero {
                         boss_hp_zero_seen = true;
                     }
+                    boss_min_hp_percent = min_hp_percent(boss_min_hp_percent, hp_percent);
                 }
                 // 无 BOSS 配置时，同名 NPC（不同 templateID 的分身/阶段）的 bFight 也算
                 // 例如水鬼有 templateID=103400 和 103848 两套同名 NPC，
@@ -1610,18 +1841,14 @@ fn analyze_jcl(
                     if hp_zero {
                         real_boss_hp_zero_seen = true;
                     }
+                    real_boss_min_hp_percent = min_hp_percent(real_boss_min_hp_percent, hp_percent);
                 }
             }
-            7 => {
+            JclEvent::NpcLeaveScene { id: dwid } => {
                 // NPC_LEAVE_SCENE: { dwID }
-                // 茗伊源码: D.InsertLog(LOG_TYPE.NPC_LEAVE_SCENE, { dwID })
                 // 主 BOSS LEAVE_SCENE 不可靠（拉托时也会离场），路径 A 不单独使用。
                 // 路径 B（小怪 JCL）使用：真实 BOSS 击杀时可能无 bFight=false，需 leave_scene 兜底。
-                // 同名 NPC 分身离场：需结合离场时间跨度区分击杀/团灭（见下方 clone_leave_signal）。
-                if fields.is_empty() {
-                    continue;
-                }
-                let dwid: i64 = fields[0].trim().parse().unwrap_or(0);
+                // 同名 NPC 分身离场：需结合主 BOSS 死亡迹象区分击杀/团灭（见下方 clone_leave_signal）。
 
                 // 文件名 BOSS 离开场景
                 if boss_dwid != 0 && dwid == boss_dwid {
@@ -1637,16 +1864,14 @@ fn analyze_jcl(
                     same_name_leave_scene_count += 1;
                 }
             }
-            28 => {
+            JclEvent::DeathNotify {
+                character_id: dead_dwid,
+                ..
+            } => {
                 // SYS_MSG_UI_OME_DEATH_NOTIFY: { dwCharacterID, dwKiller }
-                // 茗伊源码: D.InsertLog(LOG_TYPE.SYS_MSG_UI_OME_DEATH_NOTIFY, { dwCharacterID, dwKiller })
-                // 这是 JCL 中唯一的真正死亡事件，fields[0]=死亡目标ID
+                // 这是 JCL 中唯一的真正死亡事件
                 // 注意：多阶段 BOSS（如笑妆娘）有多个同名分身，分身被击杀时也会触发此事件，
                 //       需匹配 same_name_dwids 才能正确识别。
-                if fields.is_empty() {
-                    continue;
-                }
-                let dead_dwid: i64 = fields[0].trim().parse().unwrap_or(0);
                 // 主 BOSS 或同名分身死亡
                 if boss_dwid != 0
                     && (dead_dwid == boss_dwid
@@ -1659,6 +1884,25 @@ fn analyze_jcl(
                     real_boss_death_notify_seen = true;
                 }
             }
+            JclEvent::PlayerInfo {
+                id,
+                name,
+                force_id,
+                kungfu_id,
+                ..
+            } => {
+                player_infos.insert(
+                    id,
+                    JclPlayer {
+                        name,
+                        force_id,
+                        kungfu_id,
+                    },
+                );
+            }
+            JclEvent::PartyAddMember { member_id, .. } => {
+                party_member_ids.insert(member_id);
+            }
             _ => {}
         }
     }
@@ -1692,12 +1936,13 @@ fn analyze_jcl(
     //   Q1: JCL 中能找到配置 BOSS 吗？→ 否 → 小怪
     //   Q2: 配置 BOSS 进入战斗了吗？→ 否 → 小怪
     //   Q3: 双 BOSS 都死亡？→ 击杀
+    let mut signals: Vec<&'static str> = Vec::new();
     let is_kill = if file_boss_in_config {
         // 路径 A
         let treasure_ok = if raid_bosses.is_empty() {
             !treasure_box_bosses.is_empty()
         } else {
-            treasure_box_bosses.contains(jcl_boss_name)
+            treasure_box_bosses.contains(file_boss_name)
         };
         // clone_leave_signal: 分身离场 + 主 BOSS 有死亡迹象 + 之后无重生（无 BOSS 活动）。
         // 不再使用 clone_leave_span_sec>=120 阈值——该阈值过于特殊，
@@ -1720,6 +1965,19 @@ fn analyze_jcl(
             } else {
                 false
             };
+        push_signals(
+            &mut signals,
+            &[
+                (treasure_ok, "treasure_box"),
+                (boss_fight_true_seen, "boss_fight_started"),
+                (boss_death_notify_seen, "death_notify"),
+                (boss_hp_zero_seen, "hp_zero"),
+                (bfight_false_is_kill, "fight_false_without_leave"),
+                (clone_leave_signal, "clone_leave"),
+                (leave_scene_is_kill, "leave_scene_after_face_dir"),
+                (boss_face_dir_respawned, "face_dir_respawned"),
+            ],
+        );
         // death_ok: clone_leave_signal 不受 face_dir_respawned 影响（笑妆娘修复）
         let death_ok = boss_dwid != 0
             && boss_fight_true_seen
@@ -1731,6 +1989,7 @@ fn analyze_jcl(
         treasure_ok || death_ok
     } else {
         // 路径 B
+        signals.push("companion_jcl");
         if real_boss_dwid == 0 || !real_boss_fight_true_seen {
             false
         } else {
@@ -1759,7 +2018,26 @@ fn analyze_jcl(
                     || real_boss_fight_false_seen
                     || real_boss_face_dir_death
                     || real_boss_leave_scene_is_kill);
-            real_treasure_ok || (file_boss_died && real_boss_died)
+            // 规则 require_companion_death=false 时，伴生 NPC（文件名 BOSS）无需死亡
+            let require_companion_death = real_boss_rule
+                .as_ref()
+                .map(|rule| rule.require_companion_death)
+                .unwrap_or(true);
+            push_signals(
+                &mut signals,
+                &[
+                    (real_treasure_ok, "real_treasure_box"),
+                    (file_boss_died, "companion_died"),
+                    (!require_companion_death, "companion_death_not_required"),
+                    (real_boss_death_notify_seen, "real_death_notify"),
+                    (real_boss_hp_zero_seen, "real_hp_zero"),
+                    (real_boss_fight_false_seen, "real_fight_false"),
+                    (real_boss_face_dir_death, "real_face_dir_death"),
+                    (real_boss_leave_scene_is_kill, "real_leave_scene_after_face_dir"),
+                    (real_boss_face_dir_respawned, "real_face_dir_respawned"),
+                ],
+            );
+            real_treasure_ok || ((file_boss_died || !require_companion_death) && real_boss_died)
         }
     };
 
@@ -1770,19 +2048,43 @@ fn analyze_jcl(
     //   * 否则返回 None（小怪 JCL，effective boss name 回退到文件名）
     //   注意：不以对话/NPC_INFO 文本作为依据，避免出场对话 JCL 被误判为该 BOSS 的战斗。
     let final_boss_name = if file_boss_in_config {
-        Some(jcl_boss_name.to_string())
+        Some(file_boss_name.to_string())
     } else if real_boss_fight_true_seen {
         real_boss_name.clone()
     } else {
         None
     };
 
-    Some(JclAnalysis {
-        boss_name: final_boss_name,
-        fight_start_ms,
-        fight_end_ms,
-        is_kill,
-    })
+    let boss_hp_percent = if file_boss_in_config {
+        boss_min_hp_percent
+    } else {
+        real_boss_min_hp_percent
+    };
+
+    Some((
+        JclAnalysis {
+            boss_name: final_boss_name,
+            fight_start_ms,
+            fight_end_ms,
+            is_kill,
+            boss_hp_percent,
+            players: roster::party_players(player_infos, &party_member_ids),
+        },
+        signals,
+    ))
+}
+
+/// 收集成立的击杀判定信号
+fn push_signals(signals: &mut Vec<&'static str>, flags: &[(bool, &'static str)]) {
+    signals.extend(flags.iter().filter(|(fired, _)| *fired).map(|(_, name)| *name));
+}
+
+/// 取两个血量百分比中较低者（None 表示未观测到）
+fn min_hp_percent(current: Option<f64>, observed: Option<f64>) -> Option<f64> {
+    match (current, observed) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
 }
 
 /// BOSS 击杀奖励（单位：金）。JX3 金币单位为 砖/金/银/铜，
@@ -1798,74 +2100,25 @@ const BOSS_KILL_REWARD_GOLD: i64 = 10;
 /// （`name="Text_Gold"` / `name="Text_Silver"` / `name="Text_Copper"`）
 /// 才能获得真实的金/银/铜值。
 ///
-/// 返回 (金, 银, 铜)，解析失败返回 None。
-fn parse_money_from_msg(msg: &str) -> Option<(i64, i64, i64)> {
-    // JX3 chatlog 的 msg 字段为 XML 格式，金额通过多个 text 节点展示：
-    //   text="1"    name="Text_GoldB"   → 万金部分（≥10000金时出现）
-    //   text="6082" name="Text_Gold"    → 金部分（<10000金）
-    //   text="0"    name="Text_Silver"  → 银
-    //   text="0"    name="Text_Copper" → 铜
-    // 实际金额 = GoldB × 10000 + Gold
-    let goldb = GOLDB_RE
-        .captures(msg)
-        .and_then(|c| c[1].parse::<i64>().ok())
-        .unwrap_or(0);
-    let gold = GOLD_RE
-        .captures(msg)
-        .and_then(|c| c[1].parse::<i64>().ok())
-        .unwrap_or(0);
-    let silver = SILVER_RE
-        .captures(msg)
-        .and_then(|c| c[1].parse::<i64>().ok())
-        .unwrap_or(0);
-    let copper = COPPER_RE
-        .captures(msg)
-        .and_then(|c| c[1].parse::<i64>().ok())
-        .unwrap_or(0);
-
-    // 万金部分合并到金
-    let total_gold = goldb * 10000 + gold;
-
-    // 如果三个分量都为 0 且没有匹配到任何 Text_Gold 标记，说明 msg 格式不符
-    if total_gold == 0 && silver == 0 && copper == 0
-        && !msg.contains("Text_Gold")
-    {
-        return None;
-    }
-
-    Some((total_gold, silver, copper))
+/// 解析失败返回 None。
+fn parse_money_from_msg(msg: &str) -> Option<Money> {
+    Money::parse_chatlog_msg(msg)
 }
 
-/// 1 金砖 = 10000 金（JX3 标准换算）
-const GOLD_PER_BRICK: i64 = 10000;
-
-/// 从支出金额字符串解析总金币数。
+/// 从支出金额字符串解析金额。
 ///
 /// 支出消息格式：`[角色名·服务器]花费[XX金砖XX金]购买了[物品名]`
 /// 金额部分可能为：
-///   - `2000金` → 2000
-///   - `1金砖` → 10000
-///   - `1金砖6000金` → 16000
+///   - `2000金` → 2000金
+///   - `1金砖` → 10000金
+///   - `1金砖6000金` → 16000金
 ///   - `0金` → 0
-fn parse_expense_amount(amount_str: &str) -> i64 {
-    let mut total = 0i64;
-
-    // 先提取金砖部分
-    if let Some(caps) = BRICK_RE.captures(amount_str) {
-        total += caps[1].parse::<i64>().unwrap_or(0) * GOLD_PER_BRICK;
-    }
-    
-    // 移除“X金砖”后，提取剩余的金部分
-    let remaining = BRICK_RE.replace_all(amount_str, "");
-    if let Some(caps) = EXPENSE_GOLD_RE.captures(&remaining) {
-        total += caps[1].parse::<i64>().unwrap_or(0);
-    }
-
-    total
+pub(crate) fn parse_expense_amount(amount_str: &str) -> Money {
+    Money::parse_text(amount_str).unwrap_or_default()
 }
 
 /// 去除角色名中的空格（全角/半角），用于 chatlog 匹配
-fn normalize_role_name(name: &str) -> String {
+pub(crate) fn normalize_role_name(name: &str) -> String {
     name.replace(' ', "").replace('\u{3000}', "")
 }
 
@@ -1880,7 +2133,7 @@ fn normalize_role_name(name: &str) -> String {
 ///    正则提取方括号内的物品名，作为 drops 列表。
 /// 2. **金币收入**（type='MSG_MONEY'，text 以"你获得："开头）：
 ///    - BOSS 击杀奖励（Gold=10, Silver=0, Copper=0）不计入收入，仅计数 boss_kill_count。
-///    - 其余"你获得："金币累加为 other_income_gold（排除 10 金）。
+///    - 其余"你获得："金币累加为 other_income（铜精度，排除 10 金）。
 /// 3. **拍团底薪**（type='MSG_ROOM' 或 'MSG_WHISPER'，text 含"每人底薪：XXX金"）：
 ///    若存在则 base_salary = Some(XXX)，收入优先取底薪值。
 /// 4. **支出**（type='MSG_ROOM' 或 'MSG_WHISPER'，text 含"[角色名]花费[XX金砖XX金]购买了[物品名]"）：
@@ -1889,10 +2142,11 @@ fn normalize_role_name(name: &str) -> String {
 ///    注意：仅"花费了...购买了"格式计为支出；"记录给了"格式仅提取物品名加入 drops，不计入支出
 ///    （团长分配记录不代表角色实际购买了该物品）。
 ///
-/// 返回 (drops, base_salary, other_income_gold, expense_gold, boss_kill_count, income_records, boss_kill_times, purchased_items)。
-/// income_records 为非 BOSS 10金的收入记录列表 (time_sec, gold)，用于精确收入匹配。
+/// 返回 `ChatlogExtraction`：
+/// income_records 为非 BOSS 10金的收入记录列表 (time_sec, 金额)，用于精确收入匹配。
 /// boss_kill_times 为每个 BOSS 击杀（10金收入）的 time_sec 列表，用于击杀数验证。
 /// purchased_items 为当前角色花钱购买的物品名列表（来自"花费了...购买了"格式消息）。
+/// line_items 为每条参与计算的消息明细（频道、时间、买家、金额、是否计入）。
 /// 调用方根据 base_salary 是否存在决定最终收入：
 ///   - 有底薪 → 在 income_records 中找 gold >= base_salary 的第一条记录，用该 gold 作为收入
 ///   - 无底薪 → 在 income_records 中找 time_sec > last_jcl_sec 的第一条记录（拍卖工资只发一次）
@@ -1903,9 +2157,9 @@ fn extract_drops_from_chatlog(
     start_time_ms: i64,
     end_time_ms: i64,
     role_name: &str,
-) -> Result<(Vec<String>, Option<i64>, i64, i64, u32, Vec<(i64, i64)>, Vec<i64>, Vec<String>), String> {
+) -> Result<ChatlogExtraction, String> {
     if !chatlog_path.exists() {
-        return Ok((Vec::new(), None, 0, 0, 0, Vec::new(), Vec::new(), Vec::new()));
+        return Ok(ChatlogExtraction::default());
     }
 
     let conn = Connection::open(chatlog_path)
@@ -1921,7 +2175,7 @@ fn extract_drops_from_chatlog(
     // 支出消息可能出现在 MSG_ROOM（房间频道）或 MSG_WHISPER（密语频道），两种都需要查询
     let mut stmt = conn
         .prepare(
-            "SELECT type, text, msg, time FROM ChatLog \
+            "SELECT type, text, msg, time, rowid FROM ChatLog \
              WHERE time >= ?1 AND time <= ?2 AND ( \
                type = 'MSG_ITEM' \
                OR (type = 'MSG_MONEY' AND text LIKE '你获得：%') \
@@ -1937,6 +2191,7 @@ fn extract_drops_from_chatlog(
     let rows = stmt
         .query_map(params![start_sec, end_sec], |row| {
             Ok(ChatlogRecord {
+                row_key: ChatlogRowKey::new(chatlog_path, row.get::<_, i64>(4)?),
                 msg_type: row.get::<_, String>(0)?,
                 text: row.get::<_, String>(1)?,
                 msg: row.get::<_, String>(2)?,
@@ -1956,10 +2211,195 @@ fn extract_drops_from_chatlog(
     Ok(extract_drops_from_records(&records, start_time_ms, end_time_ms, role_name))
 }
 
+/// chatlog 文件的增量读取状态（按文件缓存，跨扫描复用）
+///
+/// 后台轮询每次扫描都需要 CD 窗口内的全部相关消息。首次读取整个窗口，之后只读取高水位之后的新行：
+/// - 文件（含 `-wal`）修改时间与大小未变化时不打开数据库
+/// - 新行按 `time >= 高水位时间` 查询，重新读到的高水位那一秒的行按内容哈希计数跳过
+///   （同一秒内内容相同的多行按出现次数保留，只跳过上次已读过的次数）
+#[derive(Default)]
+struct ChatlogTail {
+    /// 已加载记录的时间起点（秒），None 表示尚未加载
+    loaded_from_sec: Option<i64>,
+    /// 高水位：已读取行的最大 time（秒）
+    high_water_sec: i64,
+    /// 高水位同一秒内已读取行的哈希及出现次数
+    high_water_hashes: HashMap<u64, usize>,
+    /// 最近一次使用时间，长时间未使用的条目被淘汰
+    last_used: Option<std::time::Instant>,
+    /// 上次读取记录时的文件指纹
+    rows_fingerprint: Option<(i64, u64)>,
+    /// 上次读取 ChatInfo 时间范围时的文件指纹
+    range_fingerprint: Option<(i64, u64)>,
+    /// ChatInfo 时间范围（秒）
+    time_range: Option<(i64, i64)>,
+    /// 已加载的相关记录（按 time_sec 升序）
+    records: Vec<ChatlogRecord>,
+}
+
+static CHATLOG_TAILS: OnceLock<Mutex<HashMap<PathBuf, ChatlogTail>>> = OnceLock::new();
+
+/// chatlog 增量缓存的闲置淘汰时间（账号不再扫描、文件被删除或轮换后释放内存）
+const CHATLOG_TAIL_IDLE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+fn chatlog_tails() -> &'static Mutex<HashMap<PathBuf, ChatlogTail>> {
+    CHATLOG_TAILS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 取出文件的增量读取状态（同时淘汰闲置条目）
+///
+/// 只在取出 / 放回时短暂持锁，文件元数据与 SQLite 读取在锁外进行。
+/// 同一账号的扫描由账号级扫描锁串行，不会同时取出同一文件的状态。
+fn take_chatlog_tail(path: &Path) -> ChatlogTail {
+    let mut tails = chatlog_tails().lock().unwrap_or_else(|e| e.into_inner());
+    tails.retain(|_, tail| tail.last_used.is_some_and(|used| used.elapsed() < CHATLOG_TAIL_IDLE));
+    tails.remove(path).unwrap_or_default()
+}
+
+fn store_chatlog_tail(path: &Path, mut tail: ChatlogTail) {
+    tail.last_used = Some(std::time::Instant::now());
+    chatlog_tails()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), tail);
+}
+
+/// chatlog 文件指纹：(主文件与 -wal 的最新修改时间毫秒, 两者大小之和)，文件不存在时返回 None
+fn chatlog_fingerprint(path: &Path) -> Option<(i64, u64)> {
+    let wal_path = PathBuf::from(format!("{}-wal", path.display()));
+    let mut fingerprint: Option<(i64, u64)> = None;
+    for file in [path, wal_path.as_path()] {
+        let Ok(meta) = std::fs::metadata(file) else {
+            continue;
+        };
+        let modified_ms = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let (latest, len) = fingerprint.unwrap_or((0, 0));
+        fingerprint = Some((latest.max(modified_ms), len + meta.len()));
+    }
+    fingerprint
+}
+
+fn chatlog_row_hash(record: &ChatlogRecord) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    record.msg_type.hash(&mut hasher);
+    record.text.hash(&mut hasher);
+    record.msg.hash(&mut hasher);
+    record.time_sec.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 查询 chatlog 文件中 time >= from_sec 的相关记录（物品分配、金币获得、底薪、支出、分配记录）
+fn query_relevant_chatlog_rows(path: &Path, from_sec: i64) -> Result<Vec<ChatlogRecord>, String> {
+    let conn = Connection::open(path)
+        .map_err(|e| format!("打开 chatlog 失败: {} - {}", path.display(), e))?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT type, text, msg, time, rowid FROM ChatLog \
+             WHERE time >= ?1 AND ( \
+               type = 'MSG_ITEM' \
+               OR (type = 'MSG_MONEY' AND text LIKE '你获得：%') \
+               OR ((type = 'MSG_ROOM' OR type = 'MSG_WHISPER') AND ( \
+                 text LIKE '%每人底薪%' \
+                 OR (text LIKE '%花费[%' AND text LIKE '%购买了%') \
+                 OR text LIKE '%记录给了%' \
+               )) \
+             ) ORDER BY time",
+        )
+        .map_err(|e| format!("chatlog prepare 失败: {} - {}", path.display(), e))?;
+    let rows = stmt
+        .query_map(params![from_sec], |row| {
+            Ok(ChatlogRecord {
+                row_key: ChatlogRowKey::new(path, row.get::<_, i64>(4)?),
+                msg_type: row.get::<_, String>(0)?,
+                text: row.get::<_, String>(1)?,
+                msg: row.get::<_, String>(2)?,
+                time_sec: row.get::<_, i64>(3)?,
+            })
+        })
+        .map_err(|e| format!("chatlog 查询失败: {} - {}", path.display(), e))?;
+    Ok(rows.flatten().collect())
+}
+
+/// 读取 chatlog 文件在 start_sec 之后的相关记录到 tail，返回新读取的行数
+///
+/// 已加载范围覆盖 start_sec 时只读取高水位之后的新行，否则从 start_sec 整体重新加载
+/// （整体加载不去重，内容相同的行全部保留）。
+/// 加载起点前移（进入新的 CD 窗口）时丢弃窗口外的旧记录。
+fn read_chatlog_tail(tail: &mut ChatlogTail, path: &Path, start_sec: i64) -> Result<usize, String> {
+    let incremental = tail.loaded_from_sec.is_some_and(|from| from <= start_sec);
+    let (rows, mut already_read) = if incremental {
+        let rows = query_relevant_chatlog_rows(path, tail.high_water_sec)?;
+        (rows, std::mem::take(&mut tail.high_water_hashes))
+    } else {
+        let rows = query_relevant_chatlog_rows(path, start_sec)?;
+        tail.records.clear();
+        tail.high_water_sec = i64::MIN;
+        tail.loaded_from_sec = Some(start_sec);
+        (rows, HashMap::new())
+    };
+
+    let previous_high_water = tail.high_water_sec;
+    let mut added = 0usize;
+    for row in rows {
+        if row.time_sec < previous_high_water {
+            continue;
+        }
+        // 只有重新读到的上次高水位那一秒需要跳过已读过的行
+        if row.time_sec == previous_high_water {
+            if let Some(count) = already_read.get_mut(&chatlog_row_hash(&row)).filter(|c| **c > 0) {
+                *count -= 1;
+                continue;
+            }
+        }
+        tail.records.push(row);
+        added += 1;
+    }
+
+    // 重新统计高水位那一秒已读取的行（records 按 time_sec 升序）
+    tail.high_water_hashes.clear();
+    if let Some(last) = tail.records.last() {
+        tail.high_water_sec = last.time_sec;
+    }
+    for record in tail.records.iter().rev().take_while(|r| r.time_sec == tail.high_water_sec) {
+        *tail.high_water_hashes.entry(chatlog_row_hash(record)).or_insert(0) += 1;
+    }
+
+    if tail.loaded_from_sec.is_some_and(|from| from < start_sec) {
+        tail.records.retain(|r| r.time_sec >= start_sec);
+        tail.loaded_from_sec = Some(start_sec);
+    }
+    Ok(added)
+}
+
+/// 查询 chatlog 文件的 ChatInfo 时间范围（文件未变化时复用缓存，不打开数据库）
+fn cached_chatlog_time_ranges(chatlog_files: &[PathBuf]) -> HashMap<PathBuf, Option<(i64, i64)>> {
+    chatlog_files
+        .iter()
+        .map(|path| {
+            let fingerprint = chatlog_fingerprint(path);
+            let mut tail = take_chatlog_tail(path);
+            if fingerprint.is_none() || tail.range_fingerprint != fingerprint {
+                tail.time_range = get_chatlog_time_range(path);
+                tail.range_fingerprint = fingerprint;
+            }
+            let time_range = tail.time_range;
+            store_chatlog_tail(path, tail);
+            (path.clone(), time_range)
+        })
+        .collect()
+}
+
 /// 预加载账号下所有 chatlog 文件中的相关记录到内存
 ///
 /// 在 scan_raid_drops_with_raids 入口处调用一次，避免后续每个副本实例重复打开
 /// SQLite 连接和全表扫描。返回按 time_sec 升序排序的记录列表。
+/// 每个文件的记录增量缓存在 `ChatlogTail` 中，文件未变化时不打开数据库。
 ///
 /// 时间范围：[scan_start_ms, scan_end_ms + 2h]，2h 缓冲用于覆盖最后一个副本实例
 /// 的 chatlog_end 扩展（last_jcl_time + 2h 或 now_ms）。
@@ -1977,63 +2417,54 @@ fn preload_chatlog_records(
 
     let mut all_records: Vec<ChatlogRecord> = Vec::new();
     let mut total_files_queried = 0u32;
+    let mut unchanged_files = 0u32;
+    let mut new_rows = 0usize;
 
     for path in chatlog_files {
         // 用 chatlog_range_cache 过滤不覆盖预加载范围的文件
         if !chatlog_file_covers_range_cached(path, scan_start_ms, preload_end_ms, chatlog_range_cache) {
             continue;
         }
-        total_files_queried += 1;
-
-        let conn = match Connection::open(path) {
-            Ok(c) => c,
-            Err(e) => {
-                log::warn!("[DropScanner] 预加载打开 chatlog 失败: {} - {}", path.display(), e);
-                continue;
-            }
-        };
-
-        let mut stmt = match conn.prepare(
-            "SELECT type, text, msg, time FROM ChatLog \
-             WHERE time >= ?1 AND time <= ?2 AND ( \
-               type = 'MSG_ITEM' \
-               OR (type = 'MSG_MONEY' AND text LIKE '你获得：%') \
-               OR ((type = 'MSG_ROOM' OR type = 'MSG_WHISPER') AND ( \
-                 text LIKE '%每人底薪%' \
-                 OR (text LIKE '%花费[%' AND text LIKE '%购买了%') \
-                 OR text LIKE '%记录给了%' \
-               )) \
-             )",
-        ) {
-            Ok(s) => s,
-            Err(e) => {
-                log::warn!("[DropScanner] 预加载 prepare 失败: {} - {}", path.display(), e);
-                continue;
-            }
-        };
-
-        let rows = stmt.query_map(params![preload_start_sec, preload_end_sec], |row| {
-            Ok(ChatlogRecord {
-                msg_type: row.get::<_, String>(0)?,
-                text: row.get::<_, String>(1)?,
-                msg: row.get::<_, String>(2)?,
-                time_sec: row.get::<_, i64>(3)?,
-            })
-        });
 
-        if let Ok(rows) = rows {
-            for row in rows.flatten() {
-                all_records.push(row);
+        let fingerprint = chatlog_fingerprint(path);
+        let mut tail = take_chatlog_tail(path);
+        let covered = tail.loaded_from_sec.is_some_and(|from| from <= preload_start_sec);
+        if covered && fingerprint.is_some() && tail.rows_fingerprint == fingerprint {
+            unchanged_files += 1;
+        } else {
+            total_files_queried += 1;
+            match read_chatlog_tail(&mut tail, path, preload_start_sec) {
+                Ok(added) => {
+                    new_rows += added;
+                    tail.rows_fingerprint = fingerprint;
+                }
+                Err(e) => {
+                    // 读取失败不更新指纹，下次扫描重试
+                    log::warn!("[DropScanner] 预加载 chatlog 失败: {}", e);
+                    tail.rows_fingerprint = None;
+                    store_chatlog_tail(path, tail);
+                    continue;
+                }
             }
         }
+
+        all_records.extend(
+            tail.records
+                .iter()
+                .filter(|r| r.time_sec >= preload_start_sec && r.time_sec <= preload_end_sec)
+                .cloned(),
+        );
+        store_chatlog_tail(path, tail);
     }
 
     // 按 time_sec 升序排序，支持后续二分查找
     all_records.sort_by_key(|r| r.time_sec);
 
     log::info!(
-        "[DropScanner] chatlog 预加载完成: 查询 {} 个文件, 共 {} 条记录, 范围 [{}~{}+2h]",
+        "[DropScanner] chatlog 预加载完成: 查询 {} 个文件（新增 {} 条）, 未变化 {} 个文件, 共 {} 条记录, 范围 [{}~{}+2h]",
         total_files_queried,
+        new_rows,
+        unchanged_files,
         all_records.len(),
         scan_start_ms,
         scan_end_ms
@@ -2046,12 +2477,13 @@ fn preload_chatlog_records(
 ///
 /// 这是 extract_drops_from_chatlog 的核心逻辑，接受预加载的记录列表，
 /// 通过二分查找定位时间范围内的记录，避免重复打开 SQLite 连接。
+/// 每条参与计算的消息都会记录到 `line_items`，供待确认记录审核时展示金额来源。
 fn extract_drops_from_records(
     records: &[ChatlogRecord],
     start_time_ms: i64,
     end_time_ms: i64,
     role_name: &str,
-) -> (Vec<String>, Option<i64>, i64, i64, u32, Vec<(i64, i64)>, Vec<i64>, Vec<String>) {
+) -> ChatlogExtraction {
     let start_sec = start_time_ms / 1000;
     let end_sec = end_time_ms / 1000;
 
@@ -2061,24 +2493,28 @@ fn extract_drops_from_records(
     let relevant = &records[start_idx..end_idx];
 
     let mut drops_set: HashSet<String> = HashSet::new();
-    let mut base_salary: Option<i64> = None;
-    // 非底薪的金币收入（排除 BOSS 10金），以铜为单位累加
-    let mut other_income_copper: i64 = 0;
-    let mut expense_gold: i64 = 0;
+    let mut base_salary: Option<Money> = None;
+    // 非底薪的金币收入（排除 BOSS 10金）
+    let mut other_income = Money::ZERO;
+    let mut expense = Money::ZERO;
     let mut item_count = 0u32;
     let mut money_count = 0u32;
     let mut boss_kill_count = 0u32;
     // 每个 BOSS 击杀（10金收入）的 time_sec 列表，用于击杀数验证
     let mut boss_kill_times: Vec<i64> = Vec::new();
     // 非 BOSS 10金的收入记录列表：(time_sec, gold)，用于精确收入匹配
-    let mut income_records: Vec<(i64, i64)> = Vec::new();
+    let mut income_records: Vec<(i64, Money)> = Vec::new();
+    // 收支来源明细
+    let mut line_items: Vec<ChatlogLineItem> = Vec::new();
+    // 最后一条底薪消息在 line_items 中的下标（之前的底薪消息被覆盖）
+    let mut salary_line_idx: Option<usize> = None;
 
     let target_name = normalize_role_name(role_name);
 
     // 去重：记录已通过"花费购买了"格式检测到的支出 (物品名, 金额, 时间窗口)
     // 用于避免同一笔交易在 MSG_ROOM 和 MSG_WHISPER 两个频道中重复计算
     // 时间窗口：30秒内的相同物品+金额视为同一笔交易
-    let mut expense_dedup: HashSet<(String, i64, i64)> = HashSet::new();
+    let mut expense_dedup: HashSet<(String, Money, i64)> = HashSet::new();
 
     // 当前角色花钱购买的物品名集合（用于 notes 显示）
     let mut purchased_items_set: HashSet<String> = HashSet::new();
@@ -2088,40 +2524,77 @@ fn extract_drops_from_records(
         let text = &record.text;
         let _msg = &record.msg;
         let time_sec = record.time_sec;
+        let line_item = |kind: ChatlogItemKind| ChatlogLineItem {
+            kind,
+            time_sec,
+            channel: msg_type.clone(),
+            text: text.clone(),
+            item_name: None,
+            buyer: None,
+            amount: None,
+            counted: true,
+            note: None,
+            row_key: Some(record.row_key),
+        };
 
         if msg_type == "MSG_ITEM" {
             // 提取分配的物品名
             if let Some(caps) = ITEM_RE.captures(text) {
                 let item_name = caps[1].to_string();
+                line_items.push(ChatlogLineItem {
+                    item_name: Some(item_name.clone()),
+                    buyer: ITEM_RECEIVER_RE.captures(text).map(|c| c[1].to_string()),
+                    ..line_item(ChatlogItemKind::ItemDrop)
+                });
                 drops_set.insert(item_name);
                 item_count += 1;
             }
         } else if msg_type == "MSG_MONEY" {
             // SQL 已过滤 text LIKE '你获得：%'，此处直接解析金额
             // 解析 msg 字段获取真实的金/银/铜值
-            if let Some((gold, silver, copper)) = parse_money_from_msg(_msg) {
+            if let Some(money) = parse_money_from_msg(_msg) {
                 money_count += 1;
                 // BOSS 击杀奖励：固定 10 金，不计入收入，仅计数
-                if gold == BOSS_KILL_REWARD_GOLD && silver == 0 && copper == 0 {
+                if money == Money::from_gold(BOSS_KILL_REWARD_GOLD) {
                     boss_kill_count += 1;
                     boss_kill_times.push(time_sec);
+                    line_items.push(ChatlogLineItem {
+                        amount: Some(money),
+                        counted: false,
+                        note: Some("BOSS 击杀奖励，不计入收入".to_string()),
+                        ..line_item(ChatlogItemKind::BossReward)
+                    });
                 } else {
-                    // 其他收入累加（以铜为单位）
-                    other_income_copper += gold * 10000 + silver * 100 + copper;
+                    // 其他收入累加（铜精度）
+                    other_income += money;
                     // 记录非 BOSS 10金的收入记录，用于精确收入匹配
-                    income_records.push((time_sec, gold));
+                    income_records.push((time_sec, money));
+                    line_items.push(ChatlogLineItem {
+                        amount: Some(money),
+                        ..line_item(ChatlogItemKind::Income)
+                    });
                 }
             }
         } else if msg_type == "MSG_ROOM" || msg_type == "MSG_WHISPER" {
             // 拍团底薪/支出消息可能出现在房间频道(MSG_ROOM)或密语频道(MSG_WHISPER)
             // 拍团底薪消息：取最后一条（最终分配金额）
             if let Some(caps) = SALARY_RE.captures(text) {
-                let salary: i64 = caps[1].parse().unwrap_or(0);
+                let salary = Money::parse_text(&caps[1]).unwrap_or(Money::ZERO);
                 base_salary = Some(salary);
+                if let Some(prev_idx) = salary_line_idx {
+                    line_items[prev_idx].counted = false;
+                    line_items[prev_idx].note = Some("被后续底薪消息覆盖".to_string());
+                }
+                salary_line_idx = Some(line_items.len());
+                line_items.push(ChatlogLineItem {
+                    amount: Some(salary),
+                    ..line_item(ChatlogItemKind::Salary)
+                });
             }
             // 支出消息（直接购买格式）：[角色名]花费[金额]购买了[物品名]
             // 这是唯一确认的支出格式，"记录给了"只是团长分配记录不代表实际购买
-            if let Some(caps) = EXPENSE_MSG_RE.captures(text) {
+            let expense_caps = EXPENSE_MSG_RE.captures(text);
+            if let Some(caps) = &expense_caps {
                 let buyer_name = normalize_role_name(&caps[1]);
                 // 匹配角色名（buyer_name 格式为"角色名·服务器"，检查前缀）
                 if buyer_name == target_name
@@ -2129,22 +2602,33 @@ fn extract_drops_from_records(
                 {
                     let amount = parse_expense_amount(&caps[2]);
                     let item_name = caps[3].to_string();
+                    let mut expense_line = ChatlogLineItem {
+                        item_name: Some(item_name.clone()),
+                        buyer: Some(caps[1].to_string()),
+                        amount: Some(amount),
+                        ..line_item(ChatlogItemKind::Expense)
+                    };
                     // 去重：同一笔交易可能在 MSG_ROOM 和 MSG_WHISPER 两个频道都出现
                     let time_bucket = time_sec / 30;
                     let dedup_key = (item_name.clone(), amount, time_bucket);
-                    if !expense_dedup.contains(&dedup_key) {
-                        // 还需检查 ±1 个时间窗口（两条频道消息可能有几秒偏差）
-                        let prev_bucket = (item_name.clone(), amount, time_bucket - 1);
-                        let next_bucket = (item_name.clone(), amount, time_bucket + 1);
-                        if !expense_dedup.contains(&prev_bucket) && !expense_dedup.contains(&next_bucket) {
-                            expense_gold += amount;
-                            expense_dedup.insert(dedup_key);
-                            // 记录购买的物品名（用于 notes 显示）
-                            purchased_items_set.insert(item_name.clone());
-                            // 当前角色购买的物品也加入 drops（属于副本掉落物分配）
-                            drops_set.insert(item_name);
-                        }
+                    // 还需检查 ±1 个时间窗口（两条频道消息可能有几秒偏差）
+                    let prev_bucket = (item_name.clone(), amount, time_bucket - 1);
+                    let next_bucket = (item_name.clone(), amount, time_bucket + 1);
+                    if !expense_dedup.contains(&dedup_key)
+                        && !expense_dedup.contains(&prev_bucket)
+                        && !expense_dedup.contains(&next_bucket)
+                    {
+                        expense += amount;
+                        expense_dedup.insert(dedup_key);
+                        // 记录购买的物品名（用于 notes 显示）
+                        purchased_items_set.insert(item_name.clone());
+                        // 当前角色购买的物品也加入 drops（属于副本掉落物分配）
+                        drops_set.insert(item_name);
+                    } else {
+                        expense_line.counted = false;
+                        expense_line.note = Some("重复消息，已去重".to_string());
                     }
+                    line_items.push(expense_line);
                 }
             }
             // 团长分配记录和拍卖物品名提取仅在 MSG_ROOM（团队频道）处理
@@ -2155,12 +2639,27 @@ fn extract_drops_from_records(
                 // 仅提取物品名加入 drops（掉落物记录），不计入支出（分配不等于实际购买）
                 if let Some(caps) = ALLOCATE_TO_RE.captures(text) {
                     let item_name = caps[2].to_string();
+                    line_items.push(ChatlogLineItem {
+                        item_name: Some(item_name.clone()),
+                        buyer: Some(caps[4].to_string()),
+                        amount: Some(parse_expense_amount(&caps[3])),
+                        counted: false,
+                        note: Some("团长分配记录，不计入支出".to_string()),
+                        ..line_item(ChatlogItemKind::Allocation)
+                    });
                     drops_set.insert(item_name);
                 }
                 // 拍卖物品名提取：MSG_ROOM 中的"购买了[物品名]"都是副本掉落物分配
                 // （包括帮别人购买的情况，用于检测玄晶等稀有掉落）
                 if let Some(caps) = PURCHASED_ITEM_RE.captures(text) {
                     let item_name = caps[1].to_string();
+                    line_items.push(ChatlogLineItem {
+                        item_name: Some(item_name.clone()),
+                        buyer: expense_caps.as_ref().map(|c| c[1].to_string()),
+                        amount: expense_caps.as_ref().map(|c| parse_expense_amount(&c[2])),
+                        counted: false,
+                        ..line_item(ChatlogItemKind::AuctionItem)
+                    });
                     drops_set.insert(item_name);
                 }
             }
@@ -2171,21 +2670,29 @@ fn extract_drops_from_records(
     let drops: Vec<String> = drops_set.into_iter().collect();
     let purchased_items: Vec<String> = purchased_items_set.into_iter().collect();
 
-    // 铜转金（整数，截断银铜部分）
-    let other_income_gold = other_income_copper / 10000;
-
     log::info!(
-        "[DropScanner] chatlog 提取完成: {} 个物品分配, {} 条金币获得, 底薪={:?}, 其他收入 {} 金, 支出 {} 金, BOSS击杀奖励 {} 次, 购买物品 {} 件",
+        "[DropScanner] chatlog 提取完成: {} 个物品分配, {} 条金币获得, 底薪={:?}, 其他收入 {}, 支出 {}, BOSS击杀奖励 {} 次, 购买物品 {} 件, 明细 {} 条",
         item_count,
         money_count,
         base_salary,
-        other_income_gold,
-        expense_gold,
+        other_income,
+        expense,
         boss_kill_count,
-        purchased_items.len()
+        purchased_items.len(),
+        line_items.len()
     );
 
-    (drops, base_salary, other_income_gold, expense_gold, boss_kill_count, income_records, boss_kill_times, purchased_items)
+    ChatlogExtraction {
+        drops,
+        base_salary,
+        other_income,
+        expense,
+        boss_kill_count,
+        income_records,
+        boss_kill_times,
+        purchased_items,
+        line_items,
+    }
 }
 
 /// 扫描账号目录下所有 chatlog 数据库文件
@@ -2318,7 +2825,7 @@ fn extract_role_id_from_chatlog(chatlog_path: &PathBuf) -> Result<Option<String>
 /// 10人本：周一 07:00 ~ 周五 07:00 或 周五 07:00 ~ 下周一 07:00（半月 CD）
 ///
 /// 用于检查同一 CD 窗口内是否已存在手工记录，避免重复创建 pending。
-fn calculate_cd_window(record_time_ms: i64, is_ten_person: bool) -> (i64, i64) {
+pub(crate) fn calculate_cd_window(record_time_ms: i64, is_ten_person: bool) -> (i64, i64) {
     use chrono::{Datelike, Local, TimeZone, Timelike};
 
     let dt = match Local.timestamp_millis_opt(record_time_ms).single() {
@@ -2381,7 +2888,7 @@ const RAID_STALE_THRESHOLD_MS: i64 = 6 * 60 * 60 * 1000; // 6 小时
 ///
 /// 注意：传入的 `jx3_running` 仅作为辅助判断，主信号是 chatlog mtime。
 /// 即使 JX3 进程在运行，若 chatlog 长时间无更新，也认为角色已离线（如切换角色、断线挂机）。
-fn is_role_online(account_dir: &Path, jx3_running: bool) -> bool {
+pub(crate) fn is_role_online(account_dir: &Path, jx3_running: bool) -> bool {
     if !jx3_running {
         return false;
     }
@@ -2409,6 +2916,14 @@ fn is_role_online(account_dir: &Path, jx3_running: bool) -> bool {
         return true;
     }
 
+    // 目录监听运行中：直接使用监听器记录的最新 chatlog 写入时间
+    if let Some(watched) = crate::mingyi::scan_watcher::watched_activity(account_dir) {
+        return watched
+            .chat_log
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .is_some_and(|d| d.as_millis() as i64 >= threshold);
+    }
+
     // 目录 mtime 不够新，再检查最新 chatlog 文件 mtime
     let latest_file_mtime_ms = std::fs::read_dir(&chat_log_dir)
         .ok()
@@ -2521,7 +3036,96 @@ fn is_raid_complete(
     false
 }
 
+/// 特殊掉落标记（records.data 的 has* 字段），由掉落分类结果推导
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RareDropFlags {
+    pub xuanjing: bool,
+    pub mount: bool,
+    pub secret_book: bool,
+    pub pet: bool,
+    pub maju: bool,
+    pub pendant: bool,
+    pub appearance: bool,
+    pub title: bool,
+}
+
+impl RareDropFlags {
+    pub(crate) fn from_categories(categories: &HashMap<String, String>) -> Self {
+        let mut flags = Self::default();
+        for category in categories.values() {
+            match category.as_str() {
+                "xuanjing" => flags.xuanjing = true,
+                "mount" => flags.mount = true,
+                "secret_book" => flags.secret_book = true,
+                "pet" => flags.pet = true,
+                "maju" => flags.maju = true,
+                "pendant" => flags.pendant = true,
+                "appearance" => flags.appearance = true,
+                "title" => flags.title = true,
+                _ => {}
+            }
+        }
+        flags
+    }
+
+    /// 写入 records.data 的 has* 字段
+    pub(crate) fn write_to(&self, obj: &mut serde_json::Map<String, serde_json::Value>) {
+        for (key, value) in [
+            ("hasXuanjing", self.xuanjing),
+            ("hasMount", self.mount),
+            ("hasSecretBook", self.secret_book),
+            ("hasPet", self.pet),
+            ("hasMaJu", self.maju),
+            ("hasPendant", self.pendant),
+            ("hasAppearance", self.appearance),
+            ("hasTitle", self.title),
+        ] {
+            obj.insert(key.to_string(), serde_json::json!(value));
+        }
+    }
+}
+
+/// 掉落物分类（单表 drop_items + 批量 item_merged 接口），返回 name -> category
+///
+/// 流程：drop/v2 获取副本可能掉落 → 与实际掉落取交集 → 缓存过滤 → 仅查未命中物品 → 纯字段分类。
+/// 无 MapID 或分类失败时返回 None，调用方决定降级为 unknown 还是保留原有标记。
+pub(crate) fn classify_record_drops(
+    map_id: i64,
+    raid_name: &str,
+    drops: &[String],
+    raid_display_name: &str,
+) -> Option<HashMap<String, String>> {
+    if map_id <= 0 {
+        log::debug!("[DropScanner] 副本 {} 无 MapID，跳过掉落分类", raid_display_name);
+        return None;
+    }
+    match crate::drop_table::classify_drops_sync(map_id, Some(raid_name), drops) {
+        Ok(categories) => {
+            log::info!(
+                "[DropScanner] 副本 {} 掉落分类完成: MapID={}, 实际掉落 {} 条, 分类命中 {} 条",
+                raid_display_name,
+                map_id,
+                drops.len(),
+                categories.len()
+            );
+            Some(categories)
+        }
+        Err(e) => {
+            log::warn!(
+                "[DropScanner] 副本 {} 掉落分类失败，降级为 unknown: MapID={}, 错误={}",
+                raid_display_name,
+                map_id,
+                e
+            );
+            None
+        }
+    }
+}
+
 /// 构造 RaidRecord JSON 并写入 records 表
+///
+/// 同时移入多余的 pending 记录、重建战斗尝试/chatlog 明细/团队名单/拍卖账本，
+/// 调用方应传入事务，任一步失败时整体回滚。
 fn upsert_raid_drop_record(
     conn: &Connection,
     instance: &RaidInstance,
@@ -2535,17 +3139,21 @@ fn upsert_raid_drop_record(
     role_region: &str,
     raid_name: &str,
     raid_full_name: &str,
-    gold_income: i64,
-    gold_expense: i64,
+    gold_income: Money,
+    gold_expense: Money,
     filtered_jcl_files: &[String],
+    attempts: &[RaidAttempt],
+    chatlog_items: &[ChatlogLineItem],
+    roster: &[RosterMember],
     record_status: &str,
 ) -> Result<(), String> {
     let now = chrono::Local::now().to_rfc3339();
 
     // 检查 records 表是否已存在同账号、同副本难度、同 CD 周期的 auto_scan 记录（任意状态）
-    // 注意：raid_name 列存储的是 raids.name 短名（如 "阆风悬城"），不区分 10人/25人/普通/英雄。
-    // 仅用 raid_name 匹配会导致 10人记录被 25人实例误匹配（CD 窗口起点相同）。
-    // 因此额外用 json_extract(data, '$.raidName') 匹配完整副本名（如 "25人普通阆风悬城"）。
+    // 注意：raid_name 列由 RaidRecord 写入完整副本名（如 "25人普通阆风悬城"），
+    // 旧版扫描写入的是 raids.name 短名（如 "阆风悬城"），因此两种值都匹配；
+    // 短名不区分 10人/25人/普通/英雄，仍需用 json_extract(data, '$.raidName') 匹配完整副本名，
+    // 避免 10人记录被 25人实例误匹配（CD 窗口起点相同）。
     // 使用 CD 窗口范围匹配：同一 CD 周期内同一副本只保留一条记录。
     // 查询所有状态（含 confirmed/rejected），避免已确认/已拒绝的记录被重复创建。
     // 优先返回 pending/scanning（用于更新），其次 confirmed/rejected（用于跳过）。
@@ -2559,9 +3167,9 @@ fn upsert_raid_drop_record(
     let existing: Option<(String, String)> = conn
         .query_row(
             "SELECT id, status FROM records
-             WHERE account_id = ?1 AND raid_name = ?2
+             WHERE account_id = ?1 AND raid_name IN (?2, ?5)
                AND record_date >= ?3 AND record_date < ?4
-               AND source = 'auto_scan'
+               AND source IN ('auto_scan', 'gkp_import')
                AND json_extract(data, '$.raidName') = ?5
                AND (?6 = '' OR role_id = ?6)
              ORDER BY
@@ -2595,9 +3203,9 @@ fn upsert_raid_drop_record(
     // 注意：之前曾用 `if existing.is_none()` 包裹此检查，导致已存在 pending 记录时
     // 跳过手工检查，即使手工记录已存在也会继续 UPDATE pending，造成重复记录。
     //
-    // 注意：手工记录的 raid_name 列存的是 JSON raidName 完整名（如 "25人英雄阆风悬城"），
-    // 而 auto 记录的 raid_name 列存的是 raids.name 短名（如 "阆风悬城"）。
-    // 因此不能用 raid_name 列精确匹配，需查询同角色同 CD 窗口内所有 confirmed 记录，
+    // 注意：旧版 auto 记录的 raid_name 列存的是 raids.name 短名（如 "阆风悬城"），
+    // 手工记录存的是完整名（如 "25人英雄阆风悬城"）。
+    // 因此不用 raid_name 列精确匹配，查询同角色同 CD 窗口内所有 confirmed 记录，
     // 在 Rust 中解析 JSON data 字段的 raidName 比较。
     // 按 role_id 过滤：不同角色有独立 CD，同一账号下不同角色的手工记录不应互斥。
     log::info!(
@@ -2655,13 +3263,19 @@ fn upsert_raid_drop_record(
     }
 
     if matched {
-        // 若存在 pending auto_scan 记录但已有手工记录，删除多余的 pending 记录
+        // 若存在 pending auto_scan 记录但已有手工记录，把多余的 pending 记录连同战斗尝试、
+        // chatlog 明细等附属数据移入回收站
         if let Some((existing_id, _)) = &existing {
-            conn.execute(
-                "DELETE FROM records WHERE id = ?1 AND status = 'pending' AND source = 'auto_scan'",
-                params![existing_id],
-            )
-            .map_err(|e| format!("删除重复 pending 记录失败: {}", e))?;
+            let redundant: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM records WHERE id = ?1 AND status = 'pending' AND source = 'auto_scan'",
+                    params![existing_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("查询重复 pending 记录失败: {}", e))?;
+            if redundant {
+                db::trash::trash_record(conn, existing_id)?;
+            }
             log::info!(
                 "[DropScanner] CD 窗口内已存在手工记录，删除多余 pending: {} (window: {}~{}, pending_id={})",
                 instance.raid_display_name,
@@ -2689,67 +3303,11 @@ fn upsert_raid_drop_record(
     // 使用 existing_id（更新）或新生成的 UUID（插入）作为记录 ID。
     let record_id = existing.clone().map(|(id, _)| id).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-    // 掉落物分类（单表 drop_items + 批量 item_merged 接口）
-    // 流程：drop/v2 获取副本可能掉落 → 与实际掉落取交集 → 缓存过滤 → 仅查未命中物品 → 纯字段分类
-    // 网络请求失败不阻塞主流程，仅记录警告，降级为 unknown
-    let mut has_xuanjing = false;
-    let mut has_mount = false;
-    let mut has_secret_book = false;
-    let mut has_pet = false;
-    let mut has_maju = false;
-    let mut has_pendant = false;
-    let mut has_appearance = false;
-    let mut has_title = false;
-    // 分类结果（name -> category），用于构建 notes
-    let mut item_categories: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-
-    if instance.map_id > 0 {
-        let drop_names: Vec<String> = drops.to_vec();
-
-        match crate::drop_table::classify_drops_sync(
-            instance.map_id,
-            Some(raid_name),
-            &drop_names,
-        ) {
-            Ok(categories) => {
-                log::info!(
-                    "[DropScanner] 副本 {} 掉落分类完成: MapID={}, 实际掉落 {} 条, 分类命中 {} 条",
-                    instance.raid_display_name,
-                    instance.map_id,
-                    drop_names.len(),
-                    categories.len()
-                );
-
-                for (name, category) in &categories {
-                    item_categories.insert(name.clone(), category.clone());
-                    match category.as_str() {
-                        "xuanjing" => has_xuanjing = true,
-                        "mount" => has_mount = true,
-                        "secret_book" => has_secret_book = true,
-                        "pet" => has_pet = true,
-                        "maju" => has_maju = true,
-                        "pendant" => has_pendant = true,
-                        "appearance" => has_appearance = true,
-                        "title" => has_title = true,
-                        _ => {}
-                    }
-                }
-            }
-            Err(e) => {
-                log::warn!(
-                    "[DropScanner] 副本 {} 掉落分类失败，降级为 unknown: MapID={}, 错误={}",
-                    instance.raid_display_name,
-                    instance.map_id,
-                    e
-                );
-            }
-        }
-    } else {
-        log::debug!(
-            "[DropScanner] 副本 {} 无 MapID，跳过掉落分类",
-            instance.raid_display_name
-        );
-    }
+    // 掉落物分类，推导特殊掉落标记（网络请求失败不阻塞主流程，降级为 unknown）
+    let item_categories =
+        classify_record_drops(instance.map_id, raid_name, drops, &instance.raid_display_name)
+            .unwrap_or_default();
+    let rare_flags = RareDropFlags::from_categories(&item_categories);
 
     // 构建 notes：特殊掉落名称 + 消费购买的装备
     // 特殊掉落：分类为 xuanjing/mount/secret_book/pet/maju/pendant/appearance/title 的物品
@@ -2796,6 +3354,7 @@ fn upsert_raid_drop_record(
 
     // 构造 RaidRecord JSON
     // goldIncome 使用 chatlog 提取的分配金额作为默认值，用户确认时可在 UI 编辑。
+    // goldIncomeCopper / goldExpenseCopper 保存铜精度的原始金额（见 money::record_income）。
     let record = serde_json::json!({
         "id": record_id,
         "accountId": instance.account_id,
@@ -2806,16 +3365,18 @@ fn upsert_raid_drop_record(
         "raidName": raid_full_name,
         "mapId": instance.map_id,
         "date": instance.start_time,
-        "goldIncome": gold_income,
-        "goldExpense": gold_expense,
-        "hasXuanjing": has_xuanjing,
-        "hasMount": has_mount,
-        "hasSecretBook": has_secret_book,
-        "hasPet": has_pet,
-        "hasMaJu": has_maju,
-        "hasPendant": has_pendant,
-        "hasAppearance": has_appearance,
-        "hasTitle": has_title,
+        "goldIncome": gold_income.gold(),
+        "goldExpense": gold_expense.gold(),
+        "goldIncomeCopper": gold_income.copper(),
+        "goldExpenseCopper": gold_expense.copper(),
+        "hasXuanjing": rare_flags.xuanjing,
+        "hasMount": rare_flags.mount,
+        "hasSecretBook": rare_flags.secret_book,
+        "hasPet": rare_flags.pet,
+        "hasMaJu": rare_flags.maju,
+        "hasPendant": rare_flags.pendant,
+        "hasAppearance": rare_flags.appearance,
+        "hasTitle": rare_flags.title,
         "bossIds": boss_ids,
         "bossNames": boss_names,
         "type": "raid",
@@ -2827,21 +3388,46 @@ fn upsert_raid_drop_record(
         "notes": notes,
     });
 
-    let record_str = record.to_string();
-    let drops_json = serde_json::to_string(drops).unwrap_or_else(|_| "[]".to_string());
-    let jcl_files_json = serde_json::to_string(filtered_jcl_files)
-        .unwrap_or_else(|_| "[]".to_string());
+    // 更新已有记录时保留 GKP 导入的拍卖账本（gkp 字段由 import_gkp_record 写入，扫描不会重建）。
+    // GKP 导入新建的记录（source='gkp_import'）收支以账本为准，扫描不覆盖金额和来源。
+    let mut record = record;
+    if let Some((existing_id, _)) = &existing {
+        let previous = conn
+            .query_row(
+                "SELECT data, source FROM records WHERE id = ?1",
+                params![existing_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .ok()
+            .and_then(|(data, source)| {
+                serde_json::from_str::<serde_json::Value>(&data)
+                    .ok()
+                    .map(|json| (json, source))
+            });
+        if let Some((previous_json, source)) = previous {
+            if let Some(gkp) = previous_json.get("gkp") {
+                record["gkp"] = gkp.clone();
+            }
+            if source.as_deref() == Some("gkp_import") {
+                for key in [
+                    "goldIncome",
+                    "goldExpense",
+                    "goldIncomeCopper",
+                    "goldExpenseCopper",
+                    "source",
+                ] {
+                    if let Some(value) = previous_json.get(key) {
+                        record[key] = value.clone();
+                    }
+                }
+            }
+        }
+    }
 
-    if let Some((existing_id, _)) = existing {
+    let record = db::raid_record::RaidRecord::from_value(record)?;
+    db::raid_record::upsert_record(conn, &record)?;
+    if existing.is_some() {
         // pending/scanning 状态，覆盖更新（含 status 字段）
-        conn.execute(
-            "UPDATE records SET
-                data = ?1, drops = ?2, jcl_files = ?3, status = ?4, updated_at = ?5
-             WHERE id = ?6",
-            params![record_str, drops_json, jcl_files_json, record_status, now, existing_id],
-        )
-        .map_err(|e| format!("更新待确认记录失败: {}", e))?;
-
         log::info!(
             "[DropScanner] 更新待确认记录 [{}]: {} - {}",
             record_status,
@@ -2849,34 +3435,94 @@ fn upsert_raid_drop_record(
             instance.start_time
         );
     } else {
-        // 插入新记录
+        log::info!(
+            "[DropScanner] 新增 pending 记录: {} - {}",
+            instance.raid_display_name,
+            instance.start_time
+        );
+    }
+
+    save_raid_attempts(conn, &record_id, attempts)?;
+    save_record_chatlog_items(conn, &record_id, chatlog_items)?;
+    roster::save_record_roster(conn, &record_id, roster)?;
+    auction_ledger::save_record_auction_ledger(
+        conn,
+        &record_id,
+        &auction_ledger::build_auction_ledger(chatlog_items),
+    )?;
+
+    Ok(())
+}
+
+/// 重建记录的 BOSS 战斗尝试（扫描时每次以最新聚类结果整体覆盖）
+fn save_raid_attempts(conn: &Connection, record_id: &str, attempts: &[RaidAttempt]) -> Result<(), String> {
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute("DELETE FROM raid_attempts WHERE record_id = ?1", params![record_id])
+        .map_err(|e| format!("清理战斗尝试记录失败: {}", e))?;
+    for attempt in attempts {
         conn.execute(
-            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, record_type, source, status, drops, jcl_files, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'auto_scan', ?8, ?9, ?10, ?11, ?12)",
+            "INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, boss_hp_percent, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 record_id,
-                record_str,
-                raid_name,
-                instance.account_id,
-                role_id,
-                instance.start_time,
-                "raid",
-                record_status,
-                drops_json,
-                jcl_files_json,
-                now,
+                attempt.boss_name,
+                attempt.jcl_file,
+                attempt.attempt_index,
+                attempt.start_time,
+                attempt.end_time,
+                attempt.duration_ms,
+                if attempt.is_kill { 1 } else { 0 },
+                attempt.boss_hp_percent,
                 now
             ],
         )
-        .map_err(|e| format!("插入待确认记录失败: {}", e))?;
+        .map_err(|e| format!("写入战斗尝试记录失败: {}", e))?;
+    }
+    log::info!(
+        "[DropScanner] 记录 {} 保存 {} 次战斗尝试（灭团 {} 次）",
+        record_id,
+        attempts.len(),
+        attempts.iter().filter(|a| !a.is_kill).count()
+    );
+    Ok(())
+}
 
-        log::info!(
-            "[DropScanner] 新增 pending 记录: {} - {}",
-            instance.raid_display_name,
-            instance.start_time
-        );
+/// 重建记录的 chatlog 收支明细（扫描时每次以最新提取结果整体覆盖）
+fn save_record_chatlog_items(
+    conn: &Connection,
+    record_id: &str,
+    items: &[ChatlogLineItem],
+) -> Result<(), String> {
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute("DELETE FROM record_chatlog_items WHERE record_id = ?1", params![record_id])
+        .map_err(|e| format!("清理 chatlog 明细失败: {}", e))?;
+    for (seq, item) in items.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO record_chatlog_items (record_id, seq, kind, time_sec, channel, text, item_name, buyer, gold, gold_copper, counted, note, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                record_id,
+                seq as i64,
+                item.kind.as_str(),
+                item.time_sec,
+                item.channel,
+                item.text,
+                item.item_name,
+                item.buyer,
+                item.amount.map(Money::gold),
+                item.amount.map(Money::copper),
+                if item.counted { 1 } else { 0 },
+                item.note,
+                now
+            ],
+        )
+        .map_err(|e| format!("写入 chatlog 明细失败: {}", e))?;
     }
-
+    log::info!(
+        "[DropScanner] 记录 {} 保存 {} 条 chatlog 明细",
+        record_id,
+        items.len()
+    );
     Ok(())
 }
 
@@ -2909,8 +3555,23 @@ pub fn scan_raid_drops_internal(
     scan_raid_drops_with_raids(account_id, jx3_running, role_online, process_start_ms, start_ms, end_ms, None)
 }
 
+static ACCOUNT_SCAN_LOCKS: OnceLock<Mutex<HashMap<String, Arc<Mutex<()>>>>> = OnceLock::new();
+
+/// 账号级扫描锁：目录监听线程与前端轮询 / 手动扫描可能同时扫描同一账号，
+/// 两次扫描都查不到已有记录时会重复插入，因此同一账号的扫描 + 写入串行执行
+fn account_scan_lock(account_id: &str) -> Arc<Mutex<()>> {
+    let mut locks = ACCOUNT_SCAN_LOCKS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    locks.entry(account_id.to_string()).or_default().clone()
+}
+
 /// scan_raid_drops_internal 的扩展版本，支持传入预加载的副本配置
 /// `pre_loaded_raids`: 若 Some，则跳过 init_db + get_cached_raids，直接使用传入的配置
+///
+/// 持有账号级扫描锁（见 `account_scan_lock`），同一账号的并发扫描排队执行；
+/// 扫描结束后推送该账号进行中副本的时间线事件。
 pub fn scan_raid_drops_with_raids(
     account_id: &str,
     jx3_running: bool,
@@ -2920,6 +3581,79 @@ pub fn scan_raid_drops_with_raids(
     end_ms: i64,
     pre_loaded_raids: Option<&[RaidEntry]>,
 ) -> Result<usize, String> {
+    let options = ScanOptions {
+        jx3_running,
+        role_online,
+        process_start_ms,
+        start_ms,
+        end_ms,
+    };
+    let lock = account_scan_lock(account_id);
+    let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+    let result = scan_account_drops(account_id, options, pre_loaded_raids, None);
+
+    // 进行中副本的时间线增量（目录监听、轮询、手动扫描共用此出口）
+    match db::init_db() {
+        Ok(conn) => super::session_tracker::publish_account_events(&conn, account_id),
+        Err(e) => log::warn!("[DropScanner] 账号 {} 时间线更新跳过，打开数据库失败: {}", account_id, e),
+    }
+    result
+}
+
+/// 扫描解释（dry-run）：按扫描流程处理 [start_ms, end_ms) 范围内的 JCL，返回判定过程
+///
+/// 不写入 records / jcl_cache，不做多开同团识别，也不修复角色身份映射时发现的孤儿记录。
+pub(crate) fn explain_raid_drops(
+    account_id: &str,
+    jx3_running: bool,
+    role_online: bool,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<ScanTrace, String> {
+    let options = ScanOptions {
+        jx3_running,
+        role_online,
+        process_start_ms: 0,
+        start_ms,
+        end_ms,
+    };
+    let mut trace = ScanTrace {
+        account_id: account_id.to_string(),
+        start_ms,
+        end_ms,
+        jx3_running,
+        role_online,
+        ..Default::default()
+    };
+    scan_account_drops(account_id, options, None, Some(&mut trace))?;
+    Ok(trace)
+}
+
+/// 单次扫描的进程 / 时间范围参数，含义见 `scan_raid_drops_internal`
+#[derive(Debug, Clone, Copy)]
+struct ScanOptions {
+    jx3_running: bool,
+    role_online: bool,
+    process_start_ms: i64,
+    start_ms: i64,
+    end_ms: i64,
+}
+
+/// 扫描主流程。`trace` 为 Some 时为 dry-run：跳过 JCL 缓存、记录写入和同团识别，
+/// 把每一步的判定依据写入 trace
+fn scan_account_drops(
+    account_id: &str,
+    options: ScanOptions,
+    pre_loaded_raids: Option<&[RaidEntry]>,
+    mut trace: Option<&mut ScanTrace>,
+) -> Result<usize, String> {
+    let ScanOptions {
+        jx3_running,
+        role_online,
+        process_start_ms,
+        start_ms,
+        end_ms,
+    } = options;
     log::info!(
         "[DropScanner] 开始扫描账号 {} 的掉落记录 (jx3_running={}, role_online={}, process_start_ms={}, start_ms={}, end_ms={})",
         account_id, jx3_running, role_online, process_start_ms, start_ms, end_ms
@@ -2974,6 +3708,9 @@ pub fn scan_raid_drops_with_raids(
     let jcl_files = scan_jcl_files(&account_dir, process_start_ms, start_ms, end_ms)?;
     if jcl_files.is_empty() {
         log::info!("[DropScanner] 账号 {} 本次会话无 JCL 文件", account_id);
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.notes.push("时间范围内无 JCL 文件".to_string());
+        }
         return Ok(0);
     }
 
@@ -2983,6 +3720,22 @@ pub fn scan_raid_drops_with_raids(
         jcl_files.len()
     );
 
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.jcl_files = jcl_files
+            .iter()
+            .map(|jcl| JclFileTrace {
+                file_name: jcl.file_name.clone(),
+                timestamp: jcl.timestamp,
+                raid_display_name: jcl.raid_display_name.clone(),
+                file_boss_name: jcl.boss_name.clone(),
+                matched_raid: match_raid_name(&jcl.raid_display_name, &raids)
+                    .map(|entry| entry.raid_id.clone()),
+                verdict: None,
+                instance_index: None,
+            })
+            .collect();
+    }
+
     // 1.5 过滤未配置的副本（如5人副本、试炼之地等不在 raids 表中的副本）
     //     在聚类前过滤，避免对未配置副本做10金匹配和"拉托"判定
     let mut skipped_unconfigured: Vec<String> = Vec::new();
@@ -3010,6 +3763,9 @@ pub fn scan_raid_drops_with_raids(
 
     if configured_jcl_files.is_empty() {
         log::info!("[DropScanner] 账号 {} 过滤后无已配置副本的 JCL 文件", account_id);
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.notes.push("JCL 均为未配置副本，已全部跳过".to_string());
+        }
         return Ok(0);
     }
 
@@ -3024,15 +3780,15 @@ pub fn scan_raid_drops_with_raids(
             account_id
         );
     }
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.role_online = role_online;
+    }
 
     // 2. 查找 chatlog 文件
     let chatlog_files = find_chatlog_files(&account_dir)?;
 
     // 预缓存所有 chatlog 文件的时间范围，避免后续每个副本实例重复打开 SQLite
-    let chatlog_range_cache: HashMap<PathBuf, Option<(i64, i64)>> = chatlog_files
-        .iter()
-        .map(|path| (path.clone(), get_chatlog_time_range(path)))
-        .collect();
+    let chatlog_range_cache = cached_chatlog_time_ranges(&chatlog_files);
 
     // 2.5 预加载所有 chatlog 文件中的相关记录到内存
     //     避免后续每个副本实例重复打开 SQLite 连接和全表扫描
@@ -3043,12 +3799,16 @@ pub fn scan_raid_drops_with_raids(
         start_ms,
         end_ms,
     );
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.chatlog_files = chatlog_files.iter().map(|p| p.display().to_string()).collect();
+        trace.chatlog_record_count = preloaded_records.len();
+    }
 
     // 3. 分析每个 JCL 文件内容，提取战斗时间和击杀状态
     //    替代原 10金查询方案：通过 JCL 的 NPC_FIGHT_HINT (bFight True→False) 判定通关
     //    每个 JCL 只解析一次，结果缓存到 jcl_analyses
     //    同时使用 SQLite 持久化缓存（jcl_cache 表），跨会话复用解析结果
-    let conn = match db::init_db() {
+    let mut conn = match db::init_db() {
         Ok(c) => c,
         Err(e) => {
             log::error!("[DropScanner] 账号 {} 初始化数据库失败: {}", account_id, e);
@@ -3065,16 +3825,39 @@ pub fn scan_raid_drops_with_raids(
         };
         let raid_bosses = &raid_entry.bosses;
         let jcl_path = combat_logs_dir.join(&jcl.file_name);
-        let mut analysis = analyze_jcl_cached(&conn, &jcl_path, &jcl.boss_name, jcl.boss_id, raid_bosses).unwrap_or(JclAnalysis {
+        // dry-run 直接解析文件（不读写缓存），同时取回击杀信号
+        let (analyzed, signals) = if trace.is_some() {
+            match analyze_jcl_with_signals(&jcl_path, &jcl.boss_name, jcl.boss_id, raid_bosses, &raid_entry.boss_rules) {
+                Some((analysis, signals)) => (Some(analysis), signals),
+                None => (None, Vec::new()),
+            }
+        } else {
+            (analyze_jcl_cached(&conn, &jcl_path, &jcl.boss_name, jcl.boss_id, raid_bosses, &raid_entry.boss_rules), Vec::new())
+        };
+        let parsed = analyzed.is_some();
+        let mut analysis = analyzed.unwrap_or(JclAnalysis {
             boss_name: None,
             fight_start_ms: 0,
             fight_end_ms: 0,
             is_kill: false,
+            boss_hp_percent: None,
+            players: Vec::new(),
         });
-        // BOSS 名优先级：文件名 BOSS 在 raid_bosses 配置中时使用文件名，
-        // 否则使用 JCL 内容搜索结果（处理"须罗巨傀"→"唐怀仁"等小怪 JCL 情况）
-        if is_valid_boss(&jcl.boss_name, raid_bosses) {
-            analysis.boss_name = Some(jcl.boss_name.clone());
+        apply_file_boss_name(&mut analysis, jcl, raid_entry);
+        if let Some(file_trace) = trace
+            .as_deref_mut()
+            .and_then(|t| t.jcl_files.iter_mut().find(|f| f.file_name == jcl.file_name))
+        {
+            file_trace.verdict = Some(JclVerdict {
+                parsed,
+                boss_name: analysis.boss_name.clone(),
+                fight_start_ms: analysis.fight_start_ms,
+                fight_end_ms: analysis.fight_end_ms,
+                is_kill: analysis.is_kill,
+                boss_hp_percent: analysis.boss_hp_percent,
+                player_count: analysis.players.len(),
+                signals,
+            });
         }
         log::info!(
             "[DropScanner] JCL 分析: 文件='{}', BOSS='{}', is_kill={}, fight_start={}, fight_end={}, content_boss={:?}",
@@ -3108,19 +3891,32 @@ pub fn scan_raid_drops_with_raids(
         account_id,
         instances.len()
     );
+    if let Some(trace) = trace.as_deref_mut() {
+        for (index, instance) in instances.iter().enumerate() {
+            for file_trace in trace.jcl_files.iter_mut().filter(|f| {
+                instance.jcl_files.contains(&f.file_name)
+                    || instance.attempts.iter().any(|a| a.jcl_file == f.file_name)
+            }) {
+                file_trace.instance_index = Some(index);
+            }
+        }
+    }
 
     // 6. 解析 info.jx3dat 获取角色身份，映射到数据库 UUID
     //    records 表的 account_id/role_id 是 UUID 格式（与 accounts/roles 表对齐），
     //    不能使用茗伊目录名的数字 uid，否则 UI 过滤不到 pending 记录。
     //    conn 已在 JCL 分析阶段创建，此处直接复用
 
-    let db_identity = match resolve_db_role_identity(&conn, &account_dir, &game_path) {
+    let db_identity = match resolve_db_role_identity(&conn, &account_dir, &game_path, trace.is_none()) {
         Ok(Some(id)) => id,
         Ok(None) => {
             log::warn!(
                 "[DropScanner] 账号 {} 角色未在数据库登记，跳过扫描",
                 account_id
             );
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.notes.push("角色未在数据库登记，不会生成记录".to_string());
+            }
             return Ok(0);
         }
         Err(e) => {
@@ -3146,6 +3942,9 @@ pub fn scan_raid_drops_with_raids(
                     "[DropScanner] 副本名未匹配: {}",
                     instance.raid_display_name
                 );
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.notes.push(format!("副本名未匹配，已跳过实例: {}", instance.raid_display_name));
+                }
                 continue;
             }
         };
@@ -3200,6 +3999,7 @@ pub fn scan_raid_drops_with_raids(
         // 注意：用 JCL 时间戳而非实例 start_time，避免未通关副本（如白帝江关无 BOSS 配置）
         //       虽然不形成实例但其 JCL 仍能正确截断 chatlog 窗口
         let chatlog_start = instance.first_gold_time;
+        let mut chatlog_end_reason = "nextJcl";
         let next_jcl_time = all_jcl_timestamps
             .iter()
             .find(|&&t| t > instance.last_jcl_time)
@@ -3217,10 +4017,12 @@ pub fn scan_raid_drops_with_raids(
             if jx3_running && role_online && !raid_stale {
                 // 副本可能仍在进行（JX3运行 + 角色在线 + 最后JCL在6小时内）
                 // 使用当前时间以覆盖实时底薪发送
+                chatlog_end_reason = "now";
                 now_ms
             } else {
                 // 副本已结束：使用 last_jcl_time + 2h 作为结束时间
                 // 避免使用 SystemTime::now() 导致纳入后续家园活动等无关收入
+                chatlog_end_reason = "lastJclPlus2h";
                 instance.last_jcl_time + 2 * 60 * 60 * 1000
             }
         };
@@ -3229,29 +4031,29 @@ pub fn scan_raid_drops_with_raids(
         // 优化：直接复用预加载的 chatlog 记录进行内存处理，避免重复打开 SQLite 连接
         // （预加载在 scan_raid_drops_with_raids 入口完成，覆盖范围 [scan_start, scan_end+2h]）
         let mut drops: HashSet<String> = HashSet::new();
-        let mut base_salary: Option<i64> = None;
-        let other_income: i64;
-        let total_expense: i64;
+        let mut base_salary: Option<Money> = None;
+        let other_income: Money;
+        let total_expense: Money;
         let chatlog_query_count = if preloaded_records.is_empty() { 0u32 } else { 1u32 };
-        // 合并所有 chatlog 文件的收入记录 (time_sec, gold)，用于精确收入匹配
-        let mut all_income_records: Vec<(i64, i64)> = Vec::new();
+        // 合并所有 chatlog 文件的收入记录 (time_sec, 金额)，用于精确收入匹配
+        let mut all_income_records: Vec<(i64, Money)> = Vec::new();
         // 累加所有 chatlog 文件的 boss_kill_times，用于击杀数验证
         let mut all_boss_kill_times: Vec<i64> = Vec::new();
         // 合并所有 chatlog 文件的购买物品名（用于 notes 显示）
         let mut all_purchased_items: HashSet<String> = HashSet::new();
 
         // 单次调用处理预加载记录，时间范围 [chatlog_start, chatlog_end] 通过二分查找定位
-        let (chatlog_drops, salary, income, expense, _, income_records, boss_kill_times, purchased_items) =
+        let mut extraction =
             extract_drops_from_records(&preloaded_records, chatlog_start, chatlog_end, &db_identity.role_name);
-        drops.extend(chatlog_drops);
-        all_purchased_items.extend(purchased_items);
-        if salary.is_some() {
-            base_salary = salary;
-        }
-        other_income = income;
-        total_expense = expense;
-        all_income_records.extend(income_records);
-        all_boss_kill_times.extend(boss_kill_times);
+        drops.extend(extraction.drops.iter().cloned());
+        all_purchased_items.extend(extraction.purchased_items.iter().cloned());
+        if extraction.base_salary.is_some() {
+            base_salary = extraction.base_salary;
+        }
+        other_income = extraction.other_income;
+        total_expense = extraction.expense;
+        all_income_records.extend(extraction.income_records.iter().copied());
+        all_boss_kill_times.extend(extraction.boss_kill_times.iter().copied());
         log::info!(
             "[DropScanner] chatlog 内存处理: 预加载 {} 条记录, 查询 1 次",
             preloaded_records.len()
@@ -3324,7 +4126,7 @@ pub fn scan_raid_drops_with_raids(
         // - 找不到匹配记录时回退：有底薪回退到底薪值，无底薪回退到收入总和
         all_income_records.sort_by_key(|(t, _)| *t);
         let last_jcl_sec = instance.last_jcl_time / 1000;
-        let total_gold = if let Some(salary) = base_salary {
+        let total_income = if let Some(salary) = base_salary {
             // 有底薪：找金额 >= 底薪的第一条收入记录
             match all_income_records.iter().find(|(_, g)| *g >= salary) {
                 Some((t, g)) => {
@@ -3332,6 +4134,7 @@ pub fn scan_raid_drops_with_raids(
                         "[DropScanner] 收入匹配(底薪): 底薪={}, 匹配记录 gold={} time={} -> 收入={}",
                         salary, g, t, g
                     );
+                    extraction.mark_selected_income(*t, *g, "最终收入：首条不低于底薪的收入");
                     *g
                 }
                 None => {
@@ -3350,6 +4153,7 @@ pub fn scan_raid_drops_with_raids(
                         "[DropScanner] 收入匹配(无底薪): last_jcl_sec={}, 取 last_jcl 后第一条收入 gold={} time={} -> 收入={}",
                         last_jcl_sec, g, t, g
                     );
+                    extraction.mark_selected_income(*t, *g, "最终收入：最后一个 JCL 之后的首条收入");
                     *g
                 }
                 None => {
@@ -3365,7 +4169,7 @@ pub fn scan_raid_drops_with_raids(
             "[DropScanner] 收入汇总: 底薪={:?}, 其他收入总和={}, 最终收入={}, 支出={}, 收入记录数={}",
             base_salary,
             other_income,
-            total_gold,
+            total_income,
             total_expense,
             all_income_records.len()
         );
@@ -3386,6 +4190,14 @@ pub fn scan_raid_drops_with_raids(
             filtered_jcl_files.len()
         );
 
+        // 战斗尝试同样只保留 BOSS 战（过滤小怪 JCL）
+        let filtered_attempts: Vec<RaidAttempt> = instance
+            .attempts
+            .iter()
+            .filter(|a| is_valid_boss(&a.boss_name, raid_bosses))
+            .cloned()
+            .collect();
+
         // 写入 records 表（使用数据库 UUID，而非茗伊数字 uid）
         // 副本完成判断：根据 BOSS 击杀数、底薪、进程状态、角色在线状态决定 status
         // - scanning：副本进行中，UI 锁定不可确认，等待后续扫描补充
@@ -3413,28 +4225,82 @@ pub fn scan_raid_drops_with_raids(
             record_status
         );
 
+        if let Some(trace) = trace.as_deref_mut() {
+            let jcl_raid_name = trace
+                .jcl_files
+                .iter()
+                .find(|f| instance.jcl_files.first() == Some(&f.file_name))
+                .map(|f| f.raid_display_name.clone())
+                .unwrap_or_else(|| instance.raid_display_name.clone());
+            trace.instances.push(InstanceTrace {
+                raid_display_name: jcl_raid_name,
+                matched_raid: Some(raid_entry.raid_id.clone()),
+                raid_name: Some(raid_name.clone()),
+                start_time: instance.start_time,
+                last_jcl_time: instance.last_jcl_time,
+                jcl_files: filtered_jcl_files,
+                attempt_files: filtered_attempts.iter().map(|a| a.jcl_file.clone()).collect(),
+                bosses_killed: instance.bosses_killed.clone(),
+                boss_ids,
+                boss_names,
+                drops: {
+                    let mut drops: Vec<String> = drops.into_iter().collect();
+                    drops.sort();
+                    drops
+                },
+                chatlog_start,
+                chatlog_end,
+                chatlog_end_reason,
+                base_salary,
+                total_income,
+                total_expense,
+                raid_complete,
+                record_status,
+                chatlog_items: extraction.line_items,
+            });
+            inserted_count += 1;
+            continue;
+        }
+
+        // 团队名单：JCL 队伍成员 + chatlog 中出现的分配/拍卖角色，标记本地已添加的角色
+        let mut record_roster = roster::build_roster(&instance.party, &extraction.line_items);
+        if let Err(e) = roster::match_own_roles(&conn, &mut record_roster) {
+            log::warn!("[DropScanner] 团队名单匹配本地角色失败: {}", e);
+        }
+
         let role_id_opt: Option<String> = Some(db_identity.role_id.clone());
         let drops_vec: Vec<String> = drops.into_iter().collect();
         let purchased_items_vec: Vec<String> = all_purchased_items.into_iter().collect();
 
-        if let Err(e) = upsert_raid_drop_record(
-            &conn,
-            instance,
-            &drops_vec,
-            &purchased_items_vec,
-            &boss_ids,
-            &boss_names,
-            &role_id_opt,
-            &db_identity.role_name,
-            &db_identity.server,
-            &db_identity.region,
-            raid_name,
-            &instance.raid_display_name,
-            total_gold,
-            total_expense,
-            &filtered_jcl_files,
-            record_status,
-        ) {
+        // 记录、回收站、战斗尝试、chatlog 明细、团队名单、拍卖账本在同一事务中写入，失败时整体回滚
+        let written = conn
+            .transaction()
+            .map_err(|e| format!("开启事务失败: {}", e))
+            .and_then(|tx| {
+                upsert_raid_drop_record(
+                    &tx,
+                    instance,
+                    &drops_vec,
+                    &purchased_items_vec,
+                    &boss_ids,
+                    &boss_names,
+                    &role_id_opt,
+                    &db_identity.role_name,
+                    &db_identity.server,
+                    &db_identity.region,
+                    raid_name,
+                    &instance.raid_display_name,
+                    total_income,
+                    total_expense,
+                    &filtered_jcl_files,
+                    &filtered_attempts,
+                    &extraction.line_items,
+                    &record_roster,
+                    record_status,
+                )?;
+                tx.commit().map_err(|e| format!("提交事务失败: {}", e))
+            });
+        if let Err(e) = written {
             log::error!(
                 "[DropScanner] 写入记录失败: {} - {}",
                 instance.raid_display_name,
@@ -3452,6 +4318,14 @@ pub fn scan_raid_drops_with_raids(
         inserted_count
     );
 
+    // 多开同团识别：与其他账号同一场副本的记录归为同一分组
+    if trace.is_some() {
+        return Ok(inserted_count);
+    }
+    if let Err(e) = super::run_groups::link_run_groups(&conn, start_ms, end_ms) {
+        log::warn!("[DropScanner] 同团识别失败: {}", e);
+    }
+
     Ok(inserted_count)
 }
 
@@ -3899,14 +4773,15 @@ pub async fn scan_raids_in_range(
 /// Tauri 命令：确认 pending 记录
 ///
 /// 1. 从 records 读取 pending 记录的 data JSON
-/// 2. 合并可选的编辑数据，强制设置 source='manual', status='confirmed'
-/// 3. UPDATE records（更新 data、source、status、updated_at）
+/// 2. 合并可选的编辑数据，按 `RaidRecord` 校验（未知字段、类型不符直接拒绝），
+///    强制设置 source='manual', status='confirmed'
+/// 3. 写回 records（data 与索引列由校验后的记录生成，source 列置为 manual）
 ///
 /// 可选传入 edit_data（RaidRecord 部分字段的 JSON 字符串），用于在确认时
 /// 编辑收支金额、BOSS 选择、标记位等。提供时会合并到 data JSON 后再置为 confirmed。
 #[tauri::command]
 pub fn confirm_record(record_id: String, edit_data: Option<String>) -> Result<(), String> {
-    let conn = db::init_db()?;
+    let mut conn = db::init_db()?;
 
     // 从 records 读取当前 data JSON 和 status
     let (current_data, current_status): (String, String) = conn
@@ -3931,35 +4806,135 @@ pub fn confirm_record(record_id: String, edit_data: Option<String>) -> Result<()
         let edit_json: serde_json::Value =
             serde_json::from_str(&edit_json_str).map_err(|e| format!("解析编辑数据失败: {}", e))?;
 
-        if let (Some(current_obj), Some(edit_obj)) =
-            (current_json.as_object_mut(), edit_json.as_object())
-        {
+        let edit_obj = edit_json
+            .as_object()
+            .ok_or_else(|| "编辑数据必须为 JSON 对象".to_string())?;
+        if edit_obj.contains_key("id") {
+            return Err("编辑数据不能修改记录 ID".to_string());
+        }
+        if let Some(current_obj) = current_json.as_object_mut() {
             for (key, value) in edit_obj {
                 current_obj.insert(key.clone(), value.clone());
             }
         }
     }
 
-    // 强制设置 source='manual', status='confirmed'
-    if let Some(obj) = current_json.as_object_mut() {
-        obj.insert("source".to_string(), serde_json::json!("manual"));
-        obj.insert("status".to_string(), serde_json::json!("confirmed"));
-    }
-
-    let updated_data = serde_json::to_string(&current_json).unwrap_or(current_data);
-    let now = chrono::Local::now().to_rfc3339();
+    let mut record = db::raid_record::RaidRecord::from_value(current_json)?;
 
-    // UPDATE records：更新 data、source、status、updated_at
-    conn.execute(
-        "UPDATE records SET data = ?1, source = 'manual', status = 'confirmed', updated_at = ?2 WHERE id = ?3",
-        params![updated_data, now, record_id],
+    // 强制设置 source='manual', status='confirmed'
+    record.source = Some(db::raid_record::RecordSource::Manual);
+    record.status = Some(db::raid_record::RecordStatus::Confirmed);
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    db::raid_record::upsert_record(&tx, &record)?;
+    tx.execute(
+        "UPDATE records SET source = 'manual' WHERE id = ?1",
+        params![record_id],
     )
     .map_err(|e| format!("更新 records 记录失败: {}", e))?;
+    let confirmed_data = db::record_history::current_record_data(&tx, &record_id)?;
+    db::record_history::append_history(
+        &tx,
+        &record_id,
+        db::record_history::ACTION_CONFIRM,
+        Some(&current_data),
+        confirmed_data.as_deref(),
+    )?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
 
     log::info!("[DropScanner] 记录已确认: {}", record_id);
     Ok(())
 }
 
+/// 查询记录的全部 BOSS 战斗尝试（含灭团），按战斗时间排序
+pub(crate) fn query_raid_attempts(conn: &Connection, record_id: &str) -> Result<Vec<RaidAttempt>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, boss_hp_percent
+             FROM raid_attempts WHERE record_id = ?1
+             ORDER BY start_time ASC, id ASC",
+        )
+        .map_err(|e| format!("准备战斗尝试查询失败: {}", e))?;
+    let attempts = stmt
+        .query_map(params![record_id], |row| {
+            Ok(RaidAttempt {
+                boss_name: row.get(0)?,
+                jcl_file: row.get(1)?,
+                attempt_index: row.get(2)?,
+                start_time: row.get(3)?,
+                end_time: row.get(4)?,
+                duration_ms: row.get(5)?,
+                is_kill: row.get::<_, i64>(6)? != 0,
+                boss_hp_percent: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("查询战斗尝试失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取战斗尝试失败: {}", e))?;
+    Ok(attempts)
+}
+
+/// Tauri 命令：查询记录的全部 BOSS 战斗尝试（含灭团），按战斗时间排序
+#[tauri::command]
+pub fn list_raid_attempts(record_id: String) -> Result<Vec<RaidAttempt>, String> {
+    let conn = db::init_db()?;
+    query_raid_attempts(&conn, &record_id)
+}
+
+/// 查询记录的 chatlog 收支明细，按消息顺序排序
+pub(crate) fn query_record_chatlog_items(
+    conn: &Connection,
+    record_id: &str,
+) -> Result<Vec<ChatlogLineItem>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT kind, time_sec, channel, text, item_name, buyer,
+                    COALESCE(gold_copper, gold * 10000), counted, note
+             FROM record_chatlog_items WHERE record_id = ?1
+             ORDER BY seq ASC",
+        )
+        .map_err(|e| format!("准备 chatlog 明细查询失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![record_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                ChatlogLineItem {
+                    kind: ChatlogItemKind::Income,
+                    time_sec: row.get(1)?,
+                    channel: row.get(2)?,
+                    text: row.get(3)?,
+                    item_name: row.get(4)?,
+                    buyer: row.get(5)?,
+                    amount: row.get::<_, Option<i64>>(6)?.map(Money::from_copper),
+                    counted: row.get::<_, i64>(7)? != 0,
+                    note: row.get(8)?,
+                    row_key: None,
+                },
+            ))
+        })
+        .map_err(|e| format!("查询 chatlog 明细失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取 chatlog 明细失败: {}", e))?;
+
+    let mut items = Vec::with_capacity(rows.len());
+    for (kind, item) in rows {
+        match ChatlogItemKind::parse(&kind) {
+            Some(kind) => items.push(ChatlogLineItem { kind, ..item }),
+            None => log::warn!("[DropScanner] 未知的 chatlog 明细类型: {}", kind),
+        }
+    }
+    Ok(items)
+}
+
+/// Tauri 命令：查询记录的 chatlog 收支明细（待确认记录审核时展示金额来源），按消息顺序排序
+#[tauri::command]
+pub fn list_record_chatlog_items(record_id: String) -> Result<Vec<ChatlogLineItem>, String> {
+    let conn = db::init_db()?;
+    query_record_chatlog_items(&conn, &record_id)
+}
+
 /// 清空 JCL 缓存表
 ///
 /// 用途：当 analyze_jcl 逻辑修复后，旧版缓存可能残留错误的 is_kill 判定。
@@ -3979,37 +4954,21 @@ pub fn clear_jcl_cache() -> Result<i64, String> {
 /// Tauri 命令：拒绝 pending 记录（CD 释放）
 #[tauri::command]
 pub fn reject_record(record_id: String) -> Result<(), String> {
-    let conn = db::init_db()?;
-
-    let now = chrono::Local::now().to_rfc3339();
-
-    // 更新 records 状态和 data JSON
-    if let Ok(data) = conn.query_row(
-        "SELECT data FROM records WHERE id = ?1",
-        params![record_id],
-        |row| row.get::<_, String>(0),
-    ) {
-        if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&data) {
-            if let Some(obj) = json.as_object_mut() {
-                obj.insert("status".to_string(), serde_json::json!("rejected"));
-                let updated_data = serde_json::to_string(&json).unwrap_or(data);
-                conn.execute(
-                    "UPDATE records SET data = ?1, status = 'rejected', updated_at = ?2 WHERE id = ?3",
-                    params![updated_data, now, record_id],
-                )
-                .map_err(|e| format!("驳回记录失败: {}", e))?;
-                log::info!("[DropScanner] 记录已拒绝: {}", record_id);
-                return Ok(());
-            }
-        }
-    }
-
-    // 如果 data JSON 解析失败，至少更新 status 列
-    conn.execute(
-        "UPDATE records SET status = 'rejected', updated_at = ?1 WHERE id = ?2",
-        params![now, record_id],
-    )
-    .map_err(|e| format!("驳回记录失败: {}", e))?;
+    let mut conn = db::init_db()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+
+    let data = db::record_history::current_record_data(&tx, &record_id)?
+        .ok_or_else(|| format!("记录不存在: {}", record_id))?;
+    let mut record = db::raid_record::RaidRecord::from_json_str(&data)?;
+    record.status = Some(db::raid_record::RecordStatus::Rejected);
+    db::record_history::upsert_record_with_history(
+        &tx,
+        &record,
+        db::record_history::ACTION_REJECT,
+    )?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
 
     log::info!("[DropScanner] 记录已拒绝: {}", record_id);
     Ok(())
@@ -4040,6 +4999,45 @@ mod tests {
         (conn, temp_path)
     }
 
+    /// 赵八嫂不产生 NPC_FIGHT_HINT：有 BOSS 配置时，预置规则的 fight_time_signal 仍以全局 FIGHT_TIME 作为进战信号
+    #[test]
+    fn test_zhao_ba_sao_kill_with_configured_bosses() {
+        let lines = [
+            "A\t1\t1750000000\t10000\t1\t{true,\"uuid\",0}",
+            "A\t2\t1750000001\t11000\t8\t{500,\"赵八嫂\",12345,0,0,0,0,0}",
+            "A\t3\t1750000060\t70000\t28\t{500,1}",
+            "A\t4\t1750000061\t71000\t1\t{false,\"uuid\",61000}",
+        ];
+        let mut data = Vec::new();
+        for line in lines {
+            data.extend_from_slice(&encoding_rs::GBK.encode(line).0);
+            data.push(b'\n');
+        }
+        let dir = std::env::temp_dir().join(format!("jx3-test-zhao-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let jcl_path = dir.join("赵八嫂.jcl");
+        fs::write(&jcl_path, &data).unwrap();
+
+        let raid_bosses = vec![
+            ("baidijiangguan_1".to_string(), "赵八嫂".to_string()),
+            ("baidijiangguan_2".to_string(), "其他BOSS".to_string()),
+        ];
+        let rules: Vec<BossKillRule> =
+            serde_json::from_str(include_str!("../db/static_boss_rules.json")).unwrap();
+
+        let (analysis, signals) =
+            analyze_jcl_with_signals(&jcl_path, "赵八嫂", 12345, &raid_bosses, &rules).unwrap();
+        // 未配置规则时没有进战信号，与旧版判定一致
+        let (without_rule, _) =
+            analyze_jcl_with_signals(&jcl_path, "赵八嫂", 12345, &raid_bosses, &[]).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(analysis.is_kill);
+        assert!(signals.contains(&"boss_fight_started"));
+        assert!(signals.contains(&"death_notify"));
+        assert!(!without_rule.is_kill);
+    }
+
     /// 在测试数据库中创建测试账号和角色
     fn create_test_account_role(conn: &rusqlite::Connection) -> (String, String) {
         let account_id = Uuid::new_v4().to_string();
@@ -4260,6 +5258,209 @@ mod tests {
         assert_eq!(confirmed_json["date"].as_i64(), Some(record_date));
     }
 
+    /// 同一账号共用一把扫描锁，不同账号互不阻塞
+    #[test]
+    fn test_account_scan_lock_per_account() {
+        let first = account_scan_lock("lock_test_a");
+        let same = account_scan_lock("lock_test_a");
+        let other = account_scan_lock("lock_test_b");
+        assert!(Arc::ptr_eq(&first, &same));
+        assert!(!Arc::ptr_eq(&first, &other));
+
+        let _guard = first.lock().unwrap();
+        assert!(same.try_lock().is_err());
+        assert!(other.try_lock().is_ok());
+    }
+
+    /// GKP 导入新建的 pending 记录被扫描更新时，收支和来源保持账本数据
+    #[test]
+    fn test_scan_keeps_gkp_import_gold() {
+        let conn = crate::db::open_test_db();
+        let record_date = 1_760_000_000_000_i64;
+        let data = serde_json::json!({
+            "id": "gkp1", "accountId": "acc1", "roleId": "role1", "raidName": "25人普通河阳之战",
+            "date": record_date, "goldIncome": 2400, "goldExpense": 52000,
+            "goldIncomeCopper": 24_000_000, "goldExpenseCopper": 520_000_000,
+            "hasXuanjing": false, "source": "gkp", "status": "pending",
+            "gkp": { "auctionTotal": 60000 },
+        });
+        conn.execute(
+            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, record_type, source, status)
+             VALUES ('gkp1', ?1, '河阳之战', 'acc1', 'role1', ?2, 'raid', 'gkp_import', 'pending')",
+            sql_params![data.to_string(), record_date],
+        )
+        .unwrap();
+
+        let instance = RaidInstance {
+            account_id: "acc1".to_string(),
+            raid_display_name: "25人普通河阳之战".to_string(),
+            map_id: 0,
+            start_time: record_date,
+            end_time: record_date,
+            last_jcl_time: record_date,
+            bosses_killed: Vec::new(),
+            jcl_files: Vec::new(),
+            jcl_boss_names: Vec::new(),
+            boss_kill_count: 0,
+            first_gold_time: record_date,
+            attempts: Vec::new(),
+            party: Vec::new(),
+        };
+        upsert_raid_drop_record(
+            &conn,
+            &instance,
+            &["玄晶·翻天卷".to_string()],
+            &[],
+            &[],
+            &[],
+            &Some("role1".to_string()),
+            "张三",
+            "梦江南",
+            "电信五区",
+            "河阳之战",
+            "25人普通河阳之战",
+            Money::from_gold(100),
+            Money::ZERO,
+            &[],
+            &[],
+            &[],
+            &[],
+            "pending",
+        )
+        .unwrap();
+
+        let (source, data): (String, String) = conn
+            .query_row(
+                "SELECT source, data FROM records WHERE id = 'gkp1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(source, "gkp_import");
+        assert_eq!(json["source"], "gkp");
+        assert_eq!(json["goldIncome"], 2400);
+        assert_eq!(json["goldExpense"], 52000);
+        assert_eq!(json["gkp"]["auctionTotal"], 60000);
+        assert_eq!(json["drops"], serde_json::json!(["玄晶·翻天卷"]));
+    }
+
+    /// CD 窗口内已有手工记录时，多余的 pending 记录连同附属数据移入回收站
+    #[test]
+    fn test_scan_trashes_pending_when_manual_record_exists() {
+        let conn = crate::db::open_test_db();
+        let record_date = 1_760_000_000_000_i64;
+        let pending = serde_json::json!({
+            "id": "p1", "accountId": "acc1", "roleId": "role1", "raidName": "25人普通河阳之战",
+            "date": record_date, "goldIncome": 0, "hasXuanjing": false,
+            "source": "auto", "status": "pending",
+        });
+        let manual = serde_json::json!({
+            "id": "m1", "accountId": "acc1", "roleId": "role1", "raidName": "25人普通河阳之战",
+            "date": record_date, "goldIncome": 3000, "hasXuanjing": false,
+        });
+        conn.execute(
+            "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, record_type, source, status)
+             VALUES ('p1', ?1, '河阳之战', 'acc1', 'role1', ?3, 'raid', 'auto_scan', 'pending'),
+                    ('m1', ?2, '25人普通河阳之战', 'acc1', 'role1', ?3, 'raid', 'manual', 'confirmed')",
+            sql_params![pending.to_string(), manual.to_string(), record_date],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, boss_hp_percent, created_at)
+             VALUES ('p1', '笑妆娘', 'a.jcl', 1, 0, 1000, 1000, 1, 0.0, '')",
+            [],
+        )
+        .unwrap();
+
+        let instance = RaidInstance {
+            account_id: "acc1".to_string(),
+            raid_display_name: "25人普通河阳之战".to_string(),
+            map_id: 0,
+            start_time: record_date,
+            end_time: record_date,
+            last_jcl_time: record_date,
+            bosses_killed: Vec::new(),
+            jcl_files: Vec::new(),
+            jcl_boss_names: Vec::new(),
+            boss_kill_count: 0,
+            first_gold_time: record_date,
+            attempts: Vec::new(),
+            party: Vec::new(),
+        };
+        upsert_raid_drop_record(
+            &conn,
+            &instance,
+            &[],
+            &[],
+            &[],
+            &[],
+            &Some("role1".to_string()),
+            "张三",
+            "梦江南",
+            "电信五区",
+            "河阳之战",
+            "25人普通河阳之战",
+            Money::ZERO,
+            Money::ZERO,
+            &[],
+            &[],
+            &[],
+            &[],
+            "pending",
+        )
+        .unwrap();
+
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM records"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM raid_attempts"), 0);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM trash_items WHERE entity_id = 'p1'"),
+            1
+        );
+    }
+
+    /// 只读模式解析角色身份时不修复孤儿记录，数据库保持不变
+    #[test]
+    fn test_resolve_role_identity_read_only() {
+        let conn = crate::db::open_test_db();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, account_name) VALUES ('acc1', '大号');
+            INSERT INTO roles (id, account_id, name, server) VALUES ('role1', 'acc1', '张三', '梦江南');
+            INSERT INTO records (id, data, account_id, role_id, record_date)
+            VALUES ('r1', '{\"id\":\"r1\",\"accountId\":\"old\"}', 'old', 'role1', 1760000000000);",
+        )
+        .unwrap();
+        let account_dir = std::env::temp_dir().join(format!(
+            "jx3-raid-manager-identity-{}-{}",
+            std::process::id(),
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+        ));
+        fs::create_dir_all(&account_dir).unwrap();
+        let (info, _, _) = encoding_rs::GBK
+            .encode("return {uid=\"1001\",name=\"张三\",server=\"梦江南\",region=\"电信五区\"}");
+        fs::write(account_dir.join("info.jx3dat"), info).unwrap();
+
+        let snapshot = |conn: &rusqlite::Connection| -> (String, String) {
+            conn.query_row("SELECT account_id, data FROM records WHERE id = 'r1'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap()
+        };
+        let before = snapshot(&conn);
+        let identity = resolve_db_role_identity(&conn, &account_dir, &account_dir, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(identity.account_id, "acc1");
+        assert_eq!(snapshot(&conn), before);
+
+        resolve_db_role_identity(&conn, &account_dir, &account_dir, true).unwrap();
+        let (account_id, data) = snapshot(&conn);
+        assert_eq!(account_id, "acc1");
+        assert!(data.contains("\"accountId\":\"acc1\""));
+        fs::remove_dir_all(&account_dir).ok();
+    }
+
     /// 测试完整 reject 流程：
     /// 插入 pending 记录 → 驳回 → 验证状态更新为 rejected
     #[test]
@@ -4398,6 +5599,7 @@ mod tests {
             name: "阆风悬城".to_string(),
             raid_id: "25人普通阆风悬城".to_string(),
             bosses: vec![("1".to_string(), "笑妆娘".to_string())],
+            boss_rules: vec![],
         }];
 
         let result = match_raid_name("25人普通阆风悬城", &raids);
@@ -4411,6 +5613,7 @@ mod tests {
             name: "阆风悬城".to_string(),
             raid_id: "25人英雄阆风悬城".to_string(),
             bosses: vec![("1".to_string(), "笑妆娘".to_string())],
+            boss_rules: vec![],
         }];
 
         // JCL 名后缀匹配 raids.id
@@ -4438,16 +5641,19 @@ mod tests {
                 name: "阆风悬城".to_string(),
                 raid_id: "25人普通阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
             RaidEntry {
                 name: "阆风悬城".to_string(),
                 raid_id: "10人普通阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
             RaidEntry {
                 name: "阆风悬城".to_string(),
                 raid_id: "25人英雄阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
         ];
 
@@ -4464,16 +5670,19 @@ mod tests {
                 name: "阆风悬城".to_string(),
                 raid_id: "10人普通阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
             RaidEntry {
                 name: "阆风悬城".to_string(),
                 raid_id: "25人普通阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
             RaidEntry {
                 name: "阆风悬城".to_string(),
                 raid_id: "25人英雄阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
         ];
 
@@ -4490,16 +5699,19 @@ mod tests {
                 name: "阆风悬城".to_string(),
                 raid_id: "10人普通阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
             RaidEntry {
                 name: "阆风悬城".to_string(),
                 raid_id: "25人普通阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
             RaidEntry {
                 name: "阆风悬城".to_string(),
                 raid_id: "25人英雄阆风悬城".to_string(),
                 bosses: vec![],
+                boss_rules: vec![],
             },
         ];
 
@@ -4533,11 +5745,11 @@ mod tests {
         let mut jcl_analyses: HashMap<String, JclAnalysis> = HashMap::new();
         jcl_analyses.insert(
             "2026-06-13-18-13-59-25人普通阆风悬城(794)-笑妆娘(137088).jcl".to_string(),
-            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000, fight_end_ms: 1718265299000, is_kill: true },
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000, fight_end_ms: 1718265299000, is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         jcl_analyses.insert(
             "2026-06-13-18-30-00-25人普通阆风悬城(794)-唐醉(137089).jcl".to_string(),
-            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: 1718266200000, fight_end_ms: 1718266260000, is_kill: true },
+            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: 1718266200000, fight_end_ms: 1718266260000, is_kill: true, boss_hp_percent: None, players: vec![] },
         );
 
         let instances = cluster_raid_instances("test_account", jcl_files, &jcl_analyses, &[]);
@@ -4546,6 +5758,57 @@ mod tests {
         assert_eq!(instances[0].boss_kill_count, 2);
     }
 
+    #[test]
+    fn test_cluster_raid_instances_keeps_wipe_attempts() {
+        let wipe = "2026-06-13-18-13-59-25人普通阆风悬城(794)-笑妆娘(137088).jcl";
+        let kill = "2026-06-13-18-25-00-25人普通阆风悬城(794)-笑妆娘(137088).jcl";
+        let jcl_files = vec![
+            JclFileInfo {
+                file_name: wipe.to_string(),
+                timestamp: 1718265239000,
+                raid_display_name: "25人普通阆风悬城".to_string(),
+                raid_id: 794,
+                boss_name: "笑妆娘".to_string(),
+                boss_id: 137088,
+            },
+            JclFileInfo {
+                file_name: kill.to_string(),
+                timestamp: 1718265900000,
+                raid_display_name: "25人普通阆风悬城".to_string(),
+                raid_id: 794,
+                boss_name: "笑妆娘".to_string(),
+                boss_id: 137088,
+            },
+        ];
+
+        let mut jcl_analyses: HashMap<String, JclAnalysis> = HashMap::new();
+        jcl_analyses.insert(
+            wipe.to_string(),
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265240000, fight_end_ms: 1718265420000, is_kill: false, boss_hp_percent: Some(35.5), players: vec![] },
+        );
+        jcl_analyses.insert(
+            kill.to_string(),
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 0, fight_end_ms: 0, is_kill: true, boss_hp_percent: Some(0.0), players: vec![] },
+        );
+
+        let instances = cluster_raid_instances("test_account", jcl_files, &jcl_analyses, &[]);
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].jcl_files, vec![kill.to_string()]);
+
+        let attempts = &instances[0].attempts;
+        assert_eq!(attempts.len(), 2, "灭团也应保留为战斗尝试");
+        assert_eq!(attempts[0].jcl_file, wipe);
+        assert_eq!(attempts[0].attempt_index, 1);
+        assert!(!attempts[0].is_kill);
+        assert_eq!(attempts[0].duration_ms, 180_000);
+        assert_eq!(attempts[0].boss_hp_percent, Some(35.5));
+        assert_eq!(attempts[1].attempt_index, 2);
+        assert!(attempts[1].is_kill);
+        // 无 FIGHT_TIME 时回退到文件名时间
+        assert_eq!(attempts[1].start_time, 1718265900000);
+        assert_eq!(attempts[1].duration_ms, 0);
+    }
+
     #[test]
     fn test_cluster_raid_instances_different_raids() {
         let jcl_files = vec![
@@ -4571,11 +5834,11 @@ mod tests {
         let mut jcl_analyses: HashMap<String, JclAnalysis> = HashMap::new();
         jcl_analyses.insert(
             "2026-06-13-18-13-59-25人普通阆风悬城(794)-笑妆娘(137088).jcl".to_string(),
-            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000, fight_end_ms: 1718265299000, is_kill: true },
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000, fight_end_ms: 1718265299000, is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         jcl_analyses.insert(
             "2026-06-13-19-00-00-英雄天龙寺(683)-枯荣大师(129124).jcl".to_string(),
-            JclAnalysis { boss_name: Some("枯荣大师".to_string()), fight_start_ms: 1718266800000, fight_end_ms: 1718266860000, is_kill: true },
+            JclAnalysis { boss_name: Some("枯荣大师".to_string()), fight_start_ms: 1718266800000, fight_end_ms: 1718266860000, is_kill: true, boss_hp_percent: None, players: vec![] },
         );
 
         let instances = cluster_raid_instances("test_account", jcl_files, &jcl_analyses, &[]);
@@ -4608,11 +5871,11 @@ mod tests {
         let mut jcl_analyses: HashMap<String, JclAnalysis> = HashMap::new();
         jcl_analyses.insert(
             "2026-06-13-18-13-59-25人普通阆风悬城(794)-笑妆娘(137088).jcl".to_string(),
-            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000 + 60_000, fight_end_ms: 1718265239000 + 120_000, is_kill: true },
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000 + 60_000, fight_end_ms: 1718265239000 + 120_000, is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         jcl_analyses.insert(
             "2026-06-13-18-30-00-25人普通阆风悬城(794)-唐醉(137089).jcl".to_string(),
-            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: 1718266200000 + 60_000, fight_end_ms: 1718266200000 + 120_000, is_kill: false },
+            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: 1718266200000 + 60_000, fight_end_ms: 1718266200000 + 120_000, is_kill: false, boss_hp_percent: None, players: vec![] },
         );
 
         let instances = cluster_raid_instances("test_account", jcl_files, &jcl_analyses, &[]);
@@ -4655,11 +5918,11 @@ mod tests {
         let mut jcl_analyses: HashMap<String, JclAnalysis> = HashMap::new();
         jcl_analyses.insert(
             "2026-06-13-18-13-59-25人普通阆风悬城(794)-笑妆娘(137088).jcl".to_string(),
-            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000, fight_end_ms: 1718265299000, is_kill: false },
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: 1718265239000, fight_end_ms: 1718265299000, is_kill: false, boss_hp_percent: None, players: vec![] },
         );
         jcl_analyses.insert(
             "2026-06-13-18-30-00-25人普通阆风悬城(794)-唐醉(137089).jcl".to_string(),
-            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: 1718266200000, fight_end_ms: 1718266260000, is_kill: false },
+            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: 1718266200000, fight_end_ms: 1718266260000, is_kill: false, boss_hp_percent: None, players: vec![] },
         );
 
         let instances = cluster_raid_instances("test_account", jcl_files, &jcl_analyses, &[]);
@@ -4737,32 +6000,32 @@ mod tests {
         // 22:10 笑妆娘 — 拉托
         jcl_analyses.insert(
             "2026-06-29-22-10-53-25人英雄阆风悬城(795)-笑妆娘(137205).jcl".to_string(),
-            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: ts(22, 10, 53), fight_end_ms: ts(22, 16, 0), is_kill: false },
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: ts(22, 10, 53), fight_end_ms: ts(22, 16, 0), is_kill: false, boss_hp_percent: None, players: vec![] },
         );
         // 22:33 笑妆娘 — 通关
         jcl_analyses.insert(
             "2026-06-29-22-33-51-25人英雄阆风悬城(795)-笑妆娘(137205).jcl".to_string(),
-            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: ts(22, 33, 51), fight_end_ms: ts(22, 38, 20), is_kill: true },
+            JclAnalysis { boss_name: Some("笑妆娘".to_string()), fight_start_ms: ts(22, 33, 51), fight_end_ms: ts(22, 38, 20), is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         // 22:41 唐醉 — 通关
         jcl_analyses.insert(
             "2026-06-29-22-41-58-25人英雄阆风悬城(795)-唐醉(137117).jcl".to_string(),
-            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: ts(22, 41, 58), fight_end_ms: ts(22, 47, 24), is_kill: true },
+            JclAnalysis { boss_name: Some("唐醉".to_string()), fight_start_ms: ts(22, 41, 58), fight_end_ms: ts(22, 47, 24), is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         // 22:52 柳公子 — 通关
         jcl_analyses.insert(
             "2026-06-29-22-52-20-25人英雄阆风悬城(795)-柳公子(137135).jcl".to_string(),
-            JclAnalysis { boss_name: Some("柳公子".to_string()), fight_start_ms: ts(22, 52, 20), fight_end_ms: ts(22, 58, 28), is_kill: true },
+            JclAnalysis { boss_name: Some("柳公子".to_string()), fight_start_ms: ts(22, 52, 20), fight_end_ms: ts(22, 58, 28), is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         // 23:03 阿史那承庆 — 通关
         jcl_analyses.insert(
             "2026-06-29-23-03-37-25人英雄阆风悬城(795)-阿史那承庆(137130).jcl".to_string(),
-            JclAnalysis { boss_name: Some("阿史那承庆".to_string()), fight_start_ms: ts(23, 3, 37), fight_end_ms: ts(23, 10, 22), is_kill: true },
+            JclAnalysis { boss_name: Some("阿史那承庆".to_string()), fight_start_ms: ts(23, 3, 37), fight_end_ms: ts(23, 10, 22), is_kill: true, boss_hp_percent: None, players: vec![] },
         );
         // 23:14 须罗巨傀 — 通关（内容搜索到"唐怀仁"）
         jcl_analyses.insert(
             "2026-06-29-23-14-04-25人英雄阆风悬城(795)-须罗巨傀(137175).jcl".to_string(),
-            JclAnalysis { boss_name: Some("唐怀仁".to_string()), fight_start_ms: ts(23, 14, 4), fight_end_ms: ts(23, 21, 59), is_kill: true },
+            JclAnalysis { boss_name: Some("唐怀仁".to_string()), fight_start_ms: ts(23, 14, 4), fight_end_ms: ts(23, 21, 59), is_kill: true, boss_hp_percent: None, players: vec![] },
         );
 
         let instances = cluster_raid_instances("test_account", jcl_files, &jcl_analyses, &[]);
@@ -4801,7 +6064,7 @@ mod tests {
         let msg = r#"<text>text="你获得：" font= font=10 r=170 g=150 b=30 </text><text>text="1"  font=10 r=170 g=150 b=30  name="Text_GoldB" eventid=0 valign=1 </text><animate>path="UI/Image/Common/Money.UITex"  name="Image_GoldB" eventid=0 group=41  </animate><text>text="6082"  font=10 r=170 g=150 b=30  name="Text_Gold" eventid=0 valign=1 </text><image>path="UI/Image/Common/Money.UITex"  name="Image_Gold" eventid=0 frame=0  </image><text>text="0"  font=10 r=170 g=150 b=30  name="Text_Silver" eventid=0 valign=1 </text><image>path="UI/Image/Common/Money.UITex"  name="Image_Silver" eventid=0 frame=2  </image><text>text="0"  font=10 r=170 g=150 b=30  name="Text_Copper" eventid=0 valign=1 </text><image>path="UI/Image/Common/Money.UITex"  name="Image_Copper" eventid=0 frame=1  </image><text>text="。" font= font=10 r=170 g=150 b=30 </text>"#;
 
         let result = parse_money_from_msg(msg).expect("应解析成功");
-        assert_eq!(result, (16082, 0, 0), "1万金+6082金=16082金");
+        assert_eq!(result.parts(), (16082, 0, 0), "1万金+6082金=16082金");
     }
 
     /// 测试 parse_money_from_msg 解析普通金额（无 Text_GoldB）
@@ -4810,28 +6073,152 @@ mod tests {
         // 10金（BOSS击杀奖励）
         let msg = r#"<text>text="你获得：" font= font=10 r=170 g=150 b=30 </text><text>text="10"  font=10 r=170 g=150 b=30  name="Text_Gold" eventid=0 valign=1 </text><image>path="UI/Image/Common/Money.UITex"  name="Image_Gold" eventid=0 frame=0  </image><text>text="0"  font=10 r=170 g=150 b=30  name="Text_Silver" eventid=0 valign=1 </text><text>text="0"  font=10 r=170 g=150 b=30  name="Text_Copper" eventid=0 valign=1 </text>"#;
         let result = parse_money_from_msg(msg).expect("应解析成功");
-        assert_eq!(result, (10, 0, 0), "10金");
+        assert_eq!(result.parts(), (10, 0, 0), "10金");
 
         // 147金76银53铜
         let msg2 = r#"<text>text="你获得：" font= font=10 r=170 g=150 b=30 </text><text>text="147"  font=10 r=170 g=150 b=30  name="Text_Gold" eventid=0 valign=1 </text><text>text="76"  font=10 r=170 g=150 b=30  name="Text_Silver" eventid=0 valign=1 </text><text>text="53"  font=10 r=170 g=150 b=30  name="Text_Copper" eventid=0 valign=1 </text>"#;
         let result2 = parse_money_from_msg(msg2).expect("应解析成功");
-        assert_eq!(result2, (147, 76, 53), "147金76银53铜");
+        assert_eq!(result2.parts(), (147, 76, 53), "147金76银53铜");
     }
 
     /// 测试 parse_expense_amount 解析支出金额字符串
     #[test]
     fn test_parse_expense_amount() {
         // 纯金
-        assert_eq!(parse_expense_amount("2000金"), 2000, "纯金");
+        assert_eq!(parse_expense_amount("2000金").gold(), 2000, "纯金");
         // 0金
-        assert_eq!(parse_expense_amount("0金"), 0, "0金");
+        assert_eq!(parse_expense_amount("0金").gold(), 0, "0金");
         // 纯金砖
-        assert_eq!(parse_expense_amount("1金砖"), 10000, "1金砖=10000金");
-        assert_eq!(parse_expense_amount("4金砖"), 40000, "4金砖=40000金");
+        assert_eq!(parse_expense_amount("1金砖").gold(), 10000, "1金砖=10000金");
+        assert_eq!(parse_expense_amount("4金砖").gold(), 40000, "4金砖=40000金");
         // 金砖+金
-        assert_eq!(parse_expense_amount("1金砖6000金"), 16000, "1金砖6000金=16000金");
-        assert_eq!(parse_expense_amount("9金砖9000金"), 99000, "9金砖9000金=99000金");
-        assert_eq!(parse_expense_amount("2金砖9000金"), 29000, "2金砖9000金=29000金");
+        assert_eq!(parse_expense_amount("1金砖6000金").gold(), 16000, "1金砖6000金=16000金");
+        assert_eq!(parse_expense_amount("9金砖9000金").gold(), 99000, "9金砖9000金=99000金");
+        assert_eq!(parse_expense_amount("2金砖9000金").gold(), 29000, "2金砖9000金=29000金");
+    }
+
+    /// 测试 chatlog 增量读取：只读取高水位之后的新行，高水位同一秒内的行不重复
+    #[test]
+    fn test_read_chatlog_tail_incremental() {
+        let temp_dir = std::env::temp_dir().join(format!(
+            "jx3-test-chatlog-tail-{}-{}",
+            std::process::id(),
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+        ));
+        fs::create_dir_all(&temp_dir).expect("创建临时目录失败");
+        let path = temp_dir.join("chatlog_test.v2.db");
+        let chatlog = rusqlite::Connection::open(&path).unwrap();
+        chatlog
+            .execute_batch("CREATE TABLE ChatLog (type TEXT, text TEXT, msg TEXT, time INTEGER);")
+            .unwrap();
+        let insert = |msg_type: &str, text: &str, time: i64| {
+            chatlog
+                .execute(
+                    "INSERT INTO ChatLog VALUES (?1, ?2, '', ?3)",
+                    sql_params![msg_type, text, time],
+                )
+                .unwrap();
+        };
+        insert("MSG_ROOM", "每人底薪：3000金", 50);
+        insert("MSG_ITEM", "分配者将：[玄晶]分配给糯闪。", 100);
+        insert("MSG_ROOM", "[团长]花费[2000金]购买了[五行石]", 200);
+        insert("MSG_ROOM", "[团长]花费[2000金]购买了[五行石]", 200);
+        insert("MSG_SYS", "无关消息", 200);
+
+        // 首次整体加载不去重：同一秒内容相同的两次购买都保留
+        let mut tail = ChatlogTail::default();
+        assert_eq!(read_chatlog_tail(&mut tail, &path, 60).unwrap(), 3);
+        assert_eq!(tail.high_water_sec, 200);
+
+        // 高水位同一秒的新消息（含与已读内容相同的第三次购买）与之后的消息
+        insert("MSG_ROOM", "[团长]花费[2000金]购买了[五行石]", 200);
+        insert("MSG_ROOM", "[团长]花费[300金]购买了[挂件]", 200);
+        insert("MSG_ROOM", "每人底薪：3200金", 300);
+        assert_eq!(read_chatlog_tail(&mut tail, &path, 60).unwrap(), 3);
+        // 无新行
+        assert_eq!(read_chatlog_tail(&mut tail, &path, 60).unwrap(), 0);
+        let times: Vec<i64> = tail.records.iter().map(|r| r.time_sec).collect();
+        assert_eq!(times, vec![100, 200, 200, 200, 200, 300]);
+
+        // 窗口前移：丢弃旧记录，不重新读取
+        assert_eq!(read_chatlog_tail(&mut tail, &path, 150).unwrap(), 0);
+        assert_eq!(tail.records.len(), 5);
+
+        // 窗口后移：从新起点整体重新加载
+        assert_eq!(read_chatlog_tail(&mut tail, &path, 0).unwrap(), 7);
+
+        drop(chatlog);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    /// 测试 extract_drops_from_records 输出的收支来源明细（底薪覆盖、支出去重、BOSS 奖励）
+    #[test]
+    fn test_extract_drops_line_items() {
+        let money = |gold: i64| {
+            format!(
+                r#"<text>text="{}" name="Text_Gold" </text><text>text="0" name="Text_Silver" </text><text>text="0" name="Text_Copper" </text>"#,
+                gold
+            )
+        };
+        let record = |msg_type: &str, text: &str, msg: String, time_sec: i64| ChatlogRecord {
+            row_key: ChatlogRowKey { file: 0, rowid: time_sec },
+            msg_type: msg_type.to_string(),
+            text: text.to_string(),
+            msg,
+            time_sec,
+        };
+        let records = vec![
+            record("MSG_MONEY", "你获得：10金", money(10), 100),
+            record("MSG_ROOM", "[团长]花费[2000金]购买了[玄晶]", String::new(), 110),
+            record("MSG_WHISPER", "[团长]花费[2000金]购买了[玄晶]", String::new(), 112),
+            record("MSG_ROOM", "每人底薪：3000金", String::new(), 200),
+            record("MSG_ROOM", "每人底薪：3200金", String::new(), 210),
+            record("MSG_MONEY", "你获得：3200金", money(3200), 220),
+        ];
+
+        let extraction = extract_drops_from_records(&records, 0, 1_000_000, "团长");
+        assert_eq!(extraction.base_salary, Some(Money::from_gold(3200)));
+        assert_eq!(extraction.expense, Money::from_gold(2000));
+        assert_eq!(extraction.boss_kill_count, 1);
+        assert_eq!(extraction.income_records, vec![(220, Money::from_gold(3200))]);
+
+        let kinds: Vec<ChatlogItemKind> = extraction.line_items.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ChatlogItemKind::BossReward,
+                ChatlogItemKind::Expense,
+                ChatlogItemKind::AuctionItem,
+                ChatlogItemKind::Expense,
+                ChatlogItemKind::Salary,
+                ChatlogItemKind::Salary,
+                ChatlogItemKind::Income,
+            ]
+        );
+        let items = &extraction.line_items;
+        assert!(!items[0].counted, "BOSS 10金不计入收入");
+        assert!(items[1].counted);
+        assert_eq!(items[1].buyer.as_deref(), Some("团长"));
+        assert_eq!(items[1].amount, Some(Money::from_gold(2000)));
+        assert_eq!(items[2].amount, Some(Money::from_gold(2000)), "拍卖物品带出买家与价格");
+        assert!(!items[3].counted, "密语频道重复支出已去重");
+        assert_eq!(items[3].channel, "MSG_WHISPER");
+        assert!(!items[4].counted, "底薪取最后一条");
+        assert!(items[5].counted);
+
+        let mut extraction = extraction;
+        extraction.mark_selected_income(220, Money::from_gold(3200), "最终收入");
+        assert_eq!(extraction.line_items[6].note.as_deref(), Some("最终收入"));
+
+        // 底薪与明细金额保留银铜部分
+        let silver = extract_drops_from_records(
+            &[record("MSG_ROOM", "每人底薪：3500金50银", String::new(), 300)],
+            0,
+            1_000_000,
+            "团长",
+        );
+        assert_eq!(silver.base_salary, Some(Money::from_parts(3500, 50, 0)));
+        assert_eq!(silver.line_items[0].amount, Some(Money::from_parts(3500, 50, 0)));
     }
 
 
@@ -4882,15 +6269,15 @@ mod tests {
             };
             let raid_bosses = &raid_entry.bosses;
             let jcl_path = combat_logs_dir.join(&jcl.file_name);
-            let mut analysis = analyze_jcl_cached(&conn, &jcl_path, &jcl.boss_name, jcl.boss_id, raid_bosses).unwrap_or(JclAnalysis {
+            let mut analysis = analyze_jcl_cached(&conn, &jcl_path, &jcl.boss_name, jcl.boss_id, raid_bosses, &raid_entry.boss_rules).unwrap_or(JclAnalysis {
                 boss_name: None,
                 fight_start_ms: 0,
                 fight_end_ms: 0,
                 is_kill: false,
+                boss_hp_percent: None,
+                players: Vec::new(),
             });
-            if is_valid_boss(&jcl.boss_name, raid_bosses) {
-                analysis.boss_name = Some(jcl.boss_name.clone());
-            }
+            apply_file_boss_name(&mut analysis, jcl, raid_entry);
             jcl_analyses.insert(jcl.file_name.clone(), analysis);
         }
         println!("[DRY RUN] JCL 分析完成: {} 个", jcl_analyses.len());
@@ -4914,12 +6301,17 @@ mod tests {
             let chatlog_start = inst.first_gold_time;
             let chatlog_end = inst.last_jcl_time + 2 * 60 * 60 * 1000;
             let mut drops: Vec<String> = Vec::new();
-            let mut base_salary: Option<i64> = None;
-            let mut other_income: i64 = 0;
-            let mut total_expense: i64 = 0;
+            let mut base_salary: Option<Money> = None;
+            let mut other_income = Money::ZERO;
+            let mut total_expense = Money::ZERO;
             for chatlog_path in &chatlog_files {
-                if let Ok((chatlog_drops, salary, income, expense, _, _, _, _)) =
-                    extract_drops_from_chatlog(chatlog_path, chatlog_start, chatlog_end, "少年白了发")
+                if let Ok(ChatlogExtraction {
+                    drops: chatlog_drops,
+                    base_salary: salary,
+                    other_income: income,
+                    expense,
+                    ..
+                }) = extract_drops_from_chatlog(chatlog_path, chatlog_start, chatlog_end, "少年白了发")
                 {
                     for drop in chatlog_drops {
                         if !drops.contains(&drop) {
@@ -4939,7 +6331,7 @@ mod tests {
             println!("  金币收入: {} (手工记录: 16082)", total_gold);
 
             if inst.raid_display_name == "25人英雄阆风悬城" {
-                heroic_gold = total_gold;
+                heroic_gold = total_gold.gold();
             }
         }
 
@@ -5064,7 +6456,7 @@ mod tests {
 
         // 1. 验证 resolve_db_role_identity 能识别糯闪（修复前返回 None）
         let conn = db::init_db().expect("初始化数据库失败");
-        let identity = resolve_db_role_identity(&conn, &account_dir, &game_dir)
+        let identity = resolve_db_role_identity(&conn, &account_dir, &game_dir, true)
             .expect("resolve_db_role_identity 失败")
             .expect("糯闪账号应能被识别（修复前会被跳过）");
 
@@ -5128,16 +6520,17 @@ mod tests {
                 &jcl.boss_name,
                 jcl.boss_id,
                 raid_bosses,
+                &raid_entry.boss_rules,
             )
             .unwrap_or(JclAnalysis {
                 boss_name: None,
                 fight_start_ms: 0,
                 fight_end_ms: 0,
                 is_kill: false,
+                boss_hp_percent: None,
+                players: Vec::new(),
             });
-            if is_valid_boss(&jcl.boss_name, raid_bosses) {
-                analysis.boss_name = Some(jcl.boss_name.clone());
-            }
+            apply_file_boss_name(&mut analysis, jcl, raid_entry);
             jcl_analyses.insert(jcl.file_name.clone(), analysis);
         }
 
@@ -5199,12 +6592,17 @@ mod tests {
         );
 
         let mut drops: Vec<String> = Vec::new();
-        let mut base_salary: Option<i64> = None;
-        let mut other_income: i64 = 0;
-        let mut total_expense: i64 = 0;
+        let mut base_salary: Option<Money> = None;
+        let mut other_income = Money::ZERO;
+        let mut total_expense = Money::ZERO;
         for chatlog_path in &chatlog_files {
-            if let Ok((chatlog_drops, salary, income, expense, _, _, _, _)) =
-                extract_drops_from_chatlog(chatlog_path, chatlog_start, chatlog_end, "糯闪")
+            if let Ok(ChatlogExtraction {
+                drops: chatlog_drops,
+                base_salary: salary,
+                other_income: income,
+                expense,
+                ..
+            }) = extract_drops_from_chatlog(chatlog_path, chatlog_start, chatlog_end, "糯闪")
             {
                 for drop in chatlog_drops {
                     if !drops.contains(&drop) {
@@ -5224,9 +6622,9 @@ mod tests {
         println!("  掉落物数量: {}", drops.len());
         println!("  掉落物列表: {:?}", drops);
         println!("  底薪: {:?}", base_salary);
-        println!("  其他收入合计: {} 金", other_income);
-        println!("  支出合计: {} 金", total_expense);
-        println!("  最终收入: {} 金", total_gold);
+        println!("  其他收入合计: {}", other_income);
+        println!("  支出合计: {}", total_expense);
+        println!("  最终收入: {}", total_gold);
         println!("  副本开始: {}", fmt_ts(langfeng.first_gold_time));
         println!("  最后 JCL: {}", fmt_ts(langfeng.last_jcl_time));
     }
@@ -5286,6 +6684,8 @@ mod tests {
                 fight_start_ms: 1752168000000,
                 fight_end_ms: 1752168060000,
                 is_kill: true,
+                boss_hp_percent: None,
+                players: Vec::new(),
             },
         );
         jcl_analyses.insert(
@@ -5295,6 +6695,8 @@ mod tests {
                 fight_start_ms: 1752168900000,
                 fight_end_ms: 1752168960000,
                 is_kill: true,
+                boss_hp_percent: None,
+                players: Vec::new(),
             },
         );
 
@@ -5352,6 +6754,8 @@ mod tests {
                 fight_start_ms: 1752168000000,
                 fight_end_ms: 1752168060000,
                 is_kill: false,
+                boss_hp_percent: None,
+                players: Vec::new(),
             },
         );
 
@@ -5835,6 +7239,7 @@ mod tests {
             "笑妆娘",
             boss_template_id,
             &raid_bosses,
+            &[],
         )
         .expect("分析 JCL 失败");
 
@@ -5863,7 +7268,7 @@ mod tests {
                 sql_params![&p_str],
             )
             .expect("删除缓存失败");
-            let a = analyze_jcl_cached(&conn, &p, boss_name, *template_id, &raid_bosses)
+            let a = analyze_jcl_cached(&conn, &p, boss_name, *template_id, &raid_bosses, &[])
                 .expect("分析失败");
             println!(
                 "\n[对比] {} → boss={:?} is_kill={} start={} end={}",
@@ -6065,7 +7470,7 @@ mod tests {
             );
 
             // 调用 analyze_jcl 分析
-            let analysis = analyze_jcl(&full_path, &parsed.boss_name, parsed.boss_id, &raid_bosses)
+            let analysis = analyze_jcl(&full_path, &parsed.boss_name, parsed.boss_id, &raid_bosses, &[])
                 .expect("analyze_jcl 失败");
             println!(
                 "  → is_kill={} boss_name={:?} fight_start={} fight_end={}",
@@ -6247,6 +7652,7 @@ mod tests {
             "须罗巨傀",
             boss_template_id,
             &raid_bosses,
+            &[],
         )
         .expect("分析 JCL 失败");
 
@@ -6314,7 +7720,7 @@ mod tests {
             conn.execute("DELETE FROM jcl_cache WHERE file_path = ?1", sql_params![&path_str])
                 .expect("删除缓存失败");
 
-            let analysis = analyze_jcl_cached(&conn, &jcl_path, file_boss, *template_id, &raid_bosses)
+            let analysis = analyze_jcl_cached(&conn, &jcl_path, file_boss, *template_id, &raid_bosses, &[])
                 .expect("分析 JCL 失败");
 
             let dur = if analysis.fight_end_ms > analysis.fight_start_ms {
@@ -6399,7 +7805,7 @@ mod tests {
             conn.execute("DELETE FROM jcl_cache WHERE file_path = ?1", sql_params![&path_str])
                 .expect("删除缓存失败");
 
-            let analysis = analyze_jcl_cached(&conn, &jcl_path, &info.boss_name, info.boss_id, &raid_bosses)
+            let analysis = analyze_jcl_cached(&conn, &jcl_path, &info.boss_name, info.boss_id, &raid_bosses, &[])
                 .expect("分析 JCL 失败");
 
             println!(
@@ -6458,6 +7864,7 @@ mod tests {
             "笑妆娘",
             boss_template_id,
             &raid_bosses,
+            &[],
         )
         .expect("分析 JCL 失败");
 
@@ -6564,6 +7971,7 @@ mod tests {
                     &jcl.boss_name,
                     jcl.boss_id,
                     &raid_bosses,
+                    &[],
                 );
 
                 match analysis {
@@ -6623,7 +8031,7 @@ mod tests {
                 sql_params![&path_str],
             );
             // 重新解析
-            match analyze_jcl(path, boss_name, *boss_id, raid_bosses) {
+            match analyze_jcl(path, boss_name, *boss_id, raid_bosses, &[]) {
                 Some(a) => {
                     if a.is_kill {
                         reparse_kill += 1;
@@ -6696,7 +8104,7 @@ mod tests {
         );
         println!("[诊断] 已删除该 JCL 缓存，准备重新解析\n");
 
-        let analysis = analyze_jcl(&jcl_path, "阿史那承庆", 137130, &raid_bosses)
+        let analysis = analyze_jcl(&jcl_path, "阿史那承庆", 137130, &raid_bosses, &[])
             .expect("analyze_jcl 失败");
 
         println!("=== 7-26 阿史那承庆 JCL 分析结果 ===");
@@ -7239,7 +8647,7 @@ mod tests {
                 sql_params![&path_str],
             );
 
-            let analysis = analyze_jcl(&jcl_path, "阿史那承庆", 137130, &raid_bosses)
+            let analysis = analyze_jcl(&jcl_path, "阿史那承庆", 137130, &raid_bosses, &[])
                 .expect("analyze_jcl 失败");
 
             println!("fight_start_ms: {}", analysis.fight_start_ms);
@@ -0,0 +1,792 @@
+//! 茗伊战斗记录（MY_CombatLogs）JCL 文件解析
+//!
+//! JCL 为 GBK 编码的文本文件，每行一个事件：
+//! `CRC\tframe\tunix_ts\tclient_ms\tevent_type\t{LUA_DATA}`
+//!
+//! `JclReader` 逐行读取并把 LUA_DATA 解析为类型化的 `JclEvent`，
+//! 字段顺序基于茗伊 MY_CombatLogs.lua 源码中 `D.InsertLog(LOG_TYPE.xxx, {...})` 的调用。
+
+use std::io::BufRead;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::klua;
+
+/// MY_CombatLogs LOG_TYPE 编号
+pub mod log_type {
+    pub const FIGHT_TIME: u8 = 1;
+    pub const PLAYER_ENTER_SCENE: u8 = 2;
+    pub const PLAYER_LEAVE_SCENE: u8 = 3;
+    pub const PLAYER_INFO: u8 = 4;
+    pub const PLAYER_FIGHT_HINT: u8 = 5;
+    pub const NPC_ENTER_SCENE: u8 = 6;
+    pub const NPC_LEAVE_SCENE: u8 = 7;
+    pub const NPC_INFO: u8 = 8;
+    pub const NPC_FIGHT_HINT: u8 = 9;
+    pub const DOODAD_ENTER_SCENE: u8 = 10;
+    pub const DOODAD_LEAVE_SCENE: u8 = 11;
+    pub const DOODAD_INFO: u8 = 12;
+    pub const BUFF_UPDATE: u8 = 13;
+    pub const PLAYER_SAY: u8 = 14;
+    pub const WARNING_MESSAGE: u8 = 15;
+    pub const PARTY_ADD_MEMBER: u8 = 16;
+    pub const PARTY_SET_MEMBER_ONLINE_FLAG: u8 = 17;
+    pub const MSG_SYS: u8 = 18;
+    pub const SKILL_CAST_LOG: u8 = 19;
+    pub const SKILL_CAST_RESPOND_LOG: u8 = 20;
+    pub const SKILL_EFFECT_LOG: u8 = 21;
+    pub const SKILL_BLOCK_LOG: u8 = 22;
+    pub const SKILL_SHIELD_LOG: u8 = 23;
+    pub const SKILL_MISS_LOG: u8 = 24;
+    pub const SKILL_HIT_LOG: u8 = 25;
+    pub const SKILL_DODGE_LOG: u8 = 26;
+    pub const COMMON_HEALTH_LOG: u8 = 27;
+    pub const SYS_MSG_UI_OME_DEATH_NOTIFY: u8 = 28;
+}
+
+/// SKILL_EFFECT_LOG tResult 的键（游戏 SKILL_RESULT_TYPE 枚举）
+pub mod skill_result {
+    pub const PHYSICS_DAMAGE: usize = 0;
+    pub const SOLAR_MAGIC_DAMAGE: usize = 1;
+    pub const NEUTRAL_MAGIC_DAMAGE: usize = 2;
+    pub const LUNAR_MAGIC_DAMAGE: usize = 3;
+    pub const POISON_DAMAGE: usize = 4;
+    pub const REFLECTIED_DAMAGE: usize = 5;
+    pub const THERAPY: usize = 6;
+    pub const STEAL_LIFE: usize = 7;
+    pub const ABSORB_THERAPY: usize = 8;
+    pub const ABSORB_DAMAGE: usize = 9;
+    pub const SHIELD_DAMAGE: usize = 10;
+    pub const PARRY_DAMAGE: usize = 11;
+    pub const INSIGHT_DAMAGE: usize = 12;
+    pub const EFFECTIVE_DAMAGE: usize = 13;
+    pub const EFFECTIVE_THERAPY: usize = 14;
+    pub const TRANSFER_LIFE: usize = 15;
+    pub const TRANSFER_MANA: usize = 16;
+
+    /// 结果类型总数
+    pub const COUNT: usize = 17;
+}
+
+/// 技能效果数值（按 `skill_result` 下标存放，缺失的键为 0）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SkillResults(pub [i64; skill_result::COUNT]);
+
+impl SkillResults {
+    pub fn get(&self, key: usize) -> i64 {
+        self.0.get(key).copied().unwrap_or(0)
+    }
+
+    /// 原始伤害（外功 + 阳性 + 混元 + 阴性 + 毒性）
+    pub fn damage(&self) -> i64 {
+        self.0[skill_result::PHYSICS_DAMAGE..=skill_result::POISON_DAMAGE]
+            .iter()
+            .sum()
+    }
+
+    /// 有效伤害（扣除化解、吸收后的实际掉血）
+    pub fn effective_damage(&self) -> i64 {
+        self.get(skill_result::EFFECTIVE_DAMAGE)
+    }
+
+    /// 原始治疗量
+    pub fn therapy(&self) -> i64 {
+        self.get(skill_result::THERAPY)
+    }
+
+    /// 有效治疗量（扣除溢出后的实际回血）
+    pub fn effective_therapy(&self) -> i64 {
+        self.get(skill_result::EFFECTIVE_THERAPY)
+    }
+}
+
+/// 类型化的 JCL 事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum JclEvent {
+    /// 1: { bFighting, szUUID, nDuring }
+    FightTime {
+        fighting: bool,
+        uuid: String,
+        during: i64,
+    },
+    /// 2: { dwID }
+    PlayerEnterScene { id: i64 },
+    /// 3: { dwID }
+    PlayerLeaveScene { id: i64 },
+    /// 4: { dwID, szName, dwForceID, dwMountKungfuID, nEquipScore, ... }
+    PlayerInfo {
+        id: i64,
+        name: String,
+        force_id: i64,
+        kungfu_id: i64,
+        equip_score: i64,
+    },
+    /// 5: { dwID, bFight }
+    PlayerFightHint { id: i64, fighting: bool },
+    /// 6: { dwID }
+    NpcEnterScene { id: i64 },
+    /// 7: { dwID }
+    NpcLeaveScene { id: i64 },
+    /// 8: { dwID, szName, dwTemplateID, dwEmployer, nX, nY, nZ, nFaceDirection }
+    NpcInfo {
+        id: i64,
+        name: String,
+        template_id: i64,
+        employer_id: i64,
+        x: i64,
+        y: i64,
+        z: i64,
+        /// 缺失时为 -1
+        face_direction: i64,
+    },
+    /// 9: { dwID, bFight, fCurrentLife, fMaxLife, nCurrentMana, nMaxMana }
+    NpcFightHint {
+        id: i64,
+        fighting: bool,
+        /// 缺失时为 -1
+        current_life: f64,
+        max_life: f64,
+        current_mana: i64,
+        max_mana: i64,
+    },
+    /// 10: { dwID }
+    DoodadEnterScene { id: i64 },
+    /// 11: { dwID }
+    DoodadLeaveScene { id: i64 },
+    /// 12: { dwID, dwTemplateID, nX, nY, nZ }
+    DoodadInfo {
+        id: i64,
+        template_id: i64,
+        x: i64,
+        y: i64,
+        z: i64,
+    },
+    /// 13: { dwPlayerID, bDelete, nIndex, bCanCancel, dwBuffID, nStackNum, nEndFrame, bInit, nLevel, dwSkillSrcID }
+    BuffUpdate {
+        target_id: i64,
+        deleted: bool,
+        index: i64,
+        can_cancel: bool,
+        buff_id: i64,
+        stack_num: i64,
+        end_frame: i64,
+        init: bool,
+        level: i64,
+        source_id: i64,
+    },
+    /// 14: { szContent, dwTalkerID, nChannel, szName }
+    PlayerSay {
+        content: String,
+        talker_id: i64,
+        channel: i64,
+        talker_name: String,
+    },
+    /// 15: { szWarningType, szText }
+    WarningMessage { warning_type: String, text: String },
+    /// 16: { dwTeamID, dwMemberID, nGroupIndex }
+    PartyAddMember {
+        team_id: i64,
+        member_id: i64,
+        group_index: i64,
+    },
+    /// 17: { dwTeamID, dwMemberID, bOnline }
+    PartySetMemberOnlineFlag {
+        team_id: i64,
+        member_id: i64,
+        online: bool,
+    },
+    /// 18: { szMsg }
+    MsgSys { msg: String },
+    /// 19: { dwCaster, dwSkillID, dwLevel }
+    SkillCast {
+        caster_id: i64,
+        skill_id: i64,
+        skill_level: i64,
+    },
+    /// 20: { dwCaster, dwSkillID, dwLevel, nRespond }
+    SkillCastRespond {
+        caster_id: i64,
+        skill_id: i64,
+        skill_level: i64,
+        respond: i64,
+    },
+    /// 21: { dwCaster, dwTarget, bReact, nEffectType, dwEffectID, dwEffectLevel, bCriticalStrike, nCount, tResult }
+    SkillEffect {
+        caster_id: i64,
+        target_id: i64,
+        react: bool,
+        effect_type: i64,
+        effect_id: i64,
+        effect_level: i64,
+        critical: bool,
+        count: i64,
+        results: SkillResults,
+    },
+    /// 22: { dwCaster, dwTarget, nEffectType, dwEffectID, dwEffectLevel, nDamageType }
+    SkillBlock {
+        caster_id: i64,
+        target_id: i64,
+        effect_type: i64,
+        effect_id: i64,
+        effect_level: i64,
+        damage_type: i64,
+    },
+    /// 23: { dwCaster, dwTarget, nEffectType, dwEffectID, dwEffectLevel }
+    SkillShield {
+        caster_id: i64,
+        target_id: i64,
+        effect_type: i64,
+        effect_id: i64,
+        effect_level: i64,
+    },
+    /// 24: { dwCaster, dwTarget, nEffectType, dwEffectID, dwEffectLevel }
+    SkillMiss {
+        caster_id: i64,
+        target_id: i64,
+        effect_type: i64,
+        effect_id: i64,
+        effect_level: i64,
+    },
+    /// 25: { dwCaster, dwTarget, nEffectType, dwEffectID, dwEffectLevel }
+    SkillHit {
+        caster_id: i64,
+        target_id: i64,
+        effect_type: i64,
+        effect_id: i64,
+        effect_level: i64,
+    },
+    /// 26: { dwCaster, dwTarget, nEffectType, dwEffectID, dwEffectLevel }
+    SkillDodge {
+        caster_id: i64,
+        target_id: i64,
+        effect_type: i64,
+        effect_id: i64,
+        effect_level: i64,
+    },
+    /// 27: { dwCharacterID, nDeltaLife }
+    CommonHealth { character_id: i64, delta_life: i64 },
+    /// 28: { dwCharacterID, dwKiller }（JCL 中唯一的真正死亡事件）
+    DeathNotify { character_id: i64, killer_id: i64 },
+    /// 未知类型，保留原始数据
+    Unknown { log_type: u8, data: Value },
+}
+
+/// JCL 中的一行事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JclEntry {
+    /// 游戏逻辑帧
+    pub frame: i64,
+    /// Unix 时间戳（秒）
+    pub timestamp_sec: i64,
+    /// 客户端毫秒计时
+    pub client_ms: i64,
+    pub log_type: u8,
+    pub event: JclEvent,
+}
+
+impl JclEntry {
+    /// 事件时间（Unix 毫秒，秒级精度）
+    pub fn timestamp_ms(&self) -> i64 {
+        self.timestamp_sec * 1000
+    }
+}
+
+/// 逐行读取 JCL 的事件迭代器
+///
+/// 无法解析的行（截断、格式异常）跳过并计数，迭代结束时记录 debug 日志；
+/// 读取出错（文件被截断、锁定或 I/O 错误）时结束迭代。
+pub struct JclReader<R: BufRead> {
+    reader: R,
+    buf: Vec<u8>,
+    filter: Option<Vec<u8>>,
+    dropped: usize,
+}
+
+impl JclReader<std::io::BufReader<std::fs::File>> {
+    /// 打开 JCL 文件
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("打开 JCL 文件失败 {}: {}", path.display(), e))?;
+        Ok(Self::new(std::io::BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead> JclReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            filter: None,
+            dropped: 0,
+        }
+    }
+
+    /// 已跳过的无法解析的行数
+    pub fn dropped_lines(&self) -> usize {
+        self.dropped
+    }
+
+    fn finish(&mut self) {
+        if self.dropped > 0 {
+            log::debug!("[Jcl] 跳过 {} 行无法解析的记录", self.dropped);
+        }
+    }
+
+    /// 只产出指定类型的事件
+    ///
+    /// 过滤在 GBK 解码和 Lua 解析之前进行，大文件（45MB+）中技能类事件占绝大多数，
+    /// 只关心少数类型时能跳过大部分解析开销。
+    pub fn only(mut self, types: &[u8]) -> Self {
+        self.filter = Some(types.to_vec());
+        self
+    }
+}
+
+impl<R: BufRead> Iterator for JclReader<R> {
+    type Item = JclEntry;
+
+    fn next(&mut self) -> Option<JclEntry> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => {
+                    self.finish();
+                    return None;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("[Jcl] 读取 JCL 失败，停止解析: {}", e);
+                    self.finish();
+                    return None;
+                }
+            }
+            if self.buf.trim_ascii().is_empty() {
+                continue;
+            }
+            // 类型过滤在 GBK 解码和 Lua 解析之前进行
+            let log_type = line_log_type(&self.buf);
+            if let (Some(log_type), Some(types)) = (log_type, &self.filter) {
+                if !types.contains(&log_type) {
+                    continue;
+                }
+            }
+            match log_type.and_then(|log_type| parse_line(&self.buf, log_type)) {
+                Some(entry) => return Some(entry),
+                None => self.dropped += 1,
+            }
+        }
+    }
+}
+
+/// 第 5 列的事件类型
+fn line_log_type(line: &[u8]) -> Option<u8> {
+    parse_ascii(line.split(|&b| b == b'\t').nth(4)?)
+}
+
+/// 解析类型为 `log_type` 的单行 JCL，格式异常时返回 None
+fn parse_line(line: &[u8], log_type: u8) -> Option<JclEntry> {
+    // 按 TAB 分割前 5 列，剩余部分为 LUA_DATA（其中字符串可能包含 TAB）
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(6);
+    let mut start = 0;
+    for (i, &b) in line.iter().enumerate() {
+        if b == b'\t' {
+            parts.push(&line[start..i]);
+            start = i + 1;
+            if parts.len() >= 5 {
+                break;
+            }
+        }
+    }
+    if parts.len() < 5 {
+        return None;
+    }
+    let data = &line[start..];
+
+    let frame: i64 = parse_ascii(parts[1]).unwrap_or(0);
+    let timestamp_sec: i64 = parse_ascii(parts[2])?;
+    let client_ms: i64 = parse_ascii(parts[3]).unwrap_or(0);
+
+    let text = encoding_rs::GBK.decode(data).0;
+    let value = klua::parse_lua_text(text.trim()).ok()?;
+    let event = event_from_value(log_type, value)?;
+
+    Some(JclEntry {
+        frame,
+        timestamp_sec,
+        client_ms,
+        log_type,
+        event,
+    })
+}
+
+fn parse_ascii<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.trim().parse().ok()
+}
+
+/// 按 Lua 下标（从 1 开始）读取字段
+///
+/// 含 nil 的 table 会被 klua 转为以数字字符串为键的对象，这里两种形式都支持。
+fn field(data: &Value, index: usize) -> Option<&Value> {
+    match data {
+        Value::Array(items) => index.checked_sub(1).and_then(|i| items.get(i)),
+        Value::Object(map) => map.get(&index.to_string()),
+        _ => None,
+    }
+}
+
+fn int_at(data: &Value, index: usize) -> i64 {
+    match field(data, index) {
+        Some(Value::Number(n)) => n
+            .as_i64()
+            .or_else(|| n.as_f64().map(|f| f as i64))
+            .unwrap_or(0),
+        Some(Value::String(s)) => s.trim().parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn float_at(data: &Value, index: usize) -> Option<f64> {
+    match field(data, index)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn bool_at(data: &Value, index: usize) -> bool {
+    match field(data, index) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::Number(n)) => n.as_f64().is_some_and(|f| f != 0.0),
+        _ => false,
+    }
+}
+
+fn str_at(data: &Value, index: usize) -> String {
+    match field(data, index) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// tResult 为 `{[键]=数值}`，键可能从 0 开始
+fn skill_results(data: Option<&Value>) -> SkillResults {
+    let mut results = SkillResults::default();
+    let Some(data) = data else {
+        return results;
+    };
+    for (key, slot) in results.0.iter_mut().enumerate() {
+        let value = match data {
+            Value::Array(items) => key.checked_sub(1).and_then(|i| items.get(i)),
+            Value::Object(map) => map.get(&key.to_string()),
+            _ => None,
+        };
+        if let Some(v) = value.and_then(|v| v.as_f64()) {
+            *slot = v as i64;
+        }
+    }
+    results
+}
+
+/// 将 LUA_DATA 转换为类型化事件
+fn event_from_value(log_type: u8, data: Value) -> Option<JclEvent> {
+    use log_type::*;
+    if !matches!(data, Value::Array(_) | Value::Object(_)) {
+        return None;
+    }
+    let d = &data;
+    let event = match log_type {
+        FIGHT_TIME => JclEvent::FightTime {
+            fighting: bool_at(d, 1),
+            uuid: str_at(d, 2),
+            during: int_at(d, 3),
+        },
+        PLAYER_ENTER_SCENE => JclEvent::PlayerEnterScene { id: int_at(d, 1) },
+        PLAYER_LEAVE_SCENE => JclEvent::PlayerLeaveScene { id: int_at(d, 1) },
+        PLAYER_INFO => JclEvent::PlayerInfo {
+            id: int_at(d, 1),
+            name: str_at(d, 2),
+            force_id: int_at(d, 3),
+            kungfu_id: int_at(d, 4),
+            equip_score: int_at(d, 5),
+        },
+        PLAYER_FIGHT_HINT => JclEvent::PlayerFightHint {
+            id: int_at(d, 1),
+            fighting: bool_at(d, 2),
+        },
+        NPC_ENTER_SCENE => JclEvent::NpcEnterScene { id: int_at(d, 1) },
+        NPC_LEAVE_SCENE => JclEvent::NpcLeaveScene { id: int_at(d, 1) },
+        NPC_INFO => JclEvent::NpcInfo {
+            id: int_at(d, 1),
+            name: str_at(d, 2),
+            template_id: int_at(d, 3),
+            employer_id: int_at(d, 4),
+            x: int_at(d, 5),
+            y: int_at(d, 6),
+            z: int_at(d, 7),
+            face_direction: float_at(d, 8).map(|f| f as i64).unwrap_or(-1),
+        },
+        NPC_FIGHT_HINT => JclEvent::NpcFightHint {
+            id: int_at(d, 1),
+            fighting: bool_at(d, 2),
+            current_life: float_at(d, 3).unwrap_or(-1.0),
+            max_life: float_at(d, 4).unwrap_or(-1.0),
+            current_mana: int_at(d, 5),
+            max_mana: int_at(d, 6),
+        },
+        DOODAD_ENTER_SCENE => JclEvent::DoodadEnterScene { id: int_at(d, 1) },
+        DOODAD_LEAVE_SCENE => JclEvent::DoodadLeaveScene { id: int_at(d, 1) },
+        DOODAD_INFO => JclEvent::DoodadInfo {
+            id: int_at(d, 1),
+            template_id: int_at(d, 2),
+            x: int_at(d, 3),
+            y: int_at(d, 4),
+            z: int_at(d, 5),
+        },
+        BUFF_UPDATE => JclEvent::BuffUpdate {
+            target_id: int_at(d, 1),
+            deleted: bool_at(d, 2),
+            index: int_at(d, 3),
+            can_cancel: bool_at(d, 4),
+            buff_id: int_at(d, 5),
+            stack_num: int_at(d, 6),
+            end_frame: int_at(d, 7),
+            init: bool_at(d, 8),
+            level: int_at(d, 9),
+            source_id: int_at(d, 10),
+        },
+        PLAYER_SAY => JclEvent::PlayerSay {
+            content: str_at(d, 1),
+            talker_id: int_at(d, 2),
+            channel: int_at(d, 3),
+            talker_name: str_at(d, 4),
+        },
+        WARNING_MESSAGE => JclEvent::WarningMessage {
+            warning_type: str_at(d, 1),
+            text: str_at(d, 2),
+        },
+        PARTY_ADD_MEMBER => JclEvent::PartyAddMember {
+            team_id: int_at(d, 1),
+            member_id: int_at(d, 2),
+            group_index: int_at(d, 3),
+        },
+        PARTY_SET_MEMBER_ONLINE_FLAG => JclEvent::PartySetMemberOnlineFlag {
+            team_id: int_at(d, 1),
+            member_id: int_at(d, 2),
+            online: bool_at(d, 3),
+        },
+        MSG_SYS => JclEvent::MsgSys { msg: str_at(d, 1) },
+        SKILL_CAST_LOG => JclEvent::SkillCast {
+            caster_id: int_at(d, 1),
+            skill_id: int_at(d, 2),
+            skill_level: int_at(d, 3),
+        },
+        SKILL_CAST_RESPOND_LOG => JclEvent::SkillCastRespond {
+            caster_id: int_at(d, 1),
+            skill_id: int_at(d, 2),
+            skill_level: int_at(d, 3),
+            respond: int_at(d, 4),
+        },
+        SKILL_EFFECT_LOG => JclEvent::SkillEffect {
+            caster_id: int_at(d, 1),
+            target_id: int_at(d, 2),
+            react: bool_at(d, 3),
+            effect_type: int_at(d, 4),
+            effect_id: int_at(d, 5),
+            effect_level: int_at(d, 6),
+            critical: bool_at(d, 7),
+            count: int_at(d, 8),
+            results: skill_results(field(d, 9)),
+        },
+        SKILL_BLOCK_LOG => JclEvent::SkillBlock {
+            caster_id: int_at(d, 1),
+            target_id: int_at(d, 2),
+            effect_type: int_at(d, 3),
+            effect_id: int_at(d, 4),
+            effect_level: int_at(d, 5),
+            damage_type: int_at(d, 6),
+        },
+        SKILL_SHIELD_LOG => JclEvent::SkillShield {
+            caster_id: int_at(d, 1),
+            target_id: int_at(d, 2),
+            effect_type: int_at(d, 3),
+            effect_id: int_at(d, 4),
+            effect_level: int_at(d, 5),
+        },
+        SKILL_MISS_LOG => JclEvent::SkillMiss {
+            caster_id: int_at(d, 1),
+            target_id: int_at(d, 2),
+            effect_type: int_at(d, 3),
+            effect_id: int_at(d, 4),
+            effect_level: int_at(d, 5),
+        },
+        SKILL_HIT_LOG => JclEvent::SkillHit {
+            caster_id: int_at(d, 1),
+            target_id: int_at(d, 2),
+            effect_type: int_at(d, 3),
+            effect_id: int_at(d, 4),
+            effect_level: int_at(d, 5),
+        },
+        SKILL_DODGE_LOG => JclEvent::SkillDodge {
+            caster_id: int_at(d, 1),
+            target_id: int_at(d, 2),
+            effect_type: int_at(d, 3),
+            effect_id: int_at(d, 4),
+            effect_level: int_at(d, 5),
+        },
+        COMMON_HEALTH_LOG => JclEvent::CommonHealth {
+            character_id: int_at(d, 1),
+            delta_life: int_at(d, 2),
+        },
+        SYS_MSG_UI_OME_DEATH_NOTIFY => JclEvent::DeathNotify {
+            character_id: int_at(d, 1),
+            killer_id: int_at(d, 2),
+        },
+        other => JclEvent::Unknown {
+            log_type: other,
+            data,
+        },
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gbk_lines(lines: &[&str]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for line in lines {
+            out.extend_from_slice(&encoding_rs::GBK.encode(line).0);
+            out.extend_from_slice(b"\r\n");
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse_core_events() {
+        let data = gbk_lines(&[
+            "A1\t100\t1750000000\t5000\t1\t{true,\"uuid-1\",0}",
+            "A2\t101\t1750000001\t5100\t8\t{1077,\"唐醉\",137005,0,100,200,300,192}",
+            "A3\t102\t1750000002\t5200\t9\t{1077,false,0,5000000,0,0}",
+            "A4\t103\t1750000003\t5300\t28\t{1077,4567}",
+            "broken line",
+            "A6\t105\t1750000005\t5500\t8\t{1078,\"未闭合}",
+            "",
+            "A5\t104\t1750000004\t5400\t21\t{4567,1077,false,1,100,1,true,1,{[0]=1000,[13]=900}}",
+        ]);
+        let mut reader = JclReader::new(&data[..]);
+        let entries: Vec<JclEntry> = reader.by_ref().collect();
+        assert_eq!(entries.len(), 5);
+        // 格式异常和 Lua 解析失败的行计数，空行不计
+        assert_eq!(reader.dropped_lines(), 2);
+
+        assert!(matches!(
+            &entries[0].event,
+            JclEvent::FightTime { fighting: true, uuid, during: 0 } if uuid == "uuid-1"
+        ));
+        assert_eq!(entries[0].timestamp_ms(), 1_750_000_000_000);
+        assert_eq!(entries[0].frame, 100);
+
+        match &entries[1].event {
+            JclEvent::NpcInfo {
+                id,
+                name,
+                template_id,
+                face_direction,
+                ..
+            } => {
+                assert_eq!(*id, 1077);
+                assert_eq!(name, "唐醉");
+                assert_eq!(*template_id, 137005);
+                assert_eq!(*face_direction, 192);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        match &entries[2].event {
+            JclEvent::NpcFightHint {
+                fighting,
+                current_life,
+                max_life,
+                ..
+            } => {
+                assert!(!fighting);
+                assert_eq!(*current_life, 0.0);
+                assert_eq!(*max_life, 5_000_000.0);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(matches!(
+            entries[3].event,
+            JclEvent::DeathNotify {
+                character_id: 1077,
+                killer_id: 4567
+            }
+        ));
+
+        match &entries[4].event {
+            JclEvent::SkillEffect {
+                caster_id,
+                critical,
+                results,
+                ..
+            } => {
+                assert_eq!(*caster_id, 4567);
+                assert!(critical);
+                assert_eq!(results.damage(), 1000);
+                assert_eq!(results.effective_damage(), 900);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    /// 读取出错时结束迭代，而不是反复重试同一个错误
+    struct FailingReader;
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("EIO"))
+        }
+    }
+
+    #[test]
+    fn test_read_error_ends_iteration() {
+        let mut reader = JclReader::new(std::io::BufReader::new(FailingReader));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_only_filter_and_nil_fields() {
+        let data = gbk_lines(&[
+            "A1\t100\t1750000000\t5000\t21\t{1,2,false,1,100,1,false,1,{}}",
+            "A2\t101\t1750000001\t5100\t7\t{1077}",
+            "A3\t102\t1750000002\t5200\t8\t{1078,nil,137006}",
+        ]);
+        let entries: Vec<JclEntry> = JclReader::new(&data[..])
+            .only(&[log_type::NPC_LEAVE_SCENE, log_type::NPC_INFO])
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(entries[0].event, JclEvent::NpcLeaveScene { id: 1077 }));
+        match &entries[1].event {
+            JclEvent::NpcInfo {
+                id,
+                name,
+                template_id,
+                face_direction,
+                ..
+            } => {
+                assert_eq!(*id, 1078);
+                assert!(name.is_empty());
+                assert_eq!(*template_id, 137006);
+                assert_eq!(*face_direction, -1);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
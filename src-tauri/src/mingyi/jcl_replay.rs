@@ -0,0 +1,323 @@
+//! JCL 击杀判定回放（回归样本校验）
+//!
+//! 对一个目录下的 JCL 文件逐个执行 `analyze_jcl`（不经过 jcl_cache），
+//! 与目录中保存的期望结果清单比对，输出判定变化。用于修改判定逻辑 / BOSS 规则后
+//! 确认历史样本的判定没有被意外改变。
+//!
+//! 用法（无界面运行，只读打开本机数据库读取副本与 BOSS 规则配置，不执行迁移）：
+//! `jx3-raid-manager --replay-jcl <JCL目录> [--manifest <清单路径>] [--update]`
+//! - 清单默认为 `<JCL目录>/expected.json`
+//! - `--update`：用本次回放结果重写清单（确认变化符合预期后使用）
+//! - 退出码：0 = 与清单一致，1 = 存在差异，2 = 运行失败
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::db;
+use crate::mingyi::drop_scanner::{
+    analyze_jcl_for_raid, get_cached_raids, match_raid_name, parse_jcl_filename, RaidEntry,
+    JCL_ANALYZER_VERSION,
+};
+
+/// 默认清单文件名（位于 JCL 目录下）
+const DEFAULT_MANIFEST_NAME: &str = "expected.json";
+
+/// 单个 JCL 的判定结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplayVerdict {
+    /// 判定的 BOSS 名（None 表示小怪 / 出场对话 JCL）
+    pub boss_name: Option<String>,
+    /// 是否击杀
+    pub is_kill: bool,
+}
+
+/// 期望结果清单
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplayManifest {
+    /// 生成清单时的分析器版本（仅供参考，比对不依赖此字段）
+    #[serde(default)]
+    pub analyzer_version: i64,
+    /// 文件名 → 期望判定
+    #[serde(default)]
+    pub files: BTreeMap<String, ReplayVerdict>,
+}
+
+/// 回放差异
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ReplayDiff {
+    /// 判定与清单不一致
+    Changed {
+        file_name: String,
+        expected: ReplayVerdict,
+        actual: ReplayVerdict,
+    },
+    /// 清单中没有该文件
+    Unexpected {
+        file_name: String,
+        actual: ReplayVerdict,
+    },
+    /// 清单中的文件未能回放（文件缺失或无法解析）
+    Missing { file_name: String },
+}
+
+struct ReplayOptions {
+    jcl_dir: PathBuf,
+    manifest_path: PathBuf,
+    update: bool,
+}
+
+impl ReplayOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut jcl_dir: Option<PathBuf> = None;
+        let mut manifest_path: Option<PathBuf> = None;
+        let mut update = false;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--manifest" => {
+                    let value = iter.next().ok_or("--manifest 缺少清单路径")?;
+                    manifest_path = Some(PathBuf::from(value));
+                }
+                "--update" => update = true,
+                other if other.starts_with("--") => {
+                    return Err(format!("未知参数: {}", other));
+                }
+                other => {
+                    if jcl_dir.is_some() {
+                        return Err(format!("多余的参数: {}", other));
+                    }
+                    jcl_dir = Some(PathBuf::from(other));
+                }
+            }
+        }
+
+        let jcl_dir = jcl_dir.ok_or("缺少 JCL 目录参数")?;
+        let manifest_path = manifest_path.unwrap_or_else(|| jcl_dir.join(DEFAULT_MANIFEST_NAME));
+        Ok(ReplayOptions {
+            jcl_dir,
+            manifest_path,
+            update,
+        })
+    }
+}
+
+/// 命令行入口：返回 Ok(true) 表示与清单一致
+pub fn run_cli(args: &[String]) -> Result<bool, String> {
+    let options = ReplayOptions::parse(args)?;
+
+    let conn = db::open_db_read_only()?;
+    let raids = get_cached_raids(&conn)?;
+    let actual = replay_directory(&options.jcl_dir, &raids)?;
+
+    let manifest = load_manifest(&options.manifest_path)?;
+    let diffs = diff_verdicts(&manifest, &actual);
+
+    for diff in &diffs {
+        match diff {
+            ReplayDiff::Changed {
+                file_name,
+                expected,
+                actual,
+            } => println!(
+                "[变化] {}: 期望 boss={:?} is_kill={}，实际 boss={:?} is_kill={}",
+                file_name, expected.boss_name, expected.is_kill, actual.boss_name, actual.is_kill
+            ),
+            ReplayDiff::Unexpected { file_name, actual } => println!(
+                "[新增] {}: boss={:?} is_kill={}",
+                file_name, actual.boss_name, actual.is_kill
+            ),
+            ReplayDiff::Missing { file_name } => println!("[缺失] {}", file_name),
+        }
+    }
+    println!(
+        "回放 {} 个 JCL（分析器版本 {}，清单版本 {}），差异 {} 处",
+        actual.len(),
+        JCL_ANALYZER_VERSION,
+        manifest.analyzer_version,
+        diffs.len()
+    );
+
+    if options.update {
+        let updated = ReplayManifest {
+            analyzer_version: JCL_ANALYZER_VERSION,
+            files: actual,
+        };
+        save_manifest(&options.manifest_path, &updated)?;
+        println!("已更新清单: {}", options.manifest_path.display());
+        return Ok(true);
+    }
+
+    Ok(diffs.is_empty())
+}
+
+/// 回放目录下所有 JCL 文件（按文件名排序，不读写 jcl_cache）
+///
+/// 文件名无法解析或副本不在配置中的 JCL 会被跳过（与扫描流程一致）。
+pub(crate) fn replay_directory(
+    jcl_dir: &Path,
+    raids: &[RaidEntry],
+) -> Result<BTreeMap<String, ReplayVerdict>, String> {
+    let entries = std::fs::read_dir(jcl_dir)
+        .map_err(|e| format!("读取 JCL 目录失败 ({}): {}", jcl_dir.display(), e))?;
+
+    let mut file_names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".jcl"))
+        .collect();
+    file_names.sort();
+
+    let mut verdicts = BTreeMap::new();
+    for file_name in file_names {
+        let Some(info) = parse_jcl_filename(&file_name) else {
+            eprintln!("[跳过] 文件名无法解析: {}", file_name);
+            continue;
+        };
+        let Some(raid_entry) = match_raid_name(&info.raid_display_name, raids) else {
+            eprintln!(
+                "[跳过] 副本未配置: {} ({})",
+                file_name, info.raid_display_name
+            );
+            continue;
+        };
+        let Some(analysis) = analyze_jcl_for_raid(&jcl_dir.join(&file_name), &info, raid_entry)
+        else {
+            eprintln!("[跳过] JCL 读取失败: {}", file_name);
+            continue;
+        };
+        verdicts.insert(
+            file_name,
+            ReplayVerdict {
+                boss_name: analysis.boss_name,
+                is_kill: analysis.is_kill,
+            },
+        );
+    }
+    Ok(verdicts)
+}
+
+/// 比对期望清单与回放结果
+pub(crate) fn diff_verdicts(
+    manifest: &ReplayManifest,
+    actual: &BTreeMap<String, ReplayVerdict>,
+) -> Vec<ReplayDiff> {
+    let mut diffs = Vec::new();
+    for (file_name, verdict) in actual {
+        match manifest.files.get(file_name) {
+            Some(expected) if expected == verdict => {}
+            Some(expected) => diffs.push(ReplayDiff::Changed {
+                file_name: file_name.clone(),
+                expected: expected.clone(),
+                actual: verdict.clone(),
+            }),
+            None => diffs.push(ReplayDiff::Unexpected {
+                file_name: file_name.clone(),
+                actual: verdict.clone(),
+            }),
+        }
+    }
+    for file_name in manifest.files.keys() {
+        if !actual.contains_key(file_name) {
+            diffs.push(ReplayDiff::Missing {
+                file_name: file_name.clone(),
+            });
+        }
+    }
+    diffs
+}
+
+/// 读取清单，文件不存在时返回空清单
+fn load_manifest(path: &Path) -> Result<ReplayManifest, String> {
+    if !path.exists() {
+        return Ok(ReplayManifest::default());
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("读取清单失败 ({}): {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析清单失败 ({}): {}", path.display(), e))
+}
+
+fn save_manifest(path: &Path, manifest: &ReplayManifest) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(manifest).map_err(|e| format!("序列化清单失败: {}", e))?;
+    std::fs::write(path, content).map_err(|e| format!("写入清单失败 ({}): {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KILL_JCL: &str = "2026-06-13-18-13-59-25人普通阆风悬城(794)-唐醉(137005).jcl";
+
+    fn write_jcl(path: &Path, lines: &[&str]) {
+        let mut data = Vec::new();
+        for line in lines {
+            data.extend_from_slice(&encoding_rs::GBK.encode(line).0);
+            data.push(b'\n');
+        }
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_replay_directory_and_diff() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("jx3-test-jcl-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        write_jcl(
+            &temp_dir.join(KILL_JCL),
+            &[
+                "A\t1\t1750000000\t10000\t8\t{900,\"唐醉\",137005,0,0,0,0,0}",
+                "A\t2\t1750000001\t11000\t1\t{true,\"uuid\",0}",
+                "A\t3\t1750000001\t11000\t9\t{900,true,100,100,0,0}",
+                "A\t4\t1750000060\t70000\t28\t{900,1}",
+                "A\t5\t1750000061\t71000\t1\t{false,\"uuid\",60000}",
+            ],
+        );
+        // 副本未配置的 JCL 跳过
+        write_jcl(
+            &temp_dir.join("2026-06-13-19-00-00-25人普通白帝江关(700)-赵八嫂(1).jcl"),
+            &[],
+        );
+
+        let raids = vec![RaidEntry {
+            name: "阆风悬城".to_string(),
+            raid_id: "25人普通阆风悬城".to_string(),
+            bosses: vec![("langfengxuancheng_2".to_string(), "唐醉".to_string())],
+            boss_rules: vec![],
+        }];
+        let actual = replay_directory(&temp_dir, &raids).unwrap();
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        assert_eq!(actual.len(), 1);
+        let verdict = actual.get(KILL_JCL).unwrap();
+        assert_eq!(verdict.boss_name.as_deref(), Some("唐醉"));
+        assert!(verdict.is_kill);
+
+        let manifest: ReplayManifest = serde_json::from_str(&format!(
+            r#"{{"analyzerVersion": 1, "files": {{
+                "{}": {{"bossName": "唐醉", "isKill": false}},
+                "gone.jcl": {{"bossName": null, "isKill": false}}
+            }}}}"#,
+            KILL_JCL
+        ))
+        .unwrap();
+        let diffs = diff_verdicts(&manifest, &actual);
+        assert_eq!(diffs.len(), 2);
+        assert!(matches!(&diffs[0], ReplayDiff::Changed { actual, .. } if actual.is_kill));
+        assert_eq!(
+            diffs[1],
+            ReplayDiff::Missing {
+                file_name: "gone.jcl".to_string()
+            }
+        );
+
+        let clean = ReplayManifest {
+            analyzer_version: JCL_ANALYZER_VERSION,
+            files: actual.clone(),
+        };
+        assert!(diff_verdicts(&clean, &actual).is_empty());
+    }
+}
@@ -1,3 +1,15 @@
 /// 茗伊插件数据解析模块
 pub mod active_detector;
+pub mod auction_ledger;
+pub mod boss_rules;
+pub mod combat_stats;
 pub mod drop_scanner;
+pub mod jcl;
+pub mod jcl_replay;
+pub mod record_rescan;
+pub mod roster;
+pub mod run_groups;
+pub mod salary_sheet;
+pub mod scan_explain;
+pub mod scan_watcher;
+pub mod session_tracker;
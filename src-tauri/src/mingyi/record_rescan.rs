@@ -0,0 +1,459 @@
+//! 已确认记录重新扫描
+//!
+//! `upsert_raid_drop_record` 对已有 confirmed / rejected 记录的 CD 窗口直接跳过，
+//! 击杀判定或收支提取修复后，旧记录不会随之更新。这里对单条记录重新执行扫描
+//! （dry-run，见 `scan_explain`），按字段（掉落、BOSS、收入、支出）与记录现有数据对比，
+//! 由用户逐字段接受；未接受的字段和其他手动修改保持不变。
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::active_detector::{is_mingyi_account_dir, parse_info_jx3dat};
+use super::drop_scanner::{self, calculate_cd_window, RareDropFlags};
+use super::scan_explain::InstanceTrace;
+use crate::db;
+use crate::db::raid_record::RaidRecord;
+use crate::db::record_history;
+use crate::game_directory::MINGYI_ACCOUNTS_BASE_PATH;
+use crate::money::{self, Money};
+
+/// 可重新扫描的字段
+pub const RESCAN_FIELDS: [&str; 4] = ["drops", "bosses", "goldIncome", "goldExpense"];
+
+/// 单个字段的重新扫描对比
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RescanFieldDiff {
+    /// 字段名（drops / bosses / goldIncome / goldExpense）
+    pub field: &'static str,
+    /// 记录当前值（金额为铜）
+    pub current: Value,
+    /// 重新扫描得到的值（金额为铜）
+    pub rescanned: Value,
+    pub changed: bool,
+}
+
+/// 记录重新扫描结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordRescanDiff {
+    pub record_id: String,
+    /// 重新扫描使用的茗伊账号 UID
+    pub account_uid: String,
+    pub fields: Vec<RescanFieldDiff>,
+}
+
+fn load_record_data(conn: &Connection, record_id: &str) -> Result<Value, String> {
+    let data: String = match conn.query_row(
+        "SELECT data FROM records WHERE id = ?1",
+        params![record_id],
+        |row| row.get(0),
+    ) {
+        Ok(data) => data,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(format!("记录不存在: {}", record_id))
+        }
+        Err(e) => return Err(format!("查询 records 记录失败: {}", e)),
+    };
+    serde_json::from_str(&data).map_err(|e| format!("解析记录 data JSON 失败: {}", e))
+}
+
+fn string_list(data: &Value, key: &str) -> Vec<String> {
+    data.get(key)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn strip_spaces(text: &str) -> String {
+    text.chars()
+        .filter(|c| *c != ' ' && *c != '\u{3000}')
+        .collect()
+}
+
+/// 查找产生该 JCL 的茗伊账号 UID。
+///
+/// 多开同团时多个账号目录下有同名 JCL，按记录的角色名 + 服务器与 info.jx3dat 比对区分。
+fn find_record_account_uid(
+    game_path: &Path,
+    data: &Value,
+    jcl_file: &str,
+) -> Result<String, String> {
+    let accounts_base = game_path.join(MINGYI_ACCOUNTS_BASE_PATH);
+    let entries = std::fs::read_dir(&accounts_base)
+        .map_err(|e| format!("读取茗伊账号目录失败: {} - {}", accounts_base.display(), e))?;
+    let candidates: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(is_mingyi_account_dir)
+                && path
+                    .join("userdata")
+                    .join("combat_logs")
+                    .join(jcl_file)
+                    .exists()
+        })
+        .collect();
+
+    let role_name = data
+        .get("roleName")
+        .and_then(|v| v.as_str())
+        .map(strip_spaces);
+    let server = data.get("server").and_then(|v| v.as_str());
+    let account_dir = match candidates.as_slice() {
+        [] => return Err(format!("未找到包含 JCL 文件的茗伊账号: {}", jcl_file)),
+        [only] => only.clone(),
+        _ => candidates
+            .iter()
+            .find(|dir| {
+                parse_info_jx3dat(&dir.join("info.jx3dat"), Some(game_path)).is_some_and(|id| {
+                    Some(strip_spaces(&id.role_name)) == role_name
+                        && Some(id.server.as_str()) == server
+                })
+            })
+            .cloned()
+            .ok_or_else(|| format!("多个茗伊账号包含 JCL 文件 {}，且均与记录角色不符", jcl_file))?,
+    };
+    account_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_suffix("@zhcn_hd"))
+        .map(str::to_string)
+        .ok_or_else(|| format!("无法解析茗伊账号目录: {}", account_dir.display()))
+}
+
+/// 按记录所在 CD 窗口重新扫描（离线模式，不写入），返回 (茗伊 UID, 与记录 JCL 对应的副本实例)
+fn rescan_instance(data: &Value) -> Result<(String, InstanceTrace), String> {
+    let jcl_files = string_list(data, "jclFiles");
+    let first_jcl = jcl_files
+        .first()
+        .ok_or_else(|| "记录没有关联 JCL 文件，无法重新扫描".to_string())?;
+    let date = data
+        .get("date")
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| "记录缺少副本时间".to_string())?;
+    let is_ten_person = data
+        .get("raidName")
+        .and_then(|v| v.as_str())
+        .is_some_and(|name| name.contains("10人"));
+    let (window_start, window_end) = calculate_cd_window(date, is_ten_person);
+
+    let game_path = PathBuf::from(drop_scanner::get_game_directory()?);
+    let account_uid = find_record_account_uid(&game_path, data, first_jcl)?;
+    let trace =
+        drop_scanner::explain_raid_drops(&account_uid, false, false, window_start, window_end)?;
+    let instance = trace
+        .instances
+        .into_iter()
+        .find(|instance| {
+            instance
+                .jcl_files
+                .iter()
+                .chain(&instance.attempt_files)
+                .any(|file| jcl_files.contains(file))
+        })
+        .ok_or_else(|| "重新扫描未找到记录对应的副本实例".to_string())?;
+    Ok((account_uid, instance))
+}
+
+fn sorted(mut items: Vec<String>) -> Vec<String> {
+    items.sort();
+    items
+}
+
+fn current_value(data: &Value, field: &str) -> Value {
+    match field {
+        "drops" => json!(sorted(string_list(data, "drops"))),
+        "bosses" => json!({
+            "bossIds": string_list(data, "bossIds"),
+            "bossNames": string_list(data, "bossNames"),
+        }),
+        "goldIncome" => json!(money::record_income(data)),
+        _ => json!(money::record_expense(data)),
+    }
+}
+
+fn rescanned_value(instance: &InstanceTrace, field: &str) -> Value {
+    match field {
+        "drops" => json!(instance.drops),
+        "bosses" => json!({
+            "bossIds": instance.boss_ids,
+            "bossNames": instance.boss_names,
+        }),
+        "goldIncome" => json!(instance.total_income),
+        _ => json!(instance.total_expense),
+    }
+}
+
+/// 逐字段对比记录数据与重新扫描结果
+pub(crate) fn diff_rescan_fields(data: &Value, instance: &InstanceTrace) -> Vec<RescanFieldDiff> {
+    RESCAN_FIELDS
+        .iter()
+        .map(|field| {
+            let current = current_value(data, field);
+            let rescanned = rescanned_value(instance, field);
+            RescanFieldDiff {
+                field,
+                changed: current != rescanned,
+                current,
+                rescanned,
+            }
+        })
+        .collect()
+}
+
+fn set_money(
+    obj: &mut serde_json::Map<String, Value>,
+    gold_key: &str,
+    copper_key: &str,
+    amount: Money,
+) {
+    obj.insert(gold_key.to_string(), json!(amount.gold()));
+    obj.insert(copper_key.to_string(), json!(amount.copper()));
+}
+
+/// 将接受的字段写入记录数据，其余字段不变
+///
+/// 接受掉落时按 `categories`（掉落分类结果）重新推导 has* 标记，与扫描生成记录时一致；
+/// 分类不可用（None）时保留记录原有标记。
+pub(crate) fn apply_rescan_fields(
+    data: &mut Value,
+    instance: &InstanceTrace,
+    fields: &[String],
+    categories: Option<&HashMap<String, String>>,
+) -> Result<(), String> {
+    let obj = data
+        .as_object_mut()
+        .ok_or_else(|| "记录 data 不是 JSON 对象".to_string())?;
+    for field in fields {
+        match field.as_str() {
+            "drops" => {
+                obj.insert("drops".to_string(), json!(instance.drops));
+                if let Some(categories) = categories {
+                    RareDropFlags::from_categories(categories).write_to(obj);
+                }
+            }
+            "bosses" => {
+                obj.insert("bossIds".to_string(), json!(instance.boss_ids));
+                obj.insert("bossNames".to_string(), json!(instance.boss_names));
+            }
+            "goldIncome" => set_money(obj, "goldIncome", "goldIncomeCopper", instance.total_income),
+            "goldExpense" => set_money(
+                obj,
+                "goldExpense",
+                "goldExpenseCopper",
+                instance.total_expense,
+            ),
+            other => return Err(format!("未知的重新扫描字段: {}", other)),
+        }
+    }
+    Ok(())
+}
+
+/// 在事务内重新读取记录、写入接受的字段并追加变更历史，返回更新后的 data
+///
+/// 重新读取避免覆盖重新扫描期间的修改；经 `RaidRecord` 校验后写回，同步 drops 等表列。
+fn save_rescan_fields(
+    conn: &mut Connection,
+    record_id: &str,
+    instance: &InstanceTrace,
+    fields: &[String],
+    categories: Option<&HashMap<String, String>>,
+) -> Result<Value, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    let mut data = load_record_data(&tx, record_id)?;
+    apply_rescan_fields(&mut data, instance, fields, categories)?;
+    let record = RaidRecord::from_value(data.clone())?;
+    record_history::upsert_record_with_history(&tx, &record, record_history::ACTION_RESCAN)?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(data)
+}
+
+/// Tauri 命令：重新扫描记录，返回逐字段对比（不修改记录）
+#[tauri::command]
+pub async fn rescan_record(record_id: String) -> Result<RecordRescanDiff, String> {
+    tokio::task::spawn_blocking(move || {
+        let conn = db::init_db()?;
+        let data = load_record_data(&conn, &record_id)?;
+        let (account_uid, instance) = rescan_instance(&data)?;
+        Ok(RecordRescanDiff {
+            fields: diff_rescan_fields(&data, &instance),
+            record_id,
+            account_uid,
+        })
+    })
+    .await
+    .map_err(|e| format!("重新扫描线程异常: {}", e))?
+}
+
+/// Tauri 命令：重新扫描记录并接受指定字段，返回接受后的逐字段对比
+///
+/// `fields` 取值见 `RESCAN_FIELDS`，未列出的字段保留记录现有数据（含手动修改）。
+#[tauri::command]
+pub async fn accept_rescan_fields(
+    record_id: String,
+    fields: Vec<String>,
+) -> Result<RecordRescanDiff, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db::init_db()?;
+        let data = load_record_data(&conn, &record_id)?;
+        // 重新扫描读取 JCL / chatlog 较慢，放在事务外
+        let (account_uid, instance) = rescan_instance(&data)?;
+
+        // 掉落分类可能访问网络，同样放在事务外
+        let categories = if fields.iter().any(|field| field == "drops") {
+            let map_id = data.get("mapId").and_then(|v| v.as_i64()).unwrap_or(0);
+            let raid_name = instance.raid_name.as_deref().unwrap_or_default();
+            drop_scanner::classify_record_drops(
+                map_id,
+                raid_name,
+                &instance.drops,
+                &instance.raid_display_name,
+            )
+        } else {
+            None
+        };
+        let data = save_rescan_fields(
+            &mut conn,
+            &record_id,
+            &instance,
+            &fields,
+            categories.as_ref(),
+        )?;
+        log::info!(
+            "[RecordRescan] 记录 {} 接受重新扫描字段: {}",
+            record_id,
+            fields.join(", ")
+        );
+
+        Ok(RecordRescanDiff {
+            fields: diff_rescan_fields(&data, &instance),
+            record_id,
+            account_uid,
+        })
+    })
+    .await
+    .map_err(|e| format!("重新扫描线程异常: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance() -> InstanceTrace {
+        InstanceTrace {
+            raid_display_name: "25人英雄阆风悬城".to_string(),
+            matched_raid: Some("25人英雄阆风悬城".to_string()),
+            raid_name: Some("阆风悬城".to_string()),
+            start_time: 1_700_000_000_000,
+            last_jcl_time: 1_700_000_600_000,
+            jcl_files: vec!["a.jcl".to_string()],
+            attempt_files: vec!["a.jcl".to_string()],
+            bosses_killed: vec!["唐醉".to_string()],
+            boss_ids: vec!["1".to_string()],
+            boss_names: vec!["唐醉".to_string()],
+            drops: vec!["五行石".to_string(), "玄晶".to_string()],
+            chatlog_start: 0,
+            chatlog_end: 0,
+            chatlog_end_reason: "lastJclPlus2h",
+            base_salary: None,
+            total_income: Money::from_parts(3000, 12, 0),
+            total_expense: Money::ZERO,
+            raid_complete: true,
+            record_status: "pending",
+            chatlog_items: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_accept_selected_fields_keeps_manual_edits() {
+        let mut data = json!({
+            "drops": ["玄晶", "五行石"],
+            "bossIds": [],
+            "bossNames": [],
+            "goldIncome": 5000,
+            "goldExpense": 0,
+            "notes": "手动备注",
+        });
+        let instance = instance();
+
+        let diff = diff_rescan_fields(&data, &instance);
+        let changed: Vec<&str> = diff.iter().filter(|d| d.changed).map(|d| d.field).collect();
+        assert_eq!(changed, vec!["bosses", "goldIncome"]);
+
+        apply_rescan_fields(&mut data, &instance, &["bosses".to_string()], None).unwrap();
+        assert_eq!(data["bossNames"], json!(["唐醉"]));
+        // 未接受的收入保留手动修改
+        assert_eq!(data["goldIncome"], json!(5000));
+        assert_eq!(data["notes"], json!("手动备注"));
+
+        apply_rescan_fields(&mut data, &instance, &["goldIncome".to_string()], None).unwrap();
+        assert_eq!(money::record_income(&data), Money::from_parts(3000, 12, 0));
+        assert!(diff_rescan_fields(&data, &instance)
+            .iter()
+            .all(|d| !d.changed));
+
+        assert!(apply_rescan_fields(&mut data, &instance, &["notes".to_string()], None).is_err());
+    }
+
+    #[test]
+    fn test_accept_drops_recomputes_rare_flags() {
+        let mut conn = crate::db::open_test_db();
+        let data = json!({
+            "id": "r1", "accountId": "a1", "roleId": "role1", "raidName": "25人英雄阆风悬城",
+            "date": 1_700_000_000_000i64, "goldIncome": 5000, "hasXuanjing": false, "hasMount": true,
+            "type": "raid", "source": "auto", "status": "confirmed", "drops": ["五行石"],
+            "mapId": 650, "notes": "手动备注",
+        });
+        let record = RaidRecord::from_value(data).unwrap();
+        db::raid_record::upsert_record(&conn, &record).unwrap();
+
+        let categories: HashMap<String, String> = [
+            ("玄晶".to_string(), "xuanjing".to_string()),
+            ("五行石".to_string(), "material".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let updated = save_rescan_fields(
+            &mut conn,
+            "r1",
+            &instance(),
+            &["drops".to_string()],
+            Some(&categories),
+        )
+        .unwrap();
+        assert_eq!(updated["hasXuanjing"], json!(true));
+        assert_eq!(updated["hasMount"], json!(false));
+        assert_eq!(updated["goldIncome"], json!(5000));
+
+        // 表列与 data 同步，状态不变，变更历史记为 rescan
+        let (drops, status): (String, String) = conn
+            .query_row(
+                "SELECT drops, status FROM records WHERE id = 'r1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(drops, json!(["五行石", "玄晶"]).to_string());
+        assert_eq!(status, "confirmed");
+        let history = record_history::query_record_history(&conn, "r1").unwrap();
+        assert_eq!(history[0].action, record_history::ACTION_RESCAN);
+
+        // 分类不可用时保留原有标记
+        let mut data = updated;
+        apply_rescan_fields(&mut data, &instance(), &["drops".to_string()], None).unwrap();
+        assert_eq!(data["hasXuanjing"], json!(true));
+    }
+}
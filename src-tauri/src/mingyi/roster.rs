@@ -0,0 +1,509 @@
+//! 副本团队名单（自动扫描的每个副本实例中的全部队友）
+//!
+//! 名单来源：
+//! - JCL：PLAYER_INFO（角色名、门派、心法），只取有 PARTY_ADD_MEMBER 事件的队伍成员
+//! - chatlog：MSG_ITEM "分配给X"、MSG_ROOM 拍卖买家 / 团长分配接收者（格式为"角色名·服务器"）
+//!
+//! 两种来源按角色名合并：JCL 提供心法，chatlog 提供服务器。
+//! 名单中的本地已添加角色标记 `own_role_id`，用于识别同一场副本中的自家小号。
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::db;
+use crate::kungfu_data;
+use crate::mingyi::drop_scanner::{normalize_role_name, ChatlogItemKind, ChatlogLineItem};
+
+/// JCL 中出现的玩家（PLAYER_INFO），随 JCL 解析结果一起缓存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JclPlayer {
+    pub name: String,
+    pub force_id: i64,
+    /// 心法 ID（dwMountKungfuID），未知时为 0
+    pub kungfu_id: i64,
+}
+
+/// 团队名单成员
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RosterMember {
+    /// 角色名（不含服务器后缀）
+    pub name: String,
+    /// 服务器（仅 chatlog 中出现"角色名·服务器"时可知）
+    pub server: Option<String>,
+    pub force_id: Option<i64>,
+    pub force_name: Option<String>,
+    pub kungfu_id: Option<i64>,
+    pub kungfu_name: Option<String>,
+    /// 名单来源（jcl / chatlog）
+    pub sources: Vec<String>,
+    /// 本地已添加的角色 ID（自家小号同团时非空）
+    pub own_role_id: Option<String>,
+}
+
+/// 一段时间内同团过的队友（按同团次数汇总）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RaidCompanion {
+    pub name: String,
+    pub server: Option<String>,
+    pub kungfu_name: Option<String>,
+    /// 同团副本次数
+    pub run_count: u32,
+    /// 最近一次同团的副本开始时间（毫秒）
+    pub last_record_date: i64,
+    pub own_role_id: Option<String>,
+}
+
+const SOURCE_JCL: &str = "jcl";
+const SOURCE_CHATLOG: &str = "chatlog";
+
+/// 拆分"角色名·服务器"（跨服玩家在 JCL 中为"角色名@服务器"）
+pub(crate) fn split_role_server(raw: &str) -> (String, Option<String>) {
+    let normalized = normalize_role_name(raw);
+    match normalized.split_once(['·', '@']) {
+        Some((name, server)) if !server.is_empty() => (name.to_string(), Some(server.to_string())),
+        Some((name, _)) => (name.to_string(), None),
+        None => (normalized, None),
+    }
+}
+
+/// 从 JCL 玩家信息中筛选队伍成员
+///
+/// 只保留有 PARTY_ADD_MEMBER 事件的队伍成员（排除同场景的路人）。
+/// 没有队伍事件时无法区分队友与路人，返回空列表，名单只由 chatlog 中的团队消息构成。
+pub(crate) fn party_players(
+    players: HashMap<i64, JclPlayer>,
+    party_member_ids: &HashSet<i64>,
+) -> Vec<JclPlayer> {
+    let mut result: Vec<JclPlayer> = players
+        .into_iter()
+        .filter(|(id, player)| !player.name.is_empty() && party_member_ids.contains(id))
+        .map(|(_, player)| player)
+        .collect();
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    result
+}
+
+/// 合并 JCL 玩家与 chatlog 中出现的角色，生成团队名单（按角色名排序）
+pub(crate) fn build_roster(
+    jcl_players: &[JclPlayer],
+    chatlog_items: &[ChatlogLineItem],
+) -> Vec<RosterMember> {
+    let mut members: BTreeMap<String, RosterMember> = BTreeMap::new();
+
+    for player in jcl_players {
+        let (name, server) = split_role_server(&player.name);
+        let member = members
+            .entry(name.clone())
+            .or_insert_with(|| empty_member(name));
+        if member.server.is_none() {
+            member.server = server;
+        }
+        if player.force_id > 0 || member.force_id.is_none() {
+            member.force_id = Some(player.force_id);
+            member.force_name = kungfu_data::get_force_name(player.force_id as i32);
+        }
+        if player.kungfu_id > 0 {
+            member.kungfu_id = Some(player.kungfu_id);
+            member.kungfu_name = kungfu_data::get_kungfu_name(player.kungfu_id as i32);
+        }
+        add_source(member, SOURCE_JCL);
+    }
+
+    for item in chatlog_items {
+        let is_member_source = matches!(
+            item.kind,
+            ChatlogItemKind::ItemDrop
+                | ChatlogItemKind::Expense
+                | ChatlogItemKind::Allocation
+                | ChatlogItemKind::AuctionItem
+        );
+        let Some(raw_name) = item.buyer.as_deref().filter(|_| is_member_source) else {
+            continue;
+        };
+        let (name, server) = split_role_server(raw_name);
+        if name.is_empty() {
+            continue;
+        }
+        let member = members
+            .entry(name.clone())
+            .or_insert_with(|| empty_member(name));
+        if member.server.is_none() {
+            member.server = server;
+        }
+        add_source(member, SOURCE_CHATLOG);
+    }
+
+    members.into_values().collect()
+}
+
+fn empty_member(name: String) -> RosterMember {
+    RosterMember {
+        name,
+        server: None,
+        force_id: None,
+        force_name: None,
+        kungfu_id: None,
+        kungfu_name: None,
+        sources: Vec::new(),
+        own_role_id: None,
+    }
+}
+
+fn add_source(member: &mut RosterMember, source: &str) {
+    if !member.sources.iter().any(|s| s == source) {
+        member.sources.push(source.to_string());
+    }
+}
+
+/// 标记名单中的本地已添加角色
+///
+/// 角色名相同时：双方服务器都已知则必须一致；任一方服务器未知时，
+/// 只有本地唯一一个同名角色才视为匹配（同名角色有多个时无法判断，不标记）。
+pub(crate) fn match_own_roles(
+    conn: &Connection,
+    roster: &mut [RosterMember],
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, server FROM roles")
+        .map_err(|e| format!("准备角色查询失败: {}", e))?;
+    let roles = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .map_err(|e| format!("查询角色失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取角色失败: {}", e))?;
+
+    for member in roster.iter_mut() {
+        let same_name: Vec<&(String, String, Option<String>)> = roles
+            .iter()
+            .filter(|(_, name, _)| normalize_role_name(name) == member.name)
+            .collect();
+        let member_server = member.server.as_deref().filter(|s| !s.is_empty());
+        member.own_role_id = same_name
+            .iter()
+            .find(|(_, _, server)| {
+                let role_server = server.as_deref().map(str::trim).filter(|s| !s.is_empty());
+                match (member_server, role_server) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => same_name.len() == 1,
+                }
+            })
+            .map(|(id, _, _)| id.clone());
+    }
+    Ok(())
+}
+
+/// 重建记录的团队名单（扫描时每次以最新结果整体覆盖）
+pub(crate) fn save_record_roster(
+    conn: &Connection,
+    record_id: &str,
+    roster: &[RosterMember],
+) -> Result<(), String> {
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "DELETE FROM record_roster WHERE record_id = ?1",
+        params![record_id],
+    )
+    .map_err(|e| format!("清理团队名单失败: {}", e))?;
+    for member in roster {
+        conn.execute(
+            "INSERT INTO record_roster (record_id, name, server, force_id, kungfu_id, sources, own_role_id, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record_id,
+                member.name,
+                member.server.clone().unwrap_or_default(),
+                member.force_id,
+                member.kungfu_id,
+                member.sources.join(","),
+                member.own_role_id,
+                now
+            ],
+        )
+        .map_err(|e| format!("写入团队名单失败: {}", e))?;
+    }
+    log::info!(
+        "[Roster] 记录 {} 保存团队名单 {} 人（本地角色 {} 人）",
+        record_id,
+        roster.len(),
+        roster.iter().filter(|m| m.own_role_id.is_some()).count()
+    );
+    Ok(())
+}
+
+fn member_from_row(
+    name: String,
+    server: String,
+    force_id: Option<i64>,
+    kungfu_id: Option<i64>,
+    sources: String,
+    own_role_id: Option<String>,
+) -> RosterMember {
+    RosterMember {
+        name,
+        server: Some(server).filter(|s| !s.is_empty()),
+        force_id,
+        force_name: force_id.and_then(|id| kungfu_data::get_force_name(id as i32)),
+        kungfu_id,
+        kungfu_name: kungfu_id.and_then(|id| kungfu_data::get_kungfu_name(id as i32)),
+        sources: sources
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect(),
+        own_role_id,
+    }
+}
+
+/// Tauri 命令：查询记录的团队名单
+#[tauri::command]
+pub fn list_record_roster(record_id: String) -> Result<Vec<RosterMember>, String> {
+    let conn = db::init_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, server, force_id, kungfu_id, sources, own_role_id
+             FROM record_roster WHERE record_id = ?1
+             ORDER BY name ASC",
+        )
+        .map_err(|e| format!("准备团队名单查询失败: {}", e))?;
+    let members = stmt
+        .query_map(params![record_id], |row| {
+            Ok(member_from_row(
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        })
+        .map_err(|e| format!("查询团队名单失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取团队名单失败: {}", e))?;
+    Ok(members)
+}
+
+/// 查询角色在时间范围内同团过的队友（不含角色自身与已拒绝的记录），按同团次数降序
+///
+/// 同一队友在部分记录中服务器未知（只出现在 JCL 中）时，若其余记录中只出现过一个服务器，
+/// 归入该服务器汇总，不拆成两行。
+pub(crate) fn query_raid_companions(
+    conn: &Connection,
+    role_id: &str,
+    start_date: i64,
+    end_date: i64,
+) -> Result<Vec<RaidCompanion>, String> {
+    let mut stmt = conn
+        .prepare(
+            "WITH members AS (
+                SELECT m.name, NULLIF(m.server, '') AS server, m.kungfu_id, m.record_id,
+                       r.record_date, m.own_role_id
+                FROM record_roster m
+                JOIN records r ON r.id = m.record_id
+                WHERE r.role_id = ?1
+                  AND r.record_date >= ?2 AND r.record_date < ?3
+                  AND r.status != 'rejected'
+                  AND (m.own_role_id IS NULL OR m.own_role_id != ?1)
+             ),
+             known_servers AS (
+                SELECT name, MIN(server) AS server FROM members
+                WHERE server IS NOT NULL
+                GROUP BY name HAVING COUNT(DISTINCT server) = 1
+             )
+             SELECT m.name, COALESCE(m.server, k.server), MAX(m.kungfu_id), COUNT(DISTINCT m.record_id),
+                    MAX(m.record_date), MAX(m.own_role_id)
+             FROM members m
+             LEFT JOIN known_servers k ON k.name = m.name
+             GROUP BY m.name, COALESCE(m.server, k.server)
+             ORDER BY COUNT(DISTINCT m.record_id) DESC, MAX(m.record_date) DESC",
+        )
+        .map_err(|e| format!("准备队友查询失败: {}", e))?;
+    let companions = stmt
+        .query_map(params![role_id, start_date, end_date], |row| {
+            let kungfu_id: Option<i64> = row.get(2)?;
+            Ok(RaidCompanion {
+                name: row.get(0)?,
+                server: row.get(1)?,
+                kungfu_name: kungfu_id.and_then(|id| kungfu_data::get_kungfu_name(id as i32)),
+                run_count: row.get(3)?,
+                last_record_date: row.get(4)?,
+                own_role_id: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("查询队友失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取队友失败: {}", e))?;
+    Ok(companions)
+}
+
+/// Tauri 命令：查询角色在时间范围内（毫秒，左闭右开）同团过的队友
+#[tauri::command]
+pub fn list_raid_companions(
+    role_id: String,
+    start_date: i64,
+    end_date: i64,
+) -> Result<Vec<RaidCompanion>, String> {
+    let conn = db::init_db()?;
+    query_raid_companions(&conn, &role_id, start_date, end_date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_item(kind: ChatlogItemKind, buyer: &str) -> ChatlogLineItem {
+        ChatlogLineItem {
+            kind,
+            time_sec: 0,
+            channel: "MSG_ROOM".to_string(),
+            text: String::new(),
+            item_name: None,
+            buyer: Some(buyer.to_string()),
+            amount: None,
+            counted: true,
+            note: None,
+            row_key: None,
+        }
+    }
+
+    #[test]
+    fn test_build_roster_merges_jcl_and_chatlog() {
+        let mut players = HashMap::new();
+        players.insert(
+            1,
+            JclPlayer {
+                name: "少年白了发".to_string(),
+                force_id: 1,
+                kungfu_id: 10002,
+            },
+        );
+        players.insert(
+            2,
+            JclPlayer {
+                name: "糯闪".to_string(),
+                force_id: 214,
+                kungfu_id: 10756,
+            },
+        );
+        // 同场景路人（不在队伍中）
+        players.insert(
+            3,
+            JclPlayer {
+                name: "路人".to_string(),
+                force_id: 1,
+                kungfu_id: 10002,
+            },
+        );
+        let party: HashSet<i64> = [1, 2].into_iter().collect();
+        let jcl_players = party_players(players, &party);
+        assert_eq!(jcl_players.len(), 2);
+
+        let items = vec![
+            line_item(ChatlogItemKind::ItemDrop, "糯闪·梦江南"),
+            line_item(ChatlogItemKind::AuctionItem, "团长·梦江南"),
+            line_item(ChatlogItemKind::Salary, "不计入"),
+        ];
+        let roster = build_roster(&jcl_players, &items);
+        let names: Vec<&str> = roster.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["团长", "少年白了发", "糯闪"]);
+
+        let nuoshan = roster.iter().find(|m| m.name == "糯闪").unwrap();
+        assert_eq!(nuoshan.server.as_deref(), Some("梦江南"));
+        assert_eq!(nuoshan.kungfu_name.as_deref(), Some("山海心诀"));
+        assert_eq!(nuoshan.sources, vec!["jcl", "chatlog"]);
+
+        let leader = roster.iter().find(|m| m.name == "团长").unwrap();
+        assert_eq!(leader.kungfu_id, None);
+        assert_eq!(leader.sources, vec!["chatlog"]);
+    }
+
+    #[test]
+    fn test_party_players_without_party_events_is_empty() {
+        let mut players = HashMap::new();
+        players.insert(
+            1,
+            JclPlayer {
+                name: "路人".to_string(),
+                force_id: 1,
+                kungfu_id: 10002,
+            },
+        );
+        assert!(party_players(players, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_match_own_roles_and_companions_by_server() {
+        let conn = db::open_test_db();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, account_name) VALUES ('a1', '账号');
+             INSERT INTO roles (id, account_id, name, server) VALUES
+               ('role1', 'a1', '糯闪', '梦江南'),
+               ('role2', 'a1', '同名', '梦江南'),
+               ('role3', 'a1', '同名', '唯我独尊');",
+        )
+        .unwrap();
+
+        let member = |name: &str, server: Option<&str>| RosterMember {
+            server: server.map(str::to_string),
+            ..empty_member(name.to_string())
+        };
+        let mut roster = vec![
+            member("糯闪", Some("梦江南")),
+            // 服务器不同的同名路人
+            member("糯闪", Some("乾坤一掷")),
+            // 服务器未知，本地只有一个同名角色
+            member("糯闪", None),
+            // 服务器未知，本地有两个同名角色
+            member("同名", None),
+            member("同名", Some("唯我独尊")),
+        ];
+        match_own_roles(&conn, &mut roster).unwrap();
+        let own: Vec<Option<&str>> = roster.iter().map(|m| m.own_role_id.as_deref()).collect();
+        assert_eq!(
+            own,
+            vec![Some("role1"), None, Some("role1"), None, Some("role3")]
+        );
+
+        // 同一队友部分记录服务器未知时不拆成两行
+        conn.execute_batch(
+            "INSERT INTO records (id, data, role_id, record_date, status) VALUES
+               ('r1', '{}', 'role1', 1000, 'confirmed'),
+               ('r2', '{}', 'role1', 2000, 'confirmed'),
+               ('r3', '{}', 'role1', 3000, 'confirmed');
+             INSERT INTO record_roster (record_id, name, server, kungfu_id, created_at) VALUES
+               ('r1', '唐小糖', '梦江南', NULL, 'now'),
+               ('r2', '唐小糖', '', 10756, 'now'),
+               ('r3', '阿白', '', NULL, 'now');",
+        )
+        .unwrap();
+        let companions = query_raid_companions(&conn, "role1", 0, 10_000).unwrap();
+        assert_eq!(companions.len(), 2);
+        assert_eq!(companions[0].name, "唐小糖");
+        assert_eq!(companions[0].server.as_deref(), Some("梦江南"));
+        assert_eq!(companions[0].run_count, 2);
+        assert_eq!(companions[0].kungfu_name.as_deref(), Some("山海心诀"));
+        assert_eq!(companions[1].server, None);
+    }
+
+    #[test]
+    fn test_split_role_server() {
+        assert_eq!(
+            split_role_server("糯闪·梦江南"),
+            ("糯闪".to_string(), Some("梦江南".to_string()))
+        );
+        assert_eq!(
+            split_role_server("糯闪@梦江南"),
+            ("糯闪".to_string(), Some("梦江南".to_string()))
+        );
+        assert_eq!(split_role_server("糯 闪"), ("糯闪".to_string(), None));
+    }
+}
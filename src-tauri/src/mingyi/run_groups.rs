@@ -0,0 +1,433 @@
+//! 多开同团识别（跨账号 run group）
+//!
+//! 多开时每个账号独立扫描，同一场副本会产生多条记录（掉落、拍卖消息完全相同）。
+//! 扫描完成后对 CD 窗口内的自动扫描记录两两比对，满足以下任一条件即视为同一场副本：
+//! - 同副本（raidName 一致）且 BOSS 击杀序列吻合：同名 BOSS 的击杀开始时间相差不超过
+//!   `KILL_TIME_TOLERANCE_MS`，且吻合数不少于双方较少击杀数的一半
+//! - 同副本且至少 `MIN_SHARED_AUCTION_LINES` 条团队频道拍卖/分配消息原文相同（时间相差不超过
+//!   `AUCTION_TIME_TOLERANCE_SEC`）
+//!
+//! 同一场副本的记录写入相同的 `run_group_id`（取最早记录的 ID 作为主记录，已确认的记录优先）。
+//! 分组只写入待确认 / 进行中的记录，已确认的记录不被修改（其 ID 即可作为分组 ID）。
+//! 统计时按分组合并全组的特殊掉落，只在主记录计一次；底薪/收入仍按各角色记录分别统计。
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db;
+use crate::db::raid_record::{self, RaidRecord};
+
+/// 击杀开始时间容差（毫秒）：不同客户端写 JCL 的时间会有几秒到几十秒偏差
+const KILL_TIME_TOLERANCE_MS: i64 = 120_000;
+/// 拍卖消息时间容差（秒）
+const AUCTION_TIME_TOLERANCE_SEC: i64 = 60;
+/// 判定同团所需的最少相同拍卖消息数
+const MIN_SHARED_AUCTION_LINES: usize = 2;
+
+/// 参与比对的记录
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunCandidate {
+    pub id: String,
+    pub account_id: String,
+    pub raid_name: String,
+    pub record_date: i64,
+    /// 已确认（不再写入分组，优先作为主记录）
+    pub confirmed: bool,
+    /// 击杀的 (BOSS 名, 战斗开始时间毫秒)
+    pub kills: Vec<(String, i64)>,
+    /// 团队频道拍卖/分配消息 (原文, 时间秒)
+    pub auction_lines: Vec<(String, i64)>,
+}
+
+/// 同团分组中的一条记录
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunGroupMember {
+    pub record_id: String,
+    pub account_id: String,
+    pub role_id: Option<String>,
+    pub role_name: Option<String>,
+    pub status: String,
+    /// 主记录（共享掉落只在主记录上统计）
+    pub is_primary: bool,
+}
+
+/// 判断两条记录是否为同一场副本
+pub(crate) fn is_same_run(a: &RunCandidate, b: &RunCandidate) -> bool {
+    if a.account_id == b.account_id || a.raid_name != b.raid_name {
+        return false;
+    }
+
+    let kill_matches = a
+        .kills
+        .iter()
+        .filter(|(boss, start)| {
+            b.kills.iter().any(|(other_boss, other_start)| {
+                boss == other_boss && (start - other_start).abs() <= KILL_TIME_TOLERANCE_MS
+            })
+        })
+        .count();
+    let min_kills = a.kills.len().min(b.kills.len());
+    if kill_matches > 0 && kill_matches * 2 >= min_kills {
+        return true;
+    }
+
+    let shared_auction_lines = a
+        .auction_lines
+        .iter()
+        .filter(|(text, time)| {
+            b.auction_lines.iter().any(|(other_text, other_time)| {
+                text == other_text && (time - other_time).abs() <= AUCTION_TIME_TOLERANCE_SEC
+            })
+        })
+        .count();
+    shared_auction_lines >= MIN_SHARED_AUCTION_LINES
+}
+
+/// 将记录分组，返回 记录 ID → 分组 ID（仅包含 2 条及以上记录的分组）
+///
+/// 分组 ID 取组内最早的已确认记录（已确认的记录不再写入分组，其 ID 必须是分组 ID 才能被统计识别），
+/// 没有已确认记录时取最早的记录（record_date 最小，相同时取 ID 最小）。
+pub(crate) fn group_runs(candidates: &[RunCandidate]) -> HashMap<String, String> {
+    let mut parent: Vec<usize> = (0..candidates.len()).collect();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+
+    for i in 0..candidates.len() {
+        for j in (i + 1)..candidates.len() {
+            if is_same_run(&candidates[i], &candidates[j]) {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                if ri != rj {
+                    parent[rj] = ri;
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..candidates.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut result = HashMap::new();
+    for members in groups.values().filter(|m| m.len() > 1) {
+        let primary = members
+            .iter()
+            .map(|&i| &candidates[i])
+            .min_by(|a, b| {
+                b.confirmed
+                    .cmp(&a.confirmed)
+                    .then(a.record_date.cmp(&b.record_date))
+                    .then(a.id.cmp(&b.id))
+            })
+            .expect("分组非空");
+        for &i in members {
+            result.insert(candidates[i].id.clone(), primary.id.clone());
+        }
+    }
+    result
+}
+
+/// 加载时间范围内的自动扫描记录（不含已拒绝）及其击杀、拍卖消息
+fn load_candidates(
+    conn: &Connection,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<Vec<RunCandidate>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, account_id, data, record_date, status FROM records
+             WHERE source = 'auto_scan' AND status != 'rejected'
+               AND record_date >= ?1 AND record_date < ?2",
+        )
+        .map_err(|e| format!("准备同团记录查询失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![start_ms, end_ms], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| format!("查询同团记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取同团记录失败: {}", e))?;
+
+    let mut kill_stmt = conn
+        .prepare(
+            "SELECT boss_name, start_time FROM raid_attempts
+             WHERE record_id = ?1 AND is_kill = 1 ORDER BY start_time",
+        )
+        .map_err(|e| format!("准备击杀查询失败: {}", e))?;
+    let mut auction_stmt = conn
+        .prepare(
+            "SELECT text, time_sec FROM record_chatlog_items
+             WHERE record_id = ?1 AND kind IN ('auctionItem', 'allocation') ORDER BY seq",
+        )
+        .map_err(|e| format!("准备拍卖消息查询失败: {}", e))?;
+
+    let mut candidates = Vec::with_capacity(rows.len());
+    for (id, account_id, data, record_date, status) in rows {
+        let raid_name = data
+            .as_deref()
+            .and_then(|d| serde_json::from_str::<serde_json::Value>(d).ok())
+            .and_then(|json| json["raidName"].as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+        let kills = kill_stmt
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("查询击杀失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("读取击杀失败: {}", e))?;
+        let auction_lines = auction_stmt
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("查询拍卖消息失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("读取拍卖消息失败: {}", e))?;
+        candidates.push(RunCandidate {
+            id,
+            account_id: account_id.unwrap_or_default(),
+            raid_name,
+            record_date: record_date.unwrap_or(0),
+            confirmed: !matches!(status.as_deref(), Some("pending") | Some("scanning")),
+            kills,
+            auction_lines,
+        });
+    }
+    Ok(candidates)
+}
+
+/// 识别时间范围内的多开同团记录并写入分组（records.run_group_id 与记录 JSON 的 runGroupId / runGroupPrimary）
+///
+/// 每次扫描后整体重算：不再同团的待确认记录会清除分组。已确认的记录参与比对但不被修改。
+/// 返回已分组的记录数。
+pub(crate) fn link_run_groups(
+    conn: &Connection,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<usize, String> {
+    let candidates = load_candidates(conn, start_ms, end_ms)?;
+    let groups = group_runs(&candidates);
+
+    let mut changed = 0usize;
+    for candidate in candidates.iter().filter(|c| !c.confirmed) {
+        let group_id = groups.get(&candidate.id);
+        let (current_group, data): (Option<String>, Option<String>) = conn
+            .query_row(
+                "SELECT run_group_id, data FROM records WHERE id = ?1",
+                params![candidate.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("读取记录分组失败: {}", e))?;
+        let mut record = match data.as_deref().map(RaidRecord::from_json_str) {
+            Some(Ok(record)) => record,
+            Some(Err(e)) => {
+                log::warn!(
+                    "[RunGroups] 记录 {} 数据不合法，跳过分组: {}",
+                    candidate.id,
+                    e
+                );
+                continue;
+            }
+            None => continue,
+        };
+        // 重新扫描会覆盖记录 JSON，列与 JSON 字段都一致时才跳过
+        if current_group.as_ref() == group_id && record.run_group_id.as_ref() == group_id {
+            continue;
+        }
+
+        record.run_group_id = group_id.cloned();
+        record.run_group_primary = group_id.map(|group_id| group_id == &candidate.id);
+        raid_record::upsert_record(conn, &record)?;
+        changed += 1;
+    }
+
+    log::info!(
+        "[RunGroups] 同团识别完成: 候选记录 {} 条, 已分组 {} 条, 本次变更 {} 条",
+        candidates.len(),
+        groups.len(),
+        changed
+    );
+    Ok(groups.len())
+}
+
+/// Tauri 命令：查询记录所在同团分组的全部记录（未分组时只返回自身）
+#[tauri::command]
+pub fn list_run_group_members(record_id: String) -> Result<Vec<RunGroupMember>, String> {
+    let conn = db::init_db()?;
+    let group_id: Option<String> = conn
+        .query_row(
+            "SELECT run_group_id FROM records WHERE id = ?1",
+            params![record_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("查询记录分组失败: {}", e))?;
+    let group_id = group_id.unwrap_or_else(|| record_id.clone());
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, account_id, role_id, data, status FROM records
+             WHERE id = ?1 OR run_group_id = ?1
+             ORDER BY record_date ASC, id ASC",
+        )
+        .map_err(|e| format!("准备同团记录查询失败: {}", e))?;
+    let members = stmt
+        .query_map(params![group_id], |row| {
+            let id: String = row.get(0)?;
+            let data: Option<String> = row.get(3)?;
+            let role_name = data
+                .as_deref()
+                .and_then(|d| serde_json::from_str::<serde_json::Value>(d).ok())
+                .and_then(|json| json["roleName"].as_str().map(|s| s.to_string()));
+            Ok(RunGroupMember {
+                is_primary: id == group_id,
+                record_id: id,
+                account_id: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                role_id: row.get(2)?,
+                role_name,
+                status: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            })
+        })
+        .map_err(|e| format!("查询同团记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取同团记录失败: {}", e))?;
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, account_id: &str, record_date: i64) -> RunCandidate {
+        RunCandidate {
+            id: id.to_string(),
+            account_id: account_id.to_string(),
+            raid_name: "25人英雄阆风悬城".to_string(),
+            record_date,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_runs_by_kills_and_auction_lines() {
+        let base = 1_750_000_000_000;
+        let mut main = candidate("r-main", "acc-1", base + 5_000);
+        main.kills = vec![
+            ("笑妆娘".to_string(), base + 60_000),
+            ("唐醉".to_string(), base + 900_000),
+        ];
+        // 小号：JCL 时间有几秒偏差
+        let mut alt = candidate("r-alt", "acc-2", base);
+        alt.kills = vec![
+            ("笑妆娘".to_string(), base + 63_000),
+            ("唐醉".to_string(), base + 905_000),
+        ];
+        // 另一个账号：只有拍卖消息相同（JCL 缺失）
+        let mut alt2 = candidate("r-alt2", "acc-3", base + 10_000);
+        alt2.auction_lines = vec![
+            ("[团长]花费[2000金]购买了[玄晶]".to_string(), 1_750_000_100),
+            ("[团长]花费[300金]购买了[五行石]".to_string(), 1_750_000_200),
+        ];
+        main.auction_lines = vec![
+            ("[团长]花费[2000金]购买了[玄晶]".to_string(), 1_750_000_102),
+            ("[团长]花费[300金]购买了[五行石]".to_string(), 1_750_000_201),
+        ];
+        // 同副本不同团（时间不吻合）
+        let mut other = candidate("r-other", "acc-4", base);
+        other.kills = vec![("笑妆娘".to_string(), base + 3_600_000)];
+        // 同账号不会与自己分组
+        let mut same_account = candidate("r-same", "acc-1", base);
+        same_account.kills = main.kills.clone();
+
+        let groups = group_runs(&[main, alt, alt2, other, same_account]);
+        assert_eq!(groups.get("r-main").map(String::as_str), Some("r-alt"));
+        assert_eq!(
+            groups.get("r-alt").map(String::as_str),
+            Some("r-alt"),
+            "最早的记录为主记录"
+        );
+        assert_eq!(groups.get("r-alt2").map(String::as_str), Some("r-alt"));
+        assert!(!groups.contains_key("r-other"));
+        // r-same 与 r-alt（不同账号、击杀吻合）同团
+        assert_eq!(groups.get("r-same").map(String::as_str), Some("r-alt"));
+    }
+
+    #[test]
+    fn test_link_run_groups_leaves_confirmed_records_untouched() {
+        let conn = db::open_test_db();
+        let base = 1_750_000_000_000_i64;
+        let insert = |id: &str, account: &str, status: &str, date: i64| {
+            let data = serde_json::json!({
+                "id": id, "accountId": account, "roleId": account, "raidName": "25人英雄阆风悬城",
+                "date": date, "goldIncome": 3000, "hasXuanjing": false,
+                "source": "auto", "status": status,
+            });
+            conn.execute(
+                "INSERT INTO records (id, data, raid_name, account_id, role_id, record_date, source, status)
+                 VALUES (?1, ?2, '25人英雄阆风悬城', ?3, ?3, ?4, 'auto_scan', ?5)",
+                params![id, data.to_string(), account, date, status],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO raid_attempts (record_id, boss_name, jcl_file, attempt_index, start_time, end_time, duration_ms, is_kill, created_at)
+                 VALUES (?1, '笑妆娘', ?1, 0, ?2, ?2, 0, 1, 'now')",
+                params![id, date + 60_000],
+            )
+            .unwrap();
+        };
+        // 已确认的记录更晚，仍作为主记录且不被修改
+        insert("r-confirmed", "acc-1", "confirmed", base + 5_000);
+        insert("r-pending", "acc-2", "pending", base);
+        let data_before: String = conn
+            .query_row(
+                "SELECT data FROM records WHERE id = 'r-confirmed'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        assert_eq!(link_run_groups(&conn, base - 1, base + 10_000).unwrap(), 2);
+
+        let (data_after, confirmed_group): (String, Option<String>) = conn
+            .query_row(
+                "SELECT data, run_group_id FROM records WHERE id = 'r-confirmed'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(data_after, data_before);
+        assert_eq!(confirmed_group, None);
+
+        let (pending_data, pending_group): (String, Option<String>) = conn
+            .query_row(
+                "SELECT data, run_group_id FROM records WHERE id = 'r-pending'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        let pending: serde_json::Value = serde_json::from_str(&pending_data).unwrap();
+        assert_eq!(pending_group.as_deref(), Some("r-confirmed"));
+        assert_eq!(pending["runGroupId"], "r-confirmed");
+        assert_eq!(pending["runGroupPrimary"], false);
+    }
+
+    #[test]
+    fn test_is_same_run_requires_same_raid() {
+        let mut a = candidate("a", "acc-1", 0);
+        a.kills = vec![("笑妆娘".to_string(), 0)];
+        let mut b = candidate("b", "acc-2", 0);
+        b.kills = a.kills.clone();
+        assert!(is_same_run(&a, &b));
+        b.raid_name = "10人普通阆风悬城".to_string();
+        assert!(!is_same_run(&a, &b));
+    }
+}
@@ -0,0 +1,357 @@
+//! 团长模式：工资分配表
+//!
+//! 以记录的团队拍卖账本（`auction_ledger`）总金额为收入，按团长填写的规则计算分配：
+//! 总收入 + 罚款 - 团长费用 - 补贴（坦克/治疗/MVP 等）= 可分配金额，按人数平分为每人底薪。
+//! 金额输入沿用 chatlog 的"X金砖Y金"格式（`parse_expense_amount`），
+//! 导出文本末行为"每人底薪：X金"，与扫描时识别底薪的 `SALARY_RE` 一致。
+//! 分配表按记录保存在 `record_salary_sheets` 表（输入规则与计算结果各存一份 JSON）。
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::db;
+use crate::mingyi::auction_ledger;
+use crate::mingyi::drop_scanner::parse_expense_amount;
+
+/// 补贴类别
+const SUBSIDY_CATEGORIES: [(&str, &str); 4] = [
+    ("tank", "坦克补贴"),
+    ("healer", "治疗补贴"),
+    ("mvp", "MVP"),
+    ("other", "其他补贴"),
+];
+
+/// 补贴 / 罚款条目（团长填写）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalaryAdjustment {
+    /// 对象角色名
+    pub target: String,
+    /// 补贴类别（tank / healer / mvp / other），罚款忽略此字段
+    #[serde(default)]
+    pub category: String,
+    /// 金额，格式同 chatlog："2000金"、"1金砖5000金"，纯数字按金计
+    pub amount: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// 工资分配规则（团长填写）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalarySheetInput {
+    /// 团长费用：按总收入百分比提成
+    #[serde(default)]
+    pub leader_fee_percent: u32,
+    /// 团长费用：固定金额（与百分比同时填写时相加）
+    #[serde(default)]
+    pub leader_fee: Option<String>,
+    #[serde(default)]
+    pub subsidies: Vec<SalaryAdjustment>,
+    #[serde(default)]
+    pub penalties: Vec<SalaryAdjustment>,
+    /// 分配人数，为空时取团队名单人数
+    #[serde(default)]
+    pub headcount: Option<u32>,
+}
+
+/// 分配表中的一条补贴 / 罚款
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalarySheetLine {
+    pub target: String,
+    /// 类别显示名（坦克补贴 / 罚款 等）
+    pub label: String,
+    pub gold: i64,
+    pub note: Option<String>,
+}
+
+/// 工资分配表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalarySheet {
+    pub record_id: String,
+    /// 总收入（拍卖账本总金额）
+    pub total_income: i64,
+    pub penalty_total: i64,
+    pub leader_fee: i64,
+    pub subsidy_total: i64,
+    /// 可分配金额 = 总收入 + 罚款 - 团长费用 - 补贴
+    pub distributable: i64,
+    pub headcount: u32,
+    /// 每人底薪（可分配金额按人数向下取整）
+    pub per_head_salary: i64,
+    /// 平分后的余数
+    pub remainder: i64,
+    pub subsidies: Vec<SalarySheetLine>,
+    pub penalties: Vec<SalarySheetLine>,
+    pub input: SalarySheetInput,
+}
+
+/// 解析团长填写的金额（纯数字按金计，其余按 chatlog 金额格式解析）
+fn parse_amount(amount: &str) -> Result<i64, String> {
+    let trimmed = amount.trim();
+    if trimmed.is_empty() {
+        return Ok(0);
+    }
+    if let Ok(gold) = trimmed.parse::<i64>() {
+        return if gold >= 0 {
+            Ok(gold)
+        } else {
+            Err(format!("金额不能为负数: {}", amount))
+        };
+    }
+    let gold = parse_expense_amount(trimmed).gold();
+    if gold == 0 && !trimmed.starts_with('0') {
+        return Err(format!("金额格式无效: {}", amount));
+    }
+    Ok(gold)
+}
+
+fn adjustment_lines(
+    adjustments: &[SalaryAdjustment],
+    label_of: impl Fn(&SalaryAdjustment) -> Result<String, String>,
+) -> Result<Vec<SalarySheetLine>, String> {
+    adjustments
+        .iter()
+        .map(|adj| {
+            Ok(SalarySheetLine {
+                target: adj.target.clone(),
+                label: label_of(adj)?,
+                gold: parse_amount(&adj.amount)?,
+                note: adj.note.clone(),
+            })
+        })
+        .collect()
+}
+
+/// 按分配规则计算工资分配表
+pub(crate) fn compute_salary_sheet(
+    record_id: &str,
+    total_income: i64,
+    headcount: Option<u32>,
+    input: SalarySheetInput,
+) -> Result<SalarySheet, String> {
+    let headcount = input
+        .headcount
+        .or(headcount)
+        .filter(|&h| h > 0)
+        .ok_or_else(|| "无法确定分配人数，请填写人数".to_string())?;
+    if input.leader_fee_percent > 100 {
+        return Err(format!("团长提成比例无效: {}%", input.leader_fee_percent));
+    }
+
+    let subsidies = adjustment_lines(&input.subsidies, |adj| {
+        SUBSIDY_CATEGORIES
+            .iter()
+            .find(|(key, _)| *key == adj.category)
+            .map(|(_, label)| label.to_string())
+            .ok_or_else(|| format!("未知的补贴类别: {}", adj.category))
+    })?;
+    let penalties = adjustment_lines(&input.penalties, |_| Ok("罚款".to_string()))?;
+
+    let leader_fee = total_income * input.leader_fee_percent as i64 / 100
+        + parse_amount(input.leader_fee.as_deref().unwrap_or(""))?;
+    let subsidy_total: i64 = subsidies.iter().map(|l| l.gold).sum();
+    let penalty_total: i64 = penalties.iter().map(|l| l.gold).sum();
+    let distributable = total_income + penalty_total - leader_fee - subsidy_total;
+    if distributable < 0 {
+        return Err(format!(
+            "团长费用与补贴合计超过总收入（可分配 {} 金）",
+            distributable
+        ));
+    }
+
+    Ok(SalarySheet {
+        record_id: record_id.to_string(),
+        total_income,
+        penalty_total,
+        leader_fee,
+        subsidy_total,
+        distributable,
+        headcount,
+        per_head_salary: distributable / headcount as i64,
+        remainder: distributable % headcount as i64,
+        subsidies,
+        penalties,
+        input,
+    })
+}
+
+/// 导出为可直接发到团队频道的文本，末行为"每人底薪：X金"
+pub(crate) fn format_salary_sheet(sheet: &SalarySheet) -> String {
+    let mut lines = vec![format!("总收入：{}金", sheet.total_income)];
+    if sheet.penalty_total > 0 {
+        lines.push(format!("罚款：{}金", sheet.penalty_total));
+        for line in &sheet.penalties {
+            lines.push(format!("  {} {}金", line.target, line.gold));
+        }
+    }
+    if sheet.leader_fee > 0 {
+        lines.push(format!("团长费用：{}金", sheet.leader_fee));
+    }
+    if sheet.subsidy_total > 0 {
+        lines.push(format!("补贴：{}金", sheet.subsidy_total));
+        for line in &sheet.subsidies {
+            lines.push(format!("  {} {} {}金", line.label, line.target, line.gold));
+        }
+    }
+    lines.push(format!(
+        "可分配：{}金，{}人",
+        sheet.distributable, sheet.headcount
+    ));
+    lines.push(format!("每人底薪：{}金", sheet.per_head_salary));
+    lines.join("\n")
+}
+
+/// 根据记录的拍卖账本计算分配表（不保存）
+fn build_sheet_for_record(
+    conn: &Connection,
+    record_id: &str,
+    input: SalarySheetInput,
+) -> Result<SalarySheet, String> {
+    let ledger = auction_ledger::query_auction_ledger(conn, record_id, input.headcount)?;
+    // 分配表按整金分配（导出的底薪行为整金），总收入取账本总金额的整金部分
+    compute_salary_sheet(record_id, ledger.pot.gold(), ledger.headcount, input)
+}
+
+fn load_salary_sheet(conn: &Connection, record_id: &str) -> Result<Option<SalarySheet>, String> {
+    let sheet: String = match conn.query_row(
+        "SELECT sheet FROM record_salary_sheets WHERE record_id = ?1",
+        params![record_id],
+        |row| row.get(0),
+    ) {
+        Ok(sheet) => sheet,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(format!("查询工资分配表失败: {}", e)),
+    };
+    serde_json::from_str(&sheet)
+        .map(Some)
+        .map_err(|e| format!("解析工资分配表失败: {}", e))
+}
+
+/// Tauri 命令：按分配规则预览工资分配表（不保存）
+#[tauri::command]
+pub fn preview_salary_sheet(
+    record_id: String,
+    input: SalarySheetInput,
+) -> Result<SalarySheet, String> {
+    let conn = db::init_db()?;
+    build_sheet_for_record(&conn, &record_id, input)
+}
+
+/// Tauri 命令：计算并保存记录的工资分配表
+#[tauri::command]
+pub fn save_salary_sheet(
+    record_id: String,
+    input: SalarySheetInput,
+) -> Result<SalarySheet, String> {
+    let conn = db::init_db()?;
+    let sheet = build_sheet_for_record(&conn, &record_id, input)?;
+    let now = chrono::Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO record_salary_sheets (record_id, input, sheet, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?4)
+         ON CONFLICT(record_id) DO UPDATE SET input = excluded.input, sheet = excluded.sheet, updated_at = excluded.updated_at",
+        params![
+            record_id,
+            serde_json::to_string(&sheet.input).map_err(|e| e.to_string())?,
+            serde_json::to_string(&sheet).map_err(|e| e.to_string())?,
+            now
+        ],
+    )
+    .map_err(|e| format!("保存工资分配表失败: {}", e))?;
+    log::info!(
+        "[SalarySheet] 记录 {} 保存工资分配表: 总收入 {} 金, {} 人, 每人 {} 金",
+        record_id,
+        sheet.total_income,
+        sheet.headcount,
+        sheet.per_head_salary
+    );
+    Ok(sheet)
+}
+
+/// Tauri 命令：查询记录已保存的工资分配表
+#[tauri::command]
+pub fn get_salary_sheet(record_id: String) -> Result<Option<SalarySheet>, String> {
+    let conn = db::init_db()?;
+    load_salary_sheet(&conn, &record_id)
+}
+
+/// Tauri 命令：导出记录已保存的工资分配表文本
+#[tauri::command]
+pub fn export_salary_sheet(record_id: String) -> Result<String, String> {
+    let conn = db::init_db()?;
+    let sheet = load_salary_sheet(&conn, &record_id)?
+        .ok_or_else(|| "该记录尚未保存工资分配表".to_string())?;
+    Ok(format_salary_sheet(&sheet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mingyi::drop_scanner::SALARY_RE;
+    use crate::money::Money;
+
+    fn adjustment(target: &str, category: &str, amount: &str) -> SalaryAdjustment {
+        SalaryAdjustment {
+            target: target.to_string(),
+            category: category.to_string(),
+            amount: amount.to_string(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_compute_salary_sheet() {
+        let input = SalarySheetInput {
+            leader_fee_percent: 5,
+            leader_fee: None,
+            subsidies: vec![
+                adjustment("糯闪", "tank", "1金砖"),
+                adjustment("唐小糖", "healer", "5000金"),
+                adjustment("阿白", "mvp", "1金砖5000金"),
+            ],
+            penalties: vec![adjustment("摸鱼人", "", "2000")],
+            headcount: None,
+        };
+        let sheet = compute_salary_sheet("r1", 200_000, Some(25), input).unwrap();
+        assert_eq!(sheet.leader_fee, 10_000);
+        assert_eq!(sheet.subsidy_total, 30_000);
+        assert_eq!(sheet.penalty_total, 2_000);
+        assert_eq!(sheet.distributable, 162_000);
+        assert_eq!(sheet.per_head_salary, 6_480);
+        assert_eq!(sheet.remainder, 0);
+        assert_eq!(sheet.subsidies[0].label, "坦克补贴");
+
+        // 导出文本的底薪行与扫描识别规则一致
+        let text = format_salary_sheet(&sheet);
+        let caps = SALARY_RE.captures(&text).unwrap();
+        assert_eq!(Money::parse_text(&caps[1]), Some(Money::from_gold(6_480)));
+    }
+
+    #[test]
+    fn test_compute_salary_sheet_rejects_invalid_input() {
+        let unknown_category = SalarySheetInput {
+            subsidies: vec![adjustment("糯闪", "dps", "1000")],
+            ..Default::default()
+        };
+        assert!(compute_salary_sheet("r1", 100_000, Some(25), unknown_category).is_err());
+
+        let bad_amount = SalarySheetInput {
+            penalties: vec![adjustment("糯闪", "", "一千")],
+            ..Default::default()
+        };
+        assert!(compute_salary_sheet("r1", 100_000, Some(25), bad_amount).is_err());
+
+        let no_headcount = SalarySheetInput::default();
+        assert!(compute_salary_sheet("r1", 100_000, None, no_headcount).is_err());
+
+        let overspent = SalarySheetInput {
+            leader_fee: Some("1金砖".to_string()),
+            subsidies: vec![adjustment("糯闪", "tank", "1金砖")],
+            ..Default::default()
+        };
+        assert!(compute_salary_sheet("r1", 15_000, Some(25), overspent).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! 掉落扫描解释（dry-run）
+//!
+//! `scan_raid_drops_explain` 按与自动扫描完全相同的流程处理指定时间范围内的 JCL 和 chatlog，
+//! 但不写入 records / jcl_cache、不做多开同团识别，而是返回每一步的判定依据：
+//! 参与扫描的 JCL 文件、每个 JCL 的击杀判定及成立的信号、副本实例的聚类结果、
+//! `match_raid_name` 匹配到的副本，以及计入收入 / 支出的 chatlog 消息。
+//! 用于排查"这场副本为什么没识别 / 收入为什么不对"。
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::drop_scanner::{self, ChatlogLineItem};
+use crate::game_directory::MINGYI_ACCOUNTS_BASE_PATH;
+use crate::money::Money;
+
+/// 单个 JCL 的击杀判定
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JclVerdict {
+    /// JCL 内容是否解析成功（失败时按未击杀处理）
+    pub parsed: bool,
+    /// 判定后的 BOSS 名（已按文件名 BOSS 优先级处理）
+    pub boss_name: Option<String>,
+    pub fight_start_ms: i64,
+    pub fight_end_ms: i64,
+    pub is_kill: bool,
+    pub boss_hp_percent: Option<f64>,
+    pub player_count: usize,
+    /// 击杀判定中成立的信号（如 treasure_box、death_notify、companion_jcl）
+    pub signals: Vec<&'static str>,
+}
+
+/// 参与扫描的 JCL 文件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JclFileTrace {
+    pub file_name: String,
+    /// 文件名时间（毫秒）
+    pub timestamp: i64,
+    /// JCL 副本名
+    pub raid_display_name: String,
+    /// 文件名 BOSS
+    pub file_boss_name: String,
+    /// `match_raid_name` 匹配到的副本（raid_id），None 表示未配置副本，已跳过
+    pub matched_raid: Option<String>,
+    /// 击杀判定（未配置副本不分析）
+    pub verdict: Option<JclVerdict>,
+    /// 所属副本实例在 `instances` 中的下标，None 表示未进入任何实例（如拉托）
+    pub instance_index: Option<usize>,
+}
+
+/// 聚类得到的副本实例
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceTrace {
+    /// 聚类时的 JCL 副本名
+    pub raid_display_name: String,
+    /// `match_raid_name` 匹配到的副本（raid_id），None 表示未匹配，不会生成记录
+    pub matched_raid: Option<String>,
+    /// 匹配到的副本名（raids.name）
+    pub raid_name: Option<String>,
+    pub start_time: i64,
+    pub last_jcl_time: i64,
+    /// 成功击杀的 JCL 文件
+    pub jcl_files: Vec<String>,
+    /// 组内全部战斗尝试的 JCL 文件（含灭团）
+    pub attempt_files: Vec<String>,
+    /// 已击杀 BOSS（经 chatlog 10金校验后）
+    pub bosses_killed: Vec<String>,
+    pub boss_ids: Vec<String>,
+    pub boss_names: Vec<String>,
+    /// chatlog 识别到的掉落物（按名称排序）
+    pub drops: Vec<String>,
+    /// chatlog 分析窗口（毫秒）
+    pub chatlog_start: i64,
+    pub chatlog_end: i64,
+    /// 窗口结束依据：nextJcl / now / lastJclPlus2h
+    pub chatlog_end_reason: &'static str,
+    pub base_salary: Option<Money>,
+    pub total_income: Money,
+    pub total_expense: Money,
+    pub raid_complete: bool,
+    /// 实际扫描时写入的状态（scanning / pending）
+    pub record_status: &'static str,
+    /// 参与收支计算的 chatlog 消息（含未计入的重复消息及说明）
+    pub chatlog_items: Vec<ChatlogLineItem>,
+}
+
+/// 一次扫描的完整判定过程
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanTrace {
+    pub account_id: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub jx3_running: bool,
+    /// 判定使用的在线状态（进程运行且有 JCL 时强制在线）
+    pub role_online: bool,
+    pub jcl_files: Vec<JclFileTrace>,
+    pub chatlog_files: Vec<String>,
+    /// 预加载的 chatlog 消息数
+    pub chatlog_record_count: usize,
+    pub instances: Vec<InstanceTrace>,
+    /// 流程提前结束、实例被跳过等说明
+    pub notes: Vec<String>,
+}
+
+/// Tauri 命令：解释指定账号在时间范围内的掉落扫描过程（不写入数据库）
+///
+/// 进程状态与角色在线状态的获取方式与 `scan_raid_drops` 一致，
+/// 但不按进程启动时间过滤 JCL，只按 `start_ms` / `end_ms` 限定范围（0 表示不限）。
+#[tauri::command]
+pub async fn scan_raid_drops_explain(
+    account_id: String,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<ScanTrace, String> {
+    tokio::task::spawn_blocking(move || {
+        let game_dir = drop_scanner::get_game_directory()?;
+        let account_dir = PathBuf::from(&game_dir)
+            .join(MINGYI_ACCOUNTS_BASE_PATH)
+            .join(format!("{}@zhcn_hd", account_id));
+        let jx3_running = crate::jx3_process::get_jx3_runtime_status_internal(&game_dir).is_running;
+        let role_online = drop_scanner::is_role_online(&account_dir, jx3_running);
+        drop_scanner::explain_raid_drops(&account_id, jx3_running, role_online, start_ms, end_ms)
+    })
+    .await
+    .map_err(|e| format!("扫描解释线程异常: {}", e))?
+}
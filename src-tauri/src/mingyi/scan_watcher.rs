@@ -0,0 +1,593 @@
+//! 账号日志目录监听（由目录变化驱动掉落扫描，替代前端定时轮询）
+//!
+//! 监听每个茗伊账号的 `userdata/combat_logs`（新 JCL）与 `userdata/chat_log`（chatlog 写入），
+//! 去抖后只对发生变化的账号执行掉落扫描，并通过更新回调推送该账号最新的待确认记录
+//! （main.rs 中将回调转为 Tauri 事件 `raid-scan-updated`）。
+//!
+//! 去抖规则：
+//! - 账号目录静默 `DEBOUNCE` 后扫描；持续写入时最多等待 `MAX_WAIT`
+//! - 只有 chatlog 变化时（在线聊天会频繁写入），同一账号两次扫描至少间隔 `CHATLOG_RESCAN_INTERVAL`
+//!
+//! 监听期间同时记录各账号 chat_log / combat_logs 的最新写入时间，
+//! `active_detector` 与 `is_role_online` 直接使用，无需每次遍历目录。
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db;
+use crate::game_directory::MINGYI_ACCOUNTS_BASE_PATH;
+use crate::mingyi::active_detector;
+use crate::mingyi::drop_scanner;
+
+/// 扫描更新事件名（前端 listen）
+pub const RAID_SCAN_UPDATED_EVENT: &str = "raid-scan-updated";
+
+/// 目录静默多久后触发扫描
+const DEBOUNCE: Duration = Duration::from_secs(3);
+/// 持续写入时最长等待时间
+const MAX_WAIT: Duration = Duration::from_secs(30);
+/// 仅 chatlog 变化时同一账号的最小扫描间隔（与原前端轮询间隔一致）
+const CHATLOG_RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+/// 监听线程检查去抖队列的间隔
+const TICK: Duration = Duration::from_millis(500);
+
+const COMBAT_LOGS_DIR: &str = "combat_logs";
+const CHAT_LOG_DIR: &str = "chat_log";
+
+/// 触发扫描的目录变化类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WatchTrigger {
+    /// combat_logs 下新增/写入 JCL
+    Jcl,
+    /// chat_log 下 chatlog 数据库写入
+    Chatlog,
+}
+
+/// 单个账号扫描完成后推送的更新
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanWatchUpdate {
+    /// 茗伊账号 UID
+    pub account_id: String,
+    pub triggers: Vec<WatchTrigger>,
+    /// 扫描到的副本实例数量（成功时）
+    pub instance_count: Option<usize>,
+    /// 失败原因（失败时）
+    pub error: Option<String>,
+    /// 该账号当前的待确认记录（pending / scanning，records.data JSON）
+    pub pending_records: Vec<serde_json::Value>,
+}
+
+/// 监听状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanWatcherStatus {
+    pub running: bool,
+    pub game_directory: Option<String>,
+    /// 已监听的账号数量
+    pub account_count: usize,
+    /// 已监听的目录数量
+    pub watched_dir_count: usize,
+}
+
+/// 监听期间账号目录的最新写入时间
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WatchedActivity {
+    /// 最新 chatlog 数据库写入时间
+    pub chat_log: Option<SystemTime>,
+    /// 最新 JCL 写入时间
+    pub combat_logs: Option<SystemTime>,
+}
+
+struct ScanWatcher {
+    watcher: RecommendedWatcher,
+    game_directory: String,
+    account_count: usize,
+    watched_dir_count: usize,
+}
+
+impl ScanWatcher {
+    fn status(&self) -> ScanWatcherStatus {
+        ScanWatcherStatus {
+            running: true,
+            game_directory: Some(self.game_directory.clone()),
+            account_count: self.account_count,
+            watched_dir_count: self.watched_dir_count,
+        }
+    }
+}
+
+/// 当前监听器（停止时置空，drop 后监听线程随通道关闭退出）
+static SCAN_WATCHER: OnceLock<Mutex<Option<ScanWatcher>>> = OnceLock::new();
+
+fn scan_watcher() -> &'static Mutex<Option<ScanWatcher>> {
+    SCAN_WATCHER.get_or_init(|| Mutex::new(None))
+}
+
+/// 账号 UID → 最新写入时间（仅监听期间有效）
+static WATCHED_ACTIVITY: OnceLock<Mutex<HashMap<String, WatchedActivity>>> = OnceLock::new();
+
+fn watched_activity_map() -> &'static Mutex<HashMap<String, WatchedActivity>> {
+    WATCHED_ACTIVITY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+type UpdateListener = Box<dyn Fn(&ScanWatchUpdate) + Send + Sync>;
+
+/// 扫描更新回调（应用启动时由 main.rs 注册一次）
+static UPDATE_LISTENER: OnceLock<UpdateListener> = OnceLock::new();
+
+/// 注册扫描更新回调
+pub fn set_update_listener(listener: impl Fn(&ScanWatchUpdate) + Send + Sync + 'static) {
+    if UPDATE_LISTENER.set(Box::new(listener)).is_err() {
+        log::warn!("[ScanWatcher] 扫描更新回调已注册，忽略重复注册");
+    }
+}
+
+/// 监听中的账号目录最新写入时间；未监听该账号时返回 None（调用方回退到遍历目录）
+pub fn watched_activity(account_dir: &Path) -> Option<WatchedActivity> {
+    let uid = account_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_suffix("@zhcn_hd"))?;
+    watched_activity_map().lock().ok()?.get(uid).copied()
+}
+
+/// 将变化的文件路径归类为 (账号 UID, 变化类型)；与扫描无关的文件返回 None
+///
+/// 路径结构：`{uid}@zhcn_hd/userdata/{combat_logs|chat_log}/{文件}`
+fn classify_event_path(path: &Path) -> Option<(String, WatchTrigger)> {
+    let file_name = path.file_name()?.to_str()?;
+    let dir = path.parent()?;
+    let trigger = match dir.file_name()?.to_str()? {
+        COMBAT_LOGS_DIR if file_name.ends_with(".jcl") => WatchTrigger::Jcl,
+        CHAT_LOG_DIR
+            if file_name.starts_with("chatlog_")
+                && (file_name.ends_with(".v2.db") || file_name.ends_with(".v2.db-wal")) =>
+        {
+            WatchTrigger::Chatlog
+        }
+        _ => return None,
+    };
+    let userdata_dir = dir.parent()?;
+    if userdata_dir.file_name()?.to_str()? != "userdata" {
+        return None;
+    }
+    let account_dir_name = userdata_dir.parent()?.file_name()?.to_str()?;
+    let uid = account_dir_name.strip_suffix("@zhcn_hd")?;
+    Some((uid.to_string(), trigger))
+}
+
+/// 记录文件写入时间（取文件 mtime，只前进不后退）
+fn record_activity(uid: &str, trigger: WatchTrigger, path: &Path) {
+    let Ok(mtime) = std::fs::metadata(path).and_then(|m| m.modified()) else {
+        return;
+    };
+    let Ok(mut map) = watched_activity_map().lock() else {
+        return;
+    };
+    let activity = map.entry(uid.to_string()).or_default();
+    let slot = match trigger {
+        WatchTrigger::Jcl => &mut activity.combat_logs,
+        WatchTrigger::Chatlog => &mut activity.chat_log,
+    };
+    if slot.is_none_or(|current| mtime > current) {
+        *slot = Some(mtime);
+    }
+}
+
+struct PendingScan {
+    triggers: Vec<WatchTrigger>,
+    first_event: Instant,
+    last_event: Instant,
+}
+
+/// 按账号聚合目录变化的去抖队列
+#[derive(Default)]
+struct DebounceQueue {
+    pending: HashMap<String, PendingScan>,
+    last_scan: HashMap<String, Instant>,
+}
+
+impl DebounceQueue {
+    fn push(&mut self, uid: &str, trigger: WatchTrigger, now: Instant) {
+        let entry = self
+            .pending
+            .entry(uid.to_string())
+            .or_insert_with(|| PendingScan {
+                triggers: Vec::new(),
+                first_event: now,
+                last_event: now,
+            });
+        entry.last_event = now;
+        if !entry.triggers.contains(&trigger) {
+            entry.triggers.push(trigger);
+        }
+    }
+
+    /// 取出可以扫描的账号（按 UID 排序），并记录扫描时间
+    fn take_ready(&mut self, now: Instant) -> BTreeMap<String, Vec<WatchTrigger>> {
+        let ready: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(uid, scan)| {
+                let settled = now.duration_since(scan.last_event) >= DEBOUNCE
+                    || now.duration_since(scan.first_event) >= MAX_WAIT;
+                let throttled = !scan.triggers.contains(&WatchTrigger::Jcl)
+                    && self
+                        .last_scan
+                        .get(*uid)
+                        .is_some_and(|t| now.duration_since(*t) < CHATLOG_RESCAN_INTERVAL);
+                settled && !throttled
+            })
+            .map(|(uid, _)| uid.clone())
+            .collect();
+
+        let mut result = BTreeMap::new();
+        for uid in ready {
+            if let Some(scan) = self.pending.remove(&uid) {
+                self.last_scan.insert(uid.clone(), now);
+                result.insert(uid, scan.triggers);
+            }
+        }
+        result
+    }
+}
+
+/// 查询账号当前的待确认记录
+fn load_pending_records(
+    conn: &Connection,
+    account_id: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT data FROM records
+             WHERE account_id = ?1 AND source = 'auto_scan' AND status IN ('pending', 'scanning')
+             ORDER BY record_date DESC",
+        )
+        .map_err(|e| format!("准备待确认记录查询失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![account_id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("查询待确认记录失败: {}", e))?;
+    let mut records = Vec::new();
+    for data in rows.flatten() {
+        if let Ok(value) = serde_json::from_str(&data) {
+            records.push(value);
+        }
+    }
+    Ok(records)
+}
+
+/// 扫描发生变化的账号（与批量扫描一致：当前 CD 窗口 + 进程启动时间过滤）
+fn scan_changed_accounts(changed: BTreeMap<String, Vec<WatchTrigger>>) -> Result<(), String> {
+    let game_dir = drop_scanner::get_game_directory()?;
+    let runtime_status = crate::jx3_process::get_jx3_runtime_status_internal(&game_dir);
+    let jx3_running = runtime_status.is_running;
+    let process_start_ms = runtime_status
+        .matched_process
+        .as_ref()
+        .map(|p| (p.start_time_unix as i64) * 1000)
+        .unwrap_or(0);
+    let now_ms = chrono::Local::now().timestamp_millis();
+    let (cd_start_ms, cd_end_ms) = drop_scanner::calculate_cd_window(now_ms, false);
+
+    let conn = db::init_db()?;
+    let raids = drop_scanner::get_cached_raids(&conn)?;
+    let accounts_base = PathBuf::from(&game_dir).join(MINGYI_ACCOUNTS_BASE_PATH);
+
+    for (uid, triggers) in changed {
+        let account_dir = accounts_base.join(format!("{}@zhcn_hd", uid));
+        let role_online = drop_scanner::is_role_online(&account_dir, jx3_running);
+        log::info!(
+            "[ScanWatcher] 账号 {} 目录变化 {:?}，开始扫描 (role_online={})",
+            uid,
+            triggers,
+            role_online
+        );
+
+        let result = drop_scanner::scan_raid_drops_with_raids(
+            &uid,
+            jx3_running,
+            role_online,
+            process_start_ms,
+            cd_start_ms,
+            cd_end_ms,
+            Some(&raids),
+        );
+        let pending_records = load_pending_records(&conn, &uid).unwrap_or_else(|e| {
+            log::warn!("[ScanWatcher] 读取账号 {} 待确认记录失败: {}", uid, e);
+            Vec::new()
+        });
+        let (instance_count, error) = match result {
+            Ok(n) => (Some(n), None),
+            Err(e) => {
+                log::warn!("[ScanWatcher] 账号 {} 扫描失败: {}", uid, e);
+                (None, Some(e))
+            }
+        };
+        let update = ScanWatchUpdate {
+            account_id: uid,
+            triggers,
+            instance_count,
+            error,
+            pending_records,
+        };
+        if let Some(listener) = UPDATE_LISTENER.get() {
+            listener(&update);
+        }
+    }
+    Ok(())
+}
+
+/// 账号下新建 combat_logs / chat_log 目录时补充监听（首次打本前 combat_logs 可能不存在）
+fn watch_created_dir(path: &Path) {
+    let is_log_dir = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n == COMBAT_LOGS_DIR || n == CHAT_LOG_DIR);
+    if !is_log_dir || !path.is_dir() {
+        return;
+    }
+    let Ok(mut guard) = scan_watcher().lock() else {
+        return;
+    };
+    if let Some(state) = guard.as_mut() {
+        match state.watcher.watch(path, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                state.watched_dir_count += 1;
+                log::info!("[ScanWatcher] 新增监听目录: {}", path.display());
+            }
+            Err(e) => log::warn!("[ScanWatcher] 监听目录失败: {} - {}", path.display(), e),
+        }
+    }
+}
+
+/// 监听线程：接收目录事件、去抖并触发扫描；监听器 drop 后通道关闭，线程退出
+fn run_watch_loop(rx: Receiver<notify::Result<notify::Event>>) {
+    let mut queue = DebounceQueue::default();
+    loop {
+        match rx.recv_timeout(TICK) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                let now = Instant::now();
+                for path in &event.paths {
+                    if matches!(event.kind, EventKind::Create(_)) {
+                        watch_created_dir(path);
+                    }
+                    if let Some((uid, trigger)) = classify_event_path(path) {
+                        record_activity(&uid, trigger, path);
+                        queue.push(&uid, trigger, now);
+                    }
+                }
+            }
+            Ok(Err(e)) => log::warn!("[ScanWatcher] 目录监听错误: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let ready = queue.take_ready(Instant::now());
+        if !ready.is_empty() {
+            if let Err(e) = scan_changed_accounts(ready) {
+                log::warn!("[ScanWatcher] 扫描变化账号失败: {}", e);
+            }
+        }
+    }
+    log::info!("[ScanWatcher] 监听线程已退出");
+}
+
+/// 启动账号日志目录监听（已在监听同一游戏目录时直接返回当前状态）
+pub fn start_scan_watcher_internal() -> Result<ScanWatcherStatus, String> {
+    let game_dir = drop_scanner::get_game_directory()?;
+    let mut guard = scan_watcher()
+        .lock()
+        .map_err(|e| format!("锁定目录监听状态失败: {}", e))?;
+    if let Some(state) = guard.as_ref() {
+        if state.game_directory == game_dir {
+            return Ok(state.status());
+        }
+    }
+    // 游戏目录变化：先停止旧监听
+    *guard = None;
+
+    let accounts_base = PathBuf::from(&game_dir).join(MINGYI_ACCOUNTS_BASE_PATH);
+    let entries = std::fs::read_dir(&accounts_base)
+        .map_err(|e| format!("读取茗伊账号目录失败: {} - {}", accounts_base.display(), e))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("创建目录监听失败: {}", e))?;
+
+    let mut account_count = 0;
+    let mut watched_dir_count = 0;
+    let mut activity_map = HashMap::new();
+    for entry in entries.flatten() {
+        let account_dir = entry.path();
+        let Some(dir_name) = account_dir.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !account_dir.is_dir() || !active_detector::is_mingyi_account_dir(dir_name) {
+            continue;
+        }
+        let uid = dir_name.trim_end_matches("@zhcn_hd").to_string();
+        let userdata_dir = account_dir.join("userdata");
+
+        // combat_logs / chat_log 不存在时监听 userdata，等待目录创建后补充监听
+        let mut watch_dirs: Vec<PathBuf> = [COMBAT_LOGS_DIR, CHAT_LOG_DIR]
+            .iter()
+            .map(|name| userdata_dir.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        if watch_dirs.len() < 2 && userdata_dir.is_dir() {
+            watch_dirs.push(userdata_dir.clone());
+        }
+        let mut watched_any = false;
+        for dir in &watch_dirs {
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    watched_dir_count += 1;
+                    watched_any = true;
+                }
+                Err(e) => log::warn!("[ScanWatcher] 监听目录失败: {} - {}", dir.display(), e),
+            }
+        }
+        if !watched_any {
+            continue;
+        }
+        account_count += 1;
+
+        // 启动时遍历一次目录作为初始写入时间，之后由事件更新
+        let now = SystemTime::now();
+        activity_map.insert(
+            uid,
+            WatchedActivity {
+                chat_log: active_detector::get_latest_file_mtime_in_dir(
+                    &userdata_dir.join(CHAT_LOG_DIR),
+                    Some(".v2.db"),
+                    UNIX_EPOCH,
+                    now,
+                ),
+                combat_logs: active_detector::get_latest_file_mtime_in_dir(
+                    &userdata_dir.join(COMBAT_LOGS_DIR),
+                    Some(".jcl"),
+                    UNIX_EPOCH,
+                    now,
+                ),
+            },
+        );
+    }
+
+    *watched_activity_map()
+        .lock()
+        .map_err(|e| format!("锁定账号写入时间失败: {}", e))? = activity_map;
+
+    std::thread::Builder::new()
+        .name("raid-scan-watcher".to_string())
+        .spawn(move || run_watch_loop(rx))
+        .map_err(|e| format!("启动目录监听线程失败: {}", e))?;
+
+    log::info!(
+        "[ScanWatcher] 开始监听 {} 个账号的 {} 个目录: {}",
+        account_count,
+        watched_dir_count,
+        accounts_base.display()
+    );
+    let state = ScanWatcher {
+        watcher,
+        game_directory: game_dir,
+        account_count,
+        watched_dir_count,
+    };
+    let status = state.status();
+    *guard = Some(state);
+    Ok(status)
+}
+
+/// 停止监听（清空写入时间记录，active_detector 回退到遍历目录）
+pub fn stop_scan_watcher_internal() -> Result<(), String> {
+    let stopped = scan_watcher()
+        .lock()
+        .map_err(|e| format!("锁定目录监听状态失败: {}", e))?
+        .take()
+        .is_some();
+    if let Ok(mut map) = watched_activity_map().lock() {
+        map.clear();
+    }
+    if stopped {
+        log::info!("[ScanWatcher] 已停止目录监听");
+    }
+    Ok(())
+}
+
+/// Tauri 命令：启动账号日志目录监听，扫描结果通过 `raid-scan-updated` 事件推送
+#[tauri::command]
+pub async fn start_raid_scan_watcher() -> Result<ScanWatcherStatus, String> {
+    tokio::task::spawn_blocking(start_scan_watcher_internal)
+        .await
+        .map_err(|e| format!("启动目录监听线程异常: {}", e))?
+}
+
+/// Tauri 命令：停止账号日志目录监听
+#[tauri::command]
+pub fn stop_raid_scan_watcher() -> Result<(), String> {
+    stop_scan_watcher_internal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_event_path() {
+        let base = Path::new("interface/my#data/432345564243886337@zhcn_hd/userdata");
+        assert_eq!(
+            classify_event_path(
+                &base.join("combat_logs/2024-06-03-20-15-30-25人英雄太极宫-12345.jcl")
+            ),
+            Some(("432345564243886337".to_string(), WatchTrigger::Jcl))
+        );
+        assert_eq!(
+            classify_event_path(&base.join("chat_log/chatlog_0.v2.db-wal")),
+            Some(("432345564243886337".to_string(), WatchTrigger::Chatlog))
+        );
+        assert_eq!(
+            classify_event_path(&base.join("chat_log/chatlog_0.v2.db-shm")),
+            None
+        );
+        assert_eq!(
+            classify_event_path(&base.join("combat_logs/readme.txt")),
+            None
+        );
+        assert_eq!(classify_event_path(&base.join("userdata.db")), None);
+        assert_eq!(
+            classify_event_path(Path::new("#cache/userdata/combat_logs/a.jcl")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_debounce_queue_throttles_chatlog_only() {
+        let mut queue = DebounceQueue::default();
+        let t0 = Instant::now();
+        queue.push("1", WatchTrigger::Chatlog, t0);
+        // 仍在写入，未静默
+        assert!(queue.take_ready(t0 + Duration::from_secs(1)).is_empty());
+        let ready = queue.take_ready(t0 + DEBOUNCE);
+        assert_eq!(ready.get("1"), Some(&vec![WatchTrigger::Chatlog]));
+
+        // 刚扫描过：仅 chatlog 变化需等待最小间隔
+        let t1 = t0 + DEBOUNCE + Duration::from_secs(1);
+        queue.push("1", WatchTrigger::Chatlog, t1);
+        assert!(queue.take_ready(t1 + DEBOUNCE).is_empty());
+        assert_eq!(
+            queue
+                .take_ready(t0 + DEBOUNCE + CHATLOG_RESCAN_INTERVAL)
+                .len(),
+            1
+        );
+
+        // 新 JCL 不受最小间隔限制
+        let t2 = t0 + DEBOUNCE + CHATLOG_RESCAN_INTERVAL + Duration::from_secs(1);
+        queue.push("1", WatchTrigger::Chatlog, t2);
+        queue.push("1", WatchTrigger::Jcl, t2);
+        let ready = queue.take_ready(t2 + DEBOUNCE);
+        assert_eq!(
+            ready.get("1"),
+            Some(&vec![WatchTrigger::Chatlog, WatchTrigger::Jcl])
+        );
+
+        // 持续写入：超过最长等待时间后仍会扫描
+        let t3 = t2 + CHATLOG_RESCAN_INTERVAL + DEBOUNCE;
+        queue.push("2", WatchTrigger::Jcl, t3);
+        queue.push("2", WatchTrigger::Jcl, t3 + MAX_WAIT);
+        assert_eq!(queue.take_ready(t3 + MAX_WAIT).len(), 1);
+    }
+}
@@ -0,0 +1,427 @@
+//! 进行中副本的实时时间线（推送到 UI）
+//!
+//! 副本进行中时记录处于 `scanning` 状态。每次扫描账号后（目录监听、前端轮询与手动扫描
+//! 都经 `drop_scanner::scan_raid_drops_with_raids` 完成），由本模块读取该账号 scanning 记录的战斗尝试（raid_attempts）与 chatlog 明细
+//! （record_chatlog_items，均由 drop_scanner 解析写入），与已推送的事件比对，
+//! 只推送新出现的事件（main.rs 中转为 Tauri 事件 `raid-session-event`）：
+//! 开始副本、BOSS 开怪、击杀 / 灭团、物品掉落、拍卖成交、底薪发放、副本结束。
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use crate::db;
+use crate::mingyi::drop_scanner::{self, ChatlogItemKind, ChatlogLineItem, RaidAttempt};
+use crate::money::Money;
+
+/// 副本时间线事件名（前端 listen）
+pub const RAID_SESSION_EVENT: &str = "raid-session-event";
+
+/// 最后一次战斗结束后多久才判定击杀 / 灭团（JCL 仍在写入时 is_kill 尚未确定）
+const FIGHT_SETTLE_MS: i64 = 60 * 1000;
+
+/// 时间线事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionEventKind {
+    /// 首次检测到进行中的副本记录
+    SessionStarted,
+    BossPulled,
+    BossKilled,
+    BossWiped,
+    /// 物品分配（MSG_ITEM）
+    ItemDropped,
+    /// 拍卖成交 / 团长分配记录
+    ItemAuctioned,
+    SalaryPosted,
+    /// 记录离开 scanning 状态（副本完成或被处理）
+    SessionEnded,
+}
+
+/// 时间线事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEvent {
+    pub record_id: String,
+    /// 茗伊账号 UID
+    pub account_id: String,
+    pub raid_name: String,
+    pub kind: SessionEventKind,
+    /// 事件时间（毫秒）
+    pub time_ms: i64,
+    pub boss_name: Option<String>,
+    /// 该 BOSS 的第几次尝试
+    pub attempt_index: Option<u32>,
+    pub duration_ms: Option<i64>,
+    /// 灭团时 BOSS 最低血量百分比
+    pub boss_hp_percent: Option<f64>,
+    pub item_name: Option<String>,
+    /// 拍卖买家 / 分配接收者
+    pub buyer: Option<String>,
+    /// 成交 / 底薪金额（铜精度）
+    pub amount: Option<Money>,
+    /// chatlog 消息原文
+    pub text: Option<String>,
+}
+
+impl SessionEvent {
+    fn new(record: &LiveRecord, kind: SessionEventKind, time_ms: i64) -> Self {
+        SessionEvent {
+            record_id: record.id.clone(),
+            account_id: record.account_id.clone(),
+            raid_name: record.raid_name.clone(),
+            kind,
+            time_ms,
+            boss_name: None,
+            attempt_index: None,
+            duration_ms: None,
+            boss_hp_percent: None,
+            item_name: None,
+            buyer: None,
+            amount: None,
+            text: None,
+        }
+    }
+
+    /// 去重键：同一场战斗（JCL）只推送一次击杀 / 灭团，同一条消息只推送一次
+    fn key(&self) -> String {
+        format!(
+            "{:?}|{}|{}|{}|{}",
+            self.kind,
+            self.time_ms,
+            self.boss_name.as_deref().unwrap_or(""),
+            self.attempt_index.unwrap_or(0),
+            self.text.as_deref().unwrap_or("")
+        )
+    }
+}
+
+/// 进行中的副本记录
+#[derive(Debug, Clone)]
+struct LiveRecord {
+    id: String,
+    account_id: String,
+    raid_name: String,
+    /// 副本开始时间（毫秒）
+    start_ms: i64,
+}
+
+/// 正在跟踪的副本记录
+#[derive(Debug, Default)]
+struct TrackedSession {
+    account_id: String,
+    /// 已推送事件的去重键
+    emitted: HashSet<String>,
+}
+
+/// 记录 ID → 跟踪状态（内存态，应用重启后重新推送完整时间线）
+static TRACKED_SESSIONS: OnceLock<Mutex<HashMap<String, TrackedSession>>> = OnceLock::new();
+
+fn tracked_sessions() -> &'static Mutex<HashMap<String, TrackedSession>> {
+    TRACKED_SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+type EventListener = Box<dyn Fn(&SessionEvent) + Send + Sync>;
+
+/// 时间线事件回调（应用启动时由 main.rs 注册一次）
+static EVENT_LISTENER: OnceLock<EventListener> = OnceLock::new();
+
+/// 注册时间线事件回调
+pub fn set_event_listener(listener: impl Fn(&SessionEvent) + Send + Sync + 'static) {
+    if EVENT_LISTENER.set(Box::new(listener)).is_err() {
+        log::warn!("[SessionTracker] 时间线事件回调已注册，忽略重复注册");
+    }
+}
+
+/// 由战斗尝试与 chatlog 明细构建完整时间线（按时间排序）
+///
+/// `now_ms` 用于判断最后一场战斗是否仍在进行：结束不足 `FIGHT_SETTLE_MS` 且未击杀时只输出开怪。
+fn build_session_events(
+    record: &LiveRecord,
+    attempts: &[RaidAttempt],
+    items: &[ChatlogLineItem],
+    now_ms: i64,
+) -> Vec<SessionEvent> {
+    let mut events = vec![SessionEvent::new(
+        record,
+        SessionEventKind::SessionStarted,
+        record.start_ms,
+    )];
+
+    let last_start = attempts.iter().map(|a| a.start_time).max();
+    for attempt in attempts {
+        events.push(SessionEvent {
+            boss_name: Some(attempt.boss_name.clone()),
+            attempt_index: Some(attempt.attempt_index),
+            ..SessionEvent::new(record, SessionEventKind::BossPulled, attempt.start_time)
+        });
+
+        let in_progress = Some(attempt.start_time) == last_start
+            && !attempt.is_kill
+            && now_ms - attempt.end_time < FIGHT_SETTLE_MS;
+        if in_progress {
+            continue;
+        }
+        let kind = if attempt.is_kill {
+            SessionEventKind::BossKilled
+        } else {
+            SessionEventKind::BossWiped
+        };
+        events.push(SessionEvent {
+            boss_name: Some(attempt.boss_name.clone()),
+            attempt_index: Some(attempt.attempt_index),
+            duration_ms: Some(attempt.duration_ms),
+            boss_hp_percent: attempt.boss_hp_percent.filter(|_| !attempt.is_kill),
+            ..SessionEvent::new(record, kind, attempt.end_time)
+        });
+    }
+
+    for item in items {
+        let kind = match item.kind {
+            ChatlogItemKind::ItemDrop => SessionEventKind::ItemDropped,
+            ChatlogItemKind::AuctionItem | ChatlogItemKind::Allocation => {
+                SessionEventKind::ItemAuctioned
+            }
+            ChatlogItemKind::Salary if item.counted => SessionEventKind::SalaryPosted,
+            _ => continue,
+        };
+        events.push(SessionEvent {
+            item_name: item.item_name.clone(),
+            buyer: item.buyer.clone(),
+            amount: item.amount,
+            text: Some(item.text.clone()),
+            ..SessionEvent::new(record, kind, item.time_sec * 1000)
+        });
+    }
+
+    events.sort_by_key(|e| e.time_ms);
+    events
+}
+
+/// 读取 `SELECT id, account_id, raid_name, record_date` 行（record_date 为毫秒时间戳）
+fn live_record_from_row(row: &rusqlite::Row) -> rusqlite::Result<LiveRecord> {
+    Ok(LiveRecord {
+        id: row.get(0)?,
+        account_id: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+        raid_name: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        start_ms: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+    })
+}
+
+fn load_live_records(conn: &Connection, account_id: &str) -> Result<Vec<LiveRecord>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, account_id, raid_name, record_date FROM records
+             WHERE account_id = ?1 AND source = 'auto_scan' AND status = 'scanning'
+             ORDER BY record_date ASC",
+        )
+        .map_err(|e| format!("准备进行中记录查询失败: {}", e))?;
+    let records = stmt
+        .query_map(params![account_id], live_record_from_row)
+        .map_err(|e| format!("查询进行中记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取进行中记录失败: {}", e))?;
+    Ok(records)
+}
+
+fn load_record_events(
+    conn: &Connection,
+    record: &LiveRecord,
+    now_ms: i64,
+) -> Result<Vec<SessionEvent>, String> {
+    let attempts = drop_scanner::query_raid_attempts(conn, &record.id)?;
+    let items = drop_scanner::query_record_chatlog_items(conn, &record.id)?;
+    Ok(build_session_events(record, &attempts, &items, now_ms))
+}
+
+/// 比对账号进行中副本的时间线，返回尚未推送的新事件（并标记为已推送）
+///
+/// 之前跟踪过、现已不在 scanning 状态的记录：先补推最后一次扫描新增的事件（如底薪），
+/// 再追加一条 `SessionEnded` 并停止跟踪。
+pub(crate) fn collect_new_events(
+    conn: &Connection,
+    account_id: &str,
+) -> Result<Vec<SessionEvent>, String> {
+    let now_ms = chrono::Local::now().timestamp_millis();
+    let live_records = load_live_records(conn, account_id)?;
+    let mut sessions = tracked_sessions()
+        .lock()
+        .map_err(|e| format!("锁定副本时间线状态失败: {}", e))?;
+
+    let mut new_events = Vec::new();
+    for record in &live_records {
+        let session = sessions
+            .entry(record.id.clone())
+            .or_insert_with(|| TrackedSession {
+                account_id: record.account_id.clone(),
+                emitted: HashSet::new(),
+            });
+        for event in load_record_events(conn, record, now_ms)? {
+            if session.emitted.insert(event.key()) {
+                new_events.push(event);
+            }
+        }
+    }
+
+    let live_ids: HashSet<&str> = live_records.iter().map(|r| r.id.as_str()).collect();
+    let ended_ids: Vec<String> = sessions
+        .iter()
+        .filter(|(id, s)| s.account_id == account_id && !live_ids.contains(id.as_str()))
+        .map(|(id, _)| id.clone())
+        .collect();
+    for record_id in ended_ids {
+        let Some(session) = sessions.remove(&record_id) else {
+            continue;
+        };
+        let ended = conn.query_row(
+            "SELECT id, account_id, raid_name, record_date FROM records WHERE id = ?1",
+            params![record_id],
+            live_record_from_row,
+        );
+        let record = match ended {
+            Ok(record) => {
+                for event in load_record_events(conn, &record, now_ms)? {
+                    if !session.emitted.contains(&event.key()) {
+                        new_events.push(event);
+                    }
+                }
+                record
+            }
+            // 记录已被删除 / 合并
+            Err(rusqlite::Error::QueryReturnedNoRows) => LiveRecord {
+                id: record_id,
+                account_id: session.account_id,
+                raid_name: String::new(),
+                start_ms: 0,
+            },
+            Err(e) => return Err(format!("查询记录状态失败: {}", e)),
+        };
+        new_events.push(SessionEvent::new(
+            &record,
+            SessionEventKind::SessionEnded,
+            now_ms,
+        ));
+    }
+
+    if !new_events.is_empty() {
+        log::info!(
+            "[SessionTracker] 账号 {} 新增 {} 条副本时间线事件",
+            account_id,
+            new_events.len()
+        );
+    }
+    Ok(new_events)
+}
+
+/// 推送账号进行中副本的新事件（扫描完成后调用）
+pub(crate) fn publish_account_events(conn: &Connection, account_id: &str) {
+    match collect_new_events(conn, account_id) {
+        Ok(events) => {
+            if let Some(listener) = EVENT_LISTENER.get() {
+                for event in &events {
+                    listener(event);
+                }
+            }
+        }
+        Err(e) => log::warn!("[SessionTracker] 账号 {} 时间线更新失败: {}", account_id, e),
+    }
+}
+
+/// Tauri 命令：查询记录的完整副本时间线（UI 打开进行中副本时初始化）
+#[tauri::command]
+pub fn get_raid_session_timeline(record_id: String) -> Result<Vec<SessionEvent>, String> {
+    let conn = db::init_db()?;
+    let record = conn
+        .query_row(
+            "SELECT id, account_id, raid_name, record_date FROM records WHERE id = ?1",
+            params![record_id],
+            live_record_from_row,
+        )
+        .map_err(|e| format!("查询记录失败: {}", e))?;
+    load_record_events(&conn, &record, chrono::Local::now().timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(boss: &str, index: u32, start: i64, end: i64, is_kill: bool) -> RaidAttempt {
+        RaidAttempt {
+            boss_name: boss.to_string(),
+            jcl_file: format!("{}-{}.jcl", boss, index),
+            attempt_index: index,
+            start_time: start,
+            end_time: end,
+            duration_ms: end - start,
+            is_kill,
+            boss_hp_percent: if is_kill { None } else { Some(12.5) },
+        }
+    }
+
+    fn chat(kind: ChatlogItemKind, time_sec: i64, text: &str, counted: bool) -> ChatlogLineItem {
+        ChatlogLineItem {
+            kind,
+            time_sec,
+            channel: "MSG_ROOM".to_string(),
+            text: text.to_string(),
+            item_name: None,
+            buyer: None,
+            amount: None,
+            counted,
+            note: None,
+            row_key: None,
+        }
+    }
+
+    #[test]
+    fn test_build_session_events_orders_and_holds_running_fight() {
+        let record = LiveRecord {
+            id: "r1".to_string(),
+            account_id: "1".to_string(),
+            raid_name: "25人英雄太极宫".to_string(),
+            start_ms: 1_000_000,
+        };
+        let attempts = vec![
+            attempt("宫傲", 1, 1_100_000, 1_200_000, false),
+            attempt("宫傲", 2, 1_300_000, 1_400_000, true),
+            // 仍在战斗：结束时间距今不足 FIGHT_SETTLE_MS
+            attempt("鹰眼客", 1, 1_500_000, 1_550_000, false),
+        ];
+        let items = vec![
+            chat(
+                ChatlogItemKind::ItemDrop,
+                1_410,
+                "分配者将：[玄晶]分配给糯闪",
+                true,
+            ),
+            chat(ChatlogItemKind::BossReward, 1_401, "10金", false),
+            chat(ChatlogItemKind::Salary, 1_600, "每人底薪：3000金", false),
+        ];
+
+        let events = build_session_events(&record, &attempts, &items, 1_560_000);
+        let kinds: Vec<SessionEventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SessionEventKind::SessionStarted,
+                SessionEventKind::BossPulled,
+                SessionEventKind::BossWiped,
+                SessionEventKind::BossPulled,
+                SessionEventKind::BossKilled,
+                SessionEventKind::ItemDropped,
+                SessionEventKind::BossPulled,
+            ]
+        );
+        assert_eq!(events[2].boss_hp_percent, Some(12.5));
+
+        // 战斗结束一段时间后输出灭团结果
+        let settled = build_session_events(&record, &attempts, &items, 1_550_000 + FIGHT_SETTLE_MS);
+        assert_eq!(
+            settled.last().map(|e| e.kind),
+            Some(SessionEventKind::BossWiped)
+        );
+        let keys: HashSet<String> = settled.iter().map(|e| e.key()).collect();
+        assert_eq!(keys.len(), settled.len());
+    }
+}
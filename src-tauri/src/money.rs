@@ -0,0 +1,263 @@
+//! 金钱数值类型（铜精度）
+//!
+//! 剑网3 货币换算：1 金砖 = 10000 金，1 金 = 100 银，1 银 = 100 铜。
+//! `Money` 内部以铜为单位保存，统一 chatlog 金额解析（MSG_MONEY 的 XML 与"X金砖Y金"文本）和显示格式，
+//! 避免各处分别按金 / 铜累加时截断银铜部分。
+//!
+//! 记录 JSON 中 `goldIncomeCopper` / `goldExpenseCopper` 为唯一的存储金额（铜），
+//! `goldIncome` / `goldExpense` 由铜字段派生的整金，仅供显示与排序。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::sync::LazyLock;
+
+pub const COPPER_PER_SILVER: i64 = 100;
+pub const COPPER_PER_GOLD: i64 = 100 * COPPER_PER_SILVER;
+/// 1 金砖 = 10000 金
+pub const GOLD_PER_BRICK: i64 = 10000;
+
+// chatlog MSG_MONEY 的 msg 字段（XML）
+static MSG_GOLDB_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_GoldB""#).unwrap());
+static MSG_GOLD_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_Gold""#).unwrap());
+static MSG_SILVER_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_Silver""#).unwrap());
+static MSG_COPPER_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r#"text="(\d+)"[^>]*name="Text_Copper""#).unwrap());
+
+// "X金砖Y金Z银W铜" 文本
+static TEXT_BRICK_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(\d+)金砖").unwrap());
+static TEXT_GOLD_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(\d+)金").unwrap());
+static TEXT_SILVER_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(\d+)银").unwrap());
+static TEXT_COPPER_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(\d+)铜").unwrap());
+
+/// 金额（铜精度），序列化为铜数值
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Money(i64);
+
+fn capture_i64(re: &regex::Regex, text: &str) -> Option<i64> {
+    re.captures(text).and_then(|c| c[1].parse::<i64>().ok())
+}
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_copper(copper: i64) -> Self {
+        Money(copper)
+    }
+
+    pub const fn from_gold(gold: i64) -> Self {
+        Money(gold * COPPER_PER_GOLD)
+    }
+
+    /// 由 金 / 银 / 铜 三部分构造
+    pub const fn from_parts(gold: i64, silver: i64, copper: i64) -> Self {
+        Money(gold * COPPER_PER_GOLD + silver * COPPER_PER_SILVER + copper)
+    }
+
+    pub const fn copper(self) -> i64 {
+        self.0
+    }
+
+    /// 整金部分（向零截断银铜）
+    pub const fn gold(self) -> i64 {
+        self.0 / COPPER_PER_GOLD
+    }
+
+    /// 拆分为 (金, 银, 铜)
+    pub const fn parts(self) -> (i64, i64, i64) {
+        (
+            self.0 / COPPER_PER_GOLD,
+            self.0 % COPPER_PER_GOLD / COPPER_PER_SILVER,
+            self.0 % COPPER_PER_SILVER,
+        )
+    }
+
+    /// 解析 chatlog MSG_MONEY 的 msg 字段（XML）。
+    ///
+    /// 金额通过多个 text 节点展示：
+    ///   text="1"    name="Text_GoldB"   → 万金部分（≥10000金时出现）
+    ///   text="6082" name="Text_Gold"    → 金部分（<10000金）
+    ///   text="0"    name="Text_Silver"  → 银
+    ///   text="0"    name="Text_Copper"  → 铜
+    ///
+    /// 没有任何 Text_Gold 标记且金额为 0 时视为格式不符，返回 None。
+    pub fn parse_chatlog_msg(msg: &str) -> Option<Self> {
+        let goldb = capture_i64(&MSG_GOLDB_RE, msg).unwrap_or(0);
+        let gold = capture_i64(&MSG_GOLD_RE, msg).unwrap_or(0);
+        let silver = capture_i64(&MSG_SILVER_RE, msg).unwrap_or(0);
+        let copper = capture_i64(&MSG_COPPER_RE, msg).unwrap_or(0);
+
+        let money = Money::from_parts(goldb * GOLD_PER_BRICK + gold, silver, copper);
+        if money == Money::ZERO && !msg.contains("Text_Gold") {
+            return None;
+        }
+        Some(money)
+    }
+
+    /// 解析"X金砖Y金Z银W铜"文本（各部分均可省略，如"2000金"、"1金砖"、"1金砖6000金"）。
+    ///
+    /// 不含任何金额单位时返回 None。
+    pub fn parse_text(text: &str) -> Option<Self> {
+        let brick = capture_i64(&TEXT_BRICK_RE, text);
+        // 去掉"X金砖"后再取金，避免"金砖"中的"金"被重复匹配
+        let remaining = TEXT_BRICK_RE.replace_all(text, "");
+        let gold = capture_i64(&TEXT_GOLD_RE, &remaining);
+        let silver = capture_i64(&TEXT_SILVER_RE, text);
+        let copper = capture_i64(&TEXT_COPPER_RE, text);
+        if brick.is_none() && gold.is_none() && silver.is_none() && copper.is_none() {
+            return None;
+        }
+        Some(Money::from_parts(
+            brick.unwrap_or(0) * GOLD_PER_BRICK + gold.unwrap_or(0),
+            silver.unwrap_or(0),
+            copper.unwrap_or(0),
+        ))
+    }
+}
+
+/// 格式化为"X金砖Y金Z银W铜"，省略为 0 的部分；金额为 0 时为"0金"
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 0 {
+            write!(f, "-")?;
+        }
+        let (gold, silver, copper) = Money(self.0.abs()).parts();
+        let (brick, gold) = (gold / GOLD_PER_BRICK, gold % GOLD_PER_BRICK);
+        let mut written = false;
+        for (value, unit) in [
+            (brick, "金砖"),
+            (gold, "金"),
+            (silver, "银"),
+            (copper, "铜"),
+        ] {
+            if value != 0 {
+                write!(f, "{}{}", value, unit)?;
+                written = true;
+            }
+        }
+        if !written {
+            write!(f, "0金")?;
+        }
+        Ok(())
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        Money(iter.map(|m| m.0).sum())
+    }
+}
+
+/// 记录中保存的金额：铜字段为唯一的存储金额，仅缺少铜字段的旧记录按金字段换算。
+pub fn stored_money(gold: f64, copper: Option<i64>) -> Money {
+    match copper {
+        Some(copper) => Money::from_copper(copper),
+        None => Money::from_copper((gold * COPPER_PER_GOLD as f64).round() as i64),
+    }
+}
+
+/// 读取记录 JSON 中的铜精度金额（见 [`stored_money`]）
+fn record_money(data: &serde_json::Value, gold_key: &str, copper_key: &str) -> Money {
+    let gold = data.get(gold_key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+    stored_money(gold, data.get(copper_key).and_then(|v| v.as_i64()))
+}
+
+/// 记录收入（`goldIncomeCopper`，回退到 `goldIncome`）
+pub fn record_income(data: &serde_json::Value) -> Money {
+    record_money(data, "goldIncome", "goldIncomeCopper")
+}
+
+/// 记录支出（`goldExpenseCopper`，回退到 `goldExpense`）
+pub fn record_expense(data: &serde_json::Value) -> Money {
+    record_money(data, "goldExpense", "goldExpenseCopper")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money_parse_and_format() {
+        assert_eq!(
+            Money::parse_text("1金砖6000金"),
+            Some(Money::from_gold(16000))
+        );
+        assert_eq!(
+            Money::parse_text("147金76银53铜"),
+            Some(Money::from_parts(147, 76, 53))
+        );
+        assert_eq!(Money::parse_text("0金"), Some(Money::ZERO));
+        assert_eq!(Money::parse_text("一千"), None);
+
+        let money = Money::from_parts(16082, 5, 3);
+        assert_eq!(money.parts(), (16082, 5, 3));
+        assert_eq!(money.to_string(), "1金砖6082金5银3铜");
+        assert_eq!(Money::parse_text(&money.to_string()), Some(money));
+        assert_eq!(Money::ZERO.to_string(), "0金");
+        assert_eq!((-Money::from_gold(20)).to_string(), "-20金");
+
+        let total: Money = [Money::from_parts(1, 50, 0), Money::from_parts(0, 50, 0)]
+            .into_iter()
+            .sum();
+        assert_eq!(total, Money::from_gold(2));
+        assert_eq!((total - Money::from_copper(1)).gold(), 1);
+    }
+
+    #[test]
+    fn test_record_money_uses_copper() {
+        let data = serde_json::json!({ "goldIncome": 147, "goldIncomeCopper": 1_477_653 });
+        assert_eq!(record_income(&data), Money::from_parts(147, 76, 53));
+
+        // 只修改了银铜部分，派生的金字段不变
+        let edited = serde_json::json!({ "goldIncome": 147, "goldIncomeCopper": 1_470_000 });
+        assert_eq!(record_income(&edited), Money::from_gold(147));
+
+        let legacy = serde_json::json!({ "goldExpense": 3000 });
+        assert_eq!(record_expense(&legacy), Money::from_gold(3000));
+        assert_eq!(record_income(&legacy), Money::ZERO);
+    }
+}
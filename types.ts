@@ -70,8 +70,10 @@ export interface RaidRecord {
   roleId: string;
   raidName: string;
   date: string | number;
-  goldIncome: number;
+  goldIncome: number; // 整金，由 goldIncomeCopper 派生
   goldExpense?: number;
+  goldIncomeCopper?: number; // 铜精度收入，存储金额（显示用 getRecordIncome）
+  goldExpenseCopper?: number; // 铜精度支出（显示用 getRecordExpense）
   isCleared?: boolean;
   hasXuanjing: boolean;
   notes?: string;
@@ -90,10 +92,16 @@ export interface RaidRecord {
   bossName?: string;
   bossIds?: string[]; // 多选BOSS ID列表（25人本）
   bossNames?: string[]; // 多选BOSS名称列表（25人本）
-  source?: 'auto' | 'manual'; // 记录来源，默认 'manual'
+  source?: 'auto' | 'manual' | 'gkp'; // 记录来源，默认 'manual'；'gkp' 为 GKP 账本导入
   status?: 'pending' | 'confirmed' | 'rejected' | 'scanning'; // 确认状态，默认 'confirmed'；'scanning' 表示副本进行中，UI 锁定不可确认
   drops?: string[]; // 掉落物原始物品名列表（自动扫描）
   jclFiles?: string[]; // 关联 JCL 文件名列表（自动扫描溯源）
+  runGroupId?: string; // 多开同团分组 ID（组内最早记录的 ID）
+  runGroupPrimary?: boolean; // 是否为同团分组的主记录（共享掉落只在主记录统计）
+  region?: string; // 大区（自动扫描 / GKP 导入写入）
+  mapId?: number; // JCL 文件名中的副本 ID（自动扫描）
+  dropTable?: null; // 旧版理论掉落表，已废弃
+  gkp?: unknown; // GKP 导入的拍卖账本
 }
 
 export interface TrialPlaceRecord {
@@ -299,8 +307,10 @@ export interface BaizhanRecord {
   roleName: string;
   server: string;
   date: string | number;
-  goldIncome: number;   // 收入（金）
-  goldExpense?: number; // 支出（金）
+  goldIncome: number;   // 收入（整金，由铜字段派生）
+  goldExpense?: number; // 支出（整金，由铜字段派生）
+  goldIncomeCopper?: number;  // 铜精度收入（存储金额）
+  goldExpenseCopper?: number; // 铜精度支出
   notes?: string;
   type: 'baizhan';
 }
@@ -397,3 +407,100 @@ export interface AppConfig {
   autoScanEnabled: boolean;
   autoRefreshEquipScore: boolean;
 }
+
+/** 特殊掉落类别（记录筛选用，对应 has* 标记） */
+export type RareDropCategory =
+  | 'xuanjing'
+  | 'mount'
+  | 'secretBook'
+  | 'pet'
+  | 'maju'
+  | 'pendant'
+  | 'appearance'
+  | 'title';
+
+/** 记录筛选条件（与后端 RecordFilter 对齐），未设置的条件不过滤 */
+export interface RecordQueryFilter {
+  /** 副本时间下限（毫秒，闭区间） */
+  startMs?: number;
+  /** 副本时间上限（毫秒，开区间） */
+  endMs?: number;
+  seasonId?: number;
+  accountIds?: string[];
+  roleIds?: string[];
+  /** 完整副本名（如 "25人英雄阆风悬城"） */
+  raidNames?: string[];
+  /** 难度（如 "英雄"） */
+  difficulty?: string;
+  statuses?: Array<NonNullable<RaidRecord['status']>>;
+  /** 来源（records.source 列：manual / auto_scan / gkp_import） */
+  sources?: string[];
+  /** 命中任一类别的特殊掉落 */
+  rareDrops?: RareDropCategory[];
+  /** 在备注和掉落物中搜索 */
+  text?: string;
+}
+
+/** 记录排序方式，默认按副本时间倒序 */
+export interface RecordQuerySort {
+  field: 'date' | 'goldIncome';
+  ascending: boolean;
+}
+
+/** 分页查询结果 */
+export interface RecordQueryPage {
+  records: RaidRecord[];
+  /** 下一页游标，为空表示已是最后一页 */
+  nextCursor: string | null;
+  /** 满足筛选条件的记录总数 */
+  total: number;
+}
+
+/** 收入统计分组维度（与后端 StatsGroupBy 对齐） */
+export type IncomeStatsGroupBy = 'cdWindow' | 'week' | 'season' | 'account' | 'role' | 'raid';
+
+/** 单个分组的收入统计（金额为铜） */
+export interface IncomeStatsBucket {
+  key: string;
+  /** 展示名；时间分组为空，按 startMs/endMs 格式化 */
+  label: string;
+  startMs: number | null;
+  endMs: number | null;
+  income: number;
+  expense: number;
+  netIncome: number;
+  raidCount: number;
+  baizhanCount: number;
+  trialCount: number;
+  /** 特殊掉落数（多开同团只在主记录统计） */
+  rareDrops: Partial<Record<RareDropCategory, number>>;
+}
+
+export type RecordHistoryAction = 'create' | 'update' | 'confirm' | 'reject' | 'delete' | 'rescan' | 'undo' | 'restore';
+
+export interface RecordHistoryEntry {
+  id: number;
+  recordId: string;
+  action: RecordHistoryAction;
+  /** 变更前的记录 JSON，新建时为 null */
+  beforeData: string | null;
+  /** 变更后的记录 JSON，删除时为 null */
+  afterData: string | null;
+  createdAt: string;
+}
+
+export type TrashEntityType = 'record' | 'trialRecord' | 'baizhanRecord' | 'account' | 'role';
+
+export interface TrashItem {
+  id: number;
+  entityType: TrashEntityType;
+  entityId: string;
+  /** 展示名（账号名、角色名@服务器、副本名 · 角色名等） */
+  label: string;
+  /** 删除时间（毫秒） */
+  deletedAt: number;
+  /** 到期自动清除的时间（毫秒） */
+  expiresAt: number;
+  /** 快照中各表的行数 [表名, 行数]，展示级联删除的范围 */
+  rowCounts: [string, number][];
+}
@@ -1,6 +1,6 @@
 import { describe, expect, it } from 'vitest';
 import { RaidRecord } from '../types';
-import { buildSpecialDropRecords } from './rareDropUtils';
+import { buildSpecialDropRecords, dedupeRunGroupDrops } from './rareDropUtils';
 
 describe('buildSpecialDropRecords', () => {
   it('会展开一条记录中的全部特殊掉落类型', () => {
@@ -78,4 +78,71 @@ describe('buildSpecialDropRecords', () => {
       }),
     ]);
   });
+
+  it('多开同团的共享掉落只统计主记录', () => {
+    const records = [
+      {
+        id: 'main-record',
+        accountId: 'account-1',
+        roleId: 'role-1',
+        raidName: '25人英雄阆风悬城',
+        date: '2026-03-20T10:00:00.000Z',
+        goldIncome: 3000,
+        hasXuanjing: true,
+        runGroupId: 'main-record',
+        runGroupPrimary: true,
+      },
+      {
+        id: 'alt-record',
+        accountId: 'account-2',
+        roleId: 'role-2',
+        raidName: '25人英雄阆风悬城',
+        date: '2026-03-20T10:01:00.000Z',
+        goldIncome: 3000,
+        hasXuanjing: true,
+        runGroupId: 'main-record',
+        runGroupPrimary: false,
+      },
+    ] satisfies RaidRecord[];
+
+    expect(buildSpecialDropRecords(records, null)).toEqual([
+      expect.objectContaining({
+        id: 'main-record-hasXuanjing',
+        roleId: 'role-1',
+      }),
+    ]);
+  });
+
+  it('多开同团只有小号记录带的掉落合并到主记录，主记录不在范围内时计在组内第一条', () => {
+    const main = {
+      id: 'main-record',
+      accountId: 'account-1',
+      roleId: 'role-1',
+      raidName: '25人英雄阆风悬城',
+      date: '2026-03-20T10:00:00.000Z',
+      goldIncome: 3000,
+      hasXuanjing: false,
+    } satisfies RaidRecord;
+    const alt = {
+      id: 'alt-record',
+      accountId: 'account-2',
+      roleId: 'role-2',
+      raidName: '25人英雄阆风悬城',
+      date: '2026-03-20T10:01:00.000Z',
+      goldIncome: 3000,
+      hasXuanjing: true,
+      hasPet: true,
+      runGroupId: 'main-record',
+      runGroupPrimary: false,
+    } satisfies RaidRecord;
+
+    expect(buildSpecialDropRecords([main, alt], null)).toEqual([
+      expect.objectContaining({ id: 'main-record-hasXuanjing', roleId: 'role-1' }),
+      expect.objectContaining({ id: 'main-record-hasPet', roleId: 'role-1' }),
+    ]);
+
+    const [altOnly] = dedupeRunGroupDrops([alt]);
+    expect(altOnly.hasXuanjing).toBe(true);
+    expect(altOnly.goldIncome).toBe(3000);
+  });
 });
@@ -31,17 +31,51 @@ const getRecordTime = (date: string | number): number => {
   return typeof date === 'number' ? date : new Date(date).getTime();
 };
 
+type RunGroupDropRecord = { id: string; runGroupId?: string } & Partial<Record<SpecialDropField, boolean>>;
+
+/**
+ * 多开同团去重：同一分组的记录共享掉落，合并全组的特殊掉落标记后只在一条记录上统计。
+ * 统计落在主记录（ID 等于分组 ID）上，主记录不在列表中时落在组内第一条记录上；
+ * 组内其余记录的特殊掉落标记清空，金币等其他字段不变。
+ */
+export const dedupeRunGroupDrops = <T extends RunGroupDropRecord>(records: T[]): T[] => {
+  const groups = new Map<string, T[]>();
+  records.forEach(record => {
+    const groupId = record.runGroupId || record.id;
+    groups.set(groupId, [...(groups.get(groupId) ?? []), record]);
+  });
+
+  const merged = new Map<T, T>();
+  groups.forEach((members, groupId) => {
+    if (members.length < 2) {
+      return;
+    }
+    const primary = members.find(member => member.id === groupId) ?? members[0];
+    members.forEach(member => {
+      const flags = Object.fromEntries(
+        SPECIAL_DROP_DEFINITIONS.map(({ field }) => [
+          field,
+          member === primary ? members.some(other => other[field] === true) : false,
+        ]),
+      );
+      merged.set(member, { ...member, ...flags });
+    });
+  });
+
+  return records.map(record => merged.get(record) ?? record);
+};
+
 export const buildSpecialDropRecords = (
   records: RaidRecord[],
   periodStartTime: number | null,
 ): SpecialDropRecord[] => {
   const drops: SpecialDropRecord[] = [];
+  const periodRecords = records.filter(
+    record => periodStartTime === null || getRecordTime(record.date) >= periodStartTime,
+  );
 
-  records.forEach(record => {
-    if (periodStartTime !== null && getRecordTime(record.date) < periodStartTime) {
-      return;
-    }
-
+  // 多开同团：共享掉落合并到主记录上统计一次
+  dedupeRunGroupDrops(periodRecords).forEach(record => {
     SPECIAL_DROP_DEFINITIONS.forEach(definition => {
       if (record[definition.field] !== true) {
         return;
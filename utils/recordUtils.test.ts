@@ -0,0 +1,32 @@
+import { describe, expect, it } from 'vitest';
+import { formatGoldAmount, getRecordExpense, getRecordIncome, toMoneyFields } from './recordUtils';
+
+describe('getRecordIncome', () => {
+  it('使用铜精度金额', () => {
+    expect(getRecordIncome({ goldIncome: 147, goldIncomeCopper: 1_477_653 })).toBe(147.7653);
+    expect(formatGoldAmount(getRecordIncome({ goldIncome: 147, goldIncomeCopper: 1_477_653 }))).toBe(
+      (147.7653).toLocaleString(undefined, { maximumFractionDigits: 4 })
+    );
+  });
+
+  it('铜字段为存储金额，只修改银铜部分也会保留', () => {
+    expect(getRecordIncome({ goldIncome: 147, goldIncomeCopper: 1_470_001 })).toBe(147.0001);
+    expect(getRecordIncome({ goldIncome: 200, goldIncomeCopper: 1_477_653 })).toBe(147.7653);
+  });
+
+  it('没有铜字段的旧记录回退到金字段', () => {
+    expect(getRecordExpense({ goldExpense: 3000 })).toBe(3000);
+    expect(getRecordExpense({})).toBe(0);
+  });
+});
+
+describe('toMoneyFields', () => {
+  it('由界面金额生成铜字段，金字段为整金部分', () => {
+    expect(toMoneyFields(147.7653, 0.5)).toEqual({
+      goldIncome: 147,
+      goldExpense: 0,
+      goldIncomeCopper: 1_477_653,
+      goldExpenseCopper: 5_000,
+    });
+  });
+});
@@ -6,6 +6,46 @@ export interface MergeResult {
   updatedRecords: string[];
 }
 
+/** 1 金 = 100 银 = 10000 铜 */
+export const COPPER_PER_GOLD = 10000;
+
+interface MoneyFields {
+  goldIncome?: number;
+  goldExpense?: number;
+  goldIncomeCopper?: number;
+  goldExpenseCopper?: number;
+}
+
+/**
+ * 记录中保存的铜精度金额（与后端 money::stored_money 一致）。
+ * 铜字段为唯一的存储金额，仅缺少铜字段的旧记录按金字段换算。
+ */
+export const storedCopper = (gold: number | undefined, copper: number | undefined): number =>
+  typeof copper === 'number' ? copper : Math.round((gold || 0) * COPPER_PER_GOLD);
+
+/** 记录收入（金，银铜部分为小数） */
+export const getRecordIncome = (record: MoneyFields): number =>
+  storedCopper(record.goldIncome, record.goldIncomeCopper) / COPPER_PER_GOLD;
+
+/** 记录支出（金，银铜部分为小数） */
+export const getRecordExpense = (record: MoneyFields): number =>
+  storedCopper(record.goldExpense, record.goldExpenseCopper) / COPPER_PER_GOLD;
+
+/**
+ * 由界面输入的金额（金，可带银铜小数）生成要保存的金额字段：
+ * 铜字段为存储金额，金字段为其整金部分，仅供显示。
+ */
+export const toMoneyFields = (income: number, expense: number): Required<MoneyFields> => {
+  const goldIncomeCopper = Math.round((income || 0) * COPPER_PER_GOLD);
+  const goldExpenseCopper = Math.round((expense || 0) * COPPER_PER_GOLD);
+  return {
+    goldIncome: Math.trunc(goldIncomeCopper / COPPER_PER_GOLD),
+    goldExpense: Math.trunc(goldExpenseCopper / COPPER_PER_GOLD),
+    goldIncomeCopper,
+    goldExpenseCopper,
+  };
+};
+
 export const generateTransactionId = (): string => {
   return `txn_${Date.now()}_${Math.random().toString(36).substring(2, 9)}`;
 };
@@ -40,8 +80,8 @@ export const mergeRecords = (records: RaidRecord[]): MergeResult => {
   const processedPairs = new Set<string>();
   
   duplicates.forEach((group) => {
-    const incomeRecord = group.find(r => r.transactionType === 'income' || r.goldIncome > 0);
-    const expenseRecord = group.find(r => r.transactionType === 'expense' || (r.goldExpense && r.goldExpense > 0));
+    const incomeRecord = group.find(r => r.transactionType === 'income' || getRecordIncome(r) > 0);
+    const expenseRecord = group.find(r => r.transactionType === 'expense' || getRecordExpense(r) > 0);
     
     if (incomeRecord && expenseRecord && incomeRecord.id !== expenseRecord.id) {
       const pairKey = [incomeRecord.id, expenseRecord.id].sort().join('_');
@@ -56,6 +96,8 @@ export const mergeRecords = (records: RaidRecord[]): MergeResult => {
           transactionId: transactionId,
           goldIncome: incomeRecord.goldIncome,
           goldExpense: expenseRecord.goldExpense || 0,
+          goldIncomeCopper: storedCopper(incomeRecord.goldIncome, incomeRecord.goldIncomeCopper),
+          goldExpenseCopper: storedCopper(expenseRecord.goldExpense, expenseRecord.goldExpenseCopper),
           hasXuanjing: incomeRecord.hasXuanjing || expenseRecord.hasXuanjing,
           notes: mergeNotes(incomeRecord.notes, expenseRecord.notes),
           transactionType: 'combined',
@@ -72,11 +114,15 @@ export const mergeRecords = (records: RaidRecord[]): MergeResult => {
         processedPairs.add(pairKey);
         
         const primary = group[0];
+        const incomeCopper = Math.max(...group.map(r => storedCopper(r.goldIncome, r.goldIncomeCopper)));
+        const expenseCopper = group.reduce((sum, r) => sum + storedCopper(r.goldExpense, r.goldExpenseCopper), 0);
         const mergedRecord: RaidRecord = {
           ...primary,
           transactionId: generateTransactionId(),
-          goldIncome: Math.max(...group.map(r => r.goldIncome)),
-          goldExpense: group.reduce((sum, r) => sum + (r.goldExpense || 0), 0),
+          goldIncome: Math.trunc(incomeCopper / COPPER_PER_GOLD),
+          goldExpense: Math.trunc(expenseCopper / COPPER_PER_GOLD),
+          goldIncomeCopper: incomeCopper,
+          goldExpenseCopper: expenseCopper,
           hasXuanjing: group.some(r => r.hasXuanjing),
           transactionType: 'combined'
         };
@@ -130,10 +176,20 @@ export const deduplicateRecords = (records: RaidRecord[]): RaidRecord[] => {
     } else {
       const existing = seen.get(key)!;
       
+      const incomeCopper = Math.max(
+        storedCopper(existing.goldIncome, existing.goldIncomeCopper),
+        storedCopper(record.goldIncome, record.goldIncomeCopper)
+      );
+      const expenseCopper = Math.max(
+        storedCopper(existing.goldExpense, existing.goldExpenseCopper),
+        storedCopper(record.goldExpense, record.goldExpenseCopper)
+      );
       const combined: RaidRecord = {
         ...existing,
-        goldIncome: Math.max(existing.goldIncome, record.goldIncome),
-        goldExpense: Math.max(existing.goldExpense || 0, record.goldExpense || 0),
+        goldIncome: Math.trunc(incomeCopper / COPPER_PER_GOLD),
+        goldExpense: Math.trunc(expenseCopper / COPPER_PER_GOLD),
+        goldIncomeCopper: incomeCopper,
+        goldExpenseCopper: expenseCopper,
         hasXuanjing: existing.hasXuanjing || record.hasXuanjing,
         isCleared: (existing.isCleared && record.isCleared) || false,
         transactionType: 'combined'
@@ -151,11 +207,15 @@ export const deduplicateRecords = (records: RaidRecord[]): RaidRecord[] => {
 };
 
 export const calculateRecordTotal = (record: RaidRecord): number => {
-  const income = record.goldIncome || 0;
-  const expense = record.goldExpense || 0;
-  return income - expense;
+  return getRecordIncome(record) - getRecordExpense(record);
+};
+
+/** 自动生成、需要确认流程的记录（扫描或 GKP 导入） */
+export const isAutoSourceRecord = (record: Pick<RaidRecord, 'source'>): boolean => {
+  return record.source === 'auto' || record.source === 'gkp';
 };
 
+/** 金额（金）显示，保留银铜精度（最多 4 位小数） */
 export const formatGoldAmount = (amount: number): string => {
-  return amount.toLocaleString();
+  return amount.toLocaleString(undefined, { maximumFractionDigits: 4 });
 };